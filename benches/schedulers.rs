@@ -0,0 +1,83 @@
+//! Decision cost of every scheduler on synthetic queues, and the whole engine loop on the
+//! start of the bundled trace. Run with `cargo bench`, or `cargo bench -- easy` for a single
+//! scheduler.
+
+use chamallow::{Cluster, ClusterSpec, Engine, EngineConfig, Job, ReadyQueue, algos};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+const NODES: u32 = 1024;
+const QUEUE_SIZES: &[usize] = &[16, 256, 2048];
+const TRACE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/ANL-Intrepid-2009-1.swf");
+const TRACE_JOBS: usize = 2000;
+
+/// A random job submitted before `clock`, sizes and run times being log-uniform.
+fn random_job(rng: &mut StdRng, id: u32, clock: u64) -> Job {
+	let nodes = 2f64.powf(rng.random_range(0.0..(NODES as f64).log2())) as u32;
+	let run_time = 10f64.powf(rng.random_range(1.0..5.0)) as u64;
+	let requested = run_time + rng.random_range(0..run_time);
+	Job::new(id, nodes, clock - rng.random_range(0..clock), run_time, requested)
+}
+
+/// A cluster half full of running jobs, and a queue of `size` jobs waiting, the same for
+/// every scheduler.
+fn setup(size: usize) -> (Cluster, ReadyQueue) {
+	let mut rng = StdRng::seed_from_u64(size as u64);
+	let clock = 100_000;
+
+	let mut cluster = Cluster::new(NODES);
+	let mut id = 0;
+	while cluster.available_nodes > NODES / 2 {
+		let job = random_job(&mut rng, id, clock);
+		if job.nodes <= cluster.available_nodes - NODES / 2 {
+			cluster.schedule_job(job, clock);
+		}
+
+		id += 1;
+	}
+
+	let mut queue = ReadyQueue::new();
+	for _ in 0..size {
+		queue.push(random_job(&mut rng, id, clock));
+		id += 1;
+	}
+
+	(cluster, queue)
+}
+
+fn decisions(c: &mut Criterion) {
+	let mut group = c.benchmark_group("schedule_batch");
+	let setups = QUEUE_SIZES.iter().map(|&size| (size, setup(size))).collect::<Vec<_>>();
+
+	for name in algos::names() {
+		let mut scheduler = algos::by_name(name).expect("registered schedulers build without parameters");
+
+		for (size, (cluster, queue)) in &setups {
+			group.bench_with_input(BenchmarkId::new(name, size), size, |b, _| {
+				b.iter(|| scheduler.schedule_batch(100_000, queue, cluster))
+			});
+		}
+	}
+
+	group.finish();
+}
+
+fn engine(c: &mut Criterion) {
+	let mut group = c.benchmark_group("engine");
+	group.sample_size(10);
+
+	for name in ["fcfs", "easy"] {
+		group.bench_function(BenchmarkId::new(name, TRACE_JOBS), |b| {
+			b.iter(|| {
+				let config = EngineConfig { task_limit: Some(TRACE_JOBS), ..EngineConfig::default() };
+				let scheduler = algos::by_name(name).unwrap();
+				Engine::new(scheduler, ClusterSpec::new(NODES), TRACE, config).unwrap().run().unwrap()
+			})
+		});
+	}
+
+	group.finish();
+}
+
+criterion_group!(benches, decisions, engine);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+//! Batch scheduling simulator replaying Standard Workload Format (SWF) traces.
+//!
+//! An `Engine` simulates one trace on one cluster with one scheduler and returns an
+//! `EngineReport`. `experiment::ExperimentRunner` runs many of them in parallel.
+//!
+//! ```no_run
+//! use chamallow::{ClusterSpec, Engine, EngineConfig, algos::EasyBackfill};
+//!
+//! let scheduler = Box::new(EasyBackfill::default());
+//! let mut engine = Engine::new(scheduler, ClusterSpec::new(1024), "ANL-Intrepid-2009-1.swf", EngineConfig::default())?;
+//! let report = engine.run()?;
+//! println!("makespan: {}", report.makespan);
+//! # Ok::<(), chamallow::EngineError>(())
+//! ```
+//!
+//! New policies implement `Scheduler`, and can be built by name once added to the registry of `algos`.
+
+pub mod sim;
+#[cfg(feature = "pyo3")]
+mod python;
+
+pub use sim::{
+	Clock, DebugLevel, DecisionLatency, Engine, EngineConfig, EngineError, EventOrder, SchedulingCycle,
+	admission::{self, AdmissionPolicy, AdmissionRule},
+	allocation::{self, AllocationPolicy},
+	algos::{self, Scheduler, SchedulerSpec},
+	backlog::{Backlog, BacklogSummary},
+	budget::Budget,
+	characterization::{self, WorkloadStats},
+	cluster::{self, Cluster, ClusterSpec, NodeClassSpec, NodeSet, PartitionSpec},
+	comparison::Comparison,
+	confidence::Confidence,
+	debugger::{Breakpoint, Debugger},
+	drain::{self, Drain},
+	experiment,
+	external::{self, External},
+	failure,
+	fragmentation::Fragmentation,
+	gang::{self, TimeSlicing},
+	golden::{Drift, GoldenReports, Regression},
+	grid::{self, Routing},
+	initial::{self, InitialJob, InitialState},
+	job::{Job, Shape},
+	malleable::{self, Malleability, Moldability, SpeedupModel},
+	mark::Mark,
+	observer::{KillReason, Observer},
+	occupancy::{OccupancySnapshot, OccupancyWriter, PartitionOccupancy, RunningJob},
+	output::{Manifest, OutputManager, RunEntry},
+	power,
+	qos::{self, QosClass},
+	queue::ReadyQueue,
+	report::{Distribution, EngineReport},
+	reservation::{self, Reservation},
+	rng::SimRng,
+	runtime::{self, JobView, PredictionSummary, Predictions, RuntimeModel, RuntimePredictor},
+	snapshot::EngineSnapshot,
+	stats::{self, StatsMode},
+	synth::{self, SynthModel, SynthSpec},
+	user::{self, UserModel},
+	utilization::{self, UtilizationSampling},
+	view::SimView,
+	walltime,
+	workload::{ExportOptions, SwfJob, SwfReader, Workload, WorkloadBuilder, WorkloadFilter, WorkloadFormat},
+};
+
+#[cfg(feature = "viz")]
+pub use sim::gantt::{self, GanttColoring};
@@ -1,32 +1,708 @@
-use std::time::SystemTime;
+use std::{fmt::Display, io, path::PathBuf, process, str::FromStr, time::SystemTime};
 
-use algos::Scheduler;
-use sim::{DebugLevel, Engine, algos};
+use algos::SchedulerSpec;
+use chamallow::{AllocationPolicy, Budget, Comparison, Confidence, DebugLevel, Debugger, DecisionLatency, EventOrder, ExportOptions, SchedulingCycle, StatsMode, UtilizationSampling, Workload, WorkloadFilter, admission::AdmissionRule, algos, cluster::{NodeClassSpec, PartitionSpec}, failure::FailureModel, gang::TimeSlicing, grid::Routing, malleable::{Malleability, Moldability}, power::PowerModel, qos::QosClass, drain::Drain, reservation::Reservation, runtime::RuntimeModel, synth::{SynthModel, SynthSpec}, user::UserModel, experiment::{Experiment, OutputConfig}, walltime::{WalltimeLimit, WalltimeModel}};
+#[cfg(feature = "viz")]
+use chamallow::GanttColoring;
+use clap::{Args, Parser, Subcommand};
 
-pub mod sim;
+#[derive(Parser)]
+#[command(name = "m2ord", version, about = "Batch scheduling simulator for SWF workloads")]
+struct Cli {
+	#[command(subcommand)]
+	command: Command,
+
+	/// Format of the logs of the simulations: pretty for reading in a console, or json
+	/// for one JSON object per event
+	#[arg(long, global = true, default_value = "pretty")]
+	log_format: LogFormat,
+}
+
+#[derive(Clone, Copy)]
+enum LogFormat {
+	Pretty,
+	Json,
+}
+
+impl FromStr for LogFormat {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"pretty" => Ok(LogFormat::Pretty),
+			"json" => Ok(LogFormat::Json),
+			_ => Err(format!("unknown log format '{}' (expected pretty or json)", s)),
+		}
+	}
+}
+
+/// Prints the events the simulations log up to the given level, on stdout.
+fn init_logs(level: DebugLevel, format: LogFormat) {
+	let logs = tracing_subscriber::fmt().with_max_level(level.level_filter()).with_target(false);
+
+	match format {
+		LogFormat::Pretty => logs.without_time().init(),
+		LogFormat::Json => logs.json().init(),
+	}
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Simulate workloads for every (node count, scheduler) combination
+	Run(Box<RunArgs>),
+	/// Run the experiment described by a TOML configuration file
+	Experiment {
+		/// Path to the configuration file
+		config: PathBuf,
+	},
+	/// Write a synthetic workload as an SWF trace
+	Generate {
+		/// poisson:MEAN_INTERARRIVAL:MIN_RUN:MAX_RUN (seconds) or lublin[:ARRIVAL_FACTOR]
+		#[arg(short, long, default_value = "lublin")]
+		model: SynthModel,
+		/// Number of jobs
+		#[arg(short, long, default_value_t = 10000)]
+		jobs: usize,
+		/// Nodes of the machine the jobs are sized for
+		#[arg(short, long, default_value_t = 1024)]
+		nodes: u32,
+		#[arg(long, default_value_t = 0)]
+		seed: u64,
+		/// Path of the trace to write
+		#[arg(short, long)]
+		output: PathBuf,
+	},
+	/// Write a workload back out as an SWF trace once filtered and transformed, e.g. to share
+	/// it or feed it to other simulators
+	Export(ExportArgs),
+	/// Analyze workloads without simulating them
+	Workload {
+		#[command(subcommand)]
+		command: WorkloadCommand,
+	},
+}
+
+#[derive(Subcommand)]
+enum WorkloadCommand {
+	/// Characterize a workload: job sizes, run times, arrivals and shares of the users
+	Stats(StatsArgs),
+}
+
+#[derive(Args)]
+struct StatsArgs {
+	/// Workload to analyze: an SWF trace, a Batsim JSON workload if it ends with .json, or a
+	/// Slurm (sacct --parsable2) or PBS accounting log
+	#[arg(short, long)]
+	workload: PathBuf,
+
+	/// Processors per node, used to convert the processors requested by jobs into nodes.
+	/// Defaults to MaxProcs / MaxNodes from the trace header, or 4
+	#[arg(long)]
+	cores_per_node: Option<u32>,
+
+	/// Also tell the load the workload would offer to clusters of these sizes, comma separated
+	#[arg(short, long, value_delimiter = ',')]
+	nodes: Vec<u32>,
+
+	/// Print the statistics as JSON, with every day and every user
+	#[arg(long)]
+	json: bool,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+	/// Workload to export: an SWF trace, a Batsim JSON workload if it ends with .json, or a
+	/// Slurm (sacct --parsable2) or PBS accounting log
+	#[arg(short, long)]
+	workload: PathBuf,
+
+	/// Path of the trace to write
+	#[arg(short, long)]
+	output: PathBuf,
+
+	/// Processors per node, used to filter the jobs on their number of nodes.
+	/// Defaults to MaxProcs / MaxNodes from the trace header, or 4
+	#[arg(long)]
+	cores_per_node: Option<u32>,
+
+	/// Only write the first N jobs kept
+	#[arg(short, long)]
+	limit: Option<usize>,
+
+	/// Only keep the jobs submitted from this day of the trace (counting from 0), submit
+	/// times being re-based so that the day starts at 0
+	#[arg(long)]
+	from_day: Option<u32>,
+
+	/// Only keep the jobs submitted before this day of the trace
+	#[arg(long)]
+	to_day: Option<u32>,
+
+	/// Only keep the jobs of these users (SWF user IDs), comma separated
+	#[arg(long, value_delimiter = ',')]
+	only_users: Vec<u32>,
+
+	/// Only keep the jobs on at least this many nodes
+	#[arg(long)]
+	min_nodes: Option<u32>,
+
+	/// Divide the submit times by this factor, e.g. 1.2 for 20% more load, 0.5 for half of it
+	#[arg(long, default_value_t = 1.0)]
+	load_factor: f64,
+
+	/// Renumber the users, groups and executables in order of first appearance
+	#[arg(long)]
+	anonymize: bool,
+}
+
+#[derive(Args)]
+struct RunArgs {
+	/// Workloads to replay, comma separated: SWF traces, Batsim JSON workloads if they end with
+	/// .json, or Slurm (sacct --parsable2) and PBS accounting logs
+	#[arg(short, long, value_delimiter = ',', default_value = "ANL-Intrepid-2009-1.swf")]
+	workload: Vec<String>,
+
+	/// Cluster sizes to simulate, comma separated
+	#[arg(short, long, value_delimiter = ',', default_values_t = [64, 128, 256, 512, 1024, 2048, 4096, 8192, 16384, 32768, 65536, 131072])]
+	nodes: Vec<u32>,
+
+	/// Processors per node, used to convert the processors requested by jobs into nodes.
+	/// Defaults to MaxProcs / MaxNodes from the trace header, or 4
+	#[arg(long)]
+	cores_per_node: Option<u32>,
+
+	/// Split the cluster into partitions, tried in order to route each job, e.g.
+	/// `debug(nodes=64, max_nodes=16),large(nodes=512, min_nodes=256),batch`.
+	/// Rules are nodes, queues (SWF queues separated by ;), min_nodes and max_nodes,
+	/// and a single partition may leave nodes unset to take the nodes left
+	#[arg(long, value_parser = PartitionSpec::parse_list)]
+	partitions: Vec<Vec<PartitionSpec>>,
+
+	/// Split the nodes into classes by ID, e.g. `cpu,gpu(nodes=64),bigmem(nodes=32, memory=1048576)`.
+	/// Jobs run on the classes with enough memory per node (in KB) for them, and a single class
+	/// may leave nodes unset to take the nodes left
+	#[arg(long, value_parser = NodeClassSpec::parse_list)]
+	node_classes: Vec<Vec<NodeClassSpec>>,
+
+	/// File giving the node class of some jobs as `job_id,class` lines, overriding their memory
+	#[arg(long)]
+	class_map: Option<PathBuf>,
+
+	/// File giving the jobs running or queued when the simulation starts as
+	/// `state,nodes,requested_time,run_time,elapsed[,user]` lines, state being running or queued
+	#[arg(long)]
+	initial_state: Option<PathBuf>,
+
+	/// Memory of each node in KB, for the node classes that don't set theirs: jobs requesting
+	/// more memory per node than any node has are rejected
+	#[arg(long)]
+	node_memory: Option<u64>,
+
+	/// Keep nodes from the jobs over time windows, e.g. `maintenance(start=86400, duration=7200)`
+	/// or `demo(start=3600, duration=600, nodes=128, partition=batch)`, every node of the first
+	/// partition being reserved when nodes is unset
+	#[arg(long, value_parser = Reservation::parse_list)]
+	reservations: Vec<Vec<Reservation>>,
+
+	/// Drain nodes over scheduled downtimes, e.g. `maintenance(start=86400, duration=7200)` or
+	/// `rack3(start=3600, duration=600, first_node=256, nodes=128)`: running jobs finish on the
+	/// nodes but no job starts on them, every node from first_node (0) being drained when nodes is unset
+	#[arg(long, value_parser = Drain::parse_list)]
+	drains: Vec<Vec<Drain>>,
+
+	/// Sort the jobs into QoS classes with a target wait, tried in order, e.g.
+	/// `premium(queues=1;2, max_wait=600),standard(max_wait=86400)`, classes matching the SWF
+	/// queues and partitions given (any when unset). Reports count the jobs waiting longer
+	#[arg(long, value_parser = QosClass::parse_list)]
+	qos: Vec<Vec<QosClass>>,
+
+	/// How the nodes of the jobs are picked among the free ones: first-fit (lowest IDs),
+	/// best-fit (smallest free block holding the job), contiguous (first free block holding
+	/// the job), torus:X:Y:Z (nodes closest to each other on a 3D torus) or best-fit-memory
+	/// (nodes of the classes with the least memory the job fits on first)
+	#[arg(long, default_value = "first-fit")]
+	allocation: AllocationPolicy,
+
+	/// Schedulers to evaluate, comma separated, with optional parameters (e.g. `sjf,easy(backfill_depth=16)`)
+	#[arg(short, long, default_value = "fcfs,ff,sjf,fcfs-easy", value_parser = parse_schedulers)]
+	scheduler: Vec<Vec<SchedulerSpec>>,
+
+	/// Only load the first N jobs of the trace
+	#[arg(short, long)]
+	limit: Option<usize>,
+
+	/// Only simulate the jobs submitted from this day of the trace (counting from 0), submit
+	/// times being re-based so that the day starts at 0
+	#[arg(long)]
+	from_day: Option<u32>,
+
+	/// Only simulate the jobs submitted before this day of the trace
+	#[arg(long)]
+	to_day: Option<u32>,
+
+	/// Only simulate the jobs of these users (SWF user IDs), comma separated
+	#[arg(long, value_delimiter = ',')]
+	only_users: Vec<u32>,
+
+	/// Only simulate the jobs on at least this many nodes
+	#[arg(long)]
+	min_nodes: Option<u32>,
+
+	/// Divide the submit times by this factor, e.g. 1.2 for 20% more load, 0.5 for half of it;
+	/// several comma separated factors sweep the load, e.g. 0.5,1,1.5,2
+	#[arg(long, value_delimiter = ',', default_value = "1")]
+	load_factor: Vec<f64>,
+
+	/// Scale the number of nodes of the jobs from the size of the traced machine (from the
+	/// trace header) to the simulated node count, instead of rejecting the jobs too wide for it
+	#[arg(long)]
+	scale_widths: bool,
+
+	/// Skip the lines of the trace that can't be read, counting them in the report, instead
+	/// of stopping at the first one
+	#[arg(long)]
+	lenient: bool,
+
+	/// Simulate the first N jobs but leave them out of the metrics
+	#[arg(long, default_value_t = 0)]
+	warmup: usize,
+
+	/// Simulate the jobs finishing after this time (in seconds) but leave them out of the metrics
+	#[arg(long)]
+	cutoff: Option<u64>,
+
+	/// Stop each simulation after some simulated days, started jobs or seconds of real time,
+	/// e.g. `days:30,jobs:10000,seconds:60`, the report being flagged as truncated
+	#[arg(long, default_value = "none")]
+	budget: Budget,
+
+	/// Verbosity of the simulation logs (none, info, verbose, trace)
+	#[arg(short, long, default_value = "info")]
+	debug: DebugLevel,
+
+	/// Print the progress of each simulation on stderr: jobs done, simulated time, speed and ETA
+	#[arg(long)]
+	progress: bool,
+
+	/// Publish the progress of the runs in the Prometheus format on this address while they
+	/// run, at /metrics (e.g. `0.0.0.0:9100`)
+	#[cfg(feature = "metrics")]
+	#[arg(long)]
+	metrics: Option<String>,
+
+	/// Go through the first simulation step by step, reading commands on stdin to inspect the
+	/// queues, the clusters and the scheduler and to set breakpoints (type help for the commands)
+	#[arg(long)]
+	step: bool,
+
+	/// Also replay the trace for every workload and node count, each job starting when it
+	/// started on the real system (or once enough nodes are free), to compare the schedulers to it
+	#[arg(long)]
+	validation: bool,
+
+	/// Also let the decision process listening on this address (e.g. localhost:4242) schedule
+	/// every workload and node count, over a connection of its own per run
+	#[arg(long)]
+	external: Option<String>,
+
+	/// Submit jobs at their trace submit time even if the job they depend on didn't finish
+	#[arg(long)]
+	ignore_dependencies: bool,
+
+	/// Run the jobs the trace cancelled before they ran for no time, instead of taking them
+	/// out of the queue when they were cancelled
+	#[arg(long)]
+	ignore_cancellations: bool,
+
+	/// Run time (in seconds) under which jobs count as this value for the bounded slowdown
+	#[arg(long, default_value_t = 10)]
+	slowdown_tau: u64,
+
+	/// How medians and percentiles are computed: exact (every value is kept until the end of
+	/// the run) or streaming (estimated as jobs finish, in constant memory, for long traces)
+	#[arg(long, default_value = "exact")]
+	stats: StatsMode,
+
+	/// Allow schedulers such as psjf to preempt running jobs, which then take this time
+	/// (in seconds) to restart. Jobs are never preempted when not set
+	#[arg(long)]
+	preemption_overhead: Option<u64>,
+
+	/// Requested run times of the jobs: from the trace, exact, the run time times a factor
+	/// (factor:F) or times a random factor drawn for each job (uniform:MIN:MAX)
+	#[arg(long, default_value = "trace")]
+	walltime: WalltimeModel,
+
+	/// Jobs running longer than they requested: none (they run to completion), kill (killed at
+	/// their walltime, as failed) or resubmit:FACTOR[:RETRIES] (killed, then submitted again
+	/// requesting FACTOR times more time, once or RETRIES times)
+	#[arg(long, default_value = "none")]
+	walltime_limit: WalltimeLimit,
+
+	/// Seed of the stochastic components of the runs: random walltime models, failures, user
+	/// model and schedulers drawing at random (unless given their own seed), recorded in the reports
+	#[arg(long, default_value_t = 0)]
+	seed: u64,
+
+	/// Run every combination N times, with the seeds following --seed, and report the mean
+	/// and the 95% confidence interval of the main metrics over the runs
+	#[arg(long, default_value_t = 1)]
+	repetitions: usize,
+
+	/// Which events go first when jobs finish and are submitted at the same time
+	/// (finish-first, submit-first)
+	#[arg(long, default_value = "finish-first")]
+	event_order: EventOrder,
+
+	/// When the scheduler runs: after every event, once per instant having events (instant),
+	/// or every N simulated seconds (e.g. 30)
+	#[arg(long, default_value = "instant")]
+	cycle: SchedulingCycle,
+
+	/// Time the scheduler takes to start the jobs it picks, as CYCLE[:JOB] seconds: the jobs
+	/// of a cycle start CYCLE seconds after it plus JOB seconds per job decided until them
+	#[arg(long)]
+	decision_latency: Option<DecisionLatency>,
+
+	/// Node failures: none, trace:PATH (lines of `time duration first_node nodes`) or
+	/// mtbf:MTBF:MTTR (each node fails every MTBF seconds on average, for MTTR seconds)
+	#[arg(long, default_value = "none")]
+	failures: FailureModel,
+
+	/// Power of a node in watts, as IDLE:ACTIVE, or IDLE:ACTIVE:SPEED:WATTS to run the jobs
+	/// at a lower frequency (SPEED relative to nominal) drawing WATTS
+	#[arg(long, default_value = "100:250")]
+	power: PowerModel,
+
+	/// Sample the share of the nodes running jobs after every event (events) or every given
+	/// number of seconds (interval:SECONDS), and report its average and percentiles
+	#[arg(long)]
+	utilization: Option<UtilizationSampling>,
+
+	/// Take a snapshot of the jobs running on the nodes every given number of seconds, for
+	/// --occupancy-log, every hour by default
+	#[arg(long)]
+	occupancy: Option<u64>,
+
+	/// Policies deciding which submitted jobs enter the queues, comma separated and applied in
+	/// order: max_nodes:N, max_queue:N (jobs in the queue of the partition), max_walltime:SECONDS
+	/// (truncating the jobs), shrink (jobs wider than their partition) or reroute:PARTITION
+	/// (jobs without a partition large enough). Jobs that can't run are always rejected
+	#[arg(long, value_delimiter = ',')]
+	admission: Vec<AdmissionRule>,
+
+	/// Jobs submitted by the users during the simulation: none, resubmit:DELAY[:RETRIES] (jobs
+	/// the trace marks as failed or cancelled are submitted again after DELAY seconds or their
+	/// think time) or follow-up:PROBABILITY:MEAN_THINK_TIME (users submit their job again once
+	/// it ends, with the given probability)
+	#[arg(long, default_value = "none")]
+	users: UserModel,
+
+	/// Share the nodes in turns between up to SLOTS rows of jobs, switching rows every QUANTUM
+	/// seconds, resumed jobs spending OVERHEAD seconds restoring themselves (gang scheduling),
+	/// written QUANTUM:SLOTS[:OVERHEAD]. Jobs run to completion by default
+	#[arg(long)]
+	time_slicing: Option<TimeSlicing>,
+
+	/// Make this fraction of the jobs malleable, schedulers such as elastic shrinking or
+	/// expanding them while they run to between their traced width divided and multiplied by
+	/// FACTOR, their speed following the speedup model (linear, amdahl:SERIAL or power:ALPHA),
+	/// written FRACTION:FACTOR[:SPEEDUP]
+	#[arg(long, default_value = "none")]
+	malleability: Malleability,
+
+	/// Make this fraction of the jobs moldable, schedulers such as moldable starting them on
+	/// their traced width or that width divided or multiplied by powers of 2 up to FACTOR,
+	/// their run time following the speedup model (linear, amdahl:SERIAL, power:ALPHA or
+	/// downey:SIGMA), written FRACTION:FACTOR[:SPEEDUP]
+	#[arg(long, default_value = "none")]
+	moldability: Moldability,
+
+	/// Spread the jobs over the partitions, standing for the clusters of a grid: first (the
+	/// first partition accepting the job), round-robin, least-loaded or locality:WEIGHT (the
+	/// least loaded, running a job away from the data of its user counting as WEIGHT more load)
+	#[arg(long, default_value = "first")]
+	routing: Routing,
+
+	/// What the schedulers know of the run times of the jobs, which they plan with: requested
+	/// (the time the users requested), user-average[:JOBS] (the average run time of the last
+	/// 2 or JOBS jobs of the user) or exact (the actual run times, as a best case)
+	#[arg(long, default_value = "requested")]
+	runtime: RuntimeModel,
+
+	/// File giving predicted run times of the jobs as `job_id,run_time` lines, e.g. from a
+	/// learned model, planned with instead of those of --runtime for the jobs it gives
+	#[arg(long)]
+	predictions: Option<PathBuf>,
+
+	/// Number of simulations running at the same time, defaults to the number of CPUs
+	#[arg(short, long)]
+	threads: Option<usize>,
+
+	/// Write the results to a new directory of the experiment in this one: the reports, the
+	/// comparison, the records of each run (jobs, timeline...) and a manifest.json describing them
+	#[arg(long)]
+	output_dir: Option<PathBuf>,
+
+	/// Name of the directory of the experiment in --output-dir
+	#[arg(long, default_value = "run")]
+	experiment_name: String,
+
+	/// Write every report to this file, as CSV or as JSON lines if it ends with .json/.jsonl
+	#[arg(short, long)]
+	output: Option<PathBuf>,
+
+	/// Write a record of every simulated job to this file, as CSV or as SWF if it ends with .swf
+	#[arg(long)]
+	job_log: Option<PathBuf>,
+
+	/// Write the timeline of jobs and queue length to this file, in the Chrome trace-event
+	/// format (chrome://tracing, Perfetto) or as JSON lines if it ends with .jsonl
+	#[arg(long)]
+	timeline: Option<PathBuf>,
+
+	/// Write why each queued job did or didn't start at each scheduling pass to this file, as JSON lines
+	#[arg(long)]
+	decision_log: Option<PathBuf>,
+
+	/// Write the jobs rejected when submitted (too wide, too much memory, turned down by an
+	/// admission policy...) with the reason, as CSV
+	#[arg(long)]
+	rejects: Option<PathBuf>,
+
+	/// Write the sampled utilization of the cluster to this CSV file, sampling it at every
+	/// event unless --utilization is given
+	#[arg(long)]
+	utilization_log: Option<PathBuf>,
+
+	/// Write snapshots of the jobs running on the nodes (which job, on which nodes) and of the
+	/// queues over time to this file, as JSON lines, to animate the machine in other tools
+	#[arg(long)]
+	occupancy_log: Option<PathBuf>,
+
+	/// Draw the schedules as Gantt charts in this file, as HTML or as SVG if it ends with .svg
+	#[cfg(feature = "viz")]
+	#[arg(long)]
+	gantt: Option<PathBuf>,
+
+	/// Color the jobs of the Gantt charts by user or by size
+	#[cfg(feature = "viz")]
+	#[arg(long, default_value = "user")]
+	gantt_colors: GanttColoring,
+
+	/// Print a table comparing the schedulers to this one (or to the first one when empty),
+	/// with the difference and the rank of each scheduler for the main metrics
+	#[arg(long, num_args = 0..=1, default_missing_value = "")]
+	compare: Option<String>,
+
+	/// Also write the comparison to this CSV file
+	#[arg(long)]
+	comparison: Option<PathBuf>,
+
+	/// Write the means and confidence intervals of the metrics over the repetitions to this CSV file
+	#[arg(long)]
+	confidence: Option<PathBuf>,
+
+	/// Check the reports against golden reports recorded before (JSON lines as written by
+	/// --output, or an --output-dir directory), failing with the metrics that drifted
+	#[arg(long)]
+	golden: Option<PathBuf>,
+
+	/// Difference to the golden reports tolerated, in percent of the golden values
+	#[arg(long, default_value_t = 0.0)]
+	tolerance: f64,
+}
+
+/// Parses a list of schedulers, making sure they can be built.
+fn parse_schedulers(list: &str) -> Result<Vec<SchedulerSpec>, String> {
+	let specs = SchedulerSpec::parse_list(list).map_err(|why| why.to_string())?;
+
+	for spec in &specs {
+		spec.build().map_err(|why| why.to_string())?;
+	}
+
+	Ok(specs)
+}
+
+/// Reports an error that stops the run, without the backtrace hint of a panic.
+fn fail(why: impl Display) -> ! {
+	eprintln!("error: {}", why);
+	process::exit(1)
+}
 
 fn main() {
-    let data_file = "ANL-Intrepid-2009-1.swf";
+	let cli = Cli::parse();
+	let step = matches!(&cli.command, Command::Run(args) if args.step);
+
+	let experiment = match cli.command {
+		Command::Run(args) => Experiment {
+			workloads: args.workload,
+			nodes: args.nodes,
+			cores_per_node: args.cores_per_node,
+			partitions: args.partitions.into_iter().flatten().collect(),
+			node_classes: args.node_classes.into_iter().flatten().collect(),
+			class_map: args.class_map,
+			initial_state: args.initial_state,
+			predictions: args.predictions,
+			node_memory: args.node_memory,
+			reservations: args.reservations.into_iter().flatten().collect(),
+			drains: args.drains.into_iter().flatten().collect(),
+			qos: args.qos.into_iter().flatten().collect(),
+			allocation: args.allocation,
+			schedulers: args.scheduler.into_iter().flatten().collect(),
+			limit: args.limit,
+			warmup: args.warmup,
+			cutoff: args.cutoff,
+			budget: args.budget,
+			slowdown_tau: args.slowdown_tau,
+			stats: args.stats,
+			filter: WorkloadFilter {
+				from_day: args.from_day,
+				to_day: args.to_day,
+				users: args.only_users,
+				min_nodes: args.min_nodes,
+			},
+			load_factors: args.load_factor,
+			scale_widths: args.scale_widths,
+			lenient: args.lenient,
+			preemption_overhead: args.preemption_overhead,
+			walltime: args.walltime,
+			walltime_limit: args.walltime_limit,
+			seed: args.seed,
+			repetitions: args.repetitions,
+			event_order: args.event_order,
+			cycle: args.cycle,
+			decision_latency: args.decision_latency,
+			failures: args.failures,
+			power: args.power,
+			debug: args.debug,
+			progress: args.progress,
+			#[cfg(feature = "metrics")]
+			metrics: args.metrics,
+			dependencies: !args.ignore_dependencies,
+			cancellations: !args.ignore_cancellations,
+			utilization: args.utilization,
+			occupancy: args.occupancy,
+			admission: args.admission,
+			users: args.users,
+			time_slicing: args.time_slicing,
+			malleability: args.malleability,
+			moldability: args.moldability,
+			routing: args.routing,
+			runtime: args.runtime,
+			validation: args.validation,
+			external: args.external,
+			threads: args.threads,
+			compare: args.compare.is_some(),
+			baseline: args.compare.filter(|baseline| !baseline.is_empty()),
+			golden: args.golden,
+			tolerance: args.tolerance,
+			output: OutputConfig {
+				directory: args.output_dir,
+				name: Some(args.experiment_name),
+				reports: args.output,
+				job_log: args.job_log,
+				timeline: args.timeline,
+				decisions: args.decision_log,
+				rejects: args.rejects,
+				comparison: args.comparison,
+				confidence: args.confidence,
+				utilization: args.utilization_log,
+				occupancy: args.occupancy_log,
+				#[cfg(feature = "viz")]
+				gantt: args.gantt,
+				#[cfg(feature = "viz")]
+				gantt_colors: args.gantt_colors,
+			},
+			config_path: None,
+		},
+		Command::Experiment { config } => match Experiment::from_config(&config) {
+			Ok(experiment) => experiment,
+			Err(why) => fail(why),
+		},
+		Command::Generate { model, jobs, nodes, seed, output } => {
+			let workload = SynthSpec { model, jobs, nodes, seed }.generate();
+			if let Err(why) = workload.save_swf(&output) {
+				fail(why);
+			}
+
+			println!("Wrote {} jobs to {}", workload.jobs.len(), output.display());
+			return;
+		}
+		Command::Workload { command: WorkloadCommand::Stats(args) } => {
+			let stats = Workload::load(&args.workload).unwrap_or_else(|why| fail(why)).characterize(args.cores_per_node);
+
+			if args.json {
+				println!("{}", serde_json::to_string_pretty(&stats).expect("WorkloadStats are always serializable"));
+				return;
+			}
+
+			print!("{}", stats);
+			for nodes in args.nodes {
+				println!("on {} nodes: offered load {:.3}, {} jobs too wide", nodes, stats.offered_load(nodes), stats.too_wide(nodes));
+			}
+			return;
+		}
+		Command::Export(args) => {
+			if args.load_factor.is_nan() || args.load_factor <= 0.0 {
+				fail(format!("the load factor must be positive ({} given)", args.load_factor));
+			}
+
+			let options = ExportOptions {
+				filter: WorkloadFilter {
+					from_day: args.from_day,
+					to_day: args.to_day,
+					users: args.only_users,
+					min_nodes: args.min_nodes,
+				},
+				load_factor: args.load_factor,
+				limit: args.limit,
+				procs_per_node: args.cores_per_node,
+				anonymize: args.anonymize,
+			};
+
+			let workload = Workload::load(&args.workload).unwrap_or_else(|why| fail(why)).export(&options);
+			if let Err(why) = workload.save_swf(&args.output) {
+				fail(why);
+			}
+
+			println!("Wrote {} jobs to {}", workload.jobs.len(), args.output.display());
+			return;
+		}
+	};
+
+	init_logs(experiment.debug, cli.log_format);
 
+	if step {
+		let engine = experiment.first_engine().unwrap_or_else(|why| fail(why));
+		match Debugger::new(engine).run(io::stdin().lock(), io::stdout()) {
+			Ok(Some(report)) => println!("{:?}", report),
+			Ok(None) => {}
+			Err(why) => fail(why),
+		}
 
-    let engines: &[Box<dyn Scheduler>] = &[Box::new(algos::FCFS), Box::new(algos::FF), Box::new(algos::SJF), Box::new(algos::FCFSEasy)];
-    let node_counts: &[u32] = &[64, 128, 256, 512, 1024, 2048, 4096, 8192, 16384, 32768, 65536, 131072];
-    
-    let start_time = SystemTime::now();
+		return;
+	}
+	let start_time = SystemTime::now();
 
-    for &node_count in node_counts {
-        for engine in engines.iter() {
-            let mut engine = match Engine::new(&engine, node_count, None, data_file, DebugLevel::Info) {
-                Ok(engine) => engine,
-                Err(why) => panic!("Error during engine initialization: {}", why)
-            };
+	match experiment.run() {
+		Ok(reports) => {
+			for report in &reports {
+				println!("{:?}", report);
+			}
 
-            let report = engine.run();
-            println!("{:?}", report);
-        }
-    }
+			if experiment.compare {
+				println!("\n{}", Comparison::new(&reports, experiment.baseline.as_deref()));
 
-    let ellapsed = start_time.elapsed().unwrap();
-    println!("\n\ntook {}", humantime::format_duration(ellapsed));
+				let confidence = Confidence::new(&reports);
+				if confidence.is_repeated() {
+					println!("{}", confidence);
+				}
+			}
+		}
+		Err(why) => fail(why),
+	}
 
+	let ellapsed = start_time.elapsed().unwrap();
+	println!("\n\ntook {}", humantime::format_duration(ellapsed));
 }
@@ -0,0 +1,393 @@
+//! Python bindings, built with the `pyo3` feature (and `extension-module` for a wheel, see
+//! pyproject.toml). Reports are returned as dictionaries, with the fields of their JSON form.
+//!
+//! ```python
+//! import chamallow
+//!
+//! report = chamallow.simulate("ANL-Intrepid-2009-1.swf", 1024, "easy(backfill_depth=16)", limit=5000)
+//! reports = chamallow.sweep(["ANL-Intrepid-2009-1.swf"], [1024, 2048], ["fcfs", "easy"], walltime="uniform:1:5")
+//!
+//! class Smallest:
+//!     def schedule(self, clock, jobs, cluster):
+//!         fitting = [idx for idx, job in enumerate(jobs) if job["nodes"] <= cluster["available_nodes"]]
+//!         return [min(fitting, key=lambda idx: jobs[idx]["nodes"])] if fitting else []
+//!
+//! engine = chamallow.Engine("ANL-Intrepid-2009-1.swf", 1024, Smallest(), limit=1000)
+//! report = engine.run()
+//! ```
+
+// raised by the code the pyo3 macros generate for functions returning a PyResult
+#![allow(clippy::useless_conversion)]
+
+use std::sync::{Arc, Mutex};
+
+use pyo3::{exceptions::{PyRuntimeError, PyValueError}, prelude::*, types::{PyDict, PyList, PyTuple}};
+
+use crate::sim::{
+	Clock, Engine, EngineConfig, algos::{self, Scheduler, SchedulerSpec}, cluster::{Cluster, ClusterSpec, PartitionSpec},
+	drain::Drain, experiment::ExperimentRunner, initial::InitialState, job::Job, mark::Mark, queue::ReadyQueue, qos::QosClass, report::EngineReport, reservation::Reservation, runtime::Predictions, workload::Workload,
+};
+
+fn value_error<E: ToString>(why: E) -> PyErr {
+	PyValueError::new_err(why.to_string())
+}
+
+fn parse<T: std::str::FromStr>(value: &Bound<'_, PyAny>) -> PyResult<T>
+where T::Err: ToString {
+	value.extract::<String>()?.parse().map_err(value_error)
+}
+
+/// Engine configuration and cluster from keyword arguments, named after the options of
+/// the `run` command.
+fn options(nodes: u32, options: Option<&Bound<'_, PyDict>>) -> PyResult<(EngineConfig, ClusterSpec)> {
+	let mut config = EngineConfig::default();
+
+	let mut cluster = ClusterSpec::new(nodes);
+
+	for (key, value) in options.into_iter().flat_map(|options| options.iter()) {
+		let key = key.extract::<String>()?;
+
+		match key.as_str() {
+			"limit" => config.task_limit = value.extract()?,
+			"from_day" => config.filter.from_day = value.extract()?,
+			"to_day" => config.filter.to_day = value.extract()?,
+			"only_users" => config.filter.users = value.extract()?,
+			"min_nodes" => config.filter.min_nodes = value.extract()?,
+			"load_factor" => config.load_factor = value.extract()?,
+			"scale_widths" => config.scale_widths = value.extract()?,
+			"lenient" => config.lenient = value.extract()?,
+			"warmup" => config.warmup = value.extract()?,
+			"cutoff" => config.cutoff = value.extract()?,
+			"budget" => config.budget = parse(&value)?,
+			"slowdown_tau" => config.slowdown_tau = value.extract()?,
+			"stats" => config.stats = parse(&value)?,
+			"preemption_overhead" => config.preemption_overhead = value.extract()?,
+			"seed" => config.seed = value.extract()?,
+			"dependencies" => config.dependencies = value.extract()?,
+			"cancellations" => config.cancellations = value.extract()?,
+			"validation" => config.validation = value.extract()?,
+			"event_order" => config.event_order = parse(&value)?,
+			"cycle" => config.cycle = parse(&value)?,
+			"decision_latency" => config.decision_latency = Some(parse(&value)?),
+			"walltime" => config.walltime = parse(&value)?,
+			"walltime_limit" => config.walltime_limit = parse(&value)?,
+			"failures" => config.failures = parse(&value)?,
+			"power" => config.power = parse(&value)?,
+			"utilization" => config.utilization = Some(parse(&value)?),
+			"users" => config.users = parse(&value)?,
+			"time_slicing" => config.time_slicing = Some(parse(&value)?),
+			"malleability" => config.malleability = parse(&value)?,
+			"moldability" => config.moldability = parse(&value)?,
+			"routing" => config.routing = parse(&value)?,
+			"runtime" => config.runtime = parse(&value)?,
+			"predictions" => config.predictions = Arc::new(Predictions::read(value.extract::<String>()?).map_err(value_error)?),
+			"initial_state" => config.initial_state = Arc::new(InitialState::read(value.extract::<String>()?).map_err(value_error)?),
+			"admission" => config.admission = value.extract::<Vec<String>>()?.iter().map(|rule| rule.parse()).collect::<Result<_, _>>().map_err(value_error)?,
+			"cores_per_node" => cluster.cores_per_node = value.extract()?,
+			"node_memory" => cluster.node_memory = value.extract()?,
+			"partitions" => cluster.partitions = PartitionSpec::parse_list(&value.extract::<String>()?).map_err(value_error)?,
+			"allocation" => config.allocation = parse(&value)?,
+			"reservations" => config.reservations = Reservation::parse_list(&value.extract::<String>()?).map_err(value_error)?,
+			"drains" => config.drains = Drain::parse_list(&value.extract::<String>()?).map_err(value_error)?,
+			"qos" => config.qos = QosClass::parse_list(&value.extract::<String>()?).map_err(value_error)?,
+			_ => return Err(PyValueError::new_err(format!("unknown option '{}'", key))),
+		}
+	}
+
+	Ok((config, cluster))
+}
+
+/// Reports as dictionaries, through their JSON form.
+fn report_to_py(py: Python<'_>, report: &EngineReport) -> PyResult<PyObject> {
+	let json = serde_json::to_string(report).map_err(|why| PyRuntimeError::new_err(why.to_string()))?;
+	Ok(py.import_bound("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+fn job_to_py<'py>(py: Python<'py>, job: &Job) -> PyResult<Bound<'py, PyDict>> {
+	let dict = PyDict::new_bound(py);
+	dict.set_item("id", job.id)?;
+	dict.set_item("nodes", job.nodes)?;
+	dict.set_item("submit_time", job.submit_time)?;
+	dict.set_item("requested_run_time", job.requested_run_time)?;
+	dict.set_item("expected_end", job.expected_end)?;
+	dict.set_item("user_id", job.user_id)?;
+	dict.set_item("group_id", job.group_id)?;
+	dict.set_item("queue", job.queue)?;
+	dict.set_item("partition", job.partition)?;
+	dict.set_item("qos", job.qos)?;
+	dict.set_item("max_wait", job.max_wait)?;
+	dict.set_item("held_until", job.held_until)?;
+	dict.set_item("priority", job.priority)?;
+	Ok(dict)
+}
+
+/// A scheduler written in Python: an object with a `schedule(clock, jobs, cluster)` method
+/// returning the positions in `jobs` of the jobs to start now, each one fitting on the nodes
+/// the previous ones left. `jobs` is the queue as a list of dictionaries, and `cluster` a
+/// dictionary of the node counts, of the running jobs and of the reservations as
+/// `(start, end, nodes)` tuples. The object may also define
+/// `on_job_arrival(clock, job)`, `on_job_finish(clock, job)`, `on_job_cancel(clock, job)`, `on_tick(clock)`
+/// and `mark(clock, jobs, cluster)`, returning `(job_id, mark)` tuples such as `(12, "hold:3600")`, see `Mark`.
+///
+/// An exception stops the calls to the object, the rest of the run starting the first
+/// fitting job, and is raised once the run returns.
+struct PyScheduler {
+	object: Py<PyAny>,
+	error: Arc<Mutex<Option<PyErr>>>,
+}
+
+impl PyScheduler {
+	fn failed(&self) -> bool {
+		self.error.lock().unwrap().is_some()
+	}
+
+	fn fail(&self, why: PyErr) {
+		self.error.lock().unwrap().get_or_insert(why);
+	}
+
+	fn call_hook(&self, name: &str, args: impl FnOnce(Python<'_>) -> PyResult<Vec<PyObject>>) {
+		if self.failed() {
+			return;
+		}
+
+		let result = Python::with_gil(|py| {
+			let object = self.object.bind(py);
+			if !object.hasattr(name)? {
+				return Ok(());
+			}
+
+			let args = PyTuple::new_bound(py, args(py)?);
+			object.call_method1(name, args).map(|_| ())
+		});
+
+		if let Err(why) = result {
+			self.fail(why);
+		}
+	}
+
+	/// The queue and the state of the cluster, as given to `schedule` and `mark`.
+	fn queue_and_state<'py>(py: Python<'py>, jobs: &ReadyQueue, cluster: &Cluster) -> PyResult<(Bound<'py, PyList>, Bound<'py, PyDict>)> {
+		let queue = PyList::empty_bound(py);
+		for job in jobs.iter() {
+			queue.append(job_to_py(py, job)?)?;
+		}
+
+		let running = PyList::empty_bound(py);
+		for job in cluster.running_jobs.values() {
+			running.append(job_to_py(py, job)?)?;
+		}
+
+		let state = PyDict::new_bound(py);
+		state.set_item("total_nodes", cluster.total_nodes)?;
+		state.set_item("available_nodes", cluster.available_nodes)?;
+		state.set_item("running", running)?;
+		state.set_item("reservations", cluster.reservations().iter().map(|window| (window.start, window.end, window.nodes)).collect::<Vec<_>>())?;
+		Ok((queue, state))
+	}
+
+	fn call_mark(&self, clock: Clock, jobs: &ReadyQueue, cluster: &Cluster) -> PyResult<Vec<(u32, Mark)>> {
+		Python::with_gil(|py| {
+			let object = self.object.bind(py);
+			if !object.hasattr("mark")? {
+				return Ok(Vec::new());
+			}
+
+			let (queue, state) = Self::queue_and_state(py, jobs, cluster)?;
+			let marks = object.call_method1("mark", (clock, queue, state))?;
+			marks.extract::<Option<Vec<(u32, String)>>>()?.unwrap_or_default().into_iter()
+				.map(|(id, mark)| Ok((id, mark.parse().map_err(value_error)?)))
+				.collect()
+		})
+	}
+
+	fn call_schedule(&self, clock: Clock, jobs: &ReadyQueue, cluster: &Cluster) -> PyResult<Vec<usize>> {
+		Python::with_gil(|py| {
+			let (queue, state) = Self::queue_and_state(py, jobs, cluster)?;
+			let picks = self.object.bind(py).call_method1("schedule", (clock, queue, state))?;
+			let picks = picks.extract::<Option<Vec<usize>>>()?.unwrap_or_default();
+
+			match picks.iter().find(|&&idx| idx >= jobs.len()) {
+				Some(idx) => Err(PyValueError::new_err(format!("schedule returned {}, the queue holds {} jobs", idx, jobs.len()))),
+				None => Ok(picks),
+			}
+		})
+	}
+}
+
+impl Scheduler for PyScheduler {
+	fn name(&self) -> &'static str {
+		"Python"
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		self.schedule_batch(clock, jobs, cluster).first().copied()
+	}
+
+	fn schedule_batch(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		if !self.failed() {
+			match self.call_schedule(clock, jobs, cluster) {
+				Ok(picks) => return picks,
+				Err(why) => self.fail(why),
+			}
+		}
+
+		jobs.first_fitting(cluster.available_nodes).into_iter().collect()
+	}
+
+	fn mark(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<(u32, Mark)> {
+		if !self.failed() {
+			match self.call_mark(clock, jobs, cluster) {
+				Ok(marks) => return marks,
+				Err(why) => self.fail(why),
+			}
+		}
+
+		Vec::new()
+	}
+
+	fn on_job_arrival(&mut self, clock: u64, job: &Job) {
+		self.call_hook("on_job_arrival", |py| Ok(vec![clock.into_py(py), job_to_py(py, job)?.into_any().unbind()]));
+	}
+
+	fn on_job_finish(&mut self, clock: u64, job: &Job) {
+		self.call_hook("on_job_finish", |py| Ok(vec![clock.into_py(py), job_to_py(py, job)?.into_any().unbind()]));
+	}
+
+	fn on_job_cancel(&mut self, clock: u64, job: &Job) {
+		self.call_hook("on_job_cancel", |py| Ok(vec![clock.into_py(py), job_to_py(py, job)?.into_any().unbind()]));
+	}
+
+	fn on_tick(&mut self, clock: u64) {
+		self.call_hook("on_tick", |py| Ok(vec![clock.into_py(py)]));
+	}
+}
+
+/// A scheduler spec like `"easy(backfill_depth=16)"`, or a Python object, see `PyScheduler`.
+fn scheduler(scheduler: &Bound<'_, PyAny>, error: &Arc<Mutex<Option<PyErr>>>) -> PyResult<Box<dyn Scheduler>> {
+	match scheduler.extract::<String>() {
+		Ok(spec) => spec.parse::<SchedulerSpec>().and_then(|spec| spec.build()).map_err(value_error),
+		Err(_) => Ok(Box::new(PyScheduler {
+			object: scheduler.clone().unbind(),
+			error: error.clone(),
+		})),
+	}
+}
+
+/// A simulation that can be run in steps, see `Engine`.
+#[pyclass(name = "Engine", unsendable)]
+struct PyEngine {
+	engine: Engine,
+	error: Arc<Mutex<Option<PyErr>>>,
+}
+
+impl PyEngine {
+	fn check(&self) -> PyResult<()> {
+		match self.error.lock().unwrap().take() {
+			Some(why) => Err(why),
+			None => Ok(()),
+		}
+	}
+}
+
+#[pymethods]
+impl PyEngine {
+	#[new]
+	#[pyo3(signature = (workload, nodes, scheduler, **options))]
+	fn new(workload: &str, nodes: u32, scheduler: &Bound<'_, PyAny>, options: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+		let (config, cluster) = self::options(nodes, options)?;
+		let error = Arc::default();
+		let scheduler = self::scheduler(scheduler, &error)?;
+		let engine = Engine::new(scheduler, cluster, workload, config).map_err(value_error)?;
+		Ok(Self { engine, error })
+	}
+
+	/// Simulates the events happening until `time` included, returning whether work is left.
+	fn run_until(&mut self, time: Clock) -> PyResult<bool> {
+		let more = self.engine.run_until(time).map_err(value_error)?;
+		self.check()?;
+		Ok(more)
+	}
+
+	/// Simulates the rest of the workload and returns the report of the run.
+	fn run(&mut self, py: Python<'_>) -> PyResult<PyObject> {
+		let report = self.engine.run().map_err(value_error)?;
+		self.check()?;
+		report_to_py(py, &report)
+	}
+}
+
+/// Simulates a workload on a cluster of `nodes` nodes and returns the report of the run.
+#[pyfunction]
+#[pyo3(signature = (workload, nodes, scheduler, **options))]
+fn simulate(py: Python<'_>, workload: &str, nodes: u32, scheduler: &Bound<'_, PyAny>, options: Option<&Bound<'_, PyDict>>) -> PyResult<PyObject> {
+	PyEngine::new(workload, nodes, scheduler, options)?.run(py)
+}
+
+/// Simulates every workload for every (node count, scheduler spec) combination in parallel,
+/// `repetitions` times with the seeds following the `seed` option, and returns the reports
+/// ordered by workload, node count, scheduler, then seed.
+#[pyfunction]
+#[pyo3(signature = (workloads, nodes, schedulers, threads = None, repetitions = 1, **options))]
+fn sweep(py: Python<'_>, workloads: Vec<String>, nodes: Vec<u32>, schedulers: Vec<String>, threads: Option<usize>, repetitions: usize, options: Option<&Bound<'_, PyDict>>) -> PyResult<Vec<PyObject>> {
+	let (config, cluster) = self::options(0, options)?;
+	let specs = schedulers.iter()
+		.map(|spec| spec.parse::<SchedulerSpec>().and_then(|spec| spec.build().map(|_| spec)))
+		.collect::<Result<Vec<_>, _>>()
+		.map_err(value_error)?;
+
+	let mut reports = Vec::new();
+	for workload in &workloads {
+		let mut runner = ExperimentRunner::new(workload, config.clone())
+			.node_counts(&nodes)
+			.partitions(&cluster.partitions)
+			.repetitions(repetitions);
+
+		for spec in &specs {
+			let spec = spec.clone();
+			runner = runner.scheduler(move || spec.build().expect("schedulers are checked beforehand"));
+		}
+
+		if let Some(cores_per_node) = cluster.cores_per_node {
+			runner = runner.cores_per_node(cores_per_node);
+		}
+
+		if let Some(node_memory) = cluster.node_memory {
+			runner = runner.node_memory(node_memory);
+		}
+
+		if let Some(threads) = threads {
+			runner = runner.threads(threads);
+		}
+
+		for report in py.allow_threads(|| runner.run()) {
+			reports.push(report_to_py(py, &report.map_err(value_error)?)?);
+		}
+	}
+
+	Ok(reports)
+}
+
+/// Statistics of a workload as a dictionary, computed without simulating it, see `WorkloadStats`.
+#[pyfunction]
+#[pyo3(signature = (workload, cores_per_node = None))]
+fn workload_stats(py: Python<'_>, workload: &str, cores_per_node: Option<u32>) -> PyResult<PyObject> {
+	let stats = Workload::load(workload).map_err(value_error)?.characterize(cores_per_node);
+	let json = serde_json::to_string(&stats).map_err(|why| PyRuntimeError::new_err(why.to_string()))?;
+	Ok(py.import_bound("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+/// Names of the schedulers that can be given as specs.
+#[pyfunction]
+fn schedulers() -> Vec<&'static str> {
+	algos::names().collect()
+}
+
+#[pymodule]
+fn chamallow(module: &Bound<'_, PyModule>) -> PyResult<()> {
+	module.add_class::<PyEngine>()?;
+	module.add_function(wrap_pyfunction!(simulate, module)?)?;
+	module.add_function(wrap_pyfunction!(sweep, module)?)?;
+	module.add_function(wrap_pyfunction!(schedulers, module)?)?;
+	module.add_function(wrap_pyfunction!(workload_stats, module)?)?;
+	Ok(())
+}
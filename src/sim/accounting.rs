@@ -0,0 +1,306 @@
+use std::{io::BufRead, time::UNIX_EPOCH};
+
+use hashbrown::{HashMap, HashSet};
+
+use super::{Clock, EngineError, workload::{SwfJob, SwfStatus, WorkloadMeta}};
+
+/// A job as an accounting log tells it, times being Unix times.
+#[derive(Debug, Default)]
+struct Record {
+	id: Option<u32>,
+	submit: i64,
+	start: Option<i64>,
+	end: Option<i64>,
+	/// Time the job ran, from the log or from its start and end
+	elapsed: Option<Clock>,
+	limit: Option<Clock>,
+	nodes: Option<u32>,
+	cpus: Option<u32>,
+	user: Option<String>,
+	group: Option<String>,
+	/// Slurm partition or PBS queue
+	queue: Option<String>,
+	status: Option<SwfStatus>,
+}
+
+/// Whether the first line of a file is the header of `sacct --parsable2` (or `--parsable`).
+pub fn is_sacct_header(line: &str) -> bool {
+	line.contains('|') && line.split('|').any(|column| column.trim().eq_ignore_ascii_case("JobID"))
+}
+
+/// Whether a line is a record of a PBS (Torque, OpenPBS or PBS Pro) accounting log, which
+/// starts with `MM/DD/YYYY HH:MM:SS;`.
+pub fn is_pbs_record(line: &str) -> bool {
+	let date = line.split(';').next().unwrap_or("").as_bytes();
+	line.split(';').count() >= 4 && date.len() == 19
+		&& date.iter().enumerate().all(|(idx, &byte)| match idx {
+			2 | 5 => byte == b'/',
+			10 => byte == b' ',
+			13 | 16 => byte == b':',
+			_ => byte.is_ascii_digit(),
+		})
+}
+
+/// Reads the output of `sacct --parsable2` (or `--parsable`) as SWF records, sorted by
+/// submission time, e.g. from `sacct -a -X -S 2024-01-01 -E 2024-02-01 --parsable2
+/// -o JobIDRaw,User,Group,Partition,Submit,Start,End,Elapsed,Timelimit,NNodes,NCPUS,State`.
+///
+/// Columns are found by name in the header line, `JobID` and `Submit` being required. Job
+/// steps (`1234.batch`) are left out. Jobs without an end (pending or still running) are
+/// kept without a run time, so that the engine counts them as incomplete records, and jobs
+/// cancelled before they started are kept as cancelled. Dates are taken as UTC, which
+/// only matters for the time of day of the jobs.
+pub fn read_sacct<R: BufRead>(reader: R) -> Result<(WorkloadMeta, Vec<SwfJob>), EngineError> {
+	let mut lines = reader.lines();
+	let header = lines.next().transpose()?.unwrap_or_default();
+	if !is_sacct_header(&header) {
+		return Err(EngineError::InvalidWorkload("the sacct output has no header line (use --parsable2 without --noheader)".to_string()));
+	}
+
+	let columns = header.split('|').map(|column| column.trim().to_ascii_lowercase()).collect::<Vec<_>>();
+	let column = |names: &[&str]| names.iter().find_map(|name| columns.iter().position(|column| column == name));
+	let job_id = column(&["jobid"]).expect("checked by is_sacct_header");
+	let job_id_raw = column(&["jobidraw"]);
+	let submit = column(&["submit"])
+		.ok_or_else(|| EngineError::InvalidWorkload("the sacct output has no Submit column".to_string()))?;
+	let (start, end, elapsed, limit) = (column(&["start"]), column(&["end"]), column(&["elapsed", "elapsedraw"]), column(&["timelimit"]));
+	let (nodes, cpus) = (column(&["nnodes", "allocnodes", "reqnodes"]), column(&["ncpus", "alloccpus", "reqcpus"]));
+	let (user, group, partition, state) = (column(&["user"]), column(&["group"]), column(&["partition"]), column(&["state"]));
+
+	let mut records = Vec::new();
+	for (idx, line) in lines.enumerate() {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let fields = line.trim_end_matches('|').split('|').collect::<Vec<_>>();
+		let field = |column: Option<usize>| column.and_then(|column| fields.get(column)).map(|field| field.trim()).filter(|field| !field.is_empty());
+		let id = field(Some(job_id)).ok_or_else(|| EngineError::InvalidWorkload(format!("line {} of the sacct output has no JobID", idx + 2)))?;
+		if id.contains('.') {
+			continue;
+		}
+
+		let submit = match field(Some(submit)).and_then(date) {
+			Some(submit) => submit,
+			None => continue,
+		};
+		let start = field(start).and_then(date);
+		let end = field(end).and_then(date);
+		let ran = start.zip(end).map(|(start, end)| (end - start).max(0) as Clock);
+
+		records.push(Record {
+			id: field(job_id_raw).unwrap_or(id).parse().ok(),
+			submit,
+			start,
+			end,
+			elapsed: ran.and(field(elapsed).and_then(duration)).or(ran),
+			limit: field(limit).and_then(duration),
+			nodes: field(nodes).and_then(|nodes| nodes.parse().ok()),
+			cpus: field(cpus).and_then(|cpus| cpus.parse().ok()),
+			user: field(user).map(str::to_string),
+			group: field(group).map(str::to_string),
+			queue: field(partition).map(str::to_string),
+			status: field(state).and_then(sacct_status),
+		});
+	}
+
+	Ok(to_swf(records, false))
+}
+
+/// Reads a PBS (Torque, OpenPBS or PBS Pro) accounting log as SWF records, sorted by
+/// submission time.
+///
+/// Only the `E` records, written when jobs end, are read: jobs deleted before they started
+/// are left out. Jobs are submitted at their `qtime` (`ctime` if missing), sized from
+/// `Resource_List.nodect`, the chunks of `Resource_List.select` or `Resource_List.nodes`
+/// (e.g. `2:ppn=8`), or the hosts of `exec_host`, and fail unless their `Exit_status` is 0.
+pub fn read_pbs<R: BufRead>(reader: R) -> Result<(WorkloadMeta, Vec<SwfJob>), EngineError> {
+	let mut records = Vec::new();
+
+	for (idx, line) in reader.lines().enumerate() {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let fields = line.splitn(4, ';').collect::<Vec<_>>();
+		if fields.len() < 4 {
+			return Err(EngineError::InvalidWorkload(format!("line {} of the PBS log isn't DATE;TYPE;ID;ATTRIBUTES", idx + 1)));
+		}
+		if fields[1] != "E" {
+			continue;
+		}
+
+		let attributes = fields[3].split_whitespace().filter_map(|attribute| attribute.split_once('=')).collect::<HashMap<_, _>>();
+		let time = |key: &str| attributes.get(key).and_then(|time| time.parse::<i64>().ok()).filter(|&time| time > 0);
+		let submit = match time("qtime").or_else(|| time("ctime")) {
+			Some(submit) => submit,
+			None => continue,
+		};
+		let (start, end) = (time("start"), time("end"));
+
+		let chunks = attributes.get("Resource_List.select").or_else(|| attributes.get("Resource_List.nodes")).map(|spec| chunks(spec));
+		let hosts = attributes.get("exec_host").map(|hosts| {
+			hosts.split('+').map(|host| host.split('/').next().unwrap_or(host)).collect::<HashSet<_>>().len() as u32
+		});
+
+		records.push(Record {
+			id: leading_number(fields[2]),
+			submit,
+			start,
+			end,
+			elapsed: attributes.get("resources_used.walltime").and_then(|elapsed| duration(elapsed))
+				.or_else(|| start.zip(end).map(|(start, end)| (end - start).max(0) as Clock)),
+			limit: attributes.get("Resource_List.walltime").and_then(|limit| duration(limit)),
+			nodes: attributes.get("Resource_List.nodect").and_then(|nodes| nodes.parse().ok())
+				.or(chunks.map(|(nodes, _)| nodes))
+				.or(hosts),
+			cpus: attributes.get("Resource_List.ncpus").and_then(|cpus| cpus.parse().ok())
+				.or(chunks.and_then(|(_, cpus)| cpus)),
+			user: attributes.get("user").map(|user| user.to_string()),
+			group: attributes.get("group").map(|group| group.to_string()),
+			queue: attributes.get("queue").map(|queue| queue.to_string()),
+			status: attributes.get("Exit_status").and_then(|status| status.parse::<i64>().ok())
+				.map(|status| if status == 0 { SwfStatus::Completed } else { SwfStatus::Failed }),
+		});
+	}
+
+	Ok(to_swf(records, true))
+}
+
+/// Turns the records into SWF records submitted from 0, numbered by their IDs if they are
+/// unique and from 1 in order of submission otherwise (e.g. for array jobs). Users, groups
+/// and queues are numbered from 1 in order of first appearance, queues being SWF queues for
+/// PBS and SWF partitions for Slurm.
+///
+/// Jobs ask for whole nodes: the header tells as many processors per node as the widest
+/// job per node of the log, and as many nodes as the log keeps busy at once at most, the
+/// size of the traced machine being unknown. Jobs without a node count ask for their CPUs.
+fn to_swf(mut records: Vec<Record>, queues: bool) -> (WorkloadMeta, Vec<SwfJob>) {
+	records.sort_by_key(|record| record.submit);
+	let origin = records.first().map_or(0, |record| record.submit);
+
+	let mut ids = HashSet::new();
+	let keep_ids = records.iter().all(|record| record.id.is_some_and(|id| ids.insert(id)));
+
+	let procs_per_node = records.iter()
+		.filter_map(|record| Some(record.cpus?.div_ceil(record.nodes.filter(|&nodes| nodes > 0)?)))
+		.max()
+		.filter(|&procs| procs > 0);
+
+	let mut names: [HashMap<String, u32>; 3] = Default::default();
+	let mut number = |kind: usize, name: &Option<String>| name.as_ref().map(|name| {
+		let next = names[kind].len() as u32 + 1;
+		*names[kind].entry(name.clone()).or_insert(next)
+	});
+
+	let jobs = records.iter().enumerate().map(|(idx, record)| {
+		let queue = number(2, &record.queue);
+		let whole_nodes = record.nodes.zip(procs_per_node).map(|(nodes, procs)| nodes * procs);
+
+		SwfJob {
+			job_number: record.id.filter(|_| keep_ids).unwrap_or(idx as u32 + 1),
+			submit_time: (record.submit - origin) as Clock,
+			wait_time: record.start.or(record.end).map(|start| (start - record.submit).max(0) as Clock),
+			run_time: record.elapsed,
+			allocated_procs: record.cpus.or(whole_nodes),
+			avg_cpu_time: None,
+			used_memory: None,
+			requested_procs: whole_nodes.or(record.cpus),
+			requested_time: record.limit,
+			requested_memory: None,
+			status: record.status,
+			user_id: number(0, &record.user),
+			group_id: number(1, &record.group),
+			executable: None,
+			queue: queue.filter(|_| queues),
+			partition: queue.filter(|_| !queues),
+			preceding_job: None,
+			think_time: None,
+		}
+	}).collect::<Vec<_>>();
+
+	let mut changes = records.iter()
+		.filter_map(|record| Some((record.start?, record.end?, record.nodes?)))
+		.flat_map(|(start, end, nodes)| [(start, nodes as i64), (end, -(nodes as i64))])
+		.collect::<Vec<_>>();
+	changes.sort_by_key(|&(time, change)| (time, change));
+	let busy_nodes = changes.iter()
+		.scan(0, |busy, &(_, change)| {
+			*busy += change;
+			Some(*busy)
+		})
+		.max()
+		.filter(|&busy| busy > 0)
+		.map(|busy| busy as u32);
+
+	// as header entries, for `export` to keep them
+	let mut meta = WorkloadMeta::default();
+	meta.parse_comment(&format!("MaxJobs: {}", jobs.len()));
+	meta.parse_comment(&format!("MaxRecords: {}", jobs.len()));
+	if let Some((nodes, procs)) = busy_nodes.zip(procs_per_node) {
+		meta.parse_comment(&format!("MaxNodes: {}", nodes));
+		meta.parse_comment(&format!("MaxProcs: {}", nodes * procs));
+	}
+	meta.parse_comment(&format!("UnixStartTime: {}", origin));
+
+	(meta, jobs)
+}
+
+/// Unix time of a sacct date such as `2024-01-15T10:23:45`, None for `Unknown` or `None`.
+fn date(date: &str) -> Option<i64> {
+	let time = humantime::parse_rfc3339_weak(date).ok()?;
+	Some(time.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+/// Seconds of a duration written `[DAYS-][[HH:]MM:]SS`, or a plain number of seconds (e.g.
+/// `ElapsedRaw`), None for `UNLIMITED`, `Partition_Limit` and the like.
+fn duration(duration: &str) -> Option<Clock> {
+	let (days, time) = match duration.split_once('-') {
+		Some((days, time)) => (days.parse::<Clock>().ok()?, time),
+		None => (0, duration),
+	};
+
+	let mut seconds = 0;
+	for part in time.split(':') {
+		seconds = seconds * 60 + part.parse::<Clock>().ok()?;
+	}
+	Some(days * 86400 + seconds)
+}
+
+/// The SWF status of a Slurm job state, None for the jobs that didn't end.
+fn sacct_status(state: &str) -> Option<SwfStatus> {
+	match state.split_whitespace().next()? {
+		"COMPLETED" => Some(SwfStatus::Completed),
+		"CANCELLED" => Some(SwfStatus::Cancelled),
+		"FAILED" | "TIMEOUT" | "NODE_FAIL" | "OUT_OF_MEMORY" | "BOOT_FAIL" | "DEADLINE" | "PREEMPTED" => Some(SwfStatus::Failed),
+		_ => None,
+	}
+}
+
+/// Nodes and CPUs of a PBS chunk list such as `2:ppn=8+1:ppn=4` (Torque) or `2:ncpus=8`
+/// (PBS Pro), chunks naming a host counting as one node.
+fn chunks(spec: &str) -> (u32, Option<u32>) {
+	let mut nodes = 0;
+	let mut cpus = Some(0);
+
+	for chunk in spec.split('+') {
+		let mut parts = chunk.split(':');
+		let count = parts.next().and_then(|count| count.parse().ok()).unwrap_or(1);
+		let per_node = parts.filter_map(|part| part.split_once('='))
+			.find(|&(key, _)| key == "ppn" || key == "ncpus")
+			.and_then(|(_, cpus)| cpus.parse::<u32>().ok());
+
+		nodes += count;
+		cpus = cpus.zip(per_node).map(|(cpus, per_node)| cpus + count * per_node);
+	}
+
+	(nodes, cpus)
+}
+
+/// The number a PBS job ID starts with, e.g. 1234 for `1234.server` or `1234[5].server`.
+fn leading_number(id: &str) -> Option<u32> {
+	let end = id.find(|c: char| !c.is_ascii_digit()).unwrap_or(id.len());
+	id[..end].parse().ok()
+}
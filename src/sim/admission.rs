@@ -0,0 +1,201 @@
+use std::{convert::TryFrom, fmt::Display, str::FromStr};
+
+use serde::Deserialize;
+
+use super::{Clock, job::Job};
+
+/// What an admission policy decides for a submitted job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+	/// Let the job through, to the partition it is routed to
+	Accept,
+	/// Let the job through, to the partition at the given index instead
+	Reroute(usize),
+	/// Drop the job, the reason being counted in the report
+	Reject(&'static str),
+}
+
+/// A partition, as seen by the admission policies.
+#[derive(Debug, Clone, Copy)]
+pub struct PartitionState<'a> {
+	pub name: &'a str,
+	pub nodes: u32,
+	pub available_nodes: u32,
+	/// Jobs waiting in its queue
+	pub queued: usize,
+}
+
+/// A job being submitted, and the state of the machine at that time.
+#[derive(Debug, Clone, Copy)]
+pub struct Submission<'a> {
+	pub clock: Clock,
+	/// Index of the partition the job is routed to so far, None if no partition accepts it
+	pub route: Option<usize>,
+	pub partitions: &'a [PartitionState<'a>],
+}
+
+/// Decides whether submitted jobs enter the queues, as batch systems do with their limits.
+///
+/// Policies are called in order on each submitted job and may change it (e.g. truncate
+/// its requested time), send it to another partition or reject it, the first rejection
+/// ending the chain. Jobs that no partition accepts or that are wider than their
+/// partition are rejected once every policy accepted them.
+pub trait AdmissionPolicy: Send {
+	fn name(&self) -> &'static str;
+
+	fn admit(&mut self, job: &mut Job, submission: &Submission) -> Verdict;
+}
+
+/// Rejects the jobs requesting more than the given number of nodes.
+pub struct MaxNodes(pub u32);
+
+impl AdmissionPolicy for MaxNodes {
+	fn name(&self) -> &'static str {
+		"max_nodes"
+	}
+
+	fn admit(&mut self, job: &mut Job, _submission: &Submission) -> Verdict {
+		match job.nodes > self.0 {
+			true => Verdict::Reject("too_many_nodes"),
+			false => Verdict::Accept,
+		}
+	}
+}
+
+/// Rejects the jobs submitted while the queue of their partition holds the given number of jobs.
+pub struct MaxQueue(pub usize);
+
+impl AdmissionPolicy for MaxQueue {
+	fn name(&self) -> &'static str {
+		"max_queue"
+	}
+
+	fn admit(&mut self, _job: &mut Job, submission: &Submission) -> Verdict {
+		match submission.route {
+			Some(idx) if submission.partitions[idx].queued >= self.0 => Verdict::Reject("queue_full"),
+			_ => Verdict::Accept,
+		}
+	}
+}
+
+/// Truncates the requested time of the jobs to the given limit, the jobs running longer
+/// being killed once they reach it.
+pub struct MaxWalltime(pub Clock);
+
+impl AdmissionPolicy for MaxWalltime {
+	fn name(&self) -> &'static str {
+		"max_walltime"
+	}
+
+	fn admit(&mut self, job: &mut Job, _submission: &Submission) -> Verdict {
+		job.requested_run_time = job.requested_run_time.min(self.0);
+		job.run_time = job.run_time.min(self.0);
+		job.remaining_time = job.run_time;
+		Verdict::Accept
+	}
+}
+
+/// Truncates the jobs wider than their partition to the whole partition.
+pub struct Shrink;
+
+impl AdmissionPolicy for Shrink {
+	fn name(&self) -> &'static str {
+		"shrink"
+	}
+
+	fn admit(&mut self, job: &mut Job, submission: &Submission) -> Verdict {
+		if let Some(idx) = submission.route {
+			job.nodes = job.nodes.min(submission.partitions[idx].nodes);
+		}
+
+		Verdict::Accept
+	}
+}
+
+/// Sends the jobs that no partition accepts, or wider than their partition, to the partition
+/// with the given name if they fit there.
+pub struct Reroute(pub String);
+
+impl AdmissionPolicy for Reroute {
+	fn name(&self) -> &'static str {
+		"reroute"
+	}
+
+	fn admit(&mut self, job: &mut Job, submission: &Submission) -> Verdict {
+		let fits = |idx: usize| job.nodes <= submission.partitions[idx].nodes;
+		if submission.route.is_some_and(fits) {
+			return Verdict::Accept;
+		}
+
+		match submission.partitions.iter().position(|partition| partition.name == self.0) {
+			Some(idx) if fits(idx) => Verdict::Reroute(idx),
+			_ => Verdict::Accept,
+		}
+	}
+}
+
+/// A built-in admission policy, as written in the options.
+///
+/// Written `max_nodes:N`, `max_queue:N`, `max_walltime:SECONDS`, `shrink` or `reroute:PARTITION`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum AdmissionRule {
+	MaxNodes(u32),
+	MaxQueue(usize),
+	MaxWalltime(Clock),
+	Shrink,
+	Reroute(String),
+}
+
+impl AdmissionRule {
+	pub fn build(&self) -> Box<dyn AdmissionPolicy> {
+		match self {
+			AdmissionRule::MaxNodes(nodes) => Box::new(MaxNodes(*nodes)),
+			AdmissionRule::MaxQueue(jobs) => Box::new(MaxQueue(*jobs)),
+			AdmissionRule::MaxWalltime(walltime) => Box::new(MaxWalltime(*walltime)),
+			AdmissionRule::Shrink => Box::new(Shrink),
+			AdmissionRule::Reroute(partition) => Box::new(Reroute(partition.clone())),
+		}
+	}
+}
+
+impl FromStr for AdmissionRule {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid admission policy '{}' (expected max_nodes:N, max_queue:N, max_walltime:SECONDS, shrink or reroute:PARTITION)", s);
+
+		let parts = s.split(':').map(str::trim).collect::<Vec<_>>();
+		match parts.as_slice() {
+			["max_nodes", nodes] => nodes.parse().map(AdmissionRule::MaxNodes).map_err(|_| invalid()),
+			["max_queue", jobs] => jobs.parse().map(AdmissionRule::MaxQueue).map_err(|_| invalid()),
+			["max_walltime", walltime] => match walltime.parse() {
+				Ok(walltime) if walltime > 0 => Ok(AdmissionRule::MaxWalltime(walltime)),
+				_ => Err(invalid()),
+			},
+			["shrink"] => Ok(AdmissionRule::Shrink),
+			["reroute", partition] if !partition.is_empty() => Ok(AdmissionRule::Reroute(partition.to_string())),
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for AdmissionRule {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl Display for AdmissionRule {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			AdmissionRule::MaxNodes(nodes) => write!(f, "max_nodes:{}", nodes),
+			AdmissionRule::MaxQueue(jobs) => write!(f, "max_queue:{}", jobs),
+			AdmissionRule::MaxWalltime(walltime) => write!(f, "max_walltime:{}", walltime),
+			AdmissionRule::Shrink => write!(f, "shrink"),
+			AdmissionRule::Reroute(partition) => write!(f, "reroute:{}", partition),
+		}
+	}
+}
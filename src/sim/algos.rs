@@ -1,10 +1,324 @@
-use super::{cluster::Cluster, job::Job};
+use std::{cmp::Reverse, collections::BTreeMap, fmt::Display, str::FromStr};
 
-pub trait Scheduler {
+use hashbrown::HashMap;
+use rand::Rng;
+
+use super::{Clock, cluster::{self, Cluster}, decision::{self, Decision, Reason}, job::Job, mark::Mark, profile::Profile, queue::ReadyQueue, rng::{SimRng, Stream}, view::SimView};
+
+/// Picks the next job to start. Implement it to plug a new policy into the engine, which
+/// owns it boxed (see `Engine::new`). Every call takes `&mut self`, so schedulers keep
+/// their state in plain fields.
+pub trait Scheduler: Send {
 	fn name(&self) -> &'static str;
-	fn schedule(&self, clock: u64, jobs: &Vec<Job>, cluster: &Cluster) -> Option<usize>;
+
+	/// Name along with the parameters that differ from their defaults, e.g.
+	/// `EasyBackfill(backfill_depth=16)`, telling apart the runs of a scheduler configured
+	/// differently in the reports. Defaults to the name.
+	fn label(&self) -> String {
+		self.name().to_string()
+	}
+
+	/// Index in `jobs` (the queue, by submission order) of the job to start now,
+	/// or None to wait for the next event. The queue also offers indexed lookups, see `ReadyQueue`.
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize>;
+
+	/// Indices in `jobs` of jobs to start now, in the order they start, each job fitting
+	/// on the nodes the previous ones left. Defaults to the single job picked by `schedule`.
+	fn schedule_batch(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		self.schedule(clock, jobs, cluster).into_iter().collect()
+	}
+
+	/// Like `schedule_batch`, along with the configuration each job starts with if it is
+	/// moldable, as an index in `Job::shapes`, None keeping its traced number of nodes.
+	/// Defaults to the jobs of `schedule_batch` on their traced number of nodes.
+	fn schedule_shaped(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<(usize, Option<usize>)> {
+		self.schedule_batch(clock, jobs, cluster).into_iter().map(|idx| (idx, None)).collect()
+	}
+
+	/// Whether `schedule_batch` returns every job that can start now, so the engine calls it
+	/// once per event, instead of until it returns no job. Schedulers able to plan every
+	/// start at once avoid being called once per job this way.
+	fn complete_batches(&self) -> bool {
+		false
+	}
+
+	/// Running jobs to stop before scheduling, which go back to the queue with the run time
+	/// they have left. Only called when the engine allows preemption.
+	fn preempt(&mut self, _clock: u64, _jobs: &ReadyQueue, _cluster: &Cluster) -> Vec<u32> {
+		Vec::new()
+	}
+
+	/// New numbers of nodes of running malleable jobs (see `Job::malleable`), as (job ID,
+	/// nodes), applied before scheduling within the bounds of each job. Only called when the
+	/// engine simulates malleable jobs, see `Malleability`.
+	fn resize(&mut self, _clock: u64, _jobs: &ReadyQueue, _cluster: &Cluster) -> Vec<(u32, u32)> {
+		Vec::new()
+	}
+
+	/// Marks to apply to queued jobs (as job ID, mark) before scheduling, holding them in
+	/// the queue or changing their priority until the scheduler marks them again, see `Mark`.
+	/// The engine doesn't start held jobs even if `schedule` picks them.
+	fn mark(&mut self, _clock: u64, _jobs: &ReadyQueue, _cluster: &Cluster) -> Vec<(u32, Mark)> {
+		Vec::new()
+	}
+
+	/// Why each job of the queue is waiting, once `schedule` started every job it could.
+	/// Only called when the engine records its decisions.
+	fn explain(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<Decision> {
+		decision::explain_by_nodes(jobs, cluster)
+	}
+
+	/// Called when a job enters the queue of its partition (`job.partition_idx`), including
+	/// jobs preempted or killed by a failure, for schedulers keeping their own bookkeeping.
+	fn on_job_arrival(&mut self, _clock: u64, _job: &Job) {}
+
+	/// Called when a job finished, before the next scheduling pass.
+	fn on_job_finish(&mut self, _clock: u64, _job: &Job) {}
+
+	/// Called when a queued job is cancelled, see `Job::cancel_time`, and leaves the queue.
+	fn on_job_cancel(&mut self, _clock: u64, _job: &Job) {}
+
+	/// Called each time the simulated time moves forward, before the events of the new time.
+	/// The state of schedulers isn't part of engine snapshots.
+	fn on_tick(&mut self, _clock: u64) {}
+
+	/// Called once before the simulation with the random number generator of the run, which
+	/// schedulers drawing at random take their draws from so that the seed of the run decides them.
+	fn seed(&mut self, _rng: &SimRng) {}
+}
+
+/// Construction of a scheduler from the parameters given along its name.
+pub trait FromParams: Sized {
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchedulerError {
+	UnknownScheduler(String),
+	/// The scheduler exists, in builds with the given feature
+	MissingFeature(String, &'static str),
+	InvalidSpec(String),
+	UnknownParam(String),
+	InvalidParam(String, String),
+	/// The Rhai policy of a `script` scheduler doesn't load, see `script::Policy`
+	#[cfg(feature = "script")]
+	InvalidScript(String, String),
+}
+
+impl Display for SchedulerError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SchedulerError::UnknownScheduler(name) => write!(f, "Unknown scheduler '{}', expected one of {}", name, names().collect::<Vec<_>>().join(", ")),
+			SchedulerError::MissingFeature(name, feature) => write!(f, "The '{}' scheduler needs chamallow built with --features {}", name, feature),
+			SchedulerError::InvalidSpec(spec) => write!(f, "Invalid scheduler '{}', expected name(param=value, ...)", spec),
+			SchedulerError::UnknownParam(param) => write!(f, "Unknown scheduler parameter '{}'", param),
+			SchedulerError::InvalidParam(param, value) => write!(f, "Invalid value '{}' for scheduler parameter '{}'", value, param),
+			#[cfg(feature = "script")]
+			SchedulerError::InvalidScript(file, why) => write!(f, "Invalid policy {}: {}", file, why),
+		}
+	}
+}
+
+/// Parameters of a scheduler, as `key=value` pairs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchedulerParams(BTreeMap<String, String>);
+
+impl SchedulerParams {
+	pub fn insert(&mut self, key: &str, value: &str) {
+		self.0.insert(key.to_string(), value.to_string());
+	}
+
+	pub fn get<T: FromStr>(&self, key: &str) -> Result<Option<T>, SchedulerError> {
+		match self.0.get(key) {
+			Some(value) => value.parse()
+				.map(Some)
+				.map_err(|_| SchedulerError::InvalidParam(key.to_string(), value.clone())),
+			None => Ok(None),
+		}
+	}
+
+	pub fn get_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, SchedulerError> {
+		Ok(self.get(key)?.unwrap_or(default))
+	}
+
+	/// Fails if any parameter isn't in the given list.
+	pub fn allow_only(&self, keys: &[&str]) -> Result<(), SchedulerError> {
+		match self.0.keys().find(|key| !keys.contains(&key.as_str())) {
+			Some(key) => Err(SchedulerError::UnknownParam(key.clone())),
+			None => Ok(()),
+		}
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.0.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+	}
+}
+
+/// A scheduler name along with its parameters, written `name` or `name(param=value, ...)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerSpec {
+	pub name: String,
+	pub params: SchedulerParams,
+}
+
+impl SchedulerSpec {
+	pub fn build(&self) -> Result<Box<dyn Scheduler>, SchedulerError> {
+		match REGISTRY.iter().find(|(name, _)| name.eq_ignore_ascii_case(&self.name)) {
+			Some((_, constructor)) => constructor(&self.params),
+			#[cfg(not(feature = "script"))]
+			None if self.name.eq_ignore_ascii_case("script") => Err(SchedulerError::MissingFeature(self.name.clone(), "script")),
+			None => Err(SchedulerError::UnknownScheduler(self.name.clone())),
+		}
+	}
+
+	/// Parses a comma separated list of specs, commas between parentheses separating parameters.
+	pub fn parse_list(list: &str) -> Result<Vec<Self>, SchedulerError> {
+		split_top_level(list).into_iter().map(str::parse).collect()
+	}
+}
+
+/// Splits at the commas outside of parentheses, which belong to nested specs.
+fn split_top_level(list: &str) -> Vec<&str> {
+	let mut parts = Vec::new();
+	let mut depth = 0;
+	let mut start = 0;
+
+	for (idx, c) in list.char_indices() {
+		match c {
+			'(' => depth += 1,
+			')' => depth -= 1,
+			',' if depth == 0 => {
+				parts.push(&list[start..idx]);
+				start = idx + 1;
+			}
+			_ => {}
+		}
+	}
+
+	parts.push(&list[start..]);
+	parts
+}
+
+impl FromStr for SchedulerSpec {
+	type Err = SchedulerError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || SchedulerError::InvalidSpec(s.to_string());
+		let s = s.trim();
+
+		let (name, params) = match s.split_once('(') {
+			Some((name, rest)) => (name.trim(), rest.strip_suffix(')').ok_or_else(invalid)?),
+			None => (s, ""),
+		};
+
+		if name.is_empty() {
+			return Err(invalid());
+		}
+
+		let mut spec = SchedulerSpec {
+			name: name.to_string(),
+			params: SchedulerParams::default(),
+		};
+
+		// values may be specs themselves, e.g. `composite(primary=easy(backfill_depth=16))`
+		for param in split_top_level(params).into_iter().map(str::trim).filter(|param| !param.is_empty()) {
+			let (key, value) = param.split_once('=').ok_or_else(invalid)?;
+			spec.params.insert(key.trim(), value.trim());
+		}
+
+		Ok(spec)
+	}
+}
+
+impl Display for SchedulerSpec {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.name)?;
+
+		if self.params.0.is_empty() {
+			return Ok(());
+		}
+
+		let params = self.params.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>();
+		write!(f, "({})", params.join(", "))
+	}
+}
+
+type Constructor = fn(&SchedulerParams) -> Result<Box<dyn Scheduler>, SchedulerError>;
+
+fn construct<S: Scheduler + FromParams + 'static>(params: &SchedulerParams) -> Result<Box<dyn Scheduler>, SchedulerError> {
+	Ok(Box::new(S::from_params(params)?))
+}
+
+/// Every scheduler that can be built by name.
+const REGISTRY: &[(&str, Constructor)] = &[
+	("fcfs", construct::<FCFS>),
+	("ff", construct::<FF>),
+	("bf", construct::<BF>),
+	("wf", construct::<WF>),
+	("ffd", construct::<FFD>),
+	("sjf", construct::<SJF>),
+	("saf", construct::<SAF>),
+	("ljf", construct::<LJF>),
+	("lpt", construct::<LPT>),
+	("wfp3", construct::<WFP3>),
+	("edf", construct::<EDF>),
+	("fcfs-easy", construct::<FCFSEasy>),
+	("easy", construct::<EasyBackfill>),
+	("conservative", construct::<ConservativeBackfill>),
+	("fairshare", construct::<FairShare>),
+	("multifactor", construct::<Multifactor>),
+	("psjf", construct::<PreemptiveSJF>),
+	("elastic", construct::<Elastic>),
+	("moldable", construct::<Moldable>),
+	("lookahead", construct::<Lookahead>),
+	("rollout", construct::<Rollout>),
+	("random", construct::<RandomFit>),
+	("oracle", construct::<Oracle>),
+	("composite", construct::<Composite>),
+	("twolevel", construct::<TwoLevel>),
+	#[cfg(feature = "script")]
+	("script", construct::<super::script::Script>),
+];
+
+/// Names of the schedulers known to `by_name`.
+pub fn names() -> impl Iterator<Item = &'static str> {
+	REGISTRY.iter().map(|(name, _)| *name)
+}
+
+/// Builds a scheduler from its name and optional parameters, e.g. `easy(backfill_depth=16)`.
+pub fn by_name(spec: &str) -> Result<Box<dyn Scheduler>, SchedulerError> {
+	spec.parse::<SchedulerSpec>()?.build()
+}
+
+/// Label of a scheduler given its name and the values of its parameters, None for the
+/// parameters left to their default, see `Scheduler::label`.
+pub(crate) fn label(name: &str, params: &[(&str, Option<String>)]) -> String {
+	let set = params.iter()
+		.filter_map(|(key, value)| value.as_ref().map(|value| format!("{}={}", key, value)))
+		.collect::<Vec<_>>();
+
+	match set.is_empty() {
+		true => name.to_string(),
+		false => format!("{}({})", name, set.join(", ")),
+	}
+}
+
+/// Implements FromParams for schedulers without any parameter.
+macro_rules! no_params {
+	($($scheduler:ident),*) => {
+		$(
+			impl FromParams for $scheduler {
+				fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+					params.allow_only(&[])?;
+					Ok($scheduler)
+				}
+			}
+		)*
+	};
 }
 
+no_params!(FCFS, BF, WF, FFD, SJF, SAF, LJF, LPT, WFP3, EDF, FCFSEasy, PreemptiveSJF, Elastic, Moldable, Oracle);
+
 pub struct FCFS;
 
 impl Scheduler for FCFS {
@@ -12,33 +326,143 @@ impl Scheduler for FCFS {
 		"FCFS"
 	}
 
-    fn schedule(&self, _clock: u64, jobs: &Vec<Job>, cluster: &Cluster) -> Option<usize> {
+    fn schedule(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
 		let first = jobs.first().unwrap();
 		
-		if cluster.available_nodes >= first.nodes {
+		if cluster.fits(first) {
 			Some(0)
 		} else {
 			None
 		}
     }
+
+	fn schedule_batch(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		pack(jobs, cluster, 0..jobs.len(), true)
+	}
+
+	fn complete_batches(&self) -> bool {
+		true
+	}
+}
+
+/// First fit: the first job of the queue that fits on the free nodes.
+#[derive(Debug, Clone, Default)]
+pub struct FF {
+	/// Number of jobs from the head of the queue scanned for one that fits, all of them if None
+	pub scan_depth: Option<usize>,
+}
+
+impl FromParams for FF {
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["scan_depth"])?;
+
+		Ok(Self {
+			scan_depth: params.get("scan_depth")?,
+		})
+	}
 }
 
-pub struct FF;
+impl FF {
+	fn first_fitting(&self, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		let first = match cluster.is_homogeneous() {
+			true => jobs.first_fitting(cluster.available_nodes),
+			false => jobs.iter().position(|job| cluster.fits(job)),
+		};
+
+		first.filter(|&idx| idx < self.depth(jobs))
+	}
+
+	fn depth(&self, jobs: &ReadyQueue) -> usize {
+		self.scan_depth.map_or(jobs.len(), |depth| depth.min(jobs.len()))
+	}
+}
 
 impl Scheduler for FF {
 	fn name(&self) -> &'static str {
 		"FF"
 	}
 
-    fn schedule(&self, _clock: u64, jobs: &Vec<Job>, cluster: &Cluster) -> Option<usize> {
-		for (idx, job) in jobs.iter().enumerate() {
-			if cluster.available_nodes >= job.nodes {
-				return Some(idx);
-			}
-		}
+	fn label(&self) -> String {
+		label(self.name(), &[("scan_depth", self.scan_depth.map(|depth| depth.to_string()))])
+	}
 
-		None
+    fn schedule(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		self.first_fitting(jobs, cluster)
     }
+
+	fn schedule_batch(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		match self.first_fitting(jobs, cluster) {
+			Some(first) => pack(jobs, cluster, first..self.depth(jobs), false),
+			None => Vec::new(),
+		}
+	}
+
+	fn complete_batches(&self) -> bool {
+		true
+	}
+}
+
+/// Nodes a job would leave free among the classes it can run on, if it fits.
+fn leftover(job: &Job, cluster: &Cluster) -> u32 {
+	cluster.available_for(job.classes) - job.nodes
+}
+
+/// Best fit: the fitting job leaving the fewest free nodes, the largest one on a cluster of
+/// alike nodes.
+pub struct BF;
+
+impl Scheduler for BF {
+	fn name(&self) -> &'static str {
+		"BF"
+	}
+
+	fn schedule(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		match cluster.is_homogeneous() {
+			true => jobs.largest_fitting(cluster.available_nodes),
+			false => min_fitting(jobs, cluster, |job| leftover(job, cluster)),
+		}
+	}
+}
+
+/// Worst fit: the fitting job leaving the most free nodes, the smallest one on a cluster of
+/// alike nodes.
+pub struct WF;
+
+impl Scheduler for WF {
+	fn name(&self) -> &'static str {
+		"WF"
+	}
+
+	fn schedule(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		match cluster.is_homogeneous() {
+			true => jobs.smallest_fitting(cluster.available_nodes),
+			false => min_fitting(jobs, cluster, |job| Reverse(leftover(job, cluster))),
+		}
+	}
+}
+
+/// First fit decreasing: the queue taken by decreasing number of nodes (by submission for
+/// the same number), each job starting if it fits on the nodes the previous ones left.
+pub struct FFD;
+
+impl Scheduler for FFD {
+	fn name(&self) -> &'static str {
+		"FFD"
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		self.schedule_batch(clock, jobs, cluster).first().copied()
+	}
+
+	fn schedule_batch(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		let mut order = (0..jobs.len()).collect::<Vec<_>>();
+		order.sort_by_key(|&idx| Reverse(jobs[idx].nodes));
+		pack(jobs, cluster, order, false)
+	}
+
+	fn complete_batches(&self) -> bool {
+		true
+	}
 }
 
 pub struct SJF;
@@ -48,23 +472,123 @@ impl Scheduler for SJF {
 		"SJF"
 	}
 
-    fn schedule(&self, _clock: u64, jobs: &Vec<Job>, cluster: &Cluster) -> Option<usize> {
-		let mut min: Option<usize> = None;
-		let mut min_time = None;
+    fn schedule(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		match cluster.is_homogeneous() {
+			true => jobs.shortest_fitting(cluster.available_nodes),
+			false => min_fitting(jobs, cluster, |job| job.estimate()),
+		}
+    }
+}
+
+/// Index of the job that fits on the available nodes with the lowest key, ties going to the lowest ID.
+fn min_fitting<K: Ord>(jobs: &[Job], cluster: &Cluster, key: impl Fn(&Job) -> K) -> Option<usize> {
+	jobs.iter().enumerate()
+		.filter(|(_, job)| cluster.fits(job))
+		.min_by_key(|(_, job)| (key(job), job.id))
+		.map(|(idx, _)| idx)
+}
 
-		for (idx, job) in jobs.iter().enumerate() {
-			if cluster.available_nodes >= job.nodes {
-				let jtime = job.requested_run_time;
+/// Takes the jobs in the given order as long as they fit on the nodes left by the previous ones.
+/// Jobs that don't fit are skipped, or stop the batch if `blocking`.
+fn pack(jobs: &[Job], cluster: &Cluster, order: impl IntoIterator<Item = usize>, blocking: bool) -> Vec<usize> {
+	let mut free = cluster.free_by_class();
+	let mut batch = Vec::new();
 
-				if min_time == None || jtime < min_time.unwrap() || (jtime == min_time.unwrap() && job.id < jobs[min.unwrap()].id) {
-					min = Some(idx);
-					min_time = Some(job.requested_run_time);
-				}
-			}
+	for idx in order {
+		if cluster::claim(&mut free, &jobs[idx]) {
+			batch.push(idx);
+		} else if blocking {
+			break;
 		}
+	}
 
-		min
-    }
+	batch
+}
+
+/// Shortest area first: the fitting job with the fewest estimated node-seconds, see `Job::estimate`.
+pub struct SAF;
+
+impl Scheduler for SAF {
+	fn name(&self) -> &'static str {
+		"SAF"
+	}
+
+	fn schedule(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		min_fitting(jobs, cluster, |job| job.nodes as u64 * job.estimate())
+	}
+}
+
+/// Largest job first: the fitting job with the most nodes.
+pub struct LJF;
+
+impl Scheduler for LJF {
+	fn name(&self) -> &'static str {
+		"LJF"
+	}
+
+	fn schedule(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		match cluster.is_homogeneous() {
+			true => jobs.largest_fitting(cluster.available_nodes),
+			false => min_fitting(jobs, cluster, |job| Reverse(job.nodes)),
+		}
+	}
+}
+
+/// Longest processing time first: the fitting job with the longest estimated run time.
+pub struct LPT;
+
+impl Scheduler for LPT {
+	fn name(&self) -> &'static str {
+		"LPT"
+	}
+
+	fn schedule(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		match cluster.is_homogeneous() {
+			true => jobs.longest_fitting(cluster.available_nodes),
+			false => min_fitting(jobs, cluster, |job| Reverse(job.estimate())),
+		}
+	}
+}
+
+/// The fitting job with the highest `(wait / estimated run time)^3 * nodes`, which favors
+/// jobs that waited long relative to their length, and large jobs.
+pub struct WFP3;
+
+impl WFP3 {
+	fn priority(clock: Clock, job: &Job) -> f64 {
+		let ratio = (clock - job.submit_time) as f64 / job.estimate().max(1) as f64;
+		ratio.powi(3) * job.nodes as f64
+	}
+}
+
+impl Scheduler for WFP3 {
+	fn name(&self) -> &'static str {
+		"WFP3"
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		jobs.iter().enumerate()
+			.filter(|(_, job)| cluster.fits(job))
+			.max_by(|(_, a), (_, b)| {
+				Self::priority(clock, a).total_cmp(&Self::priority(clock, b))
+					.then(b.id.cmp(&a.id))
+			})
+			.map(|(idx, _)| idx)
+	}
+}
+
+/// Earliest deadline first: the fitting job that has to start the soonest to meet the target
+/// wait of its QoS class (see `Job::deadline`), jobs without a class coming last by submission.
+pub struct EDF;
+
+impl Scheduler for EDF {
+	fn name(&self) -> &'static str {
+		"EDF"
+	}
+
+	fn schedule(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		min_fitting(jobs, cluster, |job| (job.deadline().unwrap_or(Clock::MAX), job.submit_time))
+	}
 }
 
 pub struct FCFSEasy;
@@ -74,15 +598,15 @@ impl Scheduler for FCFSEasy {
 		"FCFSEasy"
 	}
 
-    fn schedule(&self, clock: u64, jobs: &Vec<Job>, cluster: &Cluster) -> Option<usize> {
+    fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
         let first = jobs.first().unwrap();
 		
-		if cluster.available_nodes >= first.nodes {
+		if cluster.fits(first) {
 			Some(0)
 		} else {
 			// sort the running jobs by their expected end to make it easier
 			let mut running = cluster.running_jobs.values().collect::<Vec<_>>();
-			running.sort_unstable_by_key(|job| job.requested_run_time);
+			running.sort_unstable_by_key(|job| job.estimate());
 
 			let mut available = cluster.available_nodes;
 
@@ -90,13 +614,13 @@ impl Scheduler for FCFSEasy {
 			for job in running {
 				available += job.nodes;
 				if available >= first.nodes {
-					time_before_launch = job.expected_end - clock;
+					time_before_launch = job.estimated_end(clock).saturating_sub(clock);
 					break;
 				}
 			}
 
 			for (idx, job) in jobs.iter().skip(1).enumerate() {
-				if job.requested_run_time < time_before_launch && cluster.available_nodes >= job.nodes {
+				if job.estimate() < time_before_launch && cluster.fits(job) {
 					return Some(idx + 1);
 				}
 			}
@@ -105,3 +629,1503 @@ impl Scheduler for FCFSEasy {
 		}
     }
 }
+
+/// Order in which `EasyBackfill` tries the jobs after the first one, ties keeping the queue order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackfillOrder {
+	/// Queue order
+	#[default]
+	Fcfs,
+	/// Shortest estimated run time first
+	Sjf,
+	/// Fewest estimated node-seconds first
+	Saf,
+}
+
+impl BackfillOrder {
+	fn key(&self, job: &Job) -> u64 {
+		match self {
+			BackfillOrder::Fcfs => 0,
+			BackfillOrder::Sjf => job.estimate(),
+			BackfillOrder::Saf => job.nodes as u64 * job.estimate(),
+		}
+	}
+}
+
+impl FromStr for BackfillOrder {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"fcfs" => Ok(BackfillOrder::Fcfs),
+			"sjf" => Ok(BackfillOrder::Sjf),
+			"saf" => Ok(BackfillOrder::Saf),
+			_ => Err(()),
+		}
+	}
+}
+
+impl Display for BackfillOrder {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			BackfillOrder::Fcfs => write!(f, "fcfs"),
+			BackfillOrder::Sjf => write!(f, "sjf"),
+			BackfillOrder::Saf => write!(f, "saf"),
+		}
+	}
+}
+
+/// EASY backfilling: the first job of the queue gets a reservation at the earliest time
+/// enough nodes are released (the shadow time), and any later job may start right away
+/// as long as it doesn't delay that reservation, either because it ends before the shadow
+/// time or because it only uses nodes the first job won't need (the extra nodes).
+#[derive(Debug, Clone, Default)]
+pub struct EasyBackfill {
+	/// Number of jobs after the first one considered for backfilling, all of them if None
+	pub backfill_depth: Option<usize>,
+	/// Order in which the jobs after the first one are considered, e.g. `sjf` for EASY-SJBF
+	pub backfill_order: BackfillOrder,
+}
+
+impl FromParams for EasyBackfill {
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["backfill_depth", "backfill_order"])?;
+
+		Ok(Self {
+			backfill_depth: params.get("backfill_depth")?,
+			backfill_order: params.get_or("backfill_order", BackfillOrder::default())?,
+		})
+	}
+}
+
+impl EasyBackfill {
+	/// Positions of the jobs after the first one, in the backfill order.
+	fn backfill_candidates(&self, jobs: &[Job]) -> Vec<usize> {
+		let mut candidates = (1..jobs.len()).collect::<Vec<_>>();
+		candidates.sort_by_key(|&idx| self.backfill_order.key(&jobs[idx]));
+		candidates
+	}
+
+	/// Returns the shadow time and the number of extra nodes for the given job.
+	/// Running jobs past their estimated end are expected to end right now.
+	/// The shadow time is Clock::MAX if the job can't fit until failed nodes are repaired.
+	fn shadow(clock: Clock, head: &Job, cluster: &Cluster) -> (Clock, u32) {
+		let mut running = cluster.running_jobs.values()
+			.map(|job| (job.estimated_end(clock).max(clock), job.nodes))
+			.collect::<Vec<_>>();
+		running.sort_unstable();
+
+		let mut available = cluster.available_nodes;
+		let mut shadow_time = None;
+
+		for (end, nodes) in running {
+			match shadow_time {
+				// every job ending at the shadow time also frees its nodes for the backfill
+				Some(shadow) if end > shadow => break,
+				_ => {
+					available += nodes;
+					if shadow_time.is_none() && available >= head.nodes {
+						shadow_time = Some(end);
+					}
+				}
+			}
+		}
+
+		// the first job may not fit before failed nodes are repaired, leaving no reservation to protect
+		match shadow_time {
+			Some(shadow_time) => (shadow_time, available - head.nodes),
+			None => (Clock::MAX, 0),
+		}
+	}
+}
+
+impl Scheduler for EasyBackfill {
+	fn name(&self) -> &'static str {
+		"EasyBackfill"
+	}
+
+	fn label(&self) -> String {
+		label(self.name(), &[
+			("backfill_depth", self.backfill_depth.map(|depth| depth.to_string())),
+			("backfill_order", (self.backfill_order != BackfillOrder::default()).then(|| self.backfill_order.to_string())),
+		])
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		let first = jobs.first().unwrap();
+
+		if cluster.fits(first) {
+			return Some(0);
+		}
+
+		let (shadow_time, extra_nodes) = Self::shadow(clock, first, cluster);
+		let backfills = |job: &Job| cluster.fits(job) && (clock + job.estimate() <= shadow_time || job.nodes <= extra_nodes);
+		let depth = self.backfill_depth.unwrap_or(usize::MAX);
+
+		match self.backfill_order {
+			// no need to sort the queue
+			BackfillOrder::Fcfs => jobs.iter().enumerate().skip(1).take(depth).find(|(_, job)| backfills(job)).map(|(idx, _)| idx),
+			_ => self.backfill_candidates(jobs).into_iter().take(depth).find(|&idx| backfills(&jobs[idx])),
+		}
+	}
+
+	fn explain(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<Decision> {
+		let first = match jobs.first() {
+			Some(first) if !cluster.fits(first) => first,
+			_ => return decision::explain_by_nodes(jobs, cluster),
+		};
+
+		let (shadow_time, extra_nodes) = Self::shadow(clock, first, cluster);
+		let depth = self.backfill_depth.unwrap_or(usize::MAX);
+
+		// rank of each job in the backfill order, the first job having none
+		let mut ranks = vec![0; jobs.len()];
+		for (rank, idx) in self.backfill_candidates(jobs).into_iter().enumerate() {
+			ranks[idx] = rank;
+		}
+
+		jobs.iter().enumerate().map(|(idx, job)| {
+			let reason = if idx == 0 || !cluster.fits(job) {
+				decision::waiting_reason(job, cluster)
+			} else if ranks[idx] >= depth {
+				Reason::OutsideBackfillDepth
+			} else {
+				Reason::BackfillWindowTooSmall { shadow_time, extra_nodes }
+			};
+
+			Decision::new(job.id, reason)
+		}).collect()
+	}
+}
+
+/// Conservative backfilling: every queued job gets a reservation in queue order, so a job
+/// may only start early if it doesn't delay any job queued before it.
+/// Reservations are recomputed from scratch at each call, which lets them move earlier
+/// when jobs end before their requested time. Jobs that can't fit until failed nodes are
+/// repaired get no reservation.
+#[derive(Debug, Clone, Default)]
+pub struct ConservativeBackfill {
+	/// Number of jobs from the head of the queue getting a reservation, all of them if None.
+	/// The jobs further in the queue wait
+	pub backfill_depth: Option<usize>,
+}
+
+impl FromParams for ConservativeBackfill {
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["backfill_depth"])?;
+
+		Ok(Self {
+			backfill_depth: params.get("backfill_depth")?,
+		})
+	}
+}
+
+impl Scheduler for ConservativeBackfill {
+	fn name(&self) -> &'static str {
+		"ConservativeBackfill"
+	}
+
+	fn label(&self) -> String {
+		label(self.name(), &[("backfill_depth", self.backfill_depth.map(|depth| depth.to_string()))])
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		let mut profile = Profile::new(clock, cluster);
+
+		for (idx, job) in jobs.iter().enumerate().take(self.backfill_depth.unwrap_or(usize::MAX)) {
+			let start = match profile.earliest_start(job.nodes, job.classes, job.estimate()) {
+				Some(start) => start,
+				None => continue,
+			};
+
+			if start == clock {
+				return Some(idx);
+			}
+
+			profile.reserve(start, job.estimate(), job.nodes, job.classes);
+		}
+
+		None
+	}
+
+	/// Every job whose reservation starts now, which are found with a single profile.
+	fn schedule_batch(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		let mut profile = Profile::new(clock, cluster);
+		let mut batch = Vec::new();
+
+		for (idx, job) in jobs.iter().enumerate().take(self.backfill_depth.unwrap_or(usize::MAX)) {
+			let start = match profile.earliest_start(job.nodes, job.classes, job.estimate()) {
+				Some(start) => start,
+				None => continue,
+			};
+
+			if start == clock {
+				batch.push(idx);
+			}
+
+			profile.reserve(start, job.estimate(), job.nodes, job.classes);
+		}
+
+		batch
+	}
+
+	fn complete_batches(&self) -> bool {
+		true
+	}
+
+	fn explain(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<Decision> {
+		let mut profile = Profile::new(clock, cluster);
+		let depth = self.backfill_depth.unwrap_or(usize::MAX);
+
+		jobs.iter().enumerate().map(|(idx, job)| {
+			if idx >= depth {
+				return Decision::new(job.id, Reason::OutsideBackfillDepth);
+			}
+
+			let start = match profile.earliest_start(job.nodes, job.classes, job.estimate()) {
+				Some(start) => start,
+				None => return Decision::new(job.id, decision::waiting_reason(job, cluster)),
+			};
+
+			profile.reserve(start, job.estimate(), job.nodes, job.classes);
+
+			let reason = if start == clock {
+				Reason::LowerPriority
+			} else {
+				Reason::BlockedByReservation { start }
+			};
+
+			Decision::new(job.id, reason)
+		}).collect()
+	}
+}
+
+/// Fair-share scheduling: queued jobs are ordered by how many node-seconds their user consumed
+/// so far, relative to the user's share, then by submission time.
+/// The first job in that order starts as soon as it fits, and blocks the others until then.
+#[derive(Debug, Clone, Default)]
+pub struct FairShare {
+	/// Share of each user, users not listed having a share of 1
+	pub weights: HashMap<u32, f64>,
+}
+
+impl FromParams for FairShare {
+	/// Weights are given as `weights=user:share;user:share`
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["weights"])?;
+
+		Ok(Self { weights: user_shares(params, "weights")? })
+	}
+}
+
+/// Reads the shares of the users given as `user:share;user:share`.
+fn user_shares(params: &SchedulerParams, key: &str) -> Result<HashMap<u32, f64>, SchedulerError> {
+	let mut shares = HashMap::new();
+	if let Some(list) = params.get::<String>(key)? {
+		let invalid = || SchedulerError::InvalidParam(key.to_string(), list.clone());
+
+		for pair in list.split(';').map(str::trim).filter(|pair| !pair.is_empty()) {
+			let (user, share) = pair.split_once(':').ok_or_else(invalid)?;
+			let user = user.trim().parse().map_err(|_| invalid())?;
+			let share: f64 = share.trim().parse().map_err(|_| invalid())?;
+
+			if share <= 0.0 {
+				return Err(invalid());
+			}
+
+			shares.insert(user, share);
+		}
+	}
+
+	Ok(shares)
+}
+
+impl FairShare {
+	/// Node-seconds consumed by each user, including what running jobs consumed so far.
+	fn usage(clock: Clock, cluster: &Cluster) -> HashMap<Option<u32>, u64> {
+		let mut usage = cluster.user_usage.clone();
+
+		for job in cluster.running_jobs.values() {
+			*usage.entry(job.user_id).or_insert(0) += job.nodes as u64 * (clock - job.schedule_time);
+		}
+
+		usage
+	}
+}
+
+impl Scheduler for FairShare {
+	fn name(&self) -> &'static str {
+		"FairShare"
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		let usage = Self::usage(clock, cluster);
+
+		let priority = |job: &Job| {
+			let used = usage.get(&job.user_id).copied().unwrap_or(0) as f64;
+			let weight = job.user_id.and_then(|user| self.weights.get(&user)).copied().unwrap_or(1.0);
+			used / weight
+		};
+
+		let (idx, first) = jobs.iter().enumerate()
+			.min_by(|(_, a), (_, b)| {
+				priority(a).total_cmp(&priority(b))
+					.then(a.submit_time.cmp(&b.submit_time))
+					.then(a.id.cmp(&b.id))
+			})
+			.unwrap();
+
+		if cluster.fits(first) {
+			Some(idx)
+		} else {
+			None
+		}
+	}
+}
+
+/// Priority made of weighted factors between 0 and 1, like Slurm's priority/multifactor plugin:
+/// the age of the job, its size, its QoS class and the fair-share of its user. Jobs start by
+/// decreasing priority, the first one that doesn't fit blocking the others.
+#[derive(Debug, Clone)]
+pub struct Multifactor {
+	pub age_weight: f64,
+	pub size_weight: f64,
+	pub qos_weight: f64,
+	pub fairshare_weight: f64,
+	/// Wait giving the largest age factor, in seconds (Slurm's PriorityMaxAge)
+	pub max_age: Clock,
+	/// Whether the size factor favors the narrow jobs instead of the wide ones (PriorityFavorSmall)
+	pub favor_small: bool,
+	/// Time after which the usage of a user counts half as much in its fair-share, in seconds,
+	/// 0 to never forget it (PriorityDecayHalfLife)
+	pub half_life: Clock,
+	/// Share of each user, users not listed having a share of 1
+	pub shares: HashMap<u32, f64>,
+	/// Node-seconds used by the finished jobs of each user, decayed up to the given time
+	usage: HashMap<Option<u32>, (f64, Clock)>,
+}
+
+impl Default for Multifactor {
+	fn default() -> Self {
+		Self {
+			age_weight: 1000.0,
+			size_weight: 1000.0,
+			qos_weight: 1000.0,
+			fairshare_weight: 1000.0,
+			max_age: 7 * 86400,
+			favor_small: false,
+			half_life: 7 * 86400,
+			shares: HashMap::new(),
+			usage: HashMap::new(),
+		}
+	}
+}
+
+impl FromParams for Multifactor {
+	/// Shares are given as `shares=user:share;user:share`
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["age_weight", "size_weight", "qos_weight", "fairshare_weight", "max_age", "favor_small", "half_life", "shares"])?;
+
+		let default = Self::default();
+		let weight = |key: &str, default: f64| match params.get_or(key, default)? {
+			weight if weight >= 0.0 && weight.is_finite() => Ok(weight),
+			_ => Err(SchedulerError::InvalidParam(key.to_string(), params.get::<String>(key)?.unwrap_or_default())),
+		};
+
+		Ok(Self {
+			age_weight: weight("age_weight", default.age_weight)?,
+			size_weight: weight("size_weight", default.size_weight)?,
+			qos_weight: weight("qos_weight", default.qos_weight)?,
+			fairshare_weight: weight("fairshare_weight", default.fairshare_weight)?,
+			max_age: params.get_or("max_age", default.max_age)?.max(1),
+			favor_small: params.get_or("favor_small", default.favor_small)?,
+			half_life: params.get_or("half_life", default.half_life)?,
+			shares: user_shares(params, "shares")?,
+			usage: HashMap::new(),
+		})
+	}
+}
+
+impl Multifactor {
+	/// Usage of a user decayed up to `clock`, see `Multifactor::half_life`.
+	fn decay(&self, usage: f64, since: Clock, clock: Clock) -> f64 {
+		match self.half_life {
+			0 => usage,
+			half_life => usage * 0.5f64.powf(clock.saturating_sub(since) as f64 / half_life as f64),
+		}
+	}
+
+	/// Positions of the queued jobs by decreasing priority.
+	fn order(&self, clock: Clock, jobs: &[Job], cluster: &Cluster) -> Vec<usize> {
+		let mut usage = self.usage.iter()
+			.map(|(&user, &(used, since))| (user, self.decay(used, since, clock)))
+			.collect::<HashMap<_, _>>();
+		for job in cluster.running_jobs.values() {
+			*usage.entry(job.user_id).or_insert(0.0) += job.nodes as f64 * (clock - job.schedule_time) as f64;
+		}
+
+		// fair-share factor 2^(-usage/share), both normalized over the users using the nodes
+		// or waiting for them, as Slurm's classic fair-share
+		let share = |user: Option<u32>| user.and_then(|user| self.shares.get(&user)).copied().unwrap_or(1.0);
+		let total_usage = usage.values().sum::<f64>();
+		let mut users = usage.keys().copied().chain(jobs.iter().map(|job| job.user_id)).collect::<Vec<_>>();
+		users.sort_unstable();
+		users.dedup();
+		let total_shares = users.into_iter().map(share).sum::<f64>();
+
+		let fairshare = |job: &Job| match total_usage > 0.0 {
+			true => {
+				let used = usage.get(&job.user_id).copied().unwrap_or(0.0) / total_usage;
+				0.5f64.powf(used / (share(job.user_id) / total_shares))
+			}
+			false => 1.0,
+		};
+
+		// the tightest target wait of the queue gives the largest QoS factor
+		let tightest = jobs.iter().filter_map(|job| job.max_wait).min();
+		let qos = |job: &Job| match (job.max_wait, tightest) {
+			(Some(0), _) => 1.0,
+			(Some(max_wait), Some(tightest)) => tightest as f64 / max_wait as f64,
+			_ => 0.0,
+		};
+
+		let size = |job: &Job| {
+			let size = job.nodes as f64 / cluster.total_nodes.max(1) as f64;
+			if self.favor_small { 1.0 - size } else { size }
+		};
+
+		let priority = |job: &Job| {
+			let age = (clock - job.submit_time).min(self.max_age) as f64 / self.max_age as f64;
+			self.age_weight * age + self.size_weight * size(job) + self.qos_weight * qos(job) + self.fairshare_weight * fairshare(job)
+		};
+
+		let priorities = jobs.iter().map(priority).collect::<Vec<_>>();
+		let mut order = (0..jobs.len()).collect::<Vec<_>>();
+		order.sort_by(|&a, &b| priorities[b].total_cmp(&priorities[a]).then(a.cmp(&b)));
+		order
+	}
+}
+
+impl Scheduler for Multifactor {
+	fn name(&self) -> &'static str {
+		"Multifactor"
+	}
+
+	fn label(&self) -> String {
+		let default = Self::default();
+		let changed = |value: f64, default: f64| (value != default).then(|| value.to_string());
+
+		label(self.name(), &[
+			("age_weight", changed(self.age_weight, default.age_weight)),
+			("size_weight", changed(self.size_weight, default.size_weight)),
+			("qos_weight", changed(self.qos_weight, default.qos_weight)),
+			("fairshare_weight", changed(self.fairshare_weight, default.fairshare_weight)),
+			("max_age", (self.max_age != default.max_age).then(|| self.max_age.to_string())),
+			("favor_small", self.favor_small.then(|| "true".to_string())),
+			("half_life", (self.half_life != default.half_life).then(|| self.half_life.to_string())),
+		])
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		self.schedule_batch(clock, jobs, cluster).first().copied()
+	}
+
+	fn schedule_batch(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		pack(jobs, cluster, self.order(clock, jobs, cluster), true)
+	}
+
+	fn complete_batches(&self) -> bool {
+		true
+	}
+
+	fn on_job_finish(&mut self, clock: u64, job: &Job) {
+		let used = job.nodes as f64 * (clock - job.schedule_time) as f64;
+		let (usage, since) = self.usage.get(&job.user_id).copied().unwrap_or((0.0, clock));
+		let usage = self.decay(usage, since, clock) + used;
+		self.usage.insert(job.user_id, (usage, clock));
+	}
+}
+
+/// Shortest remaining estimated run time first. When the shortest job of the queue doesn't fit,
+/// the running jobs with more requested time left are preempted, longest first,
+/// if this frees enough nodes. Behaves like SJF when preemption is disabled.
+pub struct PreemptiveSJF;
+
+impl PreemptiveSJF {
+	/// Requested time the job has left, counting the time it needs to restore itself if it was preempted.
+	fn remaining(job: &Job) -> Clock {
+		job.restart_overhead + job.estimated_remaining_time()
+	}
+}
+
+impl Scheduler for PreemptiveSJF {
+	fn name(&self) -> &'static str {
+		"PreemptiveSJF"
+	}
+
+	fn schedule(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		jobs.iter().enumerate()
+			.filter(|(_, job)| cluster.fits(job))
+			.min_by_key(|(_, job)| (Self::remaining(job), job.id))
+			.map(|(idx, _)| idx)
+	}
+
+	fn preempt(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<u32> {
+		let shortest = match jobs.iter().min_by_key(|job| (Self::remaining(job), job.id)) {
+			Some(job) if job.nodes > cluster.available_nodes => job,
+			_ => return Vec::new(),
+		};
+
+		let mut running = cluster.running_jobs.values()
+			.map(|job| (job.estimated_end(clock).saturating_sub(clock), job))
+			.filter(|&(remaining, _)| remaining > Self::remaining(shortest))
+			.collect::<Vec<_>>();
+
+		running.sort_unstable_by_key(|&(remaining, job)| (Reverse(remaining), job.id));
+
+		let mut available = cluster.available_nodes;
+		let mut preempted = Vec::new();
+
+		for (_, job) in running {
+			if available >= shortest.nodes {
+				break;
+			}
+
+			available += job.nodes;
+			preempted.push(job.id);
+		}
+
+		if available >= shortest.nodes {
+			preempted
+		} else {
+			Vec::new()
+		}
+	}
+}
+
+/// FCFS making room for the first job of the queue by shrinking the running malleable jobs,
+/// widest first, and spreading the free nodes over them once the queue is empty, see
+/// `Malleability`. Behaves like FCFS without malleable jobs.
+pub struct Elastic;
+
+impl Scheduler for Elastic {
+	fn name(&self) -> &'static str {
+		"Elastic"
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		FCFS.schedule(clock, jobs, cluster)
+	}
+
+	fn schedule_batch(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		FCFS.schedule_batch(clock, jobs, cluster)
+	}
+
+	fn complete_batches(&self) -> bool {
+		true
+	}
+
+	fn resize(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<(u32, u32)> {
+		let mut malleable = cluster.running_jobs.values()
+			.filter_map(|job| job.malleable.map(|(min, max)| (job.id, job.nodes, min, max)))
+			.collect::<Vec<_>>();
+
+		let first = match jobs.first() {
+			Some(first) if cluster.fits(first) => return Vec::new(),
+			Some(first) => first,
+			None => {
+				// least expanded first
+				malleable.sort_unstable_by_key(|&(id, nodes, _, max)| (Reverse(max - nodes), id));
+
+				let mut free = cluster.available_nodes;
+				return malleable.into_iter()
+					.filter(|&(_, nodes, _, max)| nodes < max)
+					.map_while(|(id, nodes, _, max)| {
+						let added = (max - nodes).min(free);
+						free -= added;
+						(added > 0).then_some((id, nodes + added))
+					})
+					.collect();
+			}
+		};
+
+		malleable.sort_unstable_by_key(|&(id, nodes, min, _)| (Reverse(nodes - min), id));
+
+		let mut needed = first.nodes.saturating_sub(cluster.available_nodes);
+		let mut shrunk = Vec::new();
+		for (id, nodes, min, _) in malleable {
+			if needed == 0 || nodes == min {
+				break;
+			}
+
+			let freed = (nodes - min).min(needed);
+			needed -= freed;
+			shrunk.push((id, nodes - freed));
+		}
+
+		match needed {
+			0 => shrunk,
+			_ => Vec::new(),
+		}
+	}
+}
+
+/// FCFS starting each moldable job on the configuration that fits on the free nodes with the
+/// shortest requested time, see `Moldability`. Behaves like FCFS without moldable jobs.
+pub struct Moldable;
+
+impl Moldable {
+	/// Jobs starting now in order, the first one that doesn't fit in any configuration
+	/// blocking the others.
+	fn plan(jobs: &ReadyQueue, cluster: &Cluster) -> Vec<(usize, Option<usize>)> {
+		let mut free = cluster.free_by_class();
+		let mut batch = Vec::new();
+
+		for (idx, job) in jobs.iter().enumerate() {
+			if job.shapes.is_empty() {
+				match cluster::claim(&mut free, job) {
+					true => batch.push((idx, None)),
+					false => break,
+				}
+				continue;
+			}
+
+			let fastest = job.shapes.iter()
+				.enumerate()
+				.filter(|(_, shape)| cluster::claim_nodes(&mut free.clone(), shape.nodes, job.classes))
+				.min_by_key(|(_, shape)| (shape.requested_run_time, shape.nodes))
+				.map(|(shape, _)| shape);
+
+			match fastest {
+				Some(shape) => {
+					cluster::claim_nodes(&mut free, job.shapes[shape].nodes, job.classes);
+					batch.push((idx, Some(shape)));
+				}
+				None => break,
+			}
+		}
+
+		batch
+	}
+}
+
+impl Scheduler for Moldable {
+	fn name(&self) -> &'static str {
+		"Moldable"
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		self.schedule_batch(clock, jobs, cluster).first().copied()
+	}
+
+	fn schedule_batch(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		Self::plan(jobs, cluster).into_iter().map(|(idx, _)| idx).collect()
+	}
+
+	fn schedule_shaped(&mut self, _clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<(usize, Option<usize>)> {
+		Self::plan(jobs, cluster)
+	}
+
+	fn complete_batches(&self) -> bool {
+		true
+	}
+}
+
+/// What `Lookahead` minimizes over the jobs it plans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Objective {
+	/// Sum of the projected waits
+	#[default]
+	Wait,
+	/// Projected end of the last job
+	Makespan,
+}
+
+impl FromStr for Objective {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"wait" => Ok(Objective::Wait),
+			"makespan" => Ok(Objective::Makespan),
+			_ => Err(()),
+		}
+	}
+}
+
+impl Display for Objective {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Objective::Wait => write!(f, "wait"),
+			Objective::Makespan => write!(f, "makespan"),
+		}
+	}
+}
+
+/// Plans the first `depth` jobs of the queue in every possible order, each job getting a
+/// reservation at the earliest time it fits as with conservative backfilling, and starts
+/// the jobs whose reservation starts now in the order minimizing the objective, using the
+/// requested run times. Beyond `EXHAUSTIVE_DEPTH` jobs, orders are searched by simulated
+/// annealing instead. Jobs further in the queue wait: this is a reference to compare the
+/// greedy heuristics to, not a practical policy.
+#[derive(Debug, Clone)]
+pub struct Lookahead {
+	pub depth: usize,
+	pub objective: Objective,
+	/// Orders tried by simulated annealing
+	pub iterations: usize,
+	rng: SimRng,
+}
+
+impl Default for Lookahead {
+	fn default() -> Self {
+		Self {
+			depth: 5,
+			objective: Objective::Wait,
+			iterations: 1000,
+			rng: SimRng::default(),
+		}
+	}
+}
+
+impl FromParams for Lookahead {
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["depth", "objective", "iterations"])?;
+
+		let default = Self::default();
+		let depth = params.get_or("depth", default.depth)?;
+		if depth == 0 {
+			return Err(SchedulerError::InvalidParam("depth".to_string(), depth.to_string()));
+		}
+
+		Ok(Self {
+			depth,
+			objective: params.get_or("objective", default.objective)?,
+			iterations: params.get_or("iterations", default.iterations)?,
+			..default
+		})
+	}
+}
+
+impl Lookahead {
+	/// Largest number of jobs whose orders are all tried.
+	pub const EXHAUSTIVE_DEPTH: usize = 7;
+
+	/// Plans the jobs in the given order, returning the cost of the plan and the
+	/// jobs starting now. Jobs that can't fit until failed nodes are repaired are left out.
+	fn plan(&self, clock: Clock, jobs: &[Job], order: &[usize], profile: &Profile) -> (Clock, Vec<usize>) {
+		let mut profile = profile.clone();
+		let mut cost = 0;
+		let mut starting = Vec::new();
+
+		for &idx in order {
+			let job = &jobs[idx];
+			let start = match profile.earliest_start(job.nodes, job.classes, job.estimate()) {
+				Some(start) => start,
+				None => continue,
+			};
+
+			profile.reserve(start, job.estimate(), job.nodes, job.classes);
+
+			if start == clock {
+				starting.push(idx);
+			}
+
+			cost = match self.objective {
+				Objective::Wait => cost + (start - job.submit_time),
+				Objective::Makespan => cost.max(start + job.estimate()),
+			};
+		}
+
+		(cost, starting)
+	}
+
+	/// Cheapest order among every permutation of the window, by Heap's algorithm.
+	/// Ties keep the order found first, the submission order coming first.
+	fn exhaustive(&self, clock: Clock, jobs: &[Job], profile: &Profile) -> Vec<usize> {
+		let mut order = (0..jobs.len()).collect::<Vec<_>>();
+		let mut best = self.plan(clock, jobs, &order, profile);
+		let mut counters = vec![0; order.len()];
+		let mut i = 1;
+
+		while i < order.len() {
+			if counters[i] < i {
+				order.swap(if i % 2 == 0 { 0 } else { counters[i] }, i);
+
+				let plan = self.plan(clock, jobs, &order, profile);
+				if plan.0 < best.0 {
+					best = plan;
+				}
+
+				counters[i] += 1;
+				i = 1;
+			} else {
+				counters[i] = 0;
+				i += 1;
+			}
+		}
+
+		best.1
+	}
+
+	/// Order found by swapping pairs of jobs, keeping worse orders with a probability that
+	/// decreases over the iterations. Draws depend on the seed of the run and on the clock.
+	fn annealing(&self, clock: Clock, jobs: &[Job], profile: &Profile) -> Vec<usize> {
+		let mut rng = self.rng.keyed(Stream::Scheduler, clock);
+		let mut order = (0..jobs.len()).collect::<Vec<_>>();
+		let mut current = self.plan(clock, jobs, &order, profile);
+		let mut best = current.clone();
+		let initial_temperature = (current.0 as f64 / jobs.len() as f64).max(1.0);
+
+		for iteration in 0..self.iterations {
+			let temperature = initial_temperature * (1.0 - iteration as f64 / self.iterations as f64);
+			let (a, b) = (rng.random_range(0..order.len()), rng.random_range(0..order.len()));
+			order.swap(a, b);
+
+			let plan = self.plan(clock, jobs, &order, profile);
+			let delta = plan.0 as f64 - current.0 as f64;
+
+			if delta <= 0.0 || rng.random::<f64>() < (-delta / temperature).exp() {
+				if plan.0 < best.0 {
+					best = plan.clone();
+				}
+
+				current = plan;
+			} else {
+				order.swap(a, b);
+			}
+		}
+
+		best.1
+	}
+}
+
+impl Scheduler for Lookahead {
+	fn name(&self) -> &'static str {
+		"Lookahead"
+	}
+
+	fn label(&self) -> String {
+		let default = Self::default();
+		label(self.name(), &[
+			("depth", (self.depth != default.depth).then(|| self.depth.to_string())),
+			("objective", (self.objective != default.objective).then(|| self.objective.to_string())),
+			("iterations", (self.iterations != default.iterations).then(|| self.iterations.to_string())),
+		])
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		self.schedule_batch(clock, jobs, cluster).first().copied()
+	}
+
+	fn schedule_batch(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		let window = &jobs[..jobs.len().min(self.depth)];
+		let profile = Profile::new(clock, cluster);
+
+		if window.len() <= Self::EXHAUSTIVE_DEPTH {
+			self.exhaustive(clock, window, &profile)
+		} else {
+			self.annealing(clock, window, &profile)
+		}
+	}
+}
+
+/// Tries starting each of the first `depth` queued jobs that fit now, then plays the rest of
+/// the window first-fit for `horizon` seconds in a `SimView`, and starts the job leading to the
+/// lowest total wait of the window. Ties go to the job submitted first.
+pub struct Rollout {
+	pub depth: usize,
+	pub horizon: Clock,
+}
+
+impl Default for Rollout {
+	fn default() -> Self {
+		Self {
+			depth: 8,
+			horizon: 86400,
+		}
+	}
+}
+
+impl FromParams for Rollout {
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["depth", "horizon"])?;
+
+		let default = Self::default();
+		let depth = params.get_or("depth", default.depth)?;
+		if depth == 0 {
+			return Err(SchedulerError::InvalidParam("depth".to_string(), depth.to_string()));
+		}
+
+		Ok(Self {
+			depth,
+			horizon: params.get_or("horizon", default.horizon)?,
+		})
+	}
+}
+
+impl Scheduler for Rollout {
+	fn name(&self) -> &'static str {
+		"Rollout"
+	}
+
+	fn label(&self) -> String {
+		let default = Self::default();
+		label(self.name(), &[
+			("depth", (self.depth != default.depth).then(|| self.depth.to_string())),
+			("horizon", (self.horizon != default.horizon).then(|| self.horizon.to_string())),
+		])
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		let window = &jobs[..jobs.len().min(self.depth)];
+		let base = SimView::new(clock, window, cluster, self.horizon);
+
+		(0..window.len()).filter(|&idx| base.fits(idx)).min_by_key(|&idx| {
+			let mut view = base.clone();
+			view.start(idx);
+
+			loop {
+				let fitting = view.waiting().filter(|&other| view.fits(other)).collect::<Vec<_>>();
+				for other in fitting {
+					view.start(other);
+				}
+
+				if !view.advance() {
+					break;
+				}
+			}
+
+			view.total_wait()
+		})
+	}
+}
+
+/// Starts a fitting job drawn at random, as a lower reference to compare the schedulers to.
+/// Draws only depend on the seed, the time and the length of the queue, so the same seed
+/// gives the same schedule. The seed of the run is used unless one is given.
+#[derive(Debug, Clone, Default)]
+pub struct RandomFit {
+	pub seed: Option<u64>,
+	rng: SimRng,
+}
+
+impl FromParams for RandomFit {
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["seed"])?;
+
+		let seed = params.get("seed")?;
+		Ok(Self {
+			seed,
+			rng: SimRng::new(seed.unwrap_or(0)),
+		})
+	}
+}
+
+impl Scheduler for RandomFit {
+	fn name(&self) -> &'static str {
+		"RandomFit"
+	}
+
+	fn label(&self) -> String {
+		label(self.name(), &[("seed", self.seed.map(|seed| seed.to_string()))])
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		let fitting = jobs.iter().enumerate()
+			.filter(|(_, job)| cluster.fits(job))
+			.map(|(idx, _)| idx)
+			.collect::<Vec<_>>();
+
+		if fitting.is_empty() {
+			return None;
+		}
+
+		let mut rng = self.rng.keyed(Stream::Scheduler, clock.rotate_left(32) ^ jobs.len() as u64);
+		Some(fitting[rng.random_range(0..fitting.len())])
+	}
+
+	fn seed(&mut self, rng: &SimRng) {
+		if self.seed.is_none() {
+			self.rng = *rng;
+		}
+	}
+}
+
+/// Offline reference knowing the actual run times of the jobs, which no online scheduler
+/// does: jobs are taken shortest actual run time first, the first one getting a reservation
+/// at the exact time enough nodes are released, and the others starting right away if they
+/// end before it or only use nodes it won't need. Compare the schedulers to it as a best case.
+pub struct Oracle;
+
+impl Oracle {
+	/// Time the job has left to run, counting the time it needs to restore itself if it was preempted.
+	fn remaining(job: &Job) -> Clock {
+		job.restart_overhead + job.remaining_time
+	}
+
+	/// Exact shadow time and extra nodes for the given job, see `EasyBackfill::shadow`.
+	fn shadow(head: &Job, cluster: &Cluster) -> (Clock, u32) {
+		let mut running = cluster.running_jobs.values()
+			.map(|job| (job.end_time(), job.nodes))
+			.collect::<Vec<_>>();
+		running.sort_unstable();
+
+		let mut available = cluster.available_nodes;
+		let mut shadow_time = None;
+
+		for (end, nodes) in running {
+			match shadow_time {
+				Some(shadow) if end > shadow => break,
+				_ => {
+					available += nodes;
+					if shadow_time.is_none() && available >= head.nodes {
+						shadow_time = Some(end);
+					}
+				}
+			}
+		}
+
+		match shadow_time {
+			Some(shadow_time) => (shadow_time, available - head.nodes),
+			None => (Clock::MAX, 0),
+		}
+	}
+}
+
+/// Starts the jobs at the time they started in the trace (`Job::trace_start`), as soon as
+/// possible when the trace doesn't tell or the nodes are busy, to compare the simulated
+/// policies to what the real system did. Used by the validation mode of the engine, which
+/// also runs it at the recorded start times, see `EngineConfig::validation`.
+pub struct Replay;
+
+impl Replay {
+	fn due(clock: Clock, jobs: &ReadyQueue) -> Vec<usize> {
+		let mut due = (0..jobs.len())
+			.filter(|&idx| jobs[idx].trace_start.is_none_or(|start| start <= clock))
+			.collect::<Vec<_>>();
+
+		due.sort_unstable_by_key(|&idx| (jobs[idx].trace_start.unwrap_or(0), jobs[idx].id));
+		due
+	}
+}
+
+impl Scheduler for Replay {
+	fn name(&self) -> &'static str {
+		"Replay"
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		Self::due(clock, jobs).into_iter().find(|&idx| cluster.fits(&jobs[idx]))
+	}
+
+	fn schedule_batch(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		pack(jobs, cluster, Self::due(clock, jobs), false)
+	}
+
+	fn complete_batches(&self) -> bool {
+		true
+	}
+}
+
+impl Scheduler for Oracle {
+	fn name(&self) -> &'static str {
+		"Oracle"
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		let mut order = (0..jobs.len()).collect::<Vec<_>>();
+		order.sort_unstable_by_key(|&idx| (Self::remaining(&jobs[idx]), jobs[idx].id));
+
+		let head = &jobs[order[0]];
+		if cluster.fits(head) {
+			return Some(order[0]);
+		}
+
+		let (shadow_time, extra_nodes) = Self::shadow(head, cluster);
+
+		order[1..].iter().copied().find(|&idx| {
+			let job = &jobs[idx];
+			cluster.fits(job) && (clock + Self::remaining(job) <= shadow_time || job.nodes <= extra_nodes)
+		})
+	}
+}
+
+/// Which queued jobs the primary scheduler of a `Composite` gets, the others going to the
+/// secondary one. Written `none`, `nodes:N` or `time:SECONDS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+	/// Every job: the secondary scheduler only runs when the primary one starts nothing
+	None,
+	/// Jobs on at most this many nodes
+	Nodes(u32),
+	/// Jobs requesting at most this many seconds
+	Time(Clock),
+}
+
+impl Split {
+	/// The jobs of the queue given to the primary scheduler or to the secondary one, and their
+	/// positions in the queue.
+	fn part(&self, jobs: &ReadyQueue, primary: bool) -> (ReadyQueue, Vec<usize>) {
+		let mut part = ReadyQueue::new();
+		let mut positions = Vec::new();
+
+		for (idx, job) in jobs.iter().enumerate().filter(|(_, job)| self.primary(job) == primary) {
+			part.push(job.clone());
+			positions.push(idx);
+		}
+
+		(part, positions)
+	}
+
+	fn primary(&self, job: &Job) -> bool {
+		match self {
+			Split::None => true,
+			Split::Nodes(nodes) => job.nodes <= *nodes,
+			Split::Time(time) => job.requested_run_time <= *time,
+		}
+	}
+}
+
+impl FromStr for Split {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.split_once(':') {
+			None if s == "none" => Ok(Split::None),
+			Some(("nodes", nodes)) => nodes.parse().map(Split::Nodes).map_err(|_| ()),
+			Some(("time", time)) => time.parse().map(Split::Time).map_err(|_| ()),
+			_ => Err(()),
+		}
+	}
+}
+
+impl Display for Split {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Split::None => write!(f, "none"),
+			Split::Nodes(nodes) => write!(f, "nodes:{}", nodes),
+			Split::Time(time) => write!(f, "time:{}", time),
+		}
+	}
+}
+
+/// Combines two schedulers given as specs, e.g. `composite(primary=sjf, secondary=fcfs,
+/// split=time:3600)` for SJF on the jobs requesting up to an hour and FCFS on the others.
+/// The primary scheduler starts jobs first, and the secondary one only when it starts none,
+/// each seeing the jobs `split` gives it on the whole cluster. Composites can be nested.
+pub struct Composite {
+	pub primary: Box<dyn Scheduler>,
+	pub secondary: Box<dyn Scheduler>,
+	pub split: Split,
+}
+
+impl Default for Composite {
+	fn default() -> Self {
+		Self {
+			primary: Box::new(SJF),
+			secondary: Box::new(FCFS),
+			split: Split::Time(3600),
+		}
+	}
+}
+
+impl FromParams for Composite {
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["primary", "secondary", "split"])?;
+
+		let default = Self::default();
+		let build = |key: &str, default: Box<dyn Scheduler>| match params.get::<String>(key)? {
+			Some(spec) => spec.parse::<SchedulerSpec>()?.build(),
+			None => Ok(default),
+		};
+
+		Ok(Self {
+			primary: build("primary", default.primary)?,
+			secondary: build("secondary", default.secondary)?,
+			split: params.get_or("split", default.split)?,
+		})
+	}
+}
+
+impl Composite {
+	/// Both schedulers, the primary one first, with whether they get the jobs of the primary one.
+	fn schedulers(&mut self) -> [(&mut dyn Scheduler, bool); 2] {
+		[(self.primary.as_mut(), true), (self.secondary.as_mut(), false)]
+	}
+}
+
+impl Scheduler for Composite {
+	fn name(&self) -> &'static str {
+		"Composite"
+	}
+
+	fn label(&self) -> String {
+		label(self.name(), &[
+			("primary", Some(self.primary.label())),
+			("secondary", Some(self.secondary.label())),
+			("split", Some(self.split.to_string())),
+		])
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		self.schedule_batch(clock, jobs, cluster).first().copied()
+	}
+
+	fn schedule_batch(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		self.schedule_shaped(clock, jobs, cluster).into_iter().map(|(idx, _)| idx).collect()
+	}
+
+	fn schedule_shaped(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<(usize, Option<usize>)> {
+		let split = self.split;
+		for (scheduler, primary) in self.schedulers() {
+			let batch = match split {
+				Split::None => scheduler.schedule_shaped(clock, jobs, cluster),
+				_ => {
+					let (part, positions) = split.part(jobs, primary);
+					match part.is_empty() {
+						true => Vec::new(),
+						false => scheduler.schedule_shaped(clock, &part, cluster).into_iter().map(|(idx, shape)| (positions[idx], shape)).collect(),
+					}
+				}
+			};
+
+			if !batch.is_empty() {
+				return batch;
+			}
+		}
+
+		Vec::new()
+	}
+
+	fn preempt(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<u32> {
+		let mut preempted = Vec::new();
+
+		let split = self.split;
+		for (scheduler, primary) in self.schedulers() {
+			let ids = match split {
+				Split::None => scheduler.preempt(clock, jobs, cluster),
+				_ => scheduler.preempt(clock, &split.part(jobs, primary).0, cluster),
+			};
+
+			for id in ids {
+				if !preempted.contains(&id) {
+					preempted.push(id);
+				}
+			}
+		}
+
+		preempted
+	}
+
+	fn resize(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<(u32, u32)> {
+		let mut resized: Vec<(u32, u32)> = Vec::new();
+
+		let split = self.split;
+		for (scheduler, primary) in self.schedulers() {
+			let sizes = match split {
+				Split::None => scheduler.resize(clock, jobs, cluster),
+				_ => scheduler.resize(clock, &split.part(jobs, primary).0, cluster),
+			};
+
+			for (id, nodes) in sizes {
+				if !resized.iter().any(|&(resized, _)| resized == id) {
+					resized.push((id, nodes));
+				}
+			}
+		}
+
+		resized
+	}
+
+	fn mark(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<(u32, Mark)> {
+		let split = self.split;
+		let mut marks = Vec::new();
+		for (scheduler, primary) in self.schedulers() {
+			marks.extend(match split {
+				Split::None => scheduler.mark(clock, jobs, cluster),
+				_ => scheduler.mark(clock, &split.part(jobs, primary).0, cluster),
+			});
+		}
+
+		marks
+	}
+
+	/// The primary scheduler explains every job when it sees them all, else each scheduler
+	/// explains its own jobs.
+	fn explain(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<Decision> {
+		let split = self.split;
+		match split {
+			Split::None => self.primary.explain(clock, jobs, cluster),
+			_ => {
+				let mut decisions = Vec::new();
+				for (scheduler, primary) in self.schedulers() {
+					decisions.extend(scheduler.explain(clock, &split.part(jobs, primary).0, cluster));
+				}
+				decisions
+			}
+		}
+	}
+
+	fn on_job_arrival(&mut self, clock: u64, job: &Job) {
+		self.primary.on_job_arrival(clock, job);
+		self.secondary.on_job_arrival(clock, job);
+	}
+
+	fn on_job_finish(&mut self, clock: u64, job: &Job) {
+		self.primary.on_job_finish(clock, job);
+		self.secondary.on_job_finish(clock, job);
+	}
+
+	fn on_job_cancel(&mut self, clock: u64, job: &Job) {
+		self.primary.on_job_cancel(clock, job);
+		self.secondary.on_job_cancel(clock, job);
+	}
+
+	fn on_tick(&mut self, clock: u64) {
+		self.primary.on_tick(clock);
+		self.secondary.on_tick(clock);
+	}
+
+	fn seed(&mut self, rng: &SimRng) {
+		self.primary.seed(rng);
+		self.secondary.seed(rng);
+	}
+}
+
+/// Short and long queues with node pools of their own, as production sites often run them:
+/// jobs requesting at most `short_time` seconds go to the short queue, whose pool is the
+/// `short_share` of the nodes of each partition, and the other jobs to the long queue, whose
+/// pool is the rest. Each queue runs EASY backfilling in its pool, jobs of a class never
+/// holding more nodes than its pool, so long jobs can't keep the short ones waiting and the
+/// other way around. Jobs wider than the pool of their class may take just the nodes they need.
+#[derive(Debug, Clone)]
+pub struct TwoLevel {
+	/// Longest requested time of the short jobs, in seconds
+	pub short_time: Clock,
+	/// Share of the nodes kept for the short jobs, between 0 and 1
+	pub short_share: f64,
+}
+
+impl Default for TwoLevel {
+	fn default() -> Self {
+		Self {
+			short_time: 3600,
+			short_share: 0.25,
+		}
+	}
+}
+
+impl FromParams for TwoLevel {
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["short_time", "short_share"])?;
+
+		let default = Self::default();
+		let short_share = match params.get_or("short_share", default.short_share)? {
+			share if (0.0..=1.0).contains(&share) => share,
+			_ => return Err(SchedulerError::InvalidParam("short_share".to_string(), params.get::<String>("short_share")?.unwrap_or_default())),
+		};
+
+		Ok(Self {
+			short_time: params.get_or("short_time", default.short_time)?,
+			short_share,
+		})
+	}
+}
+
+impl TwoLevel {
+	fn is_short(&self, job: &Job) -> bool {
+		job.requested_run_time <= self.short_time
+	}
+
+	/// Nodes the jobs of the class of `job` may hold at once, on a cluster of `total_nodes`.
+	fn pool(&self, job: &Job, total_nodes: u32) -> u32 {
+		let short = (total_nodes as f64 * self.short_share).round() as u32;
+		let pool = if self.is_short(job) { short } else { total_nodes - short };
+		pool.max(job.nodes)
+	}
+
+	/// Nodes held by the running jobs of the long class and of the short one.
+	fn used(&self, cluster: &Cluster) -> [u32; 2] {
+		let mut used = [0, 0];
+		for job in cluster.running_jobs.values() {
+			used[self.is_short(job) as usize] += job.nodes;
+		}
+		used
+	}
+
+	/// Shadow time and extra nodes of the first job of a class, like `EasyBackfill::shadow`
+	/// but counting the nodes of its pool only.
+	fn shadow(&self, clock: Clock, head: &Job, cluster: &Cluster, mut used: u32) -> (Clock, u32) {
+		let mut running = cluster.running_jobs.values()
+			.map(|job| (job.estimated_end(clock).max(clock), job.nodes, self.is_short(job) == self.is_short(head)))
+			.collect::<Vec<_>>();
+		running.sort_unstable();
+
+		let pool = self.pool(head, cluster.total_nodes);
+		let mut available = cluster.available_nodes;
+		let mut shadow_time = None;
+
+		for (end, nodes, same_class) in running {
+			match shadow_time {
+				Some(shadow) if end > shadow => break,
+				_ => {
+					available += nodes;
+					if same_class {
+						used -= nodes;
+					}
+
+					if shadow_time.is_none() && pool.saturating_sub(used).min(available) >= head.nodes {
+						shadow_time = Some(end);
+					}
+				}
+			}
+		}
+
+		match shadow_time {
+			Some(shadow_time) => (shadow_time, pool.saturating_sub(used).min(available) - head.nodes),
+			None => (Clock::MAX, 0),
+		}
+	}
+}
+
+impl Scheduler for TwoLevel {
+	fn name(&self) -> &'static str {
+		"TwoLevel"
+	}
+
+	fn label(&self) -> String {
+		let default = Self::default();
+
+		label(self.name(), &[
+			("short_time", (self.short_time != default.short_time).then(|| self.short_time.to_string())),
+			("short_share", (self.short_share != default.short_share).then(|| self.short_share.to_string())),
+		])
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		let used = self.used(cluster);
+		// reservation of the first job of the long queue and of the short one, once met
+		let mut shadows = [None, None];
+
+		for (idx, job) in jobs.iter().enumerate() {
+			let class = self.is_short(job) as usize;
+			let available = self.pool(job, cluster.total_nodes).saturating_sub(used[class]).min(cluster.available_nodes);
+			let fits = job.nodes <= available && cluster.fits(job);
+			match &mut shadows[class] {
+				Some((shadow_time, extra_nodes)) => if fits && (clock + job.estimate() <= *shadow_time || job.nodes <= *extra_nodes) {
+					return Some(idx);
+				},
+				shadow => {
+					if fits {
+						return Some(idx);
+					}
+
+					*shadow = Some(self.shadow(clock, job, cluster, used[class]));
+				}
+			}
+		}
+
+		None
+	}
+}
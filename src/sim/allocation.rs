@@ -0,0 +1,172 @@
+use std::{convert::TryFrom, fmt::Display, ops::Range, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Free blocks tried as the center of the allocation of a job on a torus, the largest first
+const TORUS_SEEDS: usize = 8;
+
+/// How the nodes given to a job are picked among the free ones. This doesn't change when
+/// jobs start, but how scattered they are over the machine, and so how far their processes
+/// communicate (see `EngineReport::allocation_span`).
+///
+/// Written `first-fit`, `best-fit`, `contiguous`, `torus:X:Y:Z` or `best-fit-memory`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum AllocationPolicy {
+	/// The free nodes with the lowest IDs
+	#[default]
+	FirstFit,
+	/// The smallest free block holding the whole job, else the largest blocks first
+	BestFit,
+	/// The first free block holding the whole job, else the largest blocks first
+	Contiguous,
+	/// Nodes laid out on a 3D torus of X×Y×Z nodes by increasing ID, along X then Y then Z:
+	/// the free nodes the fewest hops away from each other
+	Torus(u32, u32, u32),
+	/// The free nodes of the classes with the least memory per node the job can run on first,
+	/// keeping the nodes with more memory for the jobs needing it, by lowest IDs within a class
+	BestFitMemory,
+}
+
+impl AllocationPolicy {
+	/// Picks `count` nodes among the free blocks, given by increasing node ID, returning
+	/// the picked nodes as sorted ranges, each within a free block.
+	pub fn select(&self, free: &[Range<u32>], count: u32) -> Vec<Range<u32>> {
+		if count == 0 {
+			return Vec::new();
+		}
+
+		let holding = free.iter().filter(|block| block.end - block.start >= count);
+		let block = match self {
+			// the cluster gives the free blocks of the classes by increasing memory
+			AllocationPolicy::FirstFit | AllocationPolicy::BestFitMemory => return take(free.iter().cloned(), count),
+			AllocationPolicy::BestFit => holding.min_by_key(|block| block.end - block.start),
+			AllocationPolicy::Contiguous => holding.min_by_key(|block| block.start),
+			AllocationPolicy::Torus(x, y, z) => return torus([*x, *y, *z], free, count),
+		};
+
+		match block {
+			Some(block) => take(std::iter::once(block.clone()), count),
+			None => {
+				// as few blocks as possible
+				let mut blocks = free.to_vec();
+				blocks.sort_by_key(|block| (std::cmp::Reverse(block.end - block.start), block.start));
+
+				let mut taken = take(blocks.into_iter(), count);
+				taken.sort_by_key(|range| range.start);
+				taken
+			}
+		}
+	}
+}
+
+/// The first `count` nodes of the blocks, in order.
+fn take(blocks: impl Iterator<Item = Range<u32>>, count: u32) -> Vec<Range<u32>> {
+	let mut remaining = count;
+	let mut taken = Vec::new();
+
+	for block in blocks {
+		if remaining == 0 {
+			break;
+		}
+
+		let length = (block.end - block.start).min(remaining);
+		taken.push(block.start..block.start + length);
+		remaining -= length;
+	}
+
+	taken
+}
+
+/// Hops between two nodes of a torus, the shortest way round along each dimension.
+fn hops(dims: [u32; 3], a: u32, b: u32) -> u32 {
+	let coords = |node: u32| [node % dims[0], node / dims[0] % dims[1], node / (dims[0] * dims[1]) % dims[2]];
+	let (a, b) = (coords(a), coords(b));
+
+	(0..3).map(|dim| {
+		let distance = a[dim].abs_diff(b[dim]);
+		distance.min(dims[dim] - distance)
+	}).sum()
+}
+
+/// The `count` free nodes closest to the first node of one of the largest free blocks,
+/// keeping the block whose nodes are the fewest hops away in total.
+fn torus(dims: [u32; 3], free: &[Range<u32>], count: u32) -> Vec<Range<u32>> {
+	let nodes = free.iter().flat_map(|block| block.clone()).collect::<Vec<_>>();
+
+	let mut seeds = free.to_vec();
+	seeds.sort_by_key(|block| (std::cmp::Reverse(block.end - block.start), block.start));
+
+	let mut best: Option<(u64, Vec<u32>)> = None;
+	for seed in seeds.iter().take(TORUS_SEEDS).map(|block| block.start) {
+		let mut distances = nodes.iter().map(|&node| (hops(dims, seed, node), node)).collect::<Vec<_>>();
+		distances.select_nth_unstable(count as usize - 1);
+		distances.truncate(count as usize);
+
+		let total = distances.iter().map(|&(distance, _)| distance as u64).sum::<u64>();
+		if best.as_ref().is_none_or(|(best_total, _)| total < *best_total) {
+			best = Some((total, distances.into_iter().map(|(_, node)| node).collect()));
+		}
+	}
+
+	let mut picked = best.map_or_else(Vec::new, |(_, nodes)| nodes);
+	picked.sort_unstable();
+
+	let mut ranges: Vec<Range<u32>> = Vec::new();
+	for node in picked {
+		match ranges.last_mut() {
+			Some(last) if last.end == node => last.end += 1,
+			_ => ranges.push(node..node + 1),
+		}
+	}
+
+	ranges
+}
+
+impl FromStr for AllocationPolicy {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid allocation policy '{}' (expected first-fit, best-fit, contiguous, torus:X:Y:Z or best-fit-memory)", s);
+		let size = |value: &str| match value.parse::<u32>() {
+			Ok(size) if size > 0 => Ok(size),
+			_ => Err(invalid()),
+		};
+
+		let parts = s.split(':').map(str::trim).collect::<Vec<_>>();
+		match parts.as_slice() {
+			["first-fit"] => Ok(AllocationPolicy::FirstFit),
+			["best-fit"] => Ok(AllocationPolicy::BestFit),
+			["best-fit-memory"] => Ok(AllocationPolicy::BestFitMemory),
+			["contiguous"] => Ok(AllocationPolicy::Contiguous),
+			["torus", x, y, z] => Ok(AllocationPolicy::Torus(size(x)?, size(y)?, size(z)?)),
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for AllocationPolicy {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<AllocationPolicy> for String {
+	fn from(policy: AllocationPolicy) -> Self {
+		policy.to_string()
+	}
+}
+
+impl Display for AllocationPolicy {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			AllocationPolicy::FirstFit => write!(f, "first-fit"),
+			AllocationPolicy::BestFit => write!(f, "best-fit"),
+			AllocationPolicy::Contiguous => write!(f, "contiguous"),
+			AllocationPolicy::Torus(x, y, z) => write!(f, "torus:{}:{}:{}", x, y, z),
+			AllocationPolicy::BestFitMemory => write!(f, "best-fit-memory"),
+		}
+	}
+}
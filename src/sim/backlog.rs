@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use super::Clock;
+
+/// How much work waited over a run, to tell the effects of the scheduler from plain
+/// overload: the number of queued jobs and the nodes they requested altogether (the
+/// backlog) weighted by how long they lasted, and the time the machine was saturated, the
+/// queued jobs requesting more nodes than were free so that no scheduler could have run
+/// them all.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Backlog {
+	/// Queued jobs and the nodes they requested, since the last change
+	jobs: usize,
+	nodes: u64,
+	saturated: bool,
+	/// Time of the first record, from which the averages are taken
+	start: Option<Clock>,
+	since: Clock,
+	/// Job-seconds and node-seconds queued so far
+	job_time: u128,
+	node_time: u128,
+	max_jobs: usize,
+	max_nodes: u64,
+	saturated_time: Clock,
+}
+
+/// Summary of a `Backlog`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BacklogSummary {
+	pub avg_queue_length: f64,
+	pub max_queue_length: usize,
+	pub avg_backlog: f64,
+	pub max_backlog: u64,
+	pub saturated_time: Clock,
+}
+
+impl Backlog {
+	/// Records that `jobs` jobs requesting `nodes` nodes are queued while `free` nodes are
+	/// free, from `clock` until the next call.
+	pub fn record(&mut self, clock: Clock, jobs: usize, nodes: u64, free: u32) {
+		self.start.get_or_insert(clock);
+		self.count_until(clock);
+		self.jobs = jobs;
+		self.nodes = nodes;
+		self.saturated = nodes > free as u64;
+		self.max_jobs = self.max_jobs.max(jobs);
+		self.max_nodes = self.max_nodes.max(nodes);
+	}
+
+	/// Summary over the simulation ending at `end`, the averages being taken from the first record.
+	pub fn finish(&mut self, end: Clock) -> BacklogSummary {
+		self.count_until(end);
+
+		let span = end.saturating_sub(self.start.unwrap_or(end));
+		let average = |total: u128| if span == 0 { 0.0 } else { total as f64 / span as f64 };
+		BacklogSummary {
+			avg_queue_length: average(self.job_time),
+			max_queue_length: self.max_jobs,
+			avg_backlog: average(self.node_time),
+			max_backlog: self.max_nodes,
+			saturated_time: self.saturated_time,
+		}
+	}
+
+	fn count_until(&mut self, clock: Clock) {
+		let elapsed = clock.saturating_sub(self.since);
+		self.job_time += self.jobs as u128 * elapsed as u128;
+		self.node_time += self.nodes as u128 * elapsed as u128;
+		if self.saturated {
+			self.saturated_time += elapsed;
+		}
+
+		self.since = clock;
+	}
+}
@@ -0,0 +1,106 @@
+use std::{collections::HashMap, io::BufRead};
+
+use serde::Deserialize;
+
+use super::{Clock, EngineError, workload::{SwfJob, WorkloadMeta}};
+
+/// A workload in the JSON format of Batsim: resources, jobs and the profiles they run.
+#[derive(Debug, Deserialize)]
+struct BatsimWorkload {
+	nb_res: u32,
+	jobs: Vec<BatsimJob>,
+	#[serde(default)]
+	profiles: HashMap<String, BatsimProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatsimJob {
+	id: JobId,
+	subtime: f64,
+	/// Negative when the job has no walltime
+	#[serde(default)]
+	walltime: Option<f64>,
+	res: u32,
+	profile: String,
+}
+
+/// Batsim IDs are numbers or strings such as `w0!42`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JobId {
+	Number(u32),
+	Text(String),
+}
+
+impl JobId {
+	/// The number after the workload name, if any.
+	fn number(&self) -> Option<u32> {
+		match self {
+			JobId::Number(id) => Some(*id),
+			JobId::Text(id) => id.rsplit('!').next()?.parse().ok(),
+		}
+	}
+}
+
+#[derive(Debug, Deserialize)]
+struct BatsimProfile {
+	#[serde(rename = "type")]
+	kind: String,
+	/// Run time of `delay` profiles
+	#[serde(default)]
+	delay: Option<f64>,
+}
+
+/// Reads a Batsim workload as SWF records, sorted by submission time.
+///
+/// Resources are nodes, so the records request one processor per resource and the header
+/// tells one processor per node. Jobs run for the delay of their profile, or for their
+/// walltime for profiles that simulate the application (which can't be replayed here),
+/// and are skipped if they have neither.
+pub fn read<R: BufRead>(reader: R) -> Result<(WorkloadMeta, Vec<SwfJob>), EngineError> {
+	let workload: BatsimWorkload = serde_json::from_reader(reader)
+		.map_err(|why| EngineError::InvalidWorkload(why.to_string()))?;
+
+	let to_clock = |time: f64| time.max(0.0).round() as Clock;
+
+	let mut jobs = workload.jobs.iter().enumerate().map(|(idx, job)| {
+		let walltime = job.walltime.filter(|&walltime| walltime >= 0.0).map(to_clock);
+		let delay = workload.profiles.get(&job.profile)
+			.filter(|profile| profile.kind == "delay")
+			.and_then(|profile| profile.delay)
+			.map(to_clock);
+
+		SwfJob {
+			job_number: job.id.number().unwrap_or(idx as u32 + 1),
+			submit_time: to_clock(job.subtime),
+			wait_time: None,
+			run_time: delay.or(walltime),
+			allocated_procs: None,
+			avg_cpu_time: None,
+			used_memory: None,
+			requested_procs: Some(job.res),
+			requested_time: walltime,
+			requested_memory: None,
+			status: None,
+			user_id: None,
+			group_id: None,
+			executable: None,
+			queue: None,
+			partition: None,
+			preceding_job: None,
+			think_time: None,
+		}
+	}).collect::<Vec<_>>();
+
+	jobs.sort_by_key(|job| job.submit_time);
+
+	let meta = WorkloadMeta {
+		max_jobs: Some(jobs.len() as u32),
+		max_records: Some(jobs.len() as u32),
+		max_nodes: Some(workload.nb_res),
+		max_procs: Some(workload.nb_res),
+		..WorkloadMeta::default()
+	};
+
+	Ok((meta, jobs))
+}
@@ -0,0 +1,91 @@
+use std::{convert::TryFrom, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::Clock;
+
+/// When a simulation stops before the end of the workload, for exploratory sweeps over huge
+/// traces to finish quickly: after some days of simulated time, some started jobs or some
+/// seconds of real time, whichever comes first. The report then only covers the jobs that
+/// finished by then and is flagged as truncated.
+///
+/// Written `none` or `days:N`, `jobs:N` and `seconds:N` separated by commas, e.g.
+/// `days:30,seconds:60`. The real time budget is ignored in WebAssembly, without a clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Budget {
+	/// Days of simulated time, from time 0
+	pub days: Option<u32>,
+	/// Jobs started, restarts included
+	pub jobs: Option<usize>,
+	/// Seconds of real time spent simulating
+	pub seconds: Option<u64>,
+}
+
+impl Budget {
+	pub fn is_none(&self) -> bool {
+		self == &Self::default()
+	}
+
+	/// Whether the simulation stops before the events at `clock`, after starting `jobs` jobs
+	/// in `millis` milliseconds of real time, if it is measured.
+	pub fn exhausted(&self, clock: Clock, jobs: usize, millis: Option<u128>) -> bool {
+		self.days.is_some_and(|days| clock > days as Clock * 86400)
+			|| self.jobs.is_some_and(|max| jobs >= max)
+			|| self.seconds.zip(millis).is_some_and(|(seconds, millis)| millis >= seconds as u128 * 1000)
+	}
+}
+
+impl FromStr for Budget {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid budget '{}' (expected none or days:N, jobs:N and seconds:N separated by commas)", s);
+		let mut budget = Budget::default();
+
+		if s.trim() == "none" {
+			return Ok(budget);
+		}
+
+		for part in s.split(',') {
+			match part.trim().split_once(':') {
+				Some(("days", days)) => budget.days = Some(days.trim().parse().map_err(|_| invalid())?),
+				Some(("jobs", jobs)) => budget.jobs = Some(jobs.trim().parse().map_err(|_| invalid())?),
+				Some(("seconds", seconds)) => budget.seconds = Some(seconds.trim().parse().map_err(|_| invalid())?),
+				_ => return Err(invalid()),
+			}
+		}
+
+		Ok(budget)
+	}
+}
+
+impl TryFrom<String> for Budget {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<Budget> for String {
+	fn from(budget: Budget) -> Self {
+		budget.to_string()
+	}
+}
+
+impl Display for Budget {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let parts = [
+			self.days.map(|days| format!("days:{}", days)),
+			self.jobs.map(|jobs| format!("jobs:{}", jobs)),
+			self.seconds.map(|seconds| format!("seconds:{}", seconds)),
+		];
+		let parts = parts.iter().flatten().map(String::as_str).collect::<Vec<_>>();
+
+		match parts.is_empty() {
+			true => write!(f, "none"),
+			false => write!(f, "{}", parts.join(",")),
+		}
+	}
+}
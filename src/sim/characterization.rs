@@ -0,0 +1,237 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use hashbrown::{HashMap, HashSet};
+use serde::Serialize;
+
+use super::{Clock, cluster::DEFAULT_CORES_PER_NODE, job::Job, report::{Distribution, RUN_TIME_BUCKETS, WIDTH_BUCKETS}, workload::Workload};
+
+const DAY: Clock = 86400;
+
+/// Users listed by `WorkloadStats` when displayed, the ones asking the most work first.
+const TOP_USERS: usize = 10;
+
+/// Jobs of a bucket of `WorkloadStats`, by width or by run time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Bucket {
+	pub bucket: &'static str,
+	pub jobs: usize,
+	/// Share of the node-seconds of the workload, in percent
+	pub work_percent: f64,
+}
+
+/// Jobs submitted over a day of the trace.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Day {
+	/// Day of the trace, counting from 0
+	pub day: u64,
+	pub jobs: usize,
+	/// Node-seconds asked by the jobs submitted that day
+	pub work: u64,
+}
+
+/// Jobs of a user and its share of the workload.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UserShare {
+	/// SWF user ID, None for the jobs without one
+	pub user: Option<u32>,
+	pub jobs: usize,
+	pub work: u64,
+	/// Share of the node-seconds of the workload, in percent
+	pub work_percent: f64,
+}
+
+/// What a workload asks of a machine, computed from the trace alone without simulating it,
+/// to sanity-check traces and pick the node counts to simulate, see `Workload::characterize`.
+/// Only the jobs a simulation would load count, sized in nodes like it would size them.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkloadStats {
+	pub jobs: usize,
+	/// Records without a run time or a number of processors, left out
+	pub unusable: usize,
+	pub cores_per_node: u32,
+	/// Nodes of the traced machine, from the header
+	pub machine_nodes: Option<u32>,
+	/// Time between the first and the last submission
+	pub span: Clock,
+	pub nodes: Distribution,
+	pub run_time: Distribution,
+	pub requested_time: Distribution,
+	/// Average run time over requested time of the jobs that ran, 1 for perfect estimates
+	pub estimate_accuracy: f64,
+	/// Time between consecutive submissions
+	pub interarrival: Distribution,
+	/// Node-seconds asked by the jobs altogether
+	pub work: u64,
+	/// Nodes the jobs keep busy on average over the span, the size at which the machine is
+	/// exactly loaded
+	pub demand: f64,
+	pub by_width: Vec<Bucket>,
+	pub by_run_time: Vec<Bucket>,
+	pub days: Vec<Day>,
+	/// Jobs submitted at each hour of the day, in UTC when the header gives `UnixStartTime`,
+	/// counted from the start of the trace otherwise
+	pub hours: Vec<usize>,
+	/// Every user, the ones asking the most work first
+	pub users: Vec<UserShare>,
+	pub groups: usize,
+	/// Nodes of every job, in increasing order
+	#[serde(skip)]
+	widths: Vec<u32>,
+}
+
+impl WorkloadStats {
+	/// Share of a machine of `nodes` nodes the jobs would keep busy over the span, above 1
+	/// when it can't keep up.
+	pub fn offered_load(&self, nodes: u32) -> f64 {
+		self.demand / nodes.max(1) as f64
+	}
+
+	/// Jobs wider than a machine of `nodes` nodes, which it would reject.
+	pub fn too_wide(&self, nodes: u32) -> usize {
+		self.widths.len() - self.widths.partition_point(|&width| width <= nodes)
+	}
+}
+
+impl Workload {
+	/// Characterizes the jobs of the workload, `cores_per_node` defaulting to the one of
+	/// the header, see `WorkloadMeta::procs_per_node`.
+	pub fn characterize(&self, cores_per_node: Option<u32>) -> WorkloadStats {
+		let cores_per_node = cores_per_node.or(self.meta.procs_per_node()).unwrap_or(DEFAULT_CORES_PER_NODE).max(1);
+		let jobs = self.jobs.iter().filter_map(|swf| swf.to_job(cores_per_node)).collect::<Vec<_>>();
+		let work = |job: &Job| job.nodes as u64 * job.run_time;
+		let total_work = jobs.iter().map(work).sum::<u64>();
+		let percent = |part: u64| if total_work == 0 { 0.0 } else { part as f64 * 100.0 / total_work as f64 };
+
+		let mut submits = jobs.iter().map(|job| job.submit_time).collect::<Vec<_>>();
+		submits.sort_unstable();
+		let mut widths = jobs.iter().map(|job| job.nodes).collect::<Vec<_>>();
+		widths.sort_unstable();
+		let span = submits.last().zip(submits.first()).map_or(0, |(last, first)| last - first);
+		let mut interarrival = submits.windows(2).map(|pair| (pair[1] - pair[0]) as f64).collect::<Vec<_>>();
+
+		let buckets = |bound: &dyn Fn(&Job) -> usize, names: Vec<&'static str>| {
+			let mut counts = vec![(0, 0); names.len()];
+			for job in &jobs {
+				let count = &mut counts[bound(job)];
+				count.0 += 1;
+				count.1 += work(job);
+			}
+
+			names.into_iter().zip(counts)
+				.map(|(bucket, (jobs, work))| Bucket { bucket, jobs, work_percent: percent(work) })
+				.collect::<Vec<_>>()
+		};
+		let by_width = buckets(
+			&|job| WIDTH_BUCKETS.iter().position(|&(_, max)| job.nodes <= max).unwrap_or(WIDTH_BUCKETS.len() - 1),
+			WIDTH_BUCKETS.iter().map(|&(name, _)| name).collect(),
+		);
+		let by_run_time = buckets(
+			&|job| RUN_TIME_BUCKETS.iter().position(|&(_, max)| job.run_time <= max).unwrap_or(RUN_TIME_BUCKETS.len() - 1),
+			RUN_TIME_BUCKETS.iter().map(|&(name, _)| name).collect(),
+		);
+
+		let origin = submits.first().copied().unwrap_or(0);
+		let mut days: BTreeMap<u64, Day> = BTreeMap::new();
+		let mut hours = vec![0; 24];
+		let mut users: HashMap<Option<u32>, (usize, u64)> = HashMap::new();
+		let mut groups = HashSet::new();
+		for job in &jobs {
+			let day = (job.submit_time - origin) / DAY;
+			let day = days.entry(day).or_insert(Day { day, jobs: 0, work: 0 });
+			day.jobs += 1;
+			day.work += work(job);
+
+			let time_of_day = match self.meta.unix_start_time {
+				Some(start) => (start + job.submit_time as i64).rem_euclid(DAY as i64) as Clock,
+				None => job.submit_time % DAY,
+			};
+			hours[(time_of_day / 3600) as usize] += 1;
+
+			let user = users.entry(job.user_id).or_default();
+			user.0 += 1;
+			user.1 += work(job);
+			groups.extend(job.group_id);
+		}
+
+		let mut users = users.into_iter()
+			.map(|(user, (jobs, work))| UserShare { user, jobs, work, work_percent: percent(work) })
+			.collect::<Vec<_>>();
+		users.sort_by(|a, b| b.work.cmp(&a.work).then(a.user.cmp(&b.user)));
+
+		let accuracies = jobs.iter()
+			.filter(|job| job.requested_run_time > 0)
+			.map(|job| job.run_time as f64 / job.requested_run_time as f64)
+			.collect::<Vec<_>>();
+
+		WorkloadStats {
+			jobs: jobs.len(),
+			unusable: self.jobs.len() - jobs.len(),
+			cores_per_node,
+			machine_nodes: self.meta.max_nodes.or_else(|| Some(self.meta.max_procs? / cores_per_node)),
+			span,
+			nodes: Distribution::from_values(&mut widths.iter().map(|&nodes| nodes as f64).collect::<Vec<_>>()),
+			run_time: Distribution::from_values(&mut jobs.iter().map(|job| job.run_time as f64).collect::<Vec<_>>()),
+			requested_time: Distribution::from_values(&mut jobs.iter().map(|job| job.requested_run_time as f64).collect::<Vec<_>>()),
+			estimate_accuracy: accuracies.iter().sum::<f64>() / accuracies.len().max(1) as f64,
+			interarrival: Distribution::from_values(&mut interarrival),
+			work: total_work,
+			demand: if span == 0 { 0.0 } else { total_work as f64 / span as f64 },
+			by_width,
+			by_run_time,
+			days: days.into_values().collect(),
+			hours,
+			users,
+			groups: groups.len(),
+			widths,
+		}
+	}
+}
+
+/// Summary for a console, the days being summarized by their busiest and quietest ones.
+impl Display for WorkloadStats {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let distribution = |distribution: &Distribution| format!(
+			"avg {:.1}, median {}, p95 {}, max {}",
+			distribution.avg, distribution.median, distribution.p95, distribution.max,
+		);
+
+		writeln!(f, "{} jobs ({} records left out), {} users, {} groups, {} cores per node", self.jobs, self.unusable, self.users.len(), self.groups, self.cores_per_node)?;
+		if let Some(nodes) = self.machine_nodes {
+			writeln!(f, "traced machine: {} nodes", nodes)?;
+		}
+		writeln!(f, "span: {:.1} days", self.span as f64 / DAY as f64)?;
+		writeln!(f, "nodes: {}", distribution(&self.nodes))?;
+		writeln!(f, "run time (s): {}", distribution(&self.run_time))?;
+		writeln!(f, "requested time (s): {}", distribution(&self.requested_time))?;
+		writeln!(f, "estimate accuracy: {:.3}", self.estimate_accuracy)?;
+		writeln!(f, "interarrival (s): {}", distribution(&self.interarrival))?;
+		writeln!(f, "work: {:.3e} node-seconds, {:.1} nodes busy on average", self.work as f64, self.demand)?;
+
+		for (title, buckets) in [("by width", &self.by_width), ("by run time", &self.by_run_time)] {
+			writeln!(f, "\n{}:", title)?;
+			for bucket in buckets {
+				writeln!(f, "  {:<8} {:>8} jobs {:>6.1}% of the work", bucket.bucket, bucket.jobs, bucket.work_percent)?;
+			}
+		}
+
+		let busiest = self.days.iter().max_by_key(|day| day.jobs);
+		let quietest = self.days.iter().min_by_key(|day| day.jobs);
+		if let (Some(busiest), Some(quietest)) = (busiest, quietest) {
+			writeln!(f, "\narrivals: {} days with jobs, {:.1} jobs a day on average, busiest day {} ({} jobs), quietest day {} ({} jobs)",
+				self.days.len(), self.jobs as f64 / self.days.len() as f64, busiest.day, busiest.jobs, quietest.day, quietest.jobs)?;
+		}
+
+		let peak = self.hours.iter().copied().max().unwrap_or(0).max(1);
+		for (hour, &jobs) in self.hours.iter().enumerate() {
+			writeln!(f, "  {:02}h {:>8} {}", hour, jobs, "#".repeat(jobs * 40 / peak))?;
+		}
+
+		writeln!(f, "\ntop users:")?;
+		for user in self.users.iter().take(TOP_USERS) {
+			let name = user.user.map_or_else(|| "unknown".to_string(), |user| user.to_string());
+			writeln!(f, "  {:<8} {:>8} jobs {:>6.1}% of the work", name, user.jobs, user.work_percent)?;
+		}
+
+		Ok(())
+	}
+}
@@ -1,52 +1,994 @@
-
+use std::{collections::BTreeMap, fmt::Display, fs::File, io::{self, BufRead, BufReader}, ops::Range, path::Path, str::FromStr, sync::Arc};
 
 use hashbrown::HashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Clock, algos::SchedulerSpec, allocation::AllocationPolicy, job::Job, malleable::SpeedupModel, reservation::ReservedWindow};
+
+/// A set of node IDs, stored as sorted and disjoint ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeSet {
+	ranges: Vec<Range<u32>>,
+}
+
+impl NodeSet {
+	pub fn ranges(&self) -> &[Range<u32>] {
+		&self.ranges
+	}
+
+	pub fn len(&self) -> u32 {
+		self.ranges.iter().map(|range| range.end - range.start).sum()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ranges.is_empty()
+	}
+
+	pub fn contains(&self, node: u32) -> bool {
+		self.ranges.iter().any(|range| range.contains(&node))
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+		self.ranges.iter().flat_map(|range| range.clone())
+	}
+
+	/// Takes the `count` highest nodes out of the set.
+	fn split_off(&mut self, mut count: u32) -> NodeSet {
+		let mut taken = Vec::new();
+
+		while count > 0 {
+			let last = self.ranges.last_mut().expect("not enough nodes to take");
+			let start = last.start.max(last.end - count.min(last.end - last.start));
+			taken.push(start..last.end);
+			count -= last.end - start;
+			last.end = start;
+
+			if last.start == last.end {
+				self.ranges.pop();
+			}
+		}
+
+		taken.reverse();
+		NodeSet { ranges: taken }
+	}
+
+	/// Adds the nodes of another set, disjoint from this one.
+	fn merge(&mut self, other: NodeSet) {
+		let mut ranges = std::mem::take(&mut self.ranges);
+		ranges.extend(other.ranges);
+		ranges.sort_by_key(|range| range.start);
+
+		for range in ranges {
+			match self.ranges.last_mut() {
+				Some(last) if last.end == range.start => last.end = range.end,
+				_ => self.ranges.push(range),
+			}
+		}
+	}
+}
+
+/// Written as `first-last` ranges separated by `;`, e.g. `0-511;1024-1535`.
+impl Display for NodeSet {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		for (idx, range) in self.ranges.iter().enumerate() {
+			if idx > 0 {
+				write!(f, ";")?;
+			}
+
+			write!(f, "{}-{}", range.start, range.end - 1)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Processors per node used when neither the user nor the trace header tell otherwise
+pub const DEFAULT_CORES_PER_NODE: u32 = 4;
+
+/// A partition of the cluster (e.g. debug, batch, large) and the rules deciding which jobs it runs.
+/// Each partition has its own nodes and queue, and is scheduled on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartitionSpec {
+	pub name: String,
+	/// Nodes of the partition. A single partition may leave it unset to get the nodes the others left
+	#[serde(default)]
+	pub nodes: Option<u32>,
+	/// SWF queues routed to this partition, any queue if empty
+	#[serde(default)]
+	pub queues: Vec<u32>,
+	/// Bounds on the nodes of the jobs routed to this partition
+	#[serde(default)]
+	pub min_nodes: Option<u32>,
+	#[serde(default)]
+	pub max_nodes: Option<u32>,
+}
+
+impl PartitionSpec {
+	pub fn new(name: &str) -> Self {
+		Self {
+			name: name.to_string(),
+			..Self::default()
+		}
+	}
+
+	/// Whether the job matches the routing rules of the partition.
+	pub fn accepts(&self, job: &Job) -> bool {
+		(self.queues.is_empty() || job.queue.is_some_and(|queue| self.queues.contains(&queue)))
+			&& self.min_nodes.is_none_or(|min| job.nodes >= min)
+			&& self.max_nodes.is_none_or(|max| job.nodes <= max)
+	}
+
+	/// Parses a comma separated list of partitions.
+	pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+		SchedulerSpec::parse_list(list)
+			.map_err(|why| why.to_string())?
+			.iter()
+			.map(Self::from_spec)
+			.collect()
+	}
+
+	fn from_spec(spec: &SchedulerSpec) -> Result<Self, String> {
+		let params = &spec.params;
+		params.allow_only(&["nodes", "queues", "min_nodes", "max_nodes"]).map_err(|why| why.to_string())?;
+
+		let queues = match params.get::<String>("queues").map_err(|why| why.to_string())? {
+			Some(list) => list.split(';')
+				.map(|queue| queue.trim().parse().map_err(|_| format!("Invalid queue '{}' for partition {}", queue, spec.name)))
+				.collect::<Result<_, _>>()?,
+			None => Vec::new(),
+		};
+
+		Ok(Self {
+			name: spec.name.clone(),
+			nodes: params.get("nodes").map_err(|why| why.to_string())?,
+			queues,
+			min_nodes: params.get("min_nodes").map_err(|why| why.to_string())?,
+			max_nodes: params.get("max_nodes").map_err(|why| why.to_string())?,
+		})
+	}
+}
+
+/// Written like schedulers, e.g. `debug(nodes=64, max_nodes=16)` or `batch(queues=1;2)`.
+impl FromStr for PartitionSpec {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::from_spec(&s.parse().map_err(|why: super::algos::SchedulerError| why.to_string())?)
+	}
+}
+
+/// Node classes a job may run on, as a bit mask over the classes of the cluster.
+pub const ALL_CLASSES: u64 = u64::MAX;
+
+/// Largest number of node classes, one per bit of a class mask.
+pub const MAX_CLASSES: usize = 64;
+
+/// A class of nodes (e.g. cpu, gpu, bigmem). Classes split the nodes of the machine by ID,
+/// in order, independently of the partitions.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeClassSpec {
+	pub name: String,
+	/// Nodes of the class. A single class may leave it unset to get the nodes the others left
+	#[serde(default)]
+	pub nodes: Option<u32>,
+	/// Memory of each node in kilobytes, unlimited if unset. Jobs requesting more memory per
+	/// node don't run on the nodes of this class
+	#[serde(default)]
+	pub memory: Option<u64>,
+}
+
+impl NodeClassSpec {
+	pub fn new(name: &str) -> Self {
+		Self {
+			name: name.to_string(),
+			..Self::default()
+		}
+	}
+
+	/// Parses a comma separated list of node classes.
+	pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+		SchedulerSpec::parse_list(list)
+			.map_err(|why| why.to_string())?
+			.iter()
+			.map(Self::from_spec)
+			.collect()
+	}
+
+	fn from_spec(spec: &SchedulerSpec) -> Result<Self, String> {
+		let params = &spec.params;
+		params.allow_only(&["nodes", "memory"]).map_err(|why| why.to_string())?;
+
+		Ok(Self {
+			name: spec.name.clone(),
+			nodes: params.get("nodes").map_err(|why| why.to_string())?,
+			memory: params.get("memory").map_err(|why| why.to_string())?,
+		})
+	}
+}
+
+/// Written like schedulers, e.g. `gpu(nodes=64)` or `bigmem(nodes=32, memory=1048576)`.
+impl FromStr for NodeClassSpec {
+	type Err = String;
 
-use super::{Clock, job::Job};
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::from_spec(&s.parse().map_err(|why: super::algos::SchedulerError| why.to_string())?)
+	}
+}
 
+/// Reads the node class of some jobs from a file of `job_id,class` lines, lines starting
+/// with `#` being comments.
+pub fn read_class_map<P: AsRef<Path>>(path: P) -> io::Result<HashMap<u32, String>> {
+	let mut classes = HashMap::new();
+
+	for (idx, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+		let line = line?;
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("line {} of the class map isn't job_id,class: {}", idx + 1, line));
+		let (id, class) = line.split_once(',').ok_or_else(invalid)?;
+		let id = id.trim().parse().map_err(|_| invalid())?;
+		classes.insert(id, class.trim().to_string());
+	}
+
+	Ok(classes)
+}
+
+/// Takes the nodes of the job from the free nodes of each class, given as (class index, free
+/// nodes) in the order the cluster allocates them, see `Cluster::free_by_class`. Leaves `free`
+/// as is and returns false if the job doesn't fit.
+pub fn claim(free: &mut [(usize, u32)], job: &Job) -> bool {
+	claim_nodes(free, job.nodes, job.classes)
+}
+
+/// Like `claim`, for a job on `nodes` nodes of the classes of the `classes` mask, e.g. one
+/// of the configurations of a moldable job, see `Job::shapes`.
+pub fn claim_nodes(free: &mut [(usize, u32)], nodes: u32, classes: u64) -> bool {
+	let allowed = |idx: usize| classes & (1 << idx) != 0;
+	let available = free.iter().filter(|&&(idx, _)| allowed(idx)).map(|&(_, nodes)| nodes).sum::<u32>();
+	if nodes > available {
+		return false;
+	}
+
+	let mut remaining = nodes;
+	for (idx, nodes) in free.iter_mut().map(|(idx, nodes)| (*idx, nodes)) {
+		if allowed(idx) {
+			let taken = remaining.min(*nodes);
+			*nodes -= taken;
+			remaining -= taken;
+		}
+	}
+
+	true
+}
+
+/// Shape of a simulated cluster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterSpec {
+	pub nodes: u32,
+	/// Processors per node, used to convert the processors requested in the trace into nodes.
+	/// When None, the ratio between the MaxProcs and MaxNodes fields of the trace header is
+	/// used, or DEFAULT_CORES_PER_NODE if the header doesn't have them.
+	pub cores_per_node: Option<u32>,
+	/// Partitions splitting the nodes, in routing order. Without any, every job runs on every node
+	pub partitions: Vec<PartitionSpec>,
+	/// Classes splitting the nodes. Without any, every node is alike
+	pub node_classes: Vec<NodeClassSpec>,
+	/// Class of the jobs whose class doesn't follow from their memory, by job ID
+	pub class_map: Arc<HashMap<u32, String>>,
+	/// Memory of each node in kilobytes, for the classes that don't set theirs. Unlimited if unset
+	pub node_memory: Option<u64>,
+}
+
+impl ClusterSpec {
+	pub fn new(nodes: u32) -> Self {
+		Self {
+			nodes,
+			cores_per_node: None,
+			partitions: Vec::new(),
+			node_classes: Vec::new(),
+			class_map: Arc::default(),
+			node_memory: None,
+		}
+	}
+
+	pub fn with_cores_per_node(nodes: u32, cores_per_node: u32) -> Self {
+		Self {
+			nodes,
+			cores_per_node: Some(cores_per_node),
+			partitions: Vec::new(),
+			node_classes: Vec::new(),
+			class_map: Arc::default(),
+			node_memory: None,
+		}
+	}
+
+	/// Node count of each partition, or of the whole cluster if there are no partitions.
+	pub fn partition_sizes(&self) -> Result<Vec<u32>, String> {
+		let sizes = self.partitions.iter().map(|partition| partition.nodes).collect::<Vec<_>>();
+		split_nodes(self.nodes, &sizes, "Partitions", "partition")
+	}
+
+	/// Node count of each node class, or of the whole cluster if there are no classes.
+	pub fn class_sizes(&self) -> Result<Vec<u32>, String> {
+		if self.node_classes.len() > MAX_CLASSES {
+			return Err(format!("At most {} node classes are supported ({} given)", MAX_CLASSES, self.node_classes.len()));
+		}
+
+		let sizes = self.node_classes.iter().map(|class| class.nodes).collect::<Vec<_>>();
+		split_nodes(self.nodes, &sizes, "Node classes", "class")
+	}
+
+	/// Memory of the nodes of each class, by class index, or of every node without classes.
+	pub fn class_memory(&self) -> Vec<Option<u64>> {
+		match self.node_classes.is_empty() {
+			true => vec![self.node_memory],
+			false => self.node_classes.iter().map(|class| class.memory.or(self.node_memory)).collect(),
+		}
+	}
+
+	/// Node classes the job may run on: the class given by the class map, else the classes
+	/// with enough memory per node for the job. None if no node has enough memory.
+	pub fn job_classes(&self, job: &Job, cores_per_node: u32) -> u64 {
+		if let Some(name) = self.class_map.get(&job.id).filter(|_| !self.node_classes.is_empty()) {
+			return self.node_classes.iter()
+				.position(|class| &class.name == name)
+				.map_or(0, |idx| 1 << idx);
+		}
+
+		match job.requested_memory {
+			Some(memory) if memory > 0.0 => {
+				let per_node = memory * cores_per_node as f64;
+				let limits = self.class_memory();
+				let classes = limits.iter().enumerate()
+					.filter(|(_, limit)| limit.is_none_or(|limit| limit as f64 >= per_node))
+					.fold(0u64, |classes, (idx, _)| classes | 1 << idx);
+
+				match classes.count_ones() as usize == limits.len() {
+					true => ALL_CLASSES,
+					false => classes,
+				}
+			}
+			_ => ALL_CLASSES,
+		}
+	}
+
+	/// Index of the partition the job runs on: the first one accepting it.
+	pub fn route(&self, job: &Job) -> Option<usize> {
+		if self.partitions.is_empty() {
+			return Some(0);
+		}
+
+		self.partitions.iter().position(|partition| partition.accepts(job))
+	}
+}
+
+/// Splits the nodes of the cluster between parts of the given sizes, a single part being
+/// allowed to leave its size unset to take the nodes left.
+fn split_nodes(nodes: u32, sizes: &[Option<u32>], parts: &str, part: &str) -> Result<Vec<u32>, String> {
+	if sizes.is_empty() {
+		return Ok(vec![nodes]);
+	}
+
+	let fixed = sizes.iter().flatten().sum::<u32>();
+	let rest = match sizes.iter().filter(|size| size.is_none()).count() {
+		0 if fixed == nodes => 0,
+		1 if fixed < nodes => nodes - fixed,
+		_ => return Err(format!(
+			"{} must use the {} nodes of the cluster, either exactly or with a single {} taking the nodes left ({} given)",
+			parts, nodes, part, fixed,
+		)),
+	};
+
+	Ok(sizes.iter().map(|size| size.unwrap_or(rest)).collect())
+}
+
+/// Nodes of the simulated machine and the jobs running on them.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Cluster {
 	pub total_nodes: u32,
 	pub available_nodes: u32,
 	pub used_resources: u64,
+	/// Kilobyte-seconds of memory used by the jobs, see `Job::memory_per_node`
+	pub used_memory: u64,
 	pub running_jobs: HashMap<u32, Job>,
+	/// Node-seconds used by the finished or preempted jobs of each user, None gathering jobs without a user
+	#[serde(serialize_with = "serialize_usage", deserialize_with = "deserialize_usage")]
+	pub user_usage: HashMap<Option<u32>, u64>,
+
+	/// ID of the first node, the cluster owning the nodes from there to `first_node + total_nodes`
+	first_node: u32,
+	/// Free nodes, as a map from the first node of each free block to the end of the block.
+	/// Adjacent blocks are always merged.
+	free_blocks: BTreeMap<u32, u32>,
+	/// Time each node spent running jobs
+	node_busy_time: Vec<Clock>,
+	/// Nodes that are down, along with the time they failed
+	down_since: BTreeMap<u32, Clock>,
+	/// Node-seconds lost to nodes being down, for the nodes repaired so far
+	downtime: u64,
+	/// Nodes of the scheduled downtimes, going down once free, along with the number of
+	/// downtimes draining them, see `Drain`
+	#[serde(default)]
+	draining: BTreeMap<u32, u32>,
+	/// Nodes down for a scheduled downtime, along with the time they went down
+	#[serde(default)]
+	drained_since: BTreeMap<u32, Clock>,
+	/// Node-seconds lost to the scheduled downtimes, for the nodes back so far
+	#[serde(default)]
+	drain_downtime: u64,
+	/// Nodes of each class of the machine in this cluster, by class index
+	classes: Vec<ClassNodes>,
+	/// Advance reservations of the nodes, in the order they were added
+	reservations: Vec<ReservedWindow>,
+	/// How the nodes of the jobs are picked among the free ones
+	allocation: AllocationPolicy,
+}
+
+/// Nodes of a class within a cluster, and how many of them are free.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ClassNodes {
+	nodes: Range<u32>,
+	available: u32,
+	/// Memory of each node in kilobytes, unlimited if None
+	#[serde(default)]
+	memory: Option<u64>,
+}
+
+/// Writes the usage of the users as a list of pairs, JSON keys being strings only.
+fn serialize_usage<S: Serializer>(usage: &HashMap<Option<u32>, u64>, serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.collect_seq(usage.iter())
+}
+
+fn deserialize_usage<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<Option<u32>, u64>, D::Error> {
+	Ok(Vec::<(Option<u32>, u64)>::deserialize(deserializer)?.into_iter().collect())
 }
 
 impl Cluster {
+	/// An idle cluster of `nodes` nodes, numbered from 0.
 	pub fn new(nodes: u32) -> Self {
+		Self::with_first_node(0, nodes)
+	}
+
+	/// An idle cluster of `nodes` nodes numbered from `first_node`, e.g. a partition of a larger cluster.
+	pub fn with_first_node(first_node: u32, nodes: u32) -> Self {
+		let mut free_blocks = BTreeMap::new();
+		free_blocks.insert(first_node, first_node + nodes);
+
 		Self {
 			total_nodes: nodes,
 			available_nodes: nodes,
 			used_resources: 0,
-			running_jobs: HashMap::new()
+			used_memory: 0,
+			running_jobs: HashMap::new(),
+			user_usage: HashMap::new(),
+			first_node,
+			free_blocks,
+			node_busy_time: vec![0; nodes as usize],
+			down_since: BTreeMap::new(),
+			downtime: 0,
+			draining: BTreeMap::new(),
+			drained_since: BTreeMap::new(),
+			drain_downtime: 0,
+			classes: vec![ClassNodes { nodes: first_node..first_node + nodes, available: nodes, memory: None }],
+			reservations: Vec::new(),
+			allocation: AllocationPolicy::FirstFit,
 		}
 	}
 
+	/// An idle cluster like `with_first_node`, on a machine whose node classes hold the given
+	/// node IDs, in class order.
+	pub fn with_classes(first_node: u32, nodes: u32, classes: &[Range<u32>]) -> Self {
+		let mut cluster = Self::with_first_node(first_node, nodes);
+		cluster.classes = classes.iter().map(|class| {
+			let start = class.start.clamp(first_node, first_node + nodes);
+			let end = class.end.clamp(start, first_node + nodes);
+			ClassNodes { nodes: start..end, available: end - start, memory: None }
+		}).collect();
+
+		cluster
+	}
+
 	pub fn schedule_job(&mut self, job: Job, clock: Clock) -> bool {
-		if job.nodes > self.available_nodes {
-			println!("[{}] Job {} is trying to run on {} but only {} are available.", clock, job.id, job.nodes, self.available_nodes);
+		if !self.fits(&job) {
+			tracing::warn!(clock, job = job.id, nodes = job.nodes, available_nodes = self.available_for(job.classes), "job doesn't fit on the free nodes");
 			return false;
 		}
 
 		self.available_nodes -= job.nodes;
 		let mut job = job;
+		job.allocation = self.allocate(job.nodes, job.classes);
 		job.set_scheduled(clock);
-		
+
 		self.running_jobs.insert(job.id, job);
 		true
 	}
 
-	pub fn finish_job(&mut self, job_id: u32) {
-		if let Some(job) = self.running_jobs.remove(&job_id) {
-			self.available_nodes += job.nodes;
-			self.used_resources += job.nodes as u64 * job.run_time;
+	pub fn finish_job(&mut self, job_id: u32, clock: Clock) -> Option<Job> {
+		let job = self.running_jobs.remove(&job_id)?;
+		self.release_job(&job, clock);
+		self.take_drained(clock);
+		Some(job)
+	}
+
+	/// Stops a running job, which keeps track of the run time it has left.
+	pub fn preempt_job(&mut self, job_id: u32, clock: Clock, restart_overhead: Clock) -> Option<Job> {
+		let mut job = self.running_jobs.remove(&job_id)?;
+		self.release_job(&job, clock);
+		self.take_drained(clock);
+		job.set_preempted(clock, restart_overhead);
+		Some(job)
+	}
+
+	/// Stops a running job at the end of its time slice, see `TimeSlicing`.
+	pub fn suspend_job(&mut self, job_id: u32, clock: Clock, restart_overhead: Clock) -> Option<Job> {
+		let mut job = self.running_jobs.remove(&job_id)?;
+		self.release_job(&job, clock);
+		self.take_drained(clock);
+		job.set_suspended(clock, restart_overhead);
+		Some(job)
+	}
+
+	/// Changes the number of nodes of a running malleable job, see `Job::set_resized`, the
+	/// nodes added being picked by the allocation policy and the highest nodes being given
+	/// back. Returns false if the job doesn't run here or too few nodes are free to expand it.
+	pub fn resize_job(&mut self, job_id: u32, nodes: u32, clock: Clock, speedup: SpeedupModel) -> bool {
+		let (current, classes) = match self.running_jobs.get(&job_id) {
+			Some(job) => (job.nodes, job.classes),
+			None => return false,
+		};
+
+		let free = match self.classes.len() {
+			1 => self.available_nodes,
+			_ => self.available_for(classes),
+		};
+
+		if nodes == 0 || nodes > current + free {
+			return false;
+		}
+
+		let mut job = self.running_jobs.remove(&job_id).unwrap();
+		self.account_job(&job, clock);
+
+		if nodes < current {
+			let released = job.allocation.split_off(current - nodes);
+			self.available_nodes += current - nodes;
+			self.release(&released);
+			self.take_drained(clock);
+		} else {
+			let added = self.allocate(nodes - current, classes);
+			self.available_nodes -= nodes - current;
+			job.allocation.merge(added);
+		}
+
+		job.set_resized(clock, nodes, speedup);
+		self.running_jobs.insert(job_id, job);
+		true
+	}
+
+	/// Gives back the nodes of a job that ran from its schedule time until now.
+	fn release_job(&mut self, job: &Job, clock: Clock) {
+		self.account_job(job, clock);
+		self.available_nodes += job.nodes;
+		self.release(&job.allocation);
+	}
+
+	/// Counts the usage of the nodes of a job from its schedule time until now.
+	fn account_job(&mut self, job: &Job, clock: Clock) {
+		let duration = clock - job.schedule_time;
+
+		self.used_resources += job.nodes as u64 * duration;
+		self.used_memory += job.nodes as u64 * job.memory_per_node * duration;
+		*self.user_usage.entry(job.user_id).or_insert(0) += job.nodes as u64 * duration;
+
+		for node in job.allocation.iter() {
+			self.node_busy_time[(node - self.first_node) as usize] += duration;
+		}
+	}
+
+	/// Takes a node out of the cluster until it is repaired. The job running on it, if any,
+	/// is stopped and returned, to be submitted again from the start.
+	pub fn fail_node(&mut self, node: u32, clock: Clock) -> Option<Job> {
+		if self.down_since.contains_key(&node) || self.drained_since.contains_key(&node) {
+			return None;
+		}
+
+		if self.reservations.iter_mut().any(|window| window.drop_node(node, clock)) {
+			self.down_since.insert(node, clock);
+			return None;
+		}
+
+		let killed = self.running_jobs.values()
+			.find(|job| job.allocation.contains(node))
+			.map(|job| job.id)
+			.map(|id| {
+				let mut job = self.running_jobs.remove(&id).unwrap();
+				self.release_job(&job, clock);
+				job.set_resubmitted();
+				job
+			});
+
+		self.take_node(node);
+		self.available_nodes -= 1;
+		self.down_since.insert(node, clock);
+		self.take_drained(clock);
+		killed
+	}
+
+	/// Brings back a node that failed, which stays down if it is drained.
+	pub fn repair_node(&mut self, node: u32, clock: Clock) {
+		if let Some(since) = self.down_since.remove(&node) {
+			self.downtime += clock - since;
+			self.available_nodes += 1;
+			self.release_range(node, node + 1);
+			self.take_drained(clock);
+		}
+	}
+
+	/// Drains a node for a scheduled downtime: it goes down now if free, else once the job
+	/// running on it ends or the node comes back, and no job starts on it until `undrain_node`.
+	pub fn drain_node(&mut self, node: u32, clock: Clock) {
+		*self.draining.entry(node).or_insert(0) += 1;
+		self.take_drained(clock);
+	}
+
+	/// Ends a scheduled downtime of a node, which is free again unless it failed or another
+	/// downtime still drains it.
+	pub fn undrain_node(&mut self, node: u32, clock: Clock) {
+		match self.draining.get_mut(&node) {
+			Some(count) if *count > 1 => {
+				*count -= 1;
+				return;
+			}
+			Some(_) => {
+				self.draining.remove(&node);
+			}
+			None => return,
+		}
+
+		if let Some(since) = self.drained_since.remove(&node) {
+			self.drain_downtime += clock - since;
+			self.available_nodes += 1;
+			self.release_range(node, node + 1);
+		}
+	}
+
+	/// Takes down the drained nodes that are free.
+	fn take_drained(&mut self, clock: Clock) {
+		if self.draining.len() == self.drained_since.len() {
+			return;
+		}
+
+		let free = self.draining.keys()
+			.copied()
+			.filter(|node| !self.drained_since.contains_key(node) && self.is_node_free(*node))
+			.collect::<Vec<_>>();
+
+		for node in free {
+			self.take_node(node);
+			self.available_nodes -= 1;
+			self.drained_since.insert(node, clock);
+		}
+	}
+
+	pub fn contains_node(&self, node: u32) -> bool {
+		node >= self.first_node && node < self.first_node + self.total_nodes
+	}
+
+	/// Nodes that are down, failed or drained.
+	pub fn down_nodes(&self) -> u32 {
+		(self.down_since.len() + self.drained_since.len()) as u32
+	}
+
+	/// Nodes down for a scheduled downtime.
+	pub fn drained_nodes(&self) -> u32 {
+		self.drained_since.len() as u32
+	}
+
+	/// Node-seconds lost to nodes failing until now.
+	pub fn downtime(&self, clock: Clock) -> u64 {
+		self.downtime + self.down_since.values().map(|&since| clock - since).sum::<u64>()
+	}
+
+	/// Node-seconds lost to the scheduled downtimes until now.
+	pub fn drain_downtime(&self, clock: Clock) -> u64 {
+		self.drain_downtime + self.drained_since.values().map(|&since| clock - since).sum::<u64>()
+	}
+
+	/// Free blocks of contiguous nodes, by increasing node ID.
+	pub fn free_blocks(&self) -> impl Iterator<Item = Range<u32>> + '_ {
+		self.free_blocks.iter().map(|(&start, &end)| start..end)
+	}
+
+	pub fn largest_free_block(&self) -> u32 {
+		self.free_blocks().map(|block| block.end - block.start).max().unwrap_or(0)
+	}
+
+	pub fn is_node_free(&self, node: u32) -> bool {
+		match self.free_blocks.range(..=node).next_back() {
+			Some((_, &end)) => node < end,
+			None => false,
+		}
+	}
+
+	pub fn first_node(&self) -> u32 {
+		self.first_node
+	}
+
+	/// Whether the nodes of the cluster all belong to the same class, jobs that can't run on
+	/// it never being queued there.
+	pub fn is_homogeneous(&self) -> bool {
+		self.classes.iter().filter(|class| !class.nodes.is_empty()).count() <= 1
+	}
+
+	/// Free nodes among the given classes.
+	pub fn available_for(&self, classes: u64) -> u32 {
+		self.classes.iter().enumerate()
+			.filter(|&(idx, _)| classes & (1 << idx) != 0)
+			.map(|(_, class)| class.available)
+			.sum()
+	}
+
+	/// Nodes among the given classes, up or down.
+	pub fn nodes_for(&self, classes: u64) -> u32 {
+		self.classes.iter().enumerate()
+			.filter(|&(idx, _)| classes & (1 << idx) != 0)
+			.map(|(_, class)| class.nodes.end - class.nodes.start)
+			.sum()
+	}
+
+	/// Free nodes of each class as (class index, free nodes), in the order the cluster
+	/// allocates them, see `claim`.
+	pub fn free_by_class(&self) -> Vec<(usize, u32)> {
+		self.class_order().into_iter().map(|idx| (idx, self.classes[idx].available)).collect()
+	}
+
+	/// Nodes of the set in the class of the given index.
+	pub fn nodes_in_class(&self, nodes: &NodeSet, class: usize) -> u32 {
+		let class = &self.classes[class].nodes;
+		nodes.ranges().iter().map(|range| range.end.min(class.end).saturating_sub(range.start.max(class.start))).sum()
+	}
+
+	/// Indices of the classes in the order their nodes are allocated: by node ID, or by
+	/// increasing memory per node with `AllocationPolicy::BestFitMemory`.
+	fn class_order(&self) -> Vec<usize> {
+		let mut order = (0..self.classes.len()).collect::<Vec<_>>();
+		if self.allocation == AllocationPolicy::BestFitMemory {
+			order.sort_by_key(|&idx| (self.classes[idx].memory.is_none(), self.classes[idx].memory));
 		}
+
+		order
+	}
+
+	/// Sets the memory of the nodes of each class, by class index, in kilobytes.
+	pub fn set_class_memory(&mut self, memory: &[Option<u64>]) {
+		for (class, &memory) in self.classes.iter_mut().zip(memory) {
+			class.memory = memory;
+		}
+	}
+
+	/// Kilobytes of memory of the cluster, if every node has a known memory.
+	pub fn memory_capacity(&self) -> Option<u64> {
+		self.classes.iter()
+			.map(|class| class.memory.map(|memory| (class.nodes.end - class.nodes.start) as u64 * memory))
+			.sum()
+	}
+
+	/// Whether the job can start now on the nodes of its classes.
+	pub fn fits(&self, job: &Job) -> bool {
+		match self.classes.len() {
+			1 => job.nodes <= self.available_nodes,
+			_ => job.nodes <= self.available_for(job.classes),
+		}
+	}
+
+	pub fn allocation_policy(&self) -> AllocationPolicy {
+		self.allocation
+	}
+
+	/// Changes how the nodes of the jobs started from now on are picked among the free ones.
+	pub fn set_allocation_policy(&mut self, policy: AllocationPolicy) {
+		self.allocation = policy;
+	}
+
+	/// Calendar of the advance reservations, past, active and upcoming. The engine only starts
+	/// the jobs that fit around it (see `fits_around_reservations`), and the others wait.
+	pub fn reservations(&self) -> &[ReservedWindow] {
+		&self.reservations
+	}
+
+	/// Adds a reservation to the calendar, returning its index.
+	pub fn add_reservation(&mut self, window: ReservedWindow) -> usize {
+		self.reservations.push(window);
+		self.reservations.len() - 1
+	}
+
+	/// Nodes held by the active reservations.
+	pub fn reserved_nodes(&self) -> u32 {
+		self.reservations.iter().map(|window| window.held().len() as u32).sum()
+	}
+
+	/// Nodes the reservations keep from a job running from `from` to `to`: the nodes of the
+	/// reservations starting before `to`, and the nodes the active ones still lack.
+	pub fn reserved_between(&self, from: Clock, to: Clock) -> u32 {
+		self.reservations.iter()
+			.filter(|window| window.blocks(from, to))
+			.map(|window| if window.is_active() { window.missing() } else { window.nodes })
+			.sum()
+	}
+
+	/// Whether the job can start now and run for its requested time without keeping nodes
+	/// from the reservations.
+	pub fn fits_around_reservations(&self, job: &Job, clock: Clock) -> bool {
+		self.fits(job) && job.nodes + self.reserved_between(clock, clock + job.requested_run_time.max(1)) <= self.available_nodes
+	}
+
+	/// Starts a reservation, which takes the free nodes it needs.
+	pub fn start_reservation(&mut self, index: usize, clock: Clock) {
+		self.reservations[index].start(clock);
+		self.fill_reservations(clock);
+	}
+
+	/// Gives the free nodes to the active reservations still lacking some.
+	pub fn fill_reservations(&mut self, clock: Clock) {
+		for idx in 0..self.reservations.len() {
+			let count = self.reservations[idx].missing().min(self.available_nodes);
+			if count == 0 {
+				continue;
+			}
+
+			let nodes = self.allocate(count, ALL_CLASSES);
+			self.available_nodes -= count;
+			self.reservations[idx].hold(nodes.iter(), clock);
+		}
+	}
+
+	/// Ends a reservation, freeing its nodes.
+	pub fn end_reservation(&mut self, index: usize, clock: Clock) {
+		for node in self.reservations[index].finish(clock) {
+			self.available_nodes += 1;
+			self.release_range(node, node + 1);
+		}
+
+		self.take_drained(clock);
+	}
+
+	/// Node-seconds asked by the reservations started before `clock`, and node-seconds
+	/// they held, the rest being kept by jobs running late.
+	pub fn reservation_usage(&self, clock: Clock) -> (u64, u64) {
+		self.reservations.iter()
+			.filter(|window| window.start < clock)
+			.map(|window| (window.nodes as u64 * (window.end.min(clock) - window.start), window.obtained(clock.min(window.end))))
+			.fold((0, 0), |(asked, obtained), (a, o)| (asked + a, obtained + o))
+	}
+
+	/// Percentage of the simulated time each node spent running jobs, by node ID.
+	pub fn node_usage(&self, makespan: Clock) -> impl Iterator<Item = f64> + '_ {
+		self.node_busy_time.iter().map(move |&busy| busy as f64 * 100.0 / makespan.max(1) as f64)
+	}
+
+	/// Takes `count` free nodes among the given classes, picked by the allocation policy.
+	fn allocate(&mut self, count: u32, classes: u64) -> NodeSet {
+		let free = self.free_ranges(classes);
+		let mut picked = self.allocation.select(&free, count);
+		assert_eq!(picked.iter().map(|range| range.end - range.start).sum::<u32>(), count, "not enough free nodes to allocate");
+		picked.sort_by_key(|range| range.start);
+
+		let mut ranges: Vec<Range<u32>> = Vec::new();
+		for range in picked {
+			self.take_range(range.start, range.end);
+
+			match ranges.last_mut() {
+				Some(last) if last.end == range.start => last.end = range.end,
+				_ => ranges.push(range),
+			}
+		}
+
+		NodeSet { ranges }
+	}
+
+	/// Free blocks of contiguous nodes among the given classes, by increasing node ID within
+	/// each class, classes coming in allocation order, see `class_order`.
+	fn free_ranges(&self, classes: u64) -> Vec<Range<u32>> {
+		let mut ranges: Vec<Range<u32>> = Vec::new();
+
+		for idx in self.class_order() {
+			let class = &self.classes[idx];
+			if classes & (1 << idx) == 0 || class.available == 0 {
+				continue;
+			}
+
+			// the free block holding the first node of the class, then the ones starting in it
+			let first = self.free_blocks.range(..=class.nodes.start).next_back()
+				.filter(|&(_, &end)| end > class.nodes.start);
+			let blocks = first.into_iter().chain(self.free_blocks.range(class.nodes.start + 1..class.nodes.end));
+
+			for (&start, &end) in blocks {
+				let (start, end) = (start.max(class.nodes.start), end.min(class.nodes.end));
+
+				match ranges.last_mut() {
+					Some(last) if last.end == start => last.end = end,
+					_ => ranges.push(start..end),
+				}
+			}
+		}
+
+		ranges
+	}
+
+	/// Removes a free node from the free blocks.
+	fn take_node(&mut self, node: u32) {
+		self.take_range(node, node + 1);
+	}
+
+	/// Removes the nodes from `start` to `end`, all free and in the same block, from the free blocks.
+	fn take_range(&mut self, start: u32, end: u32) {
+		let (block_start, block_end) = match self.free_blocks.range(..=start).next_back() {
+			Some((&block_start, &block_end)) if end <= block_end => (block_start, block_end),
+			_ => panic!("nodes {} to {} aren't free", start, end - 1),
+		};
+
+		self.free_blocks.remove(&block_start);
+
+		if block_start < start {
+			self.free_blocks.insert(block_start, start);
+		}
+
+		if end < block_end {
+			self.free_blocks.insert(end, block_end);
+		}
+
+		self.count_free(start, end, false);
+	}
+
+	/// Updates the free nodes of the classes for the nodes from `start` to `end` being
+	/// released or taken.
+	fn count_free(&mut self, start: u32, end: u32, released: bool) {
+		for class in &mut self.classes {
+			let overlap = end.min(class.nodes.end).saturating_sub(start.max(class.nodes.start));
+
+			if released {
+				class.available += overlap;
+			} else {
+				class.available -= overlap;
+			}
+		}
+	}
+
+	fn release(&mut self, nodes: &NodeSet) {
+		for range in nodes.ranges() {
+			self.release_range(range.start, range.end);
+		}
+	}
+
+	/// Adds the nodes from `start` to `end` to the free blocks.
+	fn release_range(&mut self, mut start: u32, mut end: u32) {
+		self.count_free(start, end, true);
+
+		// merge with the free blocks right before and right after
+		if let Some((&prev_start, &prev_end)) = self.free_blocks.range(..start).next_back() {
+			if prev_end == start {
+				self.free_blocks.remove(&prev_start);
+				start = prev_start;
+			}
+		}
+
+		if let Some(next_end) = self.free_blocks.remove(&end) {
+			end = next_end;
+		}
+
+		self.free_blocks.insert(start, end);
 	}
 
 	pub fn print_stats(&self, makespan: Clock) {
 		let total_resources = makespan * self.total_nodes as u64;
 		let idle = total_resources - self.used_resources;
 		let idle_percent = idle * 100 / total_resources;
-		
+
 		println!("\
 			Usage of the  machine:\n\
 			- {} node-seconds used\n\
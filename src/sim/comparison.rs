@@ -0,0 +1,295 @@
+use std::{fmt::Display, fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use super::{algos, report::{EngineReport, csv_field}};
+
+/// A metric compared across schedulers, lower values being better.
+pub struct Metric {
+	pub name: &'static str,
+	pub value: fn(&EngineReport) -> f64,
+}
+
+/// Metrics of the comparison tables.
+pub const METRICS: &[Metric] = &[
+	Metric { name: "makespan", value: |report| report.makespan as f64 },
+	Metric { name: "avg_wait", value: |report| report.avg_wait },
+	Metric { name: "max_wait", value: |report| report.max_wait as f64 },
+	Metric { name: "bounded_slowdown", value: |report| report.bounded_slowdown.avg },
+	Metric { name: "idle_percent", value: |report| report.idle_percent },
+	Metric { name: "energy", value: |report| report.energy },
+];
+
+/// A metric of one scheduler, compared to the baseline and to the other schedulers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+	pub value: f64,
+	/// Relative difference to the baseline, in percent
+	pub delta: f64,
+	/// Rank among the schedulers, starting at 1, equal values sharing their rank
+	pub rank: usize,
+}
+
+/// Schedulers run on the same workload and cluster size, at the same load factor. The metrics
+/// of a scheduler run several times with other seeds are their mean over the runs.
+#[derive(Debug, Clone)]
+pub struct Group {
+	pub workload: String,
+	pub nodes: u32,
+	pub load_factor: f64,
+	pub baseline: String,
+	/// Most jobs a scheduler of the group left out as rejected, whose work the metrics
+	/// don't account for, see `EngineReport::rejected_jobs`
+	pub rejected_jobs: u32,
+	/// Scheduler name and one cell per metric of `METRICS`
+	pub rows: Vec<(String, Vec<Cell>)>,
+}
+
+/// A metric of one scheduler over several workloads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateCell {
+	pub mean: f64,
+	pub geo_mean: f64,
+	/// Geometric mean of the ratios to the baseline on each workload
+	pub relative: f64,
+	pub avg_rank: f64,
+}
+
+/// Schedulers run on several workloads with the same cluster size and load factor.
+#[derive(Debug, Clone)]
+pub struct Aggregate {
+	pub nodes: u32,
+	pub load_factor: f64,
+	pub workloads: usize,
+	/// Baseline of the first workload, the same for every workload unless it lacks the baseline
+	pub baseline: String,
+	/// Scheduler name and one cell per metric of `METRICS`, over the workloads it ran
+	pub rows: Vec<(String, Vec<AggregateCell>)>,
+}
+
+/// Reports of several schedulers side by side, for each workload, cluster size and load factor.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+	pub groups: Vec<Group>,
+	/// Metrics over every workload for each cluster size and load factor, when there are several
+	/// workloads
+	pub aggregates: Vec<Aggregate>,
+}
+
+fn geo_mean(values: impl Iterator<Item = f64>) -> f64 {
+	let (sum, count) = values.fold((0.0, 0), |(sum, count), value: f64| (sum + value.ln(), count + 1));
+	(sum / count.max(1) as f64).exp()
+}
+
+/// Aggregates the groups of each cluster size and load factor across workloads.
+fn aggregate(groups: &[Group]) -> Vec<Aggregate> {
+	let mut sizes = Vec::new();
+	for group in groups {
+		if !sizes.contains(&(group.nodes, group.load_factor)) {
+			sizes.push((group.nodes, group.load_factor));
+		}
+	}
+
+	sizes.into_iter().filter_map(|(nodes, load_factor)| {
+		let groups = groups.iter().filter(|group| group.nodes == nodes && group.load_factor == load_factor).collect::<Vec<_>>();
+		if groups.len() < 2 {
+			return None;
+		}
+
+		let mut schedulers = Vec::new();
+		for (scheduler, _) in groups.iter().flat_map(|group| &group.rows) {
+			if !schedulers.contains(scheduler) {
+				schedulers.push(scheduler.clone());
+			}
+		}
+
+		let rows = schedulers.into_iter().map(|scheduler| {
+			let runs = groups.iter()
+				.filter_map(|group| group.rows.iter().find(|(name, _)| *name == scheduler))
+				.map(|(_, cells)| cells)
+				.collect::<Vec<_>>();
+
+			let cells = (0..METRICS.len()).map(|metric| {
+				let cells = || runs.iter().map(move |cells| &cells[metric]);
+
+				AggregateCell {
+					mean: cells().map(|cell| cell.value).sum::<f64>() / runs.len() as f64,
+					geo_mean: geo_mean(cells().map(|cell| cell.value)),
+					relative: geo_mean(cells().map(|cell| 1.0 + cell.delta / 100.0)),
+					avg_rank: cells().map(|cell| cell.rank as f64).sum::<f64>() / runs.len() as f64,
+				}
+			}).collect();
+
+			(scheduler, cells)
+		}).collect();
+
+		Some(Aggregate { nodes, load_factor, workloads: groups.len(), baseline: groups[0].baseline.clone(), rows })
+	}).collect()
+}
+
+impl Comparison {
+	/// Groups the reports by workload, cluster size and load factor, comparing each scheduler to `baseline`
+	/// (a scheduler name as given to `algos::by_name` or as in the reports), or to the first
+	/// scheduler of a group if None or missing from it.
+	pub fn new(reports: &[EngineReport], baseline: Option<&str>) -> Self {
+		let baseline = baseline.map(|name| match algos::by_name(name) {
+			Ok(scheduler) => scheduler.label(),
+			Err(_) => name.to_string(),
+		});
+
+		let mut grouped: Vec<Vec<&EngineReport>> = Vec::new();
+
+		for report in reports {
+			match grouped.iter_mut().find(|group| group[0].workload == report.workload && group[0].nodes == report.nodes && group[0].load_factor == report.load_factor) {
+				Some(group) => group.push(report),
+				None => grouped.push(vec![report]),
+			}
+		}
+
+		let groups = grouped.into_iter().map(|reports| {
+			// runs of a scheduler repeated with other seeds, see `ExperimentRunner::repetitions`
+			let mut schedulers: Vec<(&str, Vec<&EngineReport>)> = Vec::new();
+			for report in &reports {
+				match schedulers.iter_mut().find(|(name, _)| *name == report.scheduler_name) {
+					Some((_, runs)) => runs.push(report),
+					None => schedulers.push((&report.scheduler_name, vec![report])),
+				}
+			}
+
+			let base = baseline.as_deref()
+				.and_then(|name| schedulers.iter().position(|(scheduler, _)| scheduler.eq_ignore_ascii_case(name)))
+				.unwrap_or(0);
+
+			let columns = METRICS.iter().map(|metric| {
+				let values = schedulers.iter()
+					.map(|(_, runs)| runs.iter().map(|report| (metric.value)(report)).sum::<f64>() / runs.len() as f64)
+					.collect::<Vec<_>>();
+				let base_value = values[base];
+
+				values.iter().map(|&value| Cell {
+					value,
+					delta: if base_value == 0.0 { 0.0 } else { (value - base_value) * 100.0 / base_value },
+					rank: 1 + values.iter().filter(|&&other| other < value).count(),
+				}).collect::<Vec<_>>()
+			}).collect::<Vec<_>>();
+
+			Group {
+				workload: reports[0].workload.clone(),
+				nodes: reports[0].nodes,
+				load_factor: reports[0].load_factor,
+				baseline: schedulers[base].0.to_string(),
+				rejected_jobs: reports.iter().map(|report| report.rejected_jobs).max().unwrap_or(0),
+				rows: schedulers.iter().enumerate()
+					.map(|(idx, (scheduler, _))| (scheduler.to_string(), columns.iter().map(|column| column[idx].clone()).collect()))
+					.collect(),
+			}
+		}).collect::<Vec<_>>();
+
+		Self {
+			aggregates: aggregate(&groups),
+			groups,
+		}
+	}
+
+	/// Writes the comparison as CSV, one line per scheduler and metric. The aggregates over
+	/// the workloads come last, with `all` as workload: their value is the geometric mean,
+	/// the difference to the baseline comes from the geometric mean of the ratios, and the
+	/// rank is the average rank.
+	pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+		writeln!(out, "workload,nodes,load_factor,scheduler,baseline,metric,value,delta_percent,rank")?;
+
+		for group in &self.groups {
+			for (scheduler, cells) in &group.rows {
+				for (metric, cell) in METRICS.iter().zip(cells) {
+					writeln!(out, "{},{},{},{},{},{},{},{},{}", csv_field(&group.workload), group.nodes, group.load_factor, csv_field(scheduler), csv_field(&group.baseline), metric.name, cell.value, cell.delta, cell.rank)?;
+				}
+			}
+		}
+
+		for aggregate in &self.aggregates {
+			for (scheduler, cells) in &aggregate.rows {
+				for (metric, cell) in METRICS.iter().zip(cells) {
+					writeln!(out, "all,{},{},{},{},{},{},{},{}", aggregate.nodes, aggregate.load_factor, csv_field(scheduler), csv_field(&aggregate.baseline), metric.name, cell.geo_mean, (cell.relative - 1.0) * 100.0, cell.avg_rank)?;
+				}
+			}
+		}
+
+		out.flush()
+	}
+
+	pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		self.write_csv(BufWriter::new(File::create(path)?))
+	}
+}
+
+/// Tables with the value, the difference to the baseline and the rank of each metric.
+impl Display for Comparison {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		const WIDTH: usize = 28;
+
+		for group in &self.groups {
+			write!(f, "{} on {} nodes{}, compared to {}", group.workload, group.nodes, load(group.load_factor), group.baseline)?;
+			match group.rejected_jobs {
+				0 => writeln!(f)?,
+				rejected => writeln!(f, " ({} jobs rejected)", rejected)?,
+			}
+
+			let name_width = group.rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("scheduler".len());
+			write!(f, "{:<name_width$}", "scheduler")?;
+			for metric in METRICS {
+				write!(f, " {:>WIDTH$}", metric.name)?;
+			}
+			writeln!(f)?;
+
+			for (scheduler, cells) in &group.rows {
+				write!(f, "{:<name_width$}", scheduler)?;
+				for cell in cells {
+					let text = format!("{} ({:+.1}%) #{}", compact(cell.value), cell.delta, cell.rank);
+					write!(f, " {:>WIDTH$}", text)?;
+				}
+				writeln!(f)?;
+			}
+
+			writeln!(f)?;
+		}
+
+		for aggregate in &self.aggregates {
+			writeln!(f, "{} workloads on {} nodes{}, compared to {}: geometric mean, ratio and average rank", aggregate.workloads, aggregate.nodes, load(aggregate.load_factor), aggregate.baseline)?;
+
+			let name_width = aggregate.rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("scheduler".len());
+			write!(f, "{:<name_width$}", "scheduler")?;
+			for metric in METRICS {
+				write!(f, " {:>WIDTH$}", metric.name)?;
+			}
+			writeln!(f)?;
+
+			for (scheduler, cells) in &aggregate.rows {
+				write!(f, "{:<name_width$}", scheduler)?;
+				for cell in cells {
+					let text = format!("{} (x{:.3}) #{:.1}", compact(cell.geo_mean), cell.relative, cell.avg_rank);
+					write!(f, " {:>WIDTH$}", text)?;
+				}
+				writeln!(f)?;
+			}
+
+			writeln!(f)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Formats large values in scientific notation to keep the columns narrow.
+pub(crate) fn compact(value: f64) -> String {
+	if value.abs() >= 1e7 {
+		format!("{:.3e}", value)
+	} else {
+		format!("{:.2}", value)
+	}
+}
+
+/// Load factor of a table heading, left out at the load of the trace.
+pub(crate) fn load(load_factor: f64) -> String {
+	match load_factor == 1.0 {
+		true => String::new(),
+		false => format!(" at load x{}", load_factor),
+	}
+}
@@ -0,0 +1,156 @@
+use std::{fmt::Display, fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use super::{comparison::{METRICS, compact, load}, report::{EngineReport, csv_field}};
+
+/// Quantiles of order 0.975 of the Student t distribution, by degrees of freedom from 1.
+const T_QUANTILES: [f64; 30] = [
+	12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+	2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+	2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+
+/// Quantile of the normal distribution the t quantiles tend to, past 30 degrees of freedom.
+const Z_QUANTILE: f64 = 1.960;
+
+/// A metric over the repetitions of a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+	pub mean: f64,
+	/// Sample standard deviation, 0 for a single run
+	pub std_dev: f64,
+	/// Half the width of the 95% confidence interval of the mean, from the Student t
+	/// distribution, 0 for a single run
+	pub half_width: f64,
+}
+
+impl Estimate {
+	fn new(values: &[f64]) -> Self {
+		let runs = values.len();
+		let mean = values.iter().sum::<f64>() / runs.max(1) as f64;
+		if runs < 2 {
+			return Self { mean, std_dev: 0.0, half_width: 0.0 };
+		}
+
+		let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / (runs - 1) as f64;
+		let std_dev = variance.sqrt();
+		let t = T_QUANTILES.get(runs - 2).copied().unwrap_or(Z_QUANTILE);
+
+		Self { mean, std_dev, half_width: t * std_dev / (runs as f64).sqrt() }
+	}
+}
+
+/// A scheduler run several times on the same workload, cluster size and load factor with
+/// other seeds.
+#[derive(Debug, Clone)]
+pub struct Repetitions {
+	pub workload: String,
+	pub nodes: u32,
+	pub load_factor: f64,
+	pub scheduler: String,
+	pub seeds: Vec<u64>,
+	/// One estimate per metric of `METRICS`
+	pub estimates: Vec<Estimate>,
+}
+
+/// Means and confidence intervals of the metrics of runs repeated with other seeds, which
+/// tell whether the differences between schedulers are larger than the noise of the
+/// stochastic components (walltime models, failures, user model...), see
+/// `ExperimentRunner::repetitions`.
+#[derive(Debug, Clone)]
+pub struct Confidence {
+	pub runs: Vec<Repetitions>,
+}
+
+impl Confidence {
+	/// Groups the reports by workload, cluster size, load factor and scheduler.
+	pub fn new(reports: &[EngineReport]) -> Self {
+		let mut grouped: Vec<Vec<&EngineReport>> = Vec::new();
+
+		for report in reports {
+			let same = |other: &&EngineReport| other.workload == report.workload && other.nodes == report.nodes
+				&& other.load_factor == report.load_factor && other.scheduler_name == report.scheduler_name;
+
+			match grouped.iter_mut().find(|group| same(&group[0])) {
+				Some(group) => group.push(report),
+				None => grouped.push(vec![report]),
+			}
+		}
+
+		let runs = grouped.into_iter().map(|reports| Repetitions {
+			workload: reports[0].workload.clone(),
+			nodes: reports[0].nodes,
+			load_factor: reports[0].load_factor,
+			scheduler: reports[0].scheduler_name.clone(),
+			seeds: reports.iter().map(|report| report.seed).collect(),
+			estimates: METRICS.iter()
+				.map(|metric| Estimate::new(&reports.iter().map(|report| (metric.value)(report)).collect::<Vec<_>>()))
+				.collect(),
+		}).collect();
+
+		Self { runs }
+	}
+
+	/// Whether some run was repeated.
+	pub fn is_repeated(&self) -> bool {
+		self.runs.iter().any(|run| run.seeds.len() > 1)
+	}
+
+	/// Writes the estimates as CSV, one line per run and metric, the confidence interval
+	/// going from `low` to `high`.
+	pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+		writeln!(out, "workload,nodes,load_factor,scheduler,runs,metric,mean,std_dev,low,high")?;
+
+		for run in &self.runs {
+			for (metric, estimate) in METRICS.iter().zip(&run.estimates) {
+				writeln!(out, "{},{},{},{},{},{},{},{},{},{}", csv_field(&run.workload), run.nodes, run.load_factor, csv_field(&run.scheduler), run.seeds.len(), metric.name,
+					estimate.mean, estimate.std_dev, estimate.mean - estimate.half_width, estimate.mean + estimate.half_width)?;
+			}
+		}
+
+		out.flush()
+	}
+
+	pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		self.write_csv(BufWriter::new(File::create(path)?))
+	}
+}
+
+/// A table per workload, cluster size and load factor, with the mean and the 95% confidence
+/// interval of each metric.
+impl Display for Confidence {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		const WIDTH: usize = 26;
+
+		let mut tables: Vec<Vec<&Repetitions>> = Vec::new();
+		for run in &self.runs {
+			match tables.iter_mut().find(|table| table[0].workload == run.workload && table[0].nodes == run.nodes && table[0].load_factor == run.load_factor) {
+				Some(table) => table.push(run),
+				None => tables.push(vec![run]),
+			}
+		}
+
+		for table in tables {
+			writeln!(f, "{} on {} nodes{}: mean and 95% confidence interval", table[0].workload, table[0].nodes, load(table[0].load_factor))?;
+
+			let name_width = table.iter().map(|run| run.scheduler.len()).max().unwrap_or(0).max("scheduler".len());
+			write!(f, "{:<name_width$} {:>4}", "scheduler", "runs")?;
+			for metric in METRICS {
+				write!(f, " {:>WIDTH$}", metric.name)?;
+			}
+			writeln!(f)?;
+
+			for run in table {
+				write!(f, "{:<name_width$} {:>4}", run.scheduler, run.seeds.len())?;
+				for estimate in &run.estimates {
+					let text = format!("{} ± {}", compact(estimate.mean), compact(estimate.half_width));
+					write!(f, " {:>WIDTH$}", text)?;
+				}
+				writeln!(f)?;
+			}
+
+			writeln!(f)?;
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,381 @@
+use std::{fmt::Display, io::{self, BufRead, Write}, str::FromStr};
+
+use super::{Clock, Engine, EngineError, Event, report::EngineReport};
+
+const HELP: &str = "\
+step [N]              simulate the next N instants having events (default 1)
+continue              simulate until a breakpoint or the end
+break time T | job ID stop once the simulation reached time T (once), or when the job is
+                      submitted, starts, is stopped or ends
+delete                remove every breakpoint
+status                clock, jobs, next events and breakpoints
+queue                 queued jobs of every partition
+cluster               nodes and running jobs of every partition
+scheduler             why the scheduler leaves each queued job waiting
+events [N]            next N pending events (default 10)
+job ID                where the job is
+dump PATH             save the state of the simulation as a snapshot (see `EngineSnapshot`)
+quit                  stop without finishing the simulation";
+
+/// Where the debugger stops, see `Debugger`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+	/// Before the first instant after this time, every event up to it being simulated.
+	/// Removed once hit
+	Time(Clock),
+	/// Once the job is submitted, starts, is stopped or ends
+	Job(u32),
+}
+
+/// Written `time T` or `job ID`.
+impl FromStr for Breakpoint {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid breakpoint '{}' (expected time T or job ID)", s);
+
+		match s.split_whitespace().collect::<Vec<_>>().as_slice() {
+			["time", time] => time.parse().map(Breakpoint::Time).map_err(|_| invalid()),
+			["job", id] => id.parse().map(Breakpoint::Job).map_err(|_| invalid()),
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl Display for Breakpoint {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Breakpoint::Time(time) => write!(f, "time {}", time),
+			Breakpoint::Job(id) => write!(f, "job {}", id),
+		}
+	}
+}
+
+/// Where a job is, telling when a job breakpoint is hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobState {
+	Queued(usize),
+	Running(usize),
+	/// Not submitted yet, held until the job it depends on finished, or done
+	Elsewhere,
+}
+
+/// Runs a simulation step by step, stopping at breakpoints to look at the queues, the
+/// clusters and the decisions of the scheduler, e.g. while developing a scheduler.
+///
+/// Steps go from an instant having events to the next one, each step simulating every
+/// event of the instant and the scheduling pass after them.
+pub struct Debugger {
+	engine: Engine,
+	breakpoints: Vec<Breakpoint>,
+	done: bool,
+}
+
+impl Debugger {
+	pub fn new(engine: Engine) -> Self {
+		Self {
+			engine,
+			breakpoints: Vec::new(),
+			done: false,
+		}
+	}
+
+	pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+		if !self.breakpoints.contains(&breakpoint) {
+			self.breakpoints.push(breakpoint);
+		}
+	}
+
+	/// Whether the whole workload was simulated.
+	pub fn is_done(&self) -> bool {
+		self.done
+	}
+
+	/// Simulates the next instant having events, returning the breakpoints it hit.
+	pub fn step(&mut self) -> Result<Vec<Breakpoint>, EngineError> {
+		if self.done {
+			return Ok(Vec::new());
+		}
+
+		let jobs = self.breakpoints.iter()
+			.filter_map(|breakpoint| match breakpoint {
+				Breakpoint::Job(id) => Some((*id, self.job_state(*id))),
+				Breakpoint::Time(_) => None,
+			})
+			.collect::<Vec<_>>();
+
+		self.done = !self.engine.step()?;
+
+		let mut hits = jobs.into_iter()
+			.filter(|&(id, state)| self.job_state(id) != state)
+			.map(|(id, _)| Breakpoint::Job(id))
+			.collect::<Vec<_>>();
+
+		// time breakpoints are hit once the next instant is past them
+		let next = if self.done { Clock::MAX } else { self.next_time().unwrap_or(Clock::MAX) };
+		self.breakpoints.retain(|breakpoint| match breakpoint {
+			Breakpoint::Time(time) if *time < next => {
+				hits.push(*breakpoint);
+				false
+			}
+			_ => true,
+		});
+
+		Ok(hits)
+	}
+
+	/// Simulates until a breakpoint is hit or the simulation ends, returning the breakpoints hit.
+	pub fn resume(&mut self) -> Result<Vec<Breakpoint>, EngineError> {
+		while !self.done {
+			let hits = self.step()?;
+			if !hits.is_empty() {
+				return Ok(hits);
+			}
+		}
+
+		Ok(Vec::new())
+	}
+
+	/// Reads commands from `input` and writes what they show to `out`, until the simulation
+	/// ends or the `quit` command. Returns the report of the simulation if it ended.
+	pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut out: W) -> Result<Option<EngineReport>, EngineError> {
+		writeln!(out, "{} on {} nodes, type help for the commands", self.engine.label, self.engine.total_nodes)?;
+		self.prompt(&mut out)?;
+
+		for line in input.lines() {
+			let line = line?;
+			let mut words = line.split_whitespace();
+
+			match (words.next(), words.next()) {
+				(None, _) => {}
+				(Some("quit" | "exit"), _) => return Ok(None),
+				(Some("step" | "s"), count) => match count.map_or(Ok(1), str::parse::<usize>) {
+					Ok(count) => {
+						let mut hits = Vec::new();
+						for _ in 0..count {
+							let events = self.next_events();
+							hits = self.step()?;
+
+							for event in events {
+								writeln!(out, "  {:>8}  {}", self.engine.clock, event)?;
+							}
+
+							if self.done || !hits.is_empty() {
+								break;
+							}
+						}
+
+						self.print_hits(&mut out, &hits)?;
+					}
+					Err(_) => writeln!(out, "invalid number of steps")?,
+				},
+				(Some("continue" | "c"), _) => {
+					let hits = self.resume()?;
+					self.print_hits(&mut out, &hits)?;
+				}
+				(Some("break" | "b"), _) => match line.split_once(char::is_whitespace).map_or(Err(String::from("expected time T or job ID")), |(_, breakpoint)| breakpoint.parse()) {
+					Ok(breakpoint) => self.add_breakpoint(breakpoint),
+					Err(why) => writeln!(out, "{}", why)?,
+				},
+				(Some("delete" | "d"), _) => self.breakpoints.clear(),
+				(Some("status"), _) => self.print_status(&mut out)?,
+				(Some("queue"), _) => self.print_queues(&mut out)?,
+				(Some("cluster"), _) => self.print_clusters(&mut out)?,
+				(Some("scheduler"), _) => self.print_decisions(&mut out)?,
+				(Some("events"), count) => match count.map_or(Ok(10), str::parse::<usize>) {
+					Ok(count) => self.print_events(&mut out, count)?,
+					Err(_) => writeln!(out, "invalid number of events")?,
+				},
+				(Some("job"), Some(id)) => match id.parse() {
+					Ok(id) => self.print_job(&mut out, id)?,
+					Err(_) => writeln!(out, "invalid job ID '{}'", id)?,
+				},
+				(Some("dump"), Some(path)) => match self.engine.snapshot().save(path) {
+					Ok(()) => writeln!(out, "saved the state at {} to {}", self.engine.clock, path)?,
+					Err(why) => writeln!(out, "unable to write {}: {}", path, why)?,
+				},
+				(Some("help" | "h"), _) => writeln!(out, "{}", HELP)?,
+				(Some(command), _) => writeln!(out, "unknown command '{}', type help for the commands", command)?,
+			}
+
+			if self.done {
+				writeln!(out, "simulation over at {}", self.engine.clock)?;
+				return Ok(Some(self.engine.report()));
+			}
+
+			self.prompt(&mut out)?;
+		}
+
+		Ok(None)
+	}
+
+	fn job_state(&self, id: u32) -> JobState {
+		self.engine.partitions.iter().enumerate()
+			.find_map(|(idx, partition)| {
+				if partition.queue.iter().any(|job| job.id == id) {
+					Some(JobState::Queued(idx))
+				} else if partition.cluster.running_jobs.contains_key(&id) {
+					Some(JobState::Running(idx))
+				} else {
+					None
+				}
+			})
+			.unwrap_or(JobState::Elsewhere)
+	}
+
+	/// Events of the next instant having events, leaving out the ends of the runs of jobs
+	/// stopped since they started.
+	fn next_events(&self) -> Vec<String> {
+		let mut events = match self.next_time() {
+			Some(time) => self.engine.events.iter().filter(|pending| pending.time == time).collect::<Vec<_>>(),
+			None => Vec::new(),
+		};
+		events.sort();
+
+		events.into_iter()
+			.filter(|pending| match pending.event {
+				Event::JobFinished(id, run) => self.engine.running_partition(id).is_some_and(|idx| self.engine.partitions[idx].cluster.running_jobs[&id].runs() == run),
+				_ => true,
+			})
+			.map(|pending| describe(&pending.event))
+			.collect()
+	}
+
+	fn next_time(&self) -> Option<Clock> {
+		self.engine.events.peek_min().map(|pending| pending.time)
+	}
+
+	fn prompt<W: Write>(&self, out: &mut W) -> io::Result<()> {
+		write!(out, "[{}] > ", self.engine.clock)?;
+		out.flush()
+	}
+
+	fn print_hits<W: Write>(&self, out: &mut W, hits: &[Breakpoint]) -> io::Result<()> {
+		for breakpoint in hits {
+			match breakpoint {
+				Breakpoint::Job(id) => write!(out, "breakpoint on job {}: ", id).and_then(|_| self.print_job(out, *id))?,
+				Breakpoint::Time(time) => writeln!(out, "breakpoint at time {}", time)?,
+			}
+		}
+
+		Ok(())
+	}
+
+	fn print_status<W: Write>(&self, out: &mut W) -> io::Result<()> {
+		let engine = &self.engine;
+		writeln!(out, "clock {}, {} jobs finished, {} queued, {} running on {} of {} nodes",
+			engine.clock, engine.state.finished_jobs, engine.queued_jobs(),
+			engine.partitions.iter().map(|partition| partition.cluster.running_jobs.len()).sum::<usize>(),
+			engine.busy_nodes(), engine.total_nodes,
+		)?;
+
+		match self.next_time() {
+			Some(time) => writeln!(out, "next events at {}", time)?,
+			None => writeln!(out, "no pending event")?,
+		}
+
+		let breakpoints = self.breakpoints.iter().map(Breakpoint::to_string).collect::<Vec<_>>();
+		writeln!(out, "breakpoints: {}", if breakpoints.is_empty() { "none".to_string() } else { breakpoints.join(", ") })
+	}
+
+	fn print_queues<W: Write>(&self, out: &mut W) -> io::Result<()> {
+		for partition in &self.engine.partitions {
+			writeln!(out, "{}: {} jobs queued", partition.name, partition.queue.len())?;
+
+			for (idx, job) in partition.queue.iter().enumerate() {
+				writeln!(out, "  {:>4}  job {:<8} {:>6} nodes  requested {:>8}  waiting for {}",
+					idx, job.id, job.nodes, job.requested_run_time, self.engine.clock.saturating_sub(job.submit_time))?;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn print_clusters<W: Write>(&self, out: &mut W) -> io::Result<()> {
+		for partition in &self.engine.partitions {
+			let cluster = &partition.cluster;
+			let free = cluster.free_blocks().map(|block| format!("{}-{}", block.start, block.end - 1)).collect::<Vec<_>>();
+
+			writeln!(out, "{}: {} nodes, {} free, {} down, {} reserved, free blocks {}",
+				partition.name, cluster.total_nodes, cluster.available_nodes, cluster.down_nodes(), cluster.reserved_nodes(),
+				if free.is_empty() { "none".to_string() } else { free.join(";") },
+			)?;
+
+			let mut running = cluster.running_jobs.values().collect::<Vec<_>>();
+			running.sort_by_key(|job| (job.expected_end, job.id));
+			for job in running {
+				writeln!(out, "  job {:<8} {:>6} nodes  started {:>8}  ends {:>8}  on {}",
+					job.id, job.nodes, job.schedule_time, job.expected_end, job.allocation)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn print_decisions<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+		let engine = &mut self.engine;
+		writeln!(out, "{}", engine.label)?;
+
+		for partition in &engine.partitions {
+			for decision in engine.scheduler.explain(engine.clock, &partition.queue, &partition.cluster) {
+				writeln!(out, "  {}: job {} {}", partition.name, decision.job_id, decision.reason)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn print_events<W: Write>(&self, out: &mut W, count: usize) -> io::Result<()> {
+		let mut events = self.engine.events.iter().collect::<Vec<_>>();
+		events.sort();
+
+		for pending in events.into_iter().take(count) {
+			writeln!(out, "  {:>8}  {}", pending.time, describe(&pending.event))?;
+		}
+
+		Ok(())
+	}
+
+	fn print_job<W: Write>(&self, out: &mut W, id: u32) -> io::Result<()> {
+		let engine = &self.engine;
+
+		match self.job_state(id) {
+			JobState::Queued(idx) => {
+				let partition = &engine.partitions[idx];
+				let position = partition.queue.iter().position(|job| job.id == id).expect("the job is queued");
+				let job = &partition.queue[position];
+				writeln!(out, "job {} queued in {} at position {}, {} nodes for {} requested, submitted at {}",
+					id, partition.name, position, job.nodes, job.requested_run_time, job.submit_time)
+			}
+			JobState::Running(idx) => {
+				let partition = &engine.partitions[idx];
+				let job = &partition.cluster.running_jobs[&id];
+				writeln!(out, "job {} running in {} on {}, started at {}, ends at {}", id, partition.name, job.allocation, job.schedule_time, job.expected_end)
+			}
+			JobState::Elsewhere => match engine.state.job_records.iter().rev().find(|record| record.id == id) {
+				Some(record) => writeln!(out, "job {} ended at {} after waiting {}", id, record.end, record.wait),
+				None => writeln!(out, "job {} is neither queued nor running", id),
+			},
+		}
+	}
+}
+
+fn describe(event: &Event) -> String {
+	match event {
+		Event::NewJob(job) => format!("job {} submitted, {} nodes", job.id, job.nodes),
+		Event::JobFinished(id, _) => format!("job {} ends", id),
+		Event::JobReleased(job) => format!("job {} released after the job it depends on", job.id),
+		Event::UserJob(job) => format!("job {} submitted by its user", job.id),
+		Event::JobCancelled(id) => format!("job {} cancelled", id),
+		Event::NodesDown(outage) => format!("nodes {}-{} down", outage.first_node, outage.first_node + outage.nodes - 1),
+		Event::NodesUp(outage) => format!("nodes {}-{} back", outage.first_node, outage.first_node + outage.nodes - 1),
+		Event::SliceEnd => "end of the time slice".to_string(),
+		Event::Cycle => "scheduling cycle".to_string(),
+		Event::TraceStart(id) => format!("job {} reaches its start time in the trace", id),
+		Event::ReservationStart(partition, window) => format!("reservation {} of partition {} starts", window, partition),
+		Event::ReservationEnd(partition, window) => format!("reservation {} of partition {} ends", window, partition),
+		Event::DrainStart(idx) => format!("downtime {} starts draining its nodes", idx),
+		Event::DrainEnd(idx) => format!("downtime {} ends", idx),
+		Event::HoldEnd(id) => format!("job {} no longer held", id),
+	}
+}
@@ -0,0 +1,117 @@
+use std::{fmt::Display, fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, cluster::Cluster, job::Job};
+
+/// Why a queued job did or didn't start when the scheduler was called.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Reason {
+	Started,
+	/// Not enough free nodes right now
+	InsufficientNodes { requested: u32, available: u32 },
+	/// The job fits, but a job ahead of it in the policy's order has to start first
+	LowerPriority,
+	/// The job can't start before its reservation
+	BlockedByReservation { start: Clock },
+	/// The job fits, but would delay the reservation of the first job: it neither ends
+	/// before the shadow time nor fits on the extra nodes
+	BackfillWindowTooSmall { shadow_time: Clock, extra_nodes: u32 },
+	/// The job is too far in the queue to be considered for backfilling
+	OutsideBackfillDepth,
+	/// The scheduler holds the job until the given time, or until it releases it, see `Mark::Hold`
+	Held { until: Option<Clock> },
+}
+
+impl Display for Reason {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Reason::Started => write!(f, "started"),
+			Reason::InsufficientNodes { requested, available } => write!(f, "needs {} nodes but {} are free", requested, available),
+			Reason::LowerPriority => write!(f, "another job comes first"),
+			Reason::BlockedByReservation { start } => write!(f, "reserved to start at {}", start),
+			Reason::BackfillWindowTooSmall { shadow_time, extra_nodes } => write!(f, "doesn't end before {} nor fits on the {} extra nodes", shadow_time, extra_nodes),
+			Reason::OutsideBackfillDepth => write!(f, "beyond the backfill depth"),
+			Reason::Held { until: Some(until) } => write!(f, "held until {}", until),
+			Reason::Held { until: None } => write!(f, "held until released"),
+		}
+	}
+}
+
+/// Explanation of the scheduler for a single job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+	pub job_id: u32,
+	pub reason: Reason,
+}
+
+impl Decision {
+	pub fn new(job_id: u32, reason: Reason) -> Self {
+		Self { job_id, reason }
+	}
+}
+
+/// Why each job is waiting, when a job may start as soon as it fits unless another job
+/// comes first: the explanation given by schedulers that don't have their own.
+pub fn explain_by_nodes(jobs: &[Job], cluster: &Cluster) -> Vec<Decision> {
+	jobs.iter().map(|job| Decision::new(job.id, waiting_reason(job, cluster))).collect()
+}
+
+/// InsufficientNodes if the job doesn't fit right now, LowerPriority otherwise.
+pub fn waiting_reason(job: &Job, cluster: &Cluster) -> Reason {
+	if !cluster.fits(job) {
+		Reason::InsufficientNodes {
+			requested: job.nodes,
+			available: cluster.available_for(job.classes),
+		}
+	} else {
+		Reason::LowerPriority
+	}
+}
+
+/// A decision taken during a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecisionRecord {
+	/// Left empty when read back, see `Engine::restore`
+	#[serde(skip_deserializing)]
+	pub scheduler: String,
+	/// Size of the simulated cluster
+	pub cluster_nodes: u32,
+	pub partition: String,
+	pub clock: Clock,
+	pub job_id: u32,
+	#[serde(flatten)]
+	pub reason: Reason,
+}
+
+/// Writes the decision records of one or more runs as JSON lines.
+pub struct DecisionLogWriter<W: Write> {
+	out: W,
+}
+
+impl DecisionLogWriter<BufWriter<File>> {
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let file = File::create(path)?;
+		Ok(Self::new(BufWriter::new(file)))
+	}
+}
+
+impl<W: Write> DecisionLogWriter<W> {
+	pub fn new(out: W) -> Self {
+		Self { out }
+	}
+
+	pub fn write_run(&mut self, records: &[DecisionRecord]) -> io::Result<()> {
+		for record in records {
+			serde_json::to_writer(&mut self.out, record)?;
+			writeln!(self.out)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.out.flush()
+	}
+}
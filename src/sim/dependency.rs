@@ -0,0 +1,90 @@
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, job::Job};
+
+/// What happens to a submitted job, given its dependency.
+pub enum Admission {
+	/// The job may be queued now
+	Ready(Job),
+	/// The job may be queued at the given time, once the think time after its predecessor elapsed
+	Delayed(Clock, Job),
+	/// The job waits for its predecessor to finish
+	Held,
+}
+
+/// Dependencies between jobs (SWF fields 17 and 18): a job is only queued once the job
+/// preceding it finished and its think time elapsed, its submit time being moved to then.
+///
+/// Dependencies on jobs that were not submitted before (missing from the trace, skipped,
+/// later in the trace or the job itself) are ignored, which rules out cycles.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Dependencies {
+	submitted: HashSet<u32>,
+	/// End time of the finished jobs
+	finished: HashMap<u32, Clock>,
+	/// Jobs waiting for a job to finish, by ID of that job
+	held: HashMap<u32, Vec<Job>>,
+	/// Jobs held or delayed
+	waiting: usize,
+	/// Number of dependencies that were ignored
+	pub ignored: u32,
+}
+
+impl Dependencies {
+	pub fn submit(&mut self, mut job: Job, clock: Clock) -> Admission {
+		self.submitted.insert(job.id);
+
+		let preceding = match job.preceding_job {
+			Some(preceding) if preceding != job.id && self.submitted.contains(&preceding) => preceding,
+			Some(_) => {
+				self.ignored += 1;
+				return Admission::Ready(job);
+			}
+			None => return Admission::Ready(job),
+		};
+
+		match self.finished.get(&preceding) {
+			Some(&end) if end + job.think_time <= clock => Admission::Ready(job),
+			Some(&end) => {
+				Self::delay(&mut job, end);
+				self.waiting += 1;
+				Admission::Delayed(job.submit_time, job)
+			}
+			None => {
+				self.held.entry(preceding).or_default().push(job);
+				self.waiting += 1;
+				Admission::Held
+			}
+		}
+	}
+
+	/// Records the end of a job, returning the jobs depending on it with the time they may be queued.
+	pub fn finish(&mut self, id: u32, clock: Clock) -> Vec<(Clock, Job)> {
+		self.finished.insert(id, clock);
+
+		self.held.remove(&id).unwrap_or_default().into_iter().map(|mut job| {
+			Self::delay(&mut job, clock);
+			(job.submit_time, job)
+		}).collect()
+	}
+
+	/// Records that a delayed job was queued.
+	pub fn release(&mut self) {
+		self.waiting -= 1;
+	}
+
+	/// Number of jobs held or delayed.
+	pub fn waiting(&self) -> usize {
+		self.waiting
+	}
+
+	/// Moves the submission of a job to the think time after the end of its predecessor.
+	fn delay(job: &mut Job, preceding_end: Clock) {
+		let eligible = preceding_end + job.think_time;
+		if eligible > job.submit_time {
+			job.dependency_wait += eligible - job.submit_time;
+			job.submit_time = eligible;
+		}
+	}
+}
@@ -0,0 +1,72 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use super::{Clock, algos::SchedulerSpec};
+
+/// A scheduled downtime of some nodes, e.g. for a maintenance: from `start`, the nodes drain,
+/// running jobs finishing on them but no job starting on them, each node going down once free,
+/// until the end of the window brings them all back. Unlike reservations, the window takes
+/// given nodes rather than any free ones, and holds them wherever they are in the partitions.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Drain {
+	pub name: String,
+	/// Seconds since the start of the trace
+	pub start: Clock,
+	pub duration: Clock,
+	/// ID of the first node drained
+	#[serde(default)]
+	pub first_node: u32,
+	/// Nodes drained from `first_node`, every node of the machine from there if unset
+	#[serde(default)]
+	pub nodes: Option<u32>,
+}
+
+impl Drain {
+	/// Parses a comma separated list of downtime windows.
+	pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+		SchedulerSpec::parse_list(list)
+			.map_err(|why| why.to_string())?
+			.iter()
+			.map(Self::from_spec)
+			.collect()
+	}
+
+	fn from_spec(spec: &SchedulerSpec) -> Result<Self, String> {
+		let params = &spec.params;
+		params.allow_only(&["start", "duration", "first_node", "nodes"]).map_err(|why| why.to_string())?;
+
+		let required = |key: &str| params.get(key)
+			.map_err(|why| why.to_string())?
+			.ok_or_else(|| format!("downtime '{}' lacks its {}", spec.name, key));
+
+		Ok(Self {
+			name: spec.name.clone(),
+			start: required("start")?,
+			duration: required("duration")?,
+			first_node: params.get("first_node").map_err(|why| why.to_string())?.unwrap_or(0),
+			nodes: params.get("nodes").map_err(|why| why.to_string())?,
+		})
+	}
+
+	pub fn end(&self) -> Clock {
+		self.start + self.duration
+	}
+
+	/// IDs of the drained nodes, on a machine of `total_nodes` nodes.
+	pub fn node_ids(&self, total_nodes: u32) -> std::ops::Range<u32> {
+		let first = self.first_node.min(total_nodes);
+		first..self.nodes.map_or(total_nodes, |nodes| first.saturating_add(nodes).min(total_nodes))
+	}
+}
+
+/// Written like schedulers, e.g. `maintenance(start=86400, duration=7200)` or
+/// `rack3(start=3600, duration=600, first_node=256, nodes=128)`.
+impl FromStr for Drain {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::from_spec(&s.parse().map_err(|why: super::algos::SchedulerError| why.to_string())?)
+	}
+}
@@ -0,0 +1,919 @@
+use std::{collections::BTreeMap, fmt::Display, fs, io, path::{Path, PathBuf}, sync::Arc};
+
+use hashbrown::HashMap;
+use rayon::{ThreadPoolBuilder, prelude::*};
+use serde::{Deserialize, Deserializer, de::Error};
+
+use super::{Clock, DebugLevel, DecisionLatency, admission::AdmissionRule, allocation::AllocationPolicy, budget::Budget, comparison::Comparison, confidence::Confidence, Engine, EngineConfig, EngineError, EventOrder, SchedulingCycle, algos::{Replay, Scheduler, SchedulerError, SchedulerParams, SchedulerSpec}, cluster::{ClusterSpec, NodeClassSpec, PartitionSpec, read_class_map}, decision::DecisionLogWriter, drain::Drain, external::External, failure::FailureModel, gang::TimeSlicing, golden::{GoldenReports, Regression}, grid::Routing, initial::InitialState, joblog::JobLogWriter, malleable::{Malleability, Moldability}, occupancy::{DEFAULT_OCCUPANCY_INTERVAL, OccupancyWriter}, output::OutputManager, power::PowerModel, qos::QosClass, rejects::RejectsWriter, report::{EngineReport, ReportWriter}, reservation::Reservation, runtime::{Predictions, RuntimeModel}, stats::StatsMode, trace::TraceWriter, user::UserModel, utilization::{UtilizationSampling, UtilizationWriter}, walltime::{WalltimeLimit, WalltimeModel}, workload::WorkloadFilter};
+#[cfg(feature = "viz")]
+use super::gantt::{GanttColoring, GanttWriter};
+#[cfg(feature = "metrics")]
+use super::metrics::LiveMetrics;
+
+/// Builds a new scheduler for each run, as runs don't share any state.
+pub type SchedulerBuilder = Box<dyn Fn() -> Box<dyn Scheduler> + Send + Sync>;
+
+/// Simulates one or more workloads for every (node count, load factor, scheduler) combination,
+/// in parallel.
+pub struct ExperimentRunner {
+	workloads: Vec<String>,
+	config: EngineConfig,
+	node_counts: Vec<u32>,
+	/// Factors the submit times are divided by, `EngineConfig::load_factor` alone if empty
+	load_factors: Vec<f64>,
+	/// Runs of every combination, with seeds following `EngineConfig::seed`
+	repetitions: usize,
+	cores_per_node: Option<u32>,
+	partitions: Vec<PartitionSpec>,
+	node_classes: Vec<NodeClassSpec>,
+	class_map: Arc<HashMap<u32, String>>,
+	node_memory: Option<u64>,
+	schedulers: Vec<SchedulerBuilder>,
+	/// Whether the trace is also replayed for every workload and node count
+	validation: bool,
+	threads: Option<usize>,
+}
+
+impl ExperimentRunner {
+	pub fn new(workload: &str, config: EngineConfig) -> Self {
+		Self {
+			workloads: vec![workload.to_string()],
+			config,
+			node_counts: Vec::new(),
+			load_factors: Vec::new(),
+			repetitions: 1,
+			cores_per_node: None,
+			partitions: Vec::new(),
+			node_classes: Vec::new(),
+			class_map: Arc::default(),
+			node_memory: None,
+			schedulers: Vec::new(),
+			validation: false,
+			threads: None,
+		}
+	}
+
+	/// Also simulates this workload, the reports of all the workloads telling how schedulers
+	/// fare across traces, see `Comparison::aggregates`.
+	pub fn workload(mut self, workload: &str) -> Self {
+		self.workloads.push(workload.to_string());
+		self
+	}
+
+	pub fn node_counts(mut self, node_counts: &[u32]) -> Self {
+		self.node_counts.extend_from_slice(node_counts);
+		self
+	}
+
+	/// Sweeps the offered load: every run is simulated with its submit times divided by each of
+	/// these factors, e.g. from 0.5 for half the load to 2 for twice of it.
+	pub fn load_factors(mut self, load_factors: &[f64]) -> Self {
+		self.load_factors.extend_from_slice(load_factors);
+		self
+	}
+
+	/// Runs every combination this many times, with the seeds following the one of the
+	/// configuration, to tell the differences between schedulers from the noise of the
+	/// stochastic components, see `Confidence`.
+	pub fn repetitions(mut self, repetitions: usize) -> Self {
+		self.repetitions = repetitions.max(1);
+		self
+	}
+
+	/// Processors per node of every simulated cluster, read from the trace header by default.
+	pub fn cores_per_node(mut self, cores_per_node: u32) -> Self {
+		self.cores_per_node = Some(cores_per_node);
+		self
+	}
+
+	/// Splits every simulated cluster into these partitions.
+	pub fn partitions(mut self, partitions: &[PartitionSpec]) -> Self {
+		self.partitions.extend_from_slice(partitions);
+		self
+	}
+
+	/// Splits the nodes of every simulated cluster into these classes.
+	pub fn node_classes(mut self, node_classes: &[NodeClassSpec]) -> Self {
+		self.node_classes.extend_from_slice(node_classes);
+		self
+	}
+
+	/// Class of the jobs whose class doesn't follow from their memory, by job ID.
+	pub fn class_map(mut self, class_map: HashMap<u32, String>) -> Self {
+		self.class_map = Arc::new(class_map);
+		self
+	}
+
+	/// Kilobytes of memory of the nodes of every simulated cluster whose class doesn't set theirs.
+	pub fn node_memory(mut self, node_memory: u64) -> Self {
+		self.node_memory = Some(node_memory);
+		self
+	}
+
+	pub fn scheduler<F>(mut self, builder: F) -> Self
+	where F: Fn() -> Box<dyn Scheduler> + Send + Sync + 'static {
+		self.schedulers.push(Box::new(builder));
+		self
+	}
+
+	/// Also replays the trace for every workload and node count, before the schedulers, to
+	/// compare them to what the real system did, see `EngineConfig::validation`.
+	pub fn validation(mut self, validation: bool) -> Self {
+		self.validation = validation;
+		self
+	}
+
+	/// Number of runs executed at the same time, defaults to the number of CPUs.
+	pub fn threads(mut self, threads: usize) -> Self {
+		self.threads = Some(threads);
+		self
+	}
+
+	/// Executes every run and returns their results ordered by workload, node count, load
+	/// factor, scheduler, in the order they were added, the replay of the trace coming first,
+	/// then seed.
+	pub fn run(&self) -> Vec<Result<EngineReport, EngineError>> {
+		// the replay of the trace stands as a run without scheduler
+		let validation = self.validation.then_some(None);
+		let load_factors = match self.load_factors.is_empty() {
+			true => vec![self.config.load_factor],
+			false => self.load_factors.clone(),
+		};
+		let runs = self.workloads.iter()
+			.flat_map(|workload| self.node_counts.iter().map(move |&nodes| (workload, nodes)))
+			.flat_map(|(workload, nodes)| load_factors.iter().map(move |&load_factor| (workload, nodes, load_factor)))
+			.flat_map(|(workload, nodes, load_factor)| validation.into_iter().chain(self.schedulers.iter().map(Some)).map(move |builder| (workload, nodes, load_factor, builder)))
+			.flat_map(|(workload, nodes, load_factor, builder)| (0..self.repetitions as u64).map(move |repetition| (workload, nodes, load_factor, builder, self.config.seed + repetition)))
+			.collect::<Vec<_>>();
+
+		let execute = || runs.par_iter()
+			.map(|&(workload, nodes, load_factor, builder, seed)| {
+				let spec = ClusterSpec {
+					nodes,
+					cores_per_node: self.cores_per_node,
+					partitions: self.partitions.clone(),
+					node_classes: self.node_classes.clone(),
+					class_map: self.class_map.clone(),
+					node_memory: self.node_memory,
+				};
+
+				let config = EngineConfig { load_factor, seed, ..self.config.clone() };
+				let mut engine = match builder {
+					Some(builder) => Engine::new(builder(), spec, workload, config)?,
+					None => Engine::new(Box::new(Replay), spec, workload, EngineConfig { validation: true, ..config })?,
+				};
+
+				engine.run()
+			})
+			.collect();
+
+		match self.threads {
+			Some(threads) => ThreadPoolBuilder::new()
+				.num_threads(threads)
+				.build()
+				.expect("unable to create the thread pool")
+				.install(execute),
+			None => execute(),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum ExperimentError {
+	ConfigError(io::Error),
+	InvalidConfig(toml::de::Error),
+	SchedulerError(SchedulerError),
+	EngineError(EngineError),
+	OutputError(PathBuf, io::Error),
+	/// The metrics can't be served on the address
+	ServerError(String, io::Error),
+	GoldenError(PathBuf, io::Error),
+	/// The reports drifted from the golden reports
+	Regression(Regression),
+}
+
+impl Display for ExperimentError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ExperimentError::ConfigError(why) => write!(f, "Unable to read the configuration file {}", why),
+			ExperimentError::InvalidConfig(why) => write!(f, "Invalid configuration file {}", why),
+			ExperimentError::SchedulerError(why) => write!(f, "{}", why),
+			ExperimentError::EngineError(why) => write!(f, "Error during the simulation: {}", why),
+			ExperimentError::OutputError(path, why) => write!(f, "Unable to write {}: {}", path.display(), why),
+			ExperimentError::ServerError(addr, why) => write!(f, "Unable to serve the metrics on {}: {}", addr, why),
+			ExperimentError::GoldenError(path, why) => write!(f, "Unable to read the golden reports {}: {}", path.display(), why),
+			ExperimentError::Regression(regression) => write!(f, "{}", regression),
+		}
+	}
+}
+
+impl From<EngineError> for ExperimentError {
+	fn from(why: EngineError) -> Self {
+		ExperimentError::EngineError(why)
+	}
+}
+
+impl From<SchedulerError> for ExperimentError {
+	fn from(why: SchedulerError) -> Self {
+		ExperimentError::SchedulerError(why)
+	}
+}
+
+/// Files written once every run of an experiment is done.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+	/// Directory the results are organized in, a directory of the experiment holding its
+	/// configuration, reports, and the records of each run, see `OutputManager`
+	pub directory: Option<PathBuf>,
+	/// Name of the directory of the experiment, the name of its configuration file by default
+	pub name: Option<String>,
+	/// One report per run, as CSV or JSON lines depending on the extension
+	pub reports: Option<PathBuf>,
+	/// One record per simulated job, as CSV or SWF depending on the extension
+	pub job_log: Option<PathBuf>,
+	/// Timeline of the jobs and of the queue length, in the Chrome trace-event format
+	/// or as JSON lines if it ends with .jsonl
+	pub timeline: Option<PathBuf>,
+	/// Why each queued job did or didn't start at each scheduling pass, as JSON lines
+	pub decisions: Option<PathBuf>,
+	/// Jobs rejected when submitted, with the reason, as CSV
+	pub rejects: Option<PathBuf>,
+	/// Comparison of the schedulers to the baseline, as CSV
+	pub comparison: Option<PathBuf>,
+	/// Means and confidence intervals of the metrics over the repetitions of each run, as CSV
+	pub confidence: Option<PathBuf>,
+	/// Utilization of the cluster at each sample, as CSV
+	pub utilization: Option<PathBuf>,
+	/// Snapshots of the jobs running on the nodes, as JSON lines
+	pub occupancy: Option<PathBuf>,
+	/// Gantt charts of the schedules, as HTML or as SVG if it ends with .svg
+	#[cfg(feature = "viz")]
+	pub gantt: Option<PathBuf>,
+	/// What the colors of the Gantt charts stand for, see `GanttColoring`
+	#[cfg(feature = "viz")]
+	#[serde(default)]
+	pub gantt_colors: GanttColoring,
+}
+
+impl OutputConfig {
+	#[cfg(feature = "viz")]
+	fn draws_gantt(&self) -> bool {
+		self.gantt.is_some()
+	}
+
+	#[cfg(not(feature = "viz"))]
+	fn draws_gantt(&self) -> bool {
+		false
+	}
+
+	/// Whether each run records every job and the queue over time.
+	fn records_jobs(&self) -> bool {
+		self.directory.is_some() || self.job_log.is_some() || self.timeline.is_some() || self.draws_gantt()
+	}
+}
+
+/// A scheduler in a configuration file, either as `"easy(backfill_depth=16)"`
+/// or as `{ name = "easy", params = { backfill_depth = 16 } }`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SchedulerEntry {
+	Spec(String),
+	Table {
+		name: String,
+		#[serde(default)]
+		params: BTreeMap<String, toml::Value>,
+	},
+}
+
+fn deserialize_schedulers<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<SchedulerSpec>, D::Error> {
+	let entries = Vec::<SchedulerEntry>::deserialize(deserializer)?;
+
+	entries.into_iter().map(|entry| match entry {
+		SchedulerEntry::Spec(spec) => spec.parse().map_err(D::Error::custom),
+		SchedulerEntry::Table { name, params } => {
+			let mut spec = SchedulerSpec {
+				name,
+				params: SchedulerParams::default(),
+			};
+
+			for (key, value) in params {
+				match value {
+					toml::Value::String(value) => spec.params.insert(&key, &value),
+					value => spec.params.insert(&key, &value.to_string()),
+				}
+			}
+
+			Ok(spec)
+		}
+	}).collect()
+}
+
+fn default_slowdown_tau() -> Clock {
+	EngineConfig::default().slowdown_tau
+}
+
+fn default_dependencies() -> bool {
+	EngineConfig::default().dependencies
+}
+
+fn default_load_factors() -> Vec<f64> {
+	vec![EngineConfig::default().load_factor]
+}
+
+/// Load factors in a configuration file, either as `1.2` or as `[0.5, 1, 1.5, 2]`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LoadFactors {
+	One(f64),
+	Many(Vec<f64>),
+}
+
+fn deserialize_load_factors<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+	Ok(match LoadFactors::deserialize(deserializer)? {
+		LoadFactors::One(load_factor) => vec![load_factor],
+		LoadFactors::Many(load_factors) => load_factors,
+	})
+}
+
+fn default_repetitions() -> usize {
+	1
+}
+
+fn default_cancellations() -> bool {
+	EngineConfig::default().cancellations
+}
+
+/// A full sweep: every workload is simulated for every (node count, scheduler) combination.
+///
+/// ```toml
+/// workloads = ["ANL-Intrepid-2009-1.swf"]
+/// nodes = [1024, 2048]
+/// schedulers = ["fcfs", "easy(backfill_depth=16)", { name = "fairshare", params = { weights = "1:2" } }]
+/// limit = 5000
+/// warmup = 100
+/// budget = "days:30,seconds:60"
+/// walltime = "uniform:1:5"
+/// seed = 42
+/// repetitions = 10
+/// decision_latency = "10:2"
+/// failures = "mtbf:5000000:3600"
+/// power = "100:250:0.8:180"
+/// utilization = "interval:3600"
+/// occupancy = 600
+/// stats = "streaming"
+/// load_factor = [0.8, 1, 1.2]
+/// scale_widths = true
+/// lenient = true
+/// admission = ["max_walltime:86400", "reroute:batch"]
+/// users = "resubmit:600:2"
+/// time_slicing = "600:4:5"
+/// malleability = "0.5:2:amdahl:0.05"
+/// moldability = "1:4:downey:1"
+/// routing = "least-loaded"
+/// runtime = "user-average:2"
+/// initial_state = "machine.csv"
+/// predictions = "predictions.csv"
+/// golden = "golden.jsonl"
+/// tolerance = 0.5
+///
+/// [[partitions]]
+/// name = "debug"
+/// nodes = 64
+/// max_nodes = 16
+///
+/// [[partitions]]
+/// name = "batch"
+///
+/// node_memory = 2097152
+///
+/// [[node_classes]]
+/// name = "cpu"
+///
+/// [[node_classes]]
+/// name = "bigmem"
+/// nodes = 128
+/// memory = 4194304
+///
+/// [[reservations]]
+/// name = "maintenance"
+/// start = 86400
+/// duration = 7200
+/// nodes = 512
+///
+/// [[drains]]
+/// name = "rack3"
+/// start = 172800
+/// duration = 14400
+/// first_node = 256
+/// nodes = 128
+///
+/// [[qos]]
+/// name = "premium"
+/// max_wait = 600
+/// queues = [1]
+///
+/// [[qos]]
+/// name = "standard"
+/// max_wait = 86400
+///
+/// [filter]
+/// from_day = 30
+/// to_day = 60
+/// min_nodes = 2
+///
+/// [output]
+/// directory = "results"
+/// reports = "results.csv"
+/// job_log = "jobs.csv"
+/// rejects = "rejects.csv"
+/// timeline = "timeline.json"
+/// utilization = "utilization.csv"
+/// occupancy = "occupancy.jsonl"
+/// confidence = "confidence.csv"
+/// gantt = "schedules.html" # with the viz feature
+/// gantt_colors = "size"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Experiment {
+	pub workloads: Vec<String>,
+	pub nodes: Vec<u32>,
+	#[serde(default)]
+	pub cores_per_node: Option<u32>,
+	/// Partitions of every cluster, see `PartitionSpec`
+	#[serde(default)]
+	pub partitions: Vec<PartitionSpec>,
+	/// Node classes of every cluster, see `NodeClassSpec`
+	#[serde(default)]
+	pub node_classes: Vec<NodeClassSpec>,
+	/// File giving the node class of some jobs as `job_id,class` lines
+	#[serde(default)]
+	pub class_map: Option<PathBuf>,
+	/// File giving the jobs running or queued when the simulations start, see `InitialState`
+	#[serde(default)]
+	pub initial_state: Option<PathBuf>,
+	/// File giving predicted run times of the jobs as `job_id,run_time` lines, see `Predictions`
+	#[serde(default)]
+	pub predictions: Option<PathBuf>,
+	/// Memory of the nodes in kilobytes, for the classes that don't set theirs
+	#[serde(default)]
+	pub node_memory: Option<u64>,
+	/// Nodes kept from the jobs over time windows, see `Reservation`
+	#[serde(default)]
+	pub reservations: Vec<Reservation>,
+	/// Scheduled downtimes draining given nodes, see `Drain`
+	#[serde(default)]
+	pub drains: Vec<Drain>,
+	/// QoS classes of the jobs and their target waits, see `QosClass`
+	#[serde(default)]
+	pub qos: Vec<QosClass>,
+	/// `first-fit`, `best-fit`, `contiguous` or `torus:X:Y:Z`, see `AllocationPolicy`
+	#[serde(default)]
+	pub allocation: AllocationPolicy,
+	#[serde(deserialize_with = "deserialize_schedulers")]
+	pub schedulers: Vec<SchedulerSpec>,
+
+	#[serde(default)]
+	pub limit: Option<usize>,
+	#[serde(default)]
+	pub warmup: usize,
+	/// Leave the jobs finishing after this time out of the metrics
+	#[serde(default)]
+	pub cutoff: Option<Clock>,
+	/// Stop each simulation early, see `Budget`
+	#[serde(default)]
+	pub budget: Budget,
+	#[serde(default = "default_slowdown_tau")]
+	pub slowdown_tau: Clock,
+	/// Whether medians and percentiles are exact or estimated in constant memory, see `StatsMode`
+	#[serde(default)]
+	pub stats: StatsMode,
+	/// Jobs of the traces simulated, see `WorkloadFilter`
+	#[serde(default)]
+	pub filter: WorkloadFilter,
+	/// Submit times are divided by this factor, see `EngineConfig::load_factor`, every run
+	/// being simulated at each factor when there are several, see `ExperimentRunner::load_factors`
+	#[serde(rename = "load_factor", default = "default_load_factors", deserialize_with = "deserialize_load_factors")]
+	pub load_factors: Vec<f64>,
+	/// Scale the widths of the jobs to the simulated cluster, see `EngineConfig::scale_widths`
+	#[serde(default)]
+	pub scale_widths: bool,
+	/// Skip the malformed lines of the trace, see `EngineConfig::lenient`
+	#[serde(default)]
+	pub lenient: bool,
+	/// Allow preemptive schedulers to stop running jobs, restarting them takes this time
+	#[serde(default)]
+	pub preemption_overhead: Option<Clock>,
+	/// Model of the requested run times, see `WalltimeModel`
+	#[serde(default)]
+	pub walltime: WalltimeModel,
+	/// Whether jobs running longer than they requested are killed, see `WalltimeLimit`
+	#[serde(default)]
+	pub walltime_limit: WalltimeLimit,
+	/// Seed of the stochastic components of the runs, see `SimRng`
+	#[serde(default)]
+	pub seed: u64,
+	/// Runs of every combination, with the seeds following `seed`, see `ExperimentRunner::repetitions`
+	#[serde(default = "default_repetitions")]
+	pub repetitions: usize,
+	/// `finish-first` or `submit-first`
+	#[serde(default)]
+	pub event_order: EventOrder,
+	/// `event`, `instant` or a number of seconds, see `SchedulingCycle`
+	#[serde(default)]
+	pub cycle: SchedulingCycle,
+	/// `CYCLE[:JOB]` seconds the scheduler takes to start the jobs it picks, see `DecisionLatency`
+	#[serde(default)]
+	pub decision_latency: Option<DecisionLatency>,
+	/// Node failures and maintenances, see `FailureModel`
+	#[serde(default)]
+	pub failures: FailureModel,
+	/// Power of the nodes in watts, see `PowerModel`
+	#[serde(default)]
+	pub power: PowerModel,
+	/// Verbosity of the logs of the runs, see `DebugLevel`
+	#[serde(default)]
+	pub debug: DebugLevel,
+	/// Print the progress of every run on stderr
+	#[serde(default)]
+	pub progress: bool,
+	/// Address the progress of the runs is published on in the Prometheus format while they
+	/// run, e.g. `0.0.0.0:9100`, see `LiveMetrics`
+	#[cfg(feature = "metrics")]
+	#[serde(default)]
+	pub metrics: Option<String>,
+	/// Submit jobs once the job they depend on finished (SWF fields 17 and 18)
+	#[serde(default = "default_dependencies")]
+	pub dependencies: bool,
+	/// Take the jobs cancelled in the trace before they ran (SWF status 5) out of the queue when they were cancelled
+	#[serde(default = "default_cancellations")]
+	pub cancellations: bool,
+	/// Sample the utilization of the cluster, see `UtilizationSampling`. Sampled at every
+	/// event when None and `output.utilization` is set
+	#[serde(default)]
+	pub utilization: Option<UtilizationSampling>,
+	/// Seconds between two snapshots of the jobs running on the nodes, see
+	/// `OccupancySnapshot`. Taken every hour when None and `output.occupancy` is set
+	#[serde(default)]
+	pub occupancy: Option<Clock>,
+	/// Policies deciding which submitted jobs enter the queues, see `AdmissionRule`
+	#[serde(default)]
+	pub admission: Vec<AdmissionRule>,
+	/// Jobs submitted by the users when their jobs end, see `UserModel`
+	#[serde(default)]
+	pub users: UserModel,
+	/// Share the nodes in turns between rows of jobs, see `TimeSlicing`
+	#[serde(default)]
+	pub time_slicing: Option<TimeSlicing>,
+	/// Jobs schedulers may shrink or expand while they run, see `Malleability`
+	#[serde(default)]
+	pub malleability: Malleability,
+	/// Jobs schedulers may start on other numbers of nodes, see `Moldability`
+	#[serde(default)]
+	pub moldability: Moldability,
+	/// How the jobs are spread over the partitions, see `Routing`
+	#[serde(default)]
+	pub routing: Routing,
+	/// What the schedulers know of the run times of the jobs, see `RuntimeModel`
+	#[serde(default)]
+	pub runtime: RuntimeModel,
+	#[serde(default)]
+	pub threads: Option<usize>,
+	/// Also replay the trace for every workload and node count, before the schedulers, see
+	/// `EngineConfig::validation`
+	#[serde(default)]
+	pub validation: bool,
+	/// Also let the decision process listening on this address schedule every workload and
+	/// node count, after the schedulers, see `External`
+	#[serde(default)]
+	pub external: Option<String>,
+	/// Print a table comparing the schedulers once every run is done, see `Comparison`
+	#[serde(default)]
+	pub compare: bool,
+	/// Scheduler the others are compared to, the first one if None
+	#[serde(default)]
+	pub baseline: Option<String>,
+	/// Reports the runs are checked against once done, failing the experiment if their
+	/// metrics drifted, see `GoldenReports`
+	#[serde(default)]
+	pub golden: Option<PathBuf>,
+	/// Difference to the golden reports tolerated, in percent of the golden values
+	#[serde(default)]
+	pub tolerance: f64,
+
+	#[serde(default)]
+	pub output: OutputConfig,
+	/// Configuration file the experiment was read from, copied to its results, see `OutputManager`
+	#[serde(skip)]
+	pub config_path: Option<PathBuf>,
+}
+
+impl Experiment {
+	pub fn from_config<P: AsRef<Path>>(path: P) -> Result<Self, ExperimentError> {
+		let content = fs::read_to_string(&path).map_err(ExperimentError::ConfigError)?;
+		let mut experiment: Experiment = toml::from_str(&content).map_err(ExperimentError::InvalidConfig)?;
+		experiment.config_path = Some(path.as_ref().to_path_buf());
+
+		for spec in &experiment.schedulers {
+			spec.build()?;
+		}
+
+		Ok(experiment)
+	}
+
+	pub fn engine_config(&self) -> EngineConfig {
+		EngineConfig {
+			progress: self.progress,
+			dependencies: self.dependencies,
+			cancellations: self.cancellations,
+			task_limit: self.limit,
+			warmup: self.warmup,
+			cutoff: self.cutoff,
+			budget: self.budget,
+			slowdown_tau: self.slowdown_tau,
+			stats: self.stats,
+			filter: self.filter.clone(),
+			load_factor: self.load_factors.first().copied().unwrap_or(EngineConfig::default().load_factor),
+			scale_widths: self.scale_widths,
+			lenient: self.lenient,
+			record_jobs: self.output.records_jobs(),
+			record_timeline: self.output.directory.is_some() || self.output.timeline.is_some(),
+			record_decisions: self.output.decisions.is_some(),
+			record_rejects: self.output.directory.is_some() || self.output.rejects.is_some(),
+			preemption_overhead: self.preemption_overhead,
+			walltime: self.walltime,
+			walltime_limit: self.walltime_limit,
+			seed: self.seed,
+			event_order: self.event_order,
+			cycle: self.cycle,
+			decision_latency: self.decision_latency,
+			failures: self.failures.clone(),
+			power: self.power,
+			admission: self.admission.clone(),
+			users: self.users,
+			time_slicing: self.time_slicing,
+			malleability: self.malleability,
+			moldability: self.moldability,
+			routing: self.routing,
+			runtime: self.runtime,
+			initial_state: Arc::default(),
+			predictions: Arc::default(),
+			reservations: self.reservations.clone(),
+			drains: self.drains.clone(),
+			allocation: self.allocation,
+			qos: self.qos.clone(),
+			validation: false,
+			#[cfg(feature = "metrics")]
+			metrics: None,
+			utilization: self.utilization.or_else(|| self.output.utilization.as_ref().map(|_| UtilizationSampling::Events)),
+			occupancy: self.occupancy.or_else(|| self.output.occupancy.as_ref().map(|_| DEFAULT_OCCUPANCY_INTERVAL)),
+		}
+	}
+
+	/// Prepares the first run of the experiment, for the first workload, node count and
+	/// scheduler, e.g. to go through it with a `Debugger`.
+	pub fn first_engine(&self) -> Result<Engine, ExperimentError> {
+		let (workload, nodes, spec) = match (self.workloads.first(), self.nodes.first(), self.schedulers.first()) {
+			(Some(workload), Some(&nodes), Some(spec)) => (workload, nodes, spec),
+			_ => return Err(EngineError::InvalidWorkload("the experiment has no run".to_string()).into()),
+		};
+
+		let class_map = match &self.class_map {
+			Some(path) => read_class_map(path).map_err(EngineError::from)?,
+			None => HashMap::new(),
+		};
+
+		let cluster = ClusterSpec {
+			nodes,
+			cores_per_node: self.cores_per_node,
+			partitions: self.partitions.clone(),
+			node_classes: self.node_classes.clone(),
+			class_map: Arc::new(class_map),
+			node_memory: self.node_memory,
+		};
+
+		let config = EngineConfig { initial_state: self.initial_state()?, predictions: self.predictions()?, ..self.engine_config() };
+		Ok(Engine::new(spec.build()?, cluster, workload, config)?)
+	}
+
+	/// Jobs on the machine when the simulations start, read from `Experiment::initial_state`.
+	fn initial_state(&self) -> Result<Arc<InitialState>, ExperimentError> {
+		match &self.initial_state {
+			Some(path) => Ok(Arc::new(InitialState::read(path).map_err(EngineError::from)?)),
+			None => Ok(Arc::default()),
+		}
+	}
+
+	/// Run times predicted ahead of the simulations, read from `Experiment::predictions`.
+	fn predictions(&self) -> Result<Arc<Predictions>, ExperimentError> {
+		match &self.predictions {
+			Some(path) => Ok(Arc::new(Predictions::read(path).map_err(EngineError::from)?)),
+			None => Ok(Arc::default()),
+		}
+	}
+
+	/// Simulates every run, writes the outputs and returns the reports, by workload,
+	/// node count then scheduler. Job records, timelines, decisions and rejected jobs are only kept in the output files.
+	pub fn run(&self) -> Result<Vec<EngineReport>, ExperimentError> {
+		let mut reports = Vec::new();
+		let class_map = match &self.class_map {
+			Some(path) => read_class_map(path).map_err(EngineError::from)?,
+			None => HashMap::new(),
+		};
+
+		#[allow(unused_mut)]
+		let mut config = EngineConfig { initial_state: self.initial_state()?, predictions: self.predictions()?, ..self.engine_config() };
+
+		#[cfg(feature = "metrics")]
+		if let Some(addr) = &self.metrics {
+			let live = Arc::new(LiveMetrics::default());
+			let local = live.serve(addr.as_str()).map_err(|why| ExperimentError::ServerError(addr.clone(), why))?;
+			tracing::info!("publishing the metrics on http://{}/metrics", local);
+			config.metrics = Some(live);
+		}
+
+		if let Some((first, others)) = self.workloads.split_first() {
+			let mut runner = ExperimentRunner::new(first, config)
+				.node_counts(&self.nodes)
+				.load_factors(&self.load_factors)
+				.repetitions(self.repetitions)
+				.partitions(&self.partitions)
+				.node_classes(&self.node_classes)
+				.class_map(class_map)
+				.validation(self.validation);
+
+			if let Some(node_memory) = self.node_memory {
+				runner = runner.node_memory(node_memory);
+			}
+
+			for workload in others {
+				runner = runner.workload(workload);
+			}
+
+			for spec in &self.schedulers {
+				let spec = spec.clone();
+				runner = runner.scheduler(move || spec.build().expect("schedulers are checked beforehand"));
+			}
+
+			if let Some(addr) = &self.external {
+				let addr = addr.clone();
+				runner = runner.scheduler(move || Box::new(External::new(&addr)));
+			}
+
+			if let Some(cores_per_node) = self.cores_per_node {
+				runner = runner.cores_per_node(cores_per_node);
+			}
+
+			if let Some(threads) = self.threads {
+				runner = runner.threads(threads);
+			}
+
+			for report in runner.run() {
+				reports.push(report?);
+			}
+		}
+
+		if let Some(parent) = &self.output.directory {
+			let output_error = |why| ExperimentError::OutputError(parent.clone(), why);
+			let name = self.output.name.clone()
+				.or_else(|| self.config_path.as_ref()?.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+				.unwrap_or_else(|| "experiment".to_string());
+			let mut manager = OutputManager::create(parent, &name).map_err(output_error)?;
+
+			if let Some(config) = &self.config_path {
+				manager.copy_config(config).map_err(output_error)?;
+			}
+
+			for report in &reports {
+				manager.write_run(report).map_err(output_error)?;
+			}
+
+			let path = manager.finish(&reports, self.baseline.as_deref()).map_err(output_error)?;
+			tracing::info!("wrote the results to {}", path.display());
+		}
+
+		if let Some(path) = &self.output.timeline {
+			let output_error = |why| ExperimentError::OutputError(path.clone(), why);
+			let mut writer = TraceWriter::create(path).map_err(output_error)?;
+
+			for report in &mut reports {
+				writer.write_run(report).map_err(output_error)?;
+				report.queue_timeline.clear();
+			}
+
+			writer.finish().map_err(output_error)?;
+		}
+
+		#[cfg(feature = "viz")]
+		if let Some(path) = &self.output.gantt {
+			let output_error = |why| ExperimentError::OutputError(path.clone(), why);
+			let mut writer = GanttWriter::create(path, self.output.gantt_colors).map_err(output_error)?;
+
+			for report in &reports {
+				writer.write_run(report).map_err(output_error)?;
+			}
+
+			writer.finish().map_err(output_error)?;
+		}
+
+		if let Some(path) = &self.output.job_log {
+			let output_error = |why| ExperimentError::OutputError(path.clone(), why);
+			let mut writer = JobLogWriter::create(path).map_err(output_error)?;
+
+			for report in &reports {
+				writer.write_run(&report.job_records).map_err(output_error)?;
+			}
+
+			writer.finish().map_err(output_error)?;
+		}
+
+		if let Some(path) = &self.output.rejects {
+			let output_error = |why| ExperimentError::OutputError(path.clone(), why);
+			let mut writer = RejectsWriter::create(path).map_err(output_error)?;
+
+			for report in &mut reports {
+				writer.write_run(&report.rejects).map_err(output_error)?;
+				report.rejects.clear();
+			}
+
+			writer.flush().map_err(output_error)?;
+		}
+
+		if let Some(path) = &self.output.decisions {
+			let output_error = |why| ExperimentError::OutputError(path.clone(), why);
+			let mut writer = DecisionLogWriter::create(path).map_err(output_error)?;
+
+			for report in &mut reports {
+				writer.write_run(&report.decisions).map_err(output_error)?;
+				report.decisions.clear();
+			}
+
+			writer.flush().map_err(output_error)?;
+		}
+
+		if let Some(path) = &self.output.utilization {
+			let output_error = |why| ExperimentError::OutputError(path.clone(), why);
+			let mut writer = UtilizationWriter::create(path).map_err(output_error)?;
+
+			for report in &mut reports {
+				writer.write_run(report).map_err(output_error)?;
+				report.utilization_timeline.clear();
+			}
+
+			writer.finish().map_err(output_error)?;
+		}
+
+		if let Some(path) = &self.output.occupancy {
+			let output_error = |why| ExperimentError::OutputError(path.clone(), why);
+			let mut writer = OccupancyWriter::create(path).map_err(output_error)?;
+
+			for report in &mut reports {
+				writer.write_run(&report.occupancy).map_err(output_error)?;
+				report.occupancy.clear();
+			}
+
+			writer.flush().map_err(output_error)?;
+		}
+
+		for report in &mut reports {
+			report.job_records.clear();
+			report.rejects.clear();
+		}
+
+		if let Some(path) = &self.output.confidence {
+			Confidence::new(&reports)
+				.save_csv(path)
+				.map_err(|why| ExperimentError::OutputError(path.clone(), why))?;
+		}
+
+		if let Some(path) = &self.output.comparison {
+			Comparison::new(&reports, self.baseline.as_deref())
+				.save_csv(path)
+				.map_err(|why| ExperimentError::OutputError(path.clone(), why))?;
+		}
+
+		if let Some(path) = &self.output.reports {
+			let output_error = |why| ExperimentError::OutputError(path.clone(), why);
+			let mut writer = ReportWriter::create(path).map_err(output_error)?;
+
+			for report in &reports {
+				writer.write(report).map_err(output_error)?;
+			}
+
+			writer.flush().map_err(output_error)?;
+		}
+
+		if let Some(path) = &self.golden {
+			let golden = GoldenReports::load(path).map_err(|why| ExperimentError::GoldenError(path.clone(), why))?;
+			let regression = golden.check(&reports, self.tolerance);
+			if !regression.passed() {
+				return Err(ExperimentError::Regression(regression));
+			}
+
+			tracing::info!("{} runs matched their golden report", regression.checked);
+		}
+
+		Ok(reports)
+	}
+}
@@ -0,0 +1,204 @@
+use std::{io::{BufRead, BufReader, BufWriter, Write}, net::TcpStream};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, algos::Scheduler, cluster::Cluster, job::Job, queue::ReadyQueue};
+
+/// Version of the protocol, sent in the `hello` message.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A job as the decision process sees it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobInfo {
+	pub id: u32,
+	pub nodes: u32,
+	pub submit: Clock,
+	pub requested_time: Clock,
+	pub user: Option<u32>,
+	pub group: Option<u32>,
+	pub queue: Option<u32>,
+	pub qos: Option<u32>,
+	/// Index of the partition the job is queued in
+	pub partition: usize,
+}
+
+impl From<&Job> for JobInfo {
+	fn from(job: &Job) -> Self {
+		Self {
+			id: job.id,
+			nodes: job.nodes,
+			submit: job.submit_time,
+			requested_time: job.requested_run_time,
+			user: job.user_id,
+			group: job.group_id,
+			queue: job.queue,
+			qos: job.qos,
+			partition: job.partition_idx,
+		}
+	}
+}
+
+/// Messages the engine sends to the decision process, one JSON object per line, tagged by
+/// `event`. Only `schedule` expects an answer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Message {
+	/// First message of a run
+	Hello { version: u32 },
+	/// A job entered the queue of its partition, including jobs preempted or killed by a failure
+	Submitted { clock: Clock, job: JobInfo },
+	Finished { clock: Clock, job: JobInfo },
+	/// A queued job was cancelled and left the queue
+	Cancelled { clock: Clock, job: JobInfo },
+	/// The engine asks which jobs of the queue of a partition to start now, answered by a `Decision`
+	Schedule {
+		clock: Clock,
+		total_nodes: u32,
+		free_nodes: u32,
+		/// IDs of the queued jobs, by submission
+		queue: Vec<u32>,
+		/// IDs of the running jobs
+		running: Vec<u32>,
+	},
+	/// Last message of a run, the connection closing after it
+	End,
+}
+
+/// Answer of the decision process to a `schedule` message.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+	/// IDs of the queued jobs to start, in order, each on the nodes the previous ones left.
+	/// Jobs that don't fit or aren't queued are left out
+	pub start: Vec<u32>,
+}
+
+struct Connection {
+	reader: BufReader<TcpStream>,
+	writer: BufWriter<TcpStream>,
+}
+
+/// Lets a process outside the engine make the decisions, e.g. a scheduler written in
+/// another language or the prototype of a batch system, like Batsim does: the engine
+/// connects to it over TCP when the run starts, tells it of the arrivals, completions and
+/// cancellations of jobs, and asks it which jobs to start whenever it schedules, see
+/// `Message` and `Decision`. Every run opens a connection of its own. The run panics if the
+/// process can't be reached or answers something else than a decision.
+pub struct External {
+	addr: String,
+	connection: Option<Connection>,
+}
+
+impl External {
+	/// Decision process listening on `addr`, e.g. `localhost:4242`, reached at the first event.
+	pub fn new(addr: &str) -> Self {
+		Self { addr: addr.to_string(), connection: None }
+	}
+
+	fn connection(&mut self) -> &mut Connection {
+		let addr = &self.addr;
+		self.connection.get_or_insert_with(|| {
+			let unreachable = |why: std::io::Error| panic!("unable to reach the external scheduler at {}: {}", addr, why);
+			let stream = TcpStream::connect(addr.as_str()).unwrap_or_else(unreachable);
+			// messages are small and answered one by one
+			let _ = stream.set_nodelay(true);
+
+			let mut connection = Connection {
+				reader: BufReader::new(stream.try_clone().unwrap_or_else(unreachable)),
+				writer: BufWriter::new(stream),
+			};
+			send(&mut connection, addr, &Message::Hello { version: PROTOCOL_VERSION });
+			connection
+		})
+	}
+
+	fn send(&mut self, message: &Message) {
+		self.connection();
+		let connection = self.connection.as_mut().expect("connected above");
+		send(connection, &self.addr, message);
+	}
+}
+
+fn send(connection: &mut Connection, addr: &str, message: &Message) {
+	let sent = serde_json::to_writer(&mut connection.writer, message)
+		.map_err(std::io::Error::from)
+		.and_then(|_| connection.writer.write_all(b"\n"));
+
+	if let Err(why) = sent {
+		panic!("unable to write to the external scheduler at {}: {}", addr, why);
+	}
+}
+
+impl Scheduler for External {
+	fn name(&self) -> &'static str {
+		"External"
+	}
+
+	fn label(&self) -> String {
+		format!("External({})", self.addr)
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		self.schedule_batch(clock, jobs, cluster).first().copied()
+	}
+
+	fn schedule_batch(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		let mut running = cluster.running_jobs.keys().copied().collect::<Vec<_>>();
+		running.sort_unstable();
+
+		self.send(&Message::Schedule {
+			clock,
+			total_nodes: cluster.total_nodes,
+			free_nodes: cluster.available_nodes,
+			queue: jobs.iter().map(|job| job.id).collect(),
+			running,
+		});
+
+		let addr = &self.addr;
+		let connection = self.connection.as_mut().expect("connected by send");
+		let mut line = String::new();
+		let read = connection.writer.flush().and_then(|_| connection.reader.read_line(&mut line));
+		match read {
+			Ok(0) => panic!("the external scheduler at {} closed the connection", addr),
+			Err(why) => panic!("unable to read from the external scheduler at {}: {}", addr, why),
+			Ok(_) => {}
+		}
+
+		let decision: Decision = serde_json::from_str(&line)
+			.unwrap_or_else(|why| panic!("invalid decision from the external scheduler at {}: {} ({})", addr, line.trim(), why));
+
+		let mut batch = Vec::new();
+		for id in decision.start {
+			if let Some(idx) = jobs.iter().position(|job| job.id == id).filter(|idx| !batch.contains(idx)) {
+				batch.push(idx);
+			}
+		}
+		batch
+	}
+
+	/// The process answers with every job to start now.
+	fn complete_batches(&self) -> bool {
+		true
+	}
+
+	fn on_job_arrival(&mut self, clock: u64, job: &Job) {
+		self.send(&Message::Submitted { clock, job: job.into() });
+	}
+
+	fn on_job_finish(&mut self, clock: u64, job: &Job) {
+		self.send(&Message::Finished { clock, job: job.into() });
+	}
+
+	fn on_job_cancel(&mut self, clock: u64, job: &Job) {
+		self.send(&Message::Cancelled { clock, job: job.into() });
+	}
+}
+
+/// Ends the run for the process, if it was reached.
+impl Drop for External {
+	fn drop(&mut self) {
+		if let Some(connection) = &mut self.connection {
+			let end = serde_json::to_string(&Message::End).expect("a Message is always serializable");
+			let _ = writeln!(connection.writer, "{}", end).and_then(|_| connection.writer.flush());
+		}
+	}
+}
@@ -0,0 +1,167 @@
+use std::{convert::TryFrom, fmt::Display, fs, path::{Path, PathBuf}, str::FromStr};
+
+use rand::{Rng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, EngineError, rng::{SimRng, Stream}};
+
+/// A range of nodes going down at the same time, for a failure or a maintenance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Outage {
+	pub time: Clock,
+	pub first_node: u32,
+	pub nodes: u32,
+	pub duration: Clock,
+}
+
+/// Outages read from a file, one per line as `time duration first_node nodes`,
+/// lines starting with `;` or `#` being comments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FailureTrace {
+	pub outages: Vec<Outage>,
+}
+
+impl FailureTrace {
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, EngineError> {
+		fs::read_to_string(path)?.parse()
+	}
+}
+
+impl FromStr for FailureTrace {
+	type Err = EngineError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut outages = Vec::new();
+
+		for line in s.lines().map(str::trim) {
+			if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+				continue;
+			}
+
+			let fields = line.split_whitespace().collect::<Vec<_>>();
+			if fields.len() != 4 {
+				return Err(EngineError::InvalidFailures(format!("expected time, duration, first node and nodes in '{}'", line)));
+			}
+
+			outages.push(Outage {
+				time: fields[0].parse()?,
+				duration: fields[1].parse()?,
+				first_node: fields[2].parse()?,
+				nodes: fields[3].parse()?,
+			});
+		}
+
+		outages.sort_unstable();
+		Ok(Self { outages })
+	}
+}
+
+/// Where node failures come from.
+///
+/// Written `none`, `trace:PATH` or `mtbf:MTBF:MTTR`.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum FailureModel {
+	/// Nodes never fail
+	#[default]
+	None,
+	/// Outages listed in a file, see `FailureTrace`
+	Trace(PathBuf),
+	/// Each node fails after an exponentially distributed time of mean `mtbf` seconds,
+	/// and is repaired `mttr` seconds later
+	Random { mtbf: f64, mttr: Clock },
+}
+
+impl FailureModel {
+	/// Outages of a cluster of `nodes` nodes, in time order.
+	pub fn outages(&self, nodes: u32, rng: &SimRng) -> Result<Box<dyn Iterator<Item = Outage> + Send>, EngineError> {
+		Ok(match self {
+			FailureModel::None => Box::new(std::iter::empty()),
+			FailureModel::Trace(path) => {
+				let trace = FailureTrace::load(path)?;
+				Box::new(trace.outages.into_iter().filter(move |outage| outage.first_node < nodes))
+			}
+			&FailureModel::Random { mtbf, mttr } => Box::new(RandomFailures {
+				rng: rng.stream(Stream::Failures),
+				rate: nodes as f64 / mtbf,
+				nodes,
+				mttr,
+				time: 0.0,
+			}),
+		})
+	}
+}
+
+impl FromStr for FailureModel {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid failure model '{}' (expected none, trace:PATH or mtbf:MTBF:MTTR)", s);
+
+		if s.eq_ignore_ascii_case("none") {
+			return Ok(FailureModel::None);
+		}
+
+		if let Some(path) = s.strip_prefix("trace:") {
+			return Ok(FailureModel::Trace(PathBuf::from(path)));
+		}
+
+		let parts = s.split(':').collect::<Vec<_>>();
+		match parts.as_slice() {
+			["mtbf", mtbf, mttr] => {
+				let mtbf = mtbf.trim().parse::<f64>().map_err(|_| invalid())?;
+				let mttr = mttr.trim().parse().map_err(|_| invalid())?;
+
+				if mtbf > 0.0 && mtbf.is_finite() {
+					Ok(FailureModel::Random { mtbf, mttr })
+				} else {
+					Err(invalid())
+				}
+			}
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for FailureModel {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl Display for FailureModel {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			FailureModel::None => write!(f, "none"),
+			FailureModel::Trace(path) => write!(f, "trace:{}", path.display()),
+			FailureModel::Random { mtbf, mttr } => write!(f, "mtbf:{}:{}", mtbf, mttr),
+		}
+	}
+}
+
+/// Failures of single nodes, the cluster failing as a whole at `nodes / mtbf` failures per second.
+struct RandomFailures {
+	rng: StdRng,
+	rate: f64,
+	nodes: u32,
+	mttr: Clock,
+	time: f64,
+}
+
+impl Iterator for RandomFailures {
+	type Item = Outage;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let uniform: f64 = self.rng.random();
+		self.time += -(1.0 - uniform).ln() / self.rate;
+
+		Some(Outage {
+			time: self.time as Clock,
+			first_node: self.rng.random_range(0..self.nodes),
+			nodes: 1,
+			duration: self.mttr,
+		})
+	}
+}
@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, report::Distribution};
+
+/// How scattered the free nodes were over a run, and what it cost: the length of the
+/// stretches of contiguous free nodes weighted by how long they lasted, the scheduling passes
+/// leaving the first queued job waiting though enough nodes were free, and the node-seconds
+/// nodes stayed free while jobs were queued (holes no job could fill).
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Fragmentation {
+	/// Seconds the stretches of each length lasted, summed over the stretches
+	stretches: BTreeMap<u32, Clock>,
+	/// Lengths of the stretches of free nodes since the last change
+	current: Vec<u32>,
+	/// Free nodes of the partitions having queued jobs, since the last change
+	holes: u32,
+	since: Clock,
+	hole_time: u64,
+	stalls: u32,
+}
+
+impl Fragmentation {
+	/// Records the stretches of free nodes and the free nodes of the partitions having
+	/// queued jobs, from `clock` until the next call.
+	pub fn record(&mut self, clock: Clock, stretches: impl Iterator<Item = u32>, holes: u32) {
+		self.count_until(clock);
+		self.current.clear();
+		self.current.extend(stretches);
+		self.holes = holes;
+	}
+
+	/// Records a scheduling pass after which the first queued job of a partition waited
+	/// though enough nodes were free for it.
+	pub fn stall(&mut self) {
+		self.stalls += 1;
+	}
+
+	/// Summary over the simulation ending at `end`.
+	pub fn finish(&mut self, end: Clock) -> (Distribution, u32, u64) {
+		self.count_until(end);
+
+		let mut weighted = self.stretches.iter()
+			.map(|(&length, &time)| (length as f64, time as f64))
+			.collect::<Vec<_>>();
+
+		(Distribution::from_weighted(&mut weighted), self.stalls, self.hole_time)
+	}
+
+	fn count_until(&mut self, clock: Clock) {
+		let elapsed = clock.saturating_sub(self.since);
+		if elapsed > 0 {
+			for &length in &self.current {
+				*self.stretches.entry(length).or_insert(0) += elapsed;
+			}
+
+			self.hole_time += self.holes as u64 * elapsed;
+		}
+
+		self.since = clock;
+	}
+}
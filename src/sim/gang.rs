@@ -0,0 +1,103 @@
+use std::{convert::TryFrom, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, job::Job};
+
+/// Time-sharing of the nodes (gang scheduling): each partition holds up to `slots` rows of
+/// jobs, and runs a single row at a time for `quantum` seconds. The scheduler fills the free
+/// nodes of the running row from the queue. Switching rows suspends every running job, and
+/// resumed jobs spend `overhead` seconds restoring themselves before making progress.
+///
+/// Written `QUANTUM:SLOTS[:OVERHEAD]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeSlicing {
+	pub quantum: Clock,
+	pub slots: usize,
+	pub overhead: Clock,
+}
+
+impl FromStr for TimeSlicing {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid time slicing '{}' (expected QUANTUM:SLOTS[:OVERHEAD])", s);
+
+		let parts = s.split(':').map(str::trim).collect::<Vec<_>>();
+		let (quantum, slots, overhead) = match parts.as_slice() {
+			[quantum, slots] => (quantum, slots, "0"),
+			[quantum, slots, overhead] => (quantum, slots, *overhead),
+			_ => return Err(invalid()),
+		};
+
+		let slicing = TimeSlicing {
+			quantum: quantum.parse().map_err(|_| invalid())?,
+			slots: slots.parse().map_err(|_| invalid())?,
+			overhead: overhead.parse().map_err(|_| invalid())?,
+		};
+
+		match slicing.quantum > 0 && slicing.slots > 0 {
+			true => Ok(slicing),
+			false => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for TimeSlicing {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<TimeSlicing> for String {
+	fn from(slicing: TimeSlicing) -> Self {
+		slicing.to_string()
+	}
+}
+
+impl Display for TimeSlicing {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}:{}:{}", self.quantum, self.slots, self.overhead)
+	}
+}
+
+/// Rows of jobs of a partition taking turns on its nodes, see `TimeSlicing`.
+/// The jobs of the active row run on the cluster, the others are suspended here.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GangMatrix {
+	rows: Vec<Vec<Job>>,
+	active: usize,
+}
+
+impl GangMatrix {
+	pub fn new(slots: usize) -> Self {
+		Self {
+			rows: vec![Vec::new(); slots],
+			active: 0,
+		}
+	}
+
+	/// Number of suspended jobs.
+	pub fn suspended(&self) -> usize {
+		self.rows.iter().map(Vec::len).sum()
+	}
+
+	/// Row to run in the next slice: the next row holding suspended jobs, or an empty row
+	/// if jobs are queued. None if the active row keeps running.
+	pub fn next_row(&self, queued: bool) -> Option<usize> {
+		(1..self.rows.len())
+			.map(|offset| (self.active + offset) % self.rows.len())
+			.find(|&row| queued || !self.rows[row].is_empty())
+	}
+
+	/// Stores the jobs suspended from the active row, and returns the jobs of the given row
+	/// to resume, which becomes the active one.
+	pub fn switch(&mut self, suspended: Vec<Job>, row: usize) -> Vec<Job> {
+		self.rows[self.active] = suspended;
+		self.active = row;
+		std::mem::take(&mut self.rows[row])
+	}
+}
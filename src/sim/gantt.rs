@@ -0,0 +1,215 @@
+use std::{convert::TryFrom, fmt::{Display, Write as _}, fs::File, io::{self, BufWriter, Write}, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, report::EngineReport};
+
+const WIDTH: f64 = 1200.0;
+const HEIGHT: f64 = 600.0;
+/// Room left of and below the chart for the axes
+const MARGIN: f64 = 60.0;
+const TICKS: u32 = 10;
+
+/// What the color of a job in a Gantt chart stands for.
+///
+/// Written `user` or `size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum GanttColoring {
+	/// One color per user, gray for jobs without one
+	#[default]
+	User,
+	/// From blue for single node jobs to red for jobs using the whole cluster
+	Size,
+}
+
+impl FromStr for GanttColoring {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"user" => Ok(GanttColoring::User),
+			"size" => Ok(GanttColoring::Size),
+			_ => Err(format!("invalid Gantt coloring '{}' (expected user or size)", s)),
+		}
+	}
+}
+
+impl TryFrom<String> for GanttColoring {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<GanttColoring> for String {
+	fn from(coloring: GanttColoring) -> Self {
+		coloring.to_string()
+	}
+}
+
+impl Display for GanttColoring {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			GanttColoring::User => write!(f, "user"),
+			GanttColoring::Size => write!(f, "size"),
+		}
+	}
+}
+
+impl GanttColoring {
+	fn color(self, user_id: Option<u32>, nodes: u32, cluster_nodes: u32) -> String {
+		match self {
+			GanttColoring::User => match user_id {
+				// golden angle steps keep the hues of close user IDs apart
+				Some(user) => format!("hsl({:.0},65%,55%)", (user as f64 * 137.508) % 360.0),
+				None => "#999".to_string(),
+			},
+			GanttColoring::Size => {
+				let share = (nodes.max(1) as f64).ln() / (cluster_nodes.max(2) as f64).ln();
+				format!("hsl({:.0},70%,50%)", 240.0 * (1.0 - share.min(1.0)))
+			}
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GanttFormat {
+	/// A page with one chart per run
+	Html,
+	/// A single image, the charts of the runs stacked on top of each other
+	Svg,
+}
+
+impl GanttFormat {
+	/// Guesses the format from the file extension, defaulting to HTML.
+	pub fn from_path(path: &Path) -> Self {
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some("svg") => GanttFormat::Svg,
+			_ => GanttFormat::Html,
+		}
+	}
+}
+
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Draws the schedule of one or more runs as Gantt charts: nodes on the Y axis, time on
+/// the X axis, and each job as a rectangle over the nodes of its last run. Hovering a job
+/// shows its ID, user, size and times. Reports must hold job records, see `EngineConfig::record_jobs`.
+pub struct GanttWriter<W: Write> {
+	out: W,
+	format: GanttFormat,
+	coloring: GanttColoring,
+	/// Charts of the runs, only written at the end for the SVG format since its size comes first
+	charts: Vec<String>,
+}
+
+impl GanttWriter<BufWriter<File>> {
+	pub fn create<P: AsRef<Path>>(path: P, coloring: GanttColoring) -> io::Result<Self> {
+		let path = path.as_ref();
+		let file = File::create(path)?;
+		Self::new(BufWriter::new(file), GanttFormat::from_path(path), coloring)
+	}
+}
+
+impl<W: Write> GanttWriter<W> {
+	pub fn new(mut out: W, format: GanttFormat, coloring: GanttColoring) -> io::Result<Self> {
+		if format == GanttFormat::Html {
+			writeln!(out, "<!DOCTYPE html>")?;
+			writeln!(out, "<html><head><meta charset=\"utf-8\"><title>Schedules</title>")?;
+			writeln!(out, "<style>body {{ font-family: sans-serif; }} rect:hover {{ stroke: #000; }}</style>")?;
+			writeln!(out, "</head><body>")?;
+		}
+
+		Ok(Self {
+			out,
+			format,
+			coloring,
+			charts: Vec::new(),
+		})
+	}
+
+	pub fn write_run(&mut self, report: &EngineReport) -> io::Result<()> {
+		let title = escape(&format!("{} on {} nodes ({})", report.scheduler_name, report.nodes, report.workload));
+		let chart = self.chart(&title, report);
+
+		match self.format {
+			GanttFormat::Html => {
+				writeln!(self.out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">", WIDTH + MARGIN, HEIGHT + MARGIN)?;
+				write!(self.out, "{}", chart)?;
+				writeln!(self.out, "</svg>")
+			}
+			GanttFormat::Svg => {
+				self.charts.push(chart);
+				Ok(())
+			}
+		}
+	}
+
+	/// Draws the chart of a run in the area between (0, 0) and (WIDTH + MARGIN, HEIGHT + MARGIN).
+	fn chart(&self, title: &str, report: &EngineReport) -> String {
+		let first = report.job_records.iter().map(|record| record.start).min().unwrap_or(0);
+		let last = report.job_records.iter().map(|record| record.end).max().unwrap_or(0);
+		let span = last.saturating_sub(first).max(1) as f64;
+		let node_height = HEIGHT / report.nodes.max(1) as f64;
+
+		let x = |time: Clock| MARGIN + (time - first) as f64 / span * WIDTH;
+		let mut svg = String::new();
+
+		// writing to a String can't fail
+		let _ = writeln!(svg, "<title>{}</title>", title);
+		let _ = writeln!(svg, "<rect x=\"{}\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"#f4f4f4\"/>", MARGIN, WIDTH, HEIGHT);
+
+		for record in &report.job_records {
+			let color = self.coloring.color(record.user_id, record.nodes, report.nodes);
+			let width = (x(record.end) - x(record.start)).max(0.5);
+			let user = record.user_id.map(|user| user.to_string()).unwrap_or_else(|| "-".to_string());
+
+			for range in record.allocation.ranges() {
+				let _ = writeln!(
+					svg,
+					"<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"{}\"><title>job {} (user {}) on {} nodes, {} to {}</title></rect>",
+					x(record.start), range.start as f64 * node_height, width, (range.end - range.start) as f64 * node_height, color,
+					record.id, user, record.nodes, record.start, record.end,
+				);
+			}
+		}
+
+		// time on the X axis, in hours since the first start
+		for tick in 0..=TICKS {
+			let time = first + (span * tick as f64 / TICKS as f64) as Clock;
+			let _ = writeln!(svg, "<line x1=\"{0:.2}\" y1=\"{1}\" x2=\"{0:.2}\" y2=\"{2}\" stroke=\"#333\"/>", x(time), HEIGHT, HEIGHT + 5.0);
+			let _ = writeln!(svg, "<text x=\"{:.2}\" y=\"{}\" font-size=\"11\" text-anchor=\"middle\">{:.1}h</text>", x(time), HEIGHT + 18.0, (time - first) as f64 / 3600.0);
+		}
+
+		let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"13\">{}</text>", MARGIN, HEIGHT + 42.0, title);
+		let _ = writeln!(svg, "<text x=\"{}\" y=\"12\" font-size=\"11\" text-anchor=\"end\">0</text>", MARGIN - 5.0);
+		let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">{}</text>", MARGIN - 5.0, HEIGHT, report.nodes);
+		let _ = writeln!(svg, "<text x=\"12\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\" transform=\"rotate(-90 12 {})\">nodes</text>", HEIGHT / 2.0, HEIGHT / 2.0);
+		svg
+	}
+
+	/// Terminates the output, which is incomplete until then.
+	pub fn finish(mut self) -> io::Result<()> {
+		match self.format {
+			GanttFormat::Html => writeln!(self.out, "</body></html>")?,
+			GanttFormat::Svg => {
+				let height = HEIGHT + MARGIN;
+				writeln!(self.out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">", WIDTH + MARGIN, height * self.charts.len().max(1) as f64)?;
+
+				for (idx, chart) in self.charts.iter().enumerate() {
+					writeln!(self.out, "<g transform=\"translate(0 {})\">", idx as f64 * height)?;
+					write!(self.out, "{}", chart)?;
+					writeln!(self.out, "</g>")?;
+				}
+
+				writeln!(self.out, "</svg>")?;
+			}
+		}
+
+		self.out.flush()
+	}
+}
@@ -0,0 +1,182 @@
+use std::{fmt::Display, fs, io, path::Path};
+
+use serde_json::{Map, Value};
+
+use super::{output::Manifest, report::EngineReport};
+
+/// Fields of the reports that differ from one run to the next, never compared.
+const UNSTABLE_FIELDS: &[&str] = &["time_took"];
+
+/// Relative difference under which values are taken as equal whatever the tolerance, as
+/// floats written to JSON may not read back to the exact same value.
+const ROUNDING: f64 = 1e-9;
+
+/// Reports recorded as the expected results of runs, e.g. before refactoring a scheduler,
+/// that later runs are checked against with `GoldenReports::check`.
+#[derive(Debug, Clone, Default)]
+pub struct GoldenReports {
+	reports: Vec<Map<String, Value>>,
+}
+
+/// A field of a report that changed from the golden report of its run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+	/// Path of the field, e.g. `bounded_slowdown.avg` or `by_width[2].jobs`
+	pub field: String,
+	pub golden: Value,
+	/// None if the report lacks the field
+	pub value: Option<Value>,
+}
+
+/// Runs whose reports drifted from their golden report, and runs without one.
+#[derive(Debug, Clone, Default)]
+pub struct Regression {
+	/// Tolerance the values were checked with, in percent
+	pub tolerance: f64,
+	/// Number of runs checked against a golden report
+	pub checked: usize,
+	/// Runs (as `scheduler on workload with N nodes, seed S`) and their drifted fields
+	pub drifts: Vec<(String, Vec<Drift>)>,
+	/// Runs without a golden report
+	pub unmatched: Vec<String>,
+}
+
+impl GoldenReports {
+	/// Reads the reports written as JSON lines (see `ReportWriter`), or those of the runs of an
+	/// experiment directory (see `OutputManager`).
+	pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let path = path.as_ref();
+		if !path.is_dir() {
+			return Self::parse(&fs::read_to_string(path)?);
+		}
+
+		let manifest: Manifest = serde_json::from_str(&fs::read_to_string(path.join("manifest.json"))?)?;
+		let mut golden = Self::default();
+		for run in &manifest.runs {
+			golden.reports.extend(Self::parse(&fs::read_to_string(path.join(&run.directory).join("report.json"))?)?.reports);
+		}
+
+		Ok(golden)
+	}
+
+	/// Reads reports written as JSON lines.
+	pub fn parse(text: &str) -> io::Result<Self> {
+		let reports = text.lines()
+			.filter(|line| !line.trim().is_empty())
+			.map(|line| match serde_json::from_str(line)? {
+				Value::Object(report) => Ok(report),
+				_ => Err(io::Error::new(io::ErrorKind::InvalidData, "a golden report isn't a JSON object")),
+			})
+			.collect::<io::Result<Vec<_>>>()?;
+
+		Ok(Self { reports })
+	}
+
+	pub fn len(&self) -> usize {
+		self.reports.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.reports.is_empty()
+	}
+
+	/// Compares the reports to the golden reports of the same runs (same workload, node count,
+	/// scheduler, seed and load factor, 1 when a golden report lacks it), numbers drifting when they differ by more than `tolerance` percent
+	/// of their golden value, and other values when they differ at all. Only the fields of the
+	/// golden reports are compared, so that reports may gain fields since they were recorded.
+	pub fn check(&self, reports: &[EngineReport], tolerance: f64) -> Regression {
+		let mut regression = Regression { tolerance, ..Regression::default() };
+
+		for report in reports {
+			let mut run = format!("{} on {} with {} nodes, seed {}", report.scheduler_name, report.workload, report.nodes, report.seed);
+			if report.load_factor != 1.0 {
+				run += &format!(", load x{}", report.load_factor);
+			}
+
+			let golden = self.reports.iter().find(|golden| {
+				golden.get("workload").and_then(Value::as_str) == Some(&report.workload)
+					&& golden.get("scheduler_name").and_then(Value::as_str) == Some(&report.scheduler_name)
+					&& golden.get("nodes").and_then(Value::as_u64) == Some(report.nodes as u64)
+					&& golden.get("seed").and_then(Value::as_u64) == Some(report.seed)
+					&& golden.get("load_factor").and_then(Value::as_f64).unwrap_or(1.0) == report.load_factor
+			});
+
+			let golden = match golden {
+				Some(golden) => golden,
+				None => {
+					regression.unmatched.push(run);
+					continue;
+				}
+			};
+
+			let value = serde_json::to_value(report).expect("an EngineReport is always serializable");
+			let mut drifts = Vec::new();
+			for (field, golden) in golden.iter().filter(|(field, _)| !UNSTABLE_FIELDS.contains(&field.as_str())) {
+				compare(field.clone(), golden, value.get(field), tolerance, &mut drifts);
+			}
+
+			regression.checked += 1;
+			if !drifts.is_empty() {
+				regression.drifts.push((run, drifts));
+			}
+		}
+
+		regression
+	}
+}
+
+/// Compares a golden value to the one of a report, going through objects and arrays.
+fn compare(field: String, golden: &Value, value: Option<&Value>, tolerance: f64, drifts: &mut Vec<Drift>) {
+	match (golden, value) {
+		(Value::Object(golden), Some(Value::Object(value))) => {
+			for (key, golden) in golden {
+				compare(format!("{}.{}", field, key), golden, value.get(key), tolerance, drifts);
+			}
+		}
+		(Value::Array(golden), Some(Value::Array(value))) if golden.len() == value.len() => {
+			for (idx, (golden, value)) in golden.iter().zip(value).enumerate() {
+				compare(format!("{}[{}]", field, idx), golden, Some(value), tolerance, drifts);
+			}
+		}
+		(Value::Number(number), Some(Value::Number(other))) => {
+			let (expected, actual) = (number.as_f64().unwrap_or(f64::NAN), other.as_f64().unwrap_or(f64::NAN));
+			if (actual - expected).abs() > expected.abs() * (tolerance / 100.0).max(ROUNDING) {
+				drifts.push(Drift { field, golden: golden.clone(), value: value.cloned() });
+			}
+		}
+		(golden, value) if Some(golden) != value => drifts.push(Drift { field, golden: golden.clone(), value: value.cloned() }),
+		_ => {}
+	}
+}
+
+impl Regression {
+	/// Whether every run had a golden report and none drifted from it.
+	pub fn passed(&self) -> bool {
+		self.drifts.is_empty() && self.unmatched.is_empty()
+	}
+}
+
+/// What drifted, as a diff of the golden values (-) and of the new ones (+).
+impl Display for Regression {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(f, "{} of {} runs drifted from their golden report by more than {}%", self.drifts.len(), self.checked, self.tolerance)?;
+
+		for (run, drifts) in &self.drifts {
+			writeln!(f, "{}:", run)?;
+			for drift in drifts {
+				writeln!(f, "-  {}: {}", drift.field, drift.golden)?;
+				match (&drift.value, drift.golden.as_f64(), drift.value.as_ref().and_then(Value::as_f64)) {
+					(_, Some(golden), Some(value)) if golden != 0.0 => writeln!(f, "+  {}: {} ({:+.2}%)", drift.field, value, (value - golden) * 100.0 / golden.abs())?,
+					(Some(value), _, _) => writeln!(f, "+  {}: {}", drift.field, value)?,
+					(None, _, _) => writeln!(f, "+  {}: missing", drift.field)?,
+				}
+			}
+		}
+
+		for run in &self.unmatched {
+			writeln!(f, "no golden report for {}", run)?;
+		}
+
+		Ok(())
+	}
+}
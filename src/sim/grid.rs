@@ -0,0 +1,115 @@
+use std::{convert::TryFrom, fmt::Display, str::FromStr};
+
+use serde::Deserialize;
+
+use super::job::Job;
+
+/// A cluster of the grid (a partition of the simulated machine), as seen by the meta-scheduler.
+#[derive(Debug, Clone, Copy)]
+pub struct Site {
+	/// Whether the routing rules of the partition accept the job, see `PartitionSpec::accepts`
+	pub accepts: bool,
+	/// Whether the partition has enough nodes of the classes of the job
+	pub fits: bool,
+	/// Nodes of its running and queued jobs, relative to its nodes
+	pub load: f64,
+}
+
+/// How the meta-scheduler spreads the submitted jobs over the clusters of a grid, each
+/// partition standing for a cluster with its own queue and scheduler.
+///
+/// Written `first`, `round-robin`, `least-loaded` or `locality:WEIGHT`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Routing {
+	/// The first partition whose rules accept the job
+	#[default]
+	First,
+	/// The accepting partitions large enough for the job in turns
+	RoundRobin,
+	/// The accepting partition large enough for the job with the lowest load, see `Site::load`
+	LeastLoaded,
+	/// Like `LeastLoaded`, running the job away from the cluster holding the data of its user
+	/// (see `Routing::home`) adding this to the load of the other clusters
+	Locality(f64),
+}
+
+impl Routing {
+	/// Index of the site the job goes to, `turn` being the next site in round-robin order.
+	/// Falls back to the first accepting site when none is large enough, for the job to be
+	/// rejected as too wide.
+	pub fn route(&self, job: &Job, sites: &[Site], turn: &mut usize) -> Option<usize> {
+		let first = sites.iter().position(|site| site.accepts);
+		let candidates = sites.iter().enumerate().filter(|(_, site)| site.accepts && site.fits);
+
+		let picked = match *self {
+			Routing::First => return first,
+			Routing::RoundRobin => {
+				let picked = candidates.map(|(idx, _)| idx).min_by_key(|&idx| (idx < *turn, idx));
+				if let Some(idx) = picked {
+					*turn = idx + 1;
+				}
+				picked
+			}
+			Routing::LeastLoaded => candidates
+				.min_by(|(_, a), (_, b)| a.load.total_cmp(&b.load))
+				.map(|(idx, _)| idx),
+			Routing::Locality(weight) => {
+				let home = Self::home(job, sites);
+				let cost = |idx: usize, site: &Site| site.load + if idx == home { 0.0 } else { weight };
+				candidates
+					.min_by(|&(a, site_a), &(b, site_b)| cost(a, site_a).total_cmp(&cost(b, site_b)))
+					.map(|(idx, _)| idx)
+			}
+		};
+
+		picked.or(first)
+	}
+
+	/// Index of the site holding the data of the job: the sites share the users in turns by
+	/// user ID, jobs without a user having theirs on the first accepting site.
+	pub fn home(job: &Job, sites: &[Site]) -> usize {
+		match job.user_id {
+			Some(user) if !sites.is_empty() => user as usize % sites.len(),
+			_ => sites.iter().position(|site| site.accepts).unwrap_or(0),
+		}
+	}
+}
+
+impl FromStr for Routing {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid routing '{}' (expected first, round-robin, least-loaded or locality:WEIGHT)", s);
+
+		match s.trim().split_once(':') {
+			None if s.trim() == "first" => Ok(Routing::First),
+			None if s.trim() == "round-robin" => Ok(Routing::RoundRobin),
+			None if s.trim() == "least-loaded" => Ok(Routing::LeastLoaded),
+			Some(("locality", weight)) => match weight.trim().parse::<f64>() {
+				Ok(weight) if weight >= 0.0 && weight.is_finite() => Ok(Routing::Locality(weight)),
+				_ => Err(invalid()),
+			},
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for Routing {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl Display for Routing {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Routing::First => write!(f, "first"),
+			Routing::RoundRobin => write!(f, "round-robin"),
+			Routing::LeastLoaded => write!(f, "least-loaded"),
+			Routing::Locality(weight) => write!(f, "locality:{}", weight),
+		}
+	}
+}
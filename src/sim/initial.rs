@@ -0,0 +1,87 @@
+use std::{fs::File, io::{self, BufRead, BufReader}, path::Path};
+
+use super::{Clock, job::Job};
+
+/// A job on the machine when the simulation starts, see `InitialState`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitialJob {
+	/// Whether the job is running, else it is queued
+	pub running: bool,
+	pub nodes: u32,
+	pub requested_run_time: Clock,
+	/// Whole run time of the job, including the time it already ran
+	pub run_time: Clock,
+	/// Time the job already ran if it is running, or already waited if it is queued
+	pub elapsed: Clock,
+	pub user_id: Option<u32>,
+}
+
+impl InitialJob {
+	/// The job as it is at `clock`, when the simulation starts: running jobs start then with
+	/// the time they have left, queued jobs were submitted `elapsed` seconds before (or at
+	/// the start of the trace).
+	pub fn job(&self, id: u32, clock: Clock) -> Job {
+		let mut job = match self.running {
+			true => Job::new(id, self.nodes, clock, self.run_time.saturating_sub(self.elapsed).max(1), self.requested_run_time.saturating_sub(self.elapsed).max(1)),
+			false => Job::new(id, self.nodes, clock.saturating_sub(self.elapsed), self.run_time, self.requested_run_time),
+		};
+
+		job.user_id = self.user_id;
+		job.preloaded = true;
+		job
+	}
+}
+
+/// Jobs running or waiting on the machine when the simulation starts, for studying its
+/// steady state without simulating the months of ramp-up leading to it, e.g. from the output
+/// of the batch system of the real machine.
+///
+/// Read from `state,nodes,requested_time,run_time,elapsed[,user]` lines, `state` being
+/// `running` or `queued` and lines starting with `#` being comments. The jobs take IDs
+/// counting down from `u32::MAX` so as not to clash with the jobs of the trace, and are left
+/// out of the metrics.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InitialState {
+	pub jobs: Vec<InitialJob>,
+}
+
+impl InitialState {
+	pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let mut jobs = Vec::new();
+
+		for (idx, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+			let line = line?;
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("line {} of the initial state isn't state,nodes,requested_time,run_time,elapsed[,user]: {}", idx + 1, line));
+			let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+			if fields.len() != 5 && fields.len() != 6 {
+				return Err(invalid());
+			}
+
+			let running = match fields[0] {
+				"running" => true,
+				"queued" => false,
+				_ => return Err(invalid()),
+			};
+			let number = |field: &str| field.parse::<u64>().map_err(|_| invalid());
+
+			jobs.push(InitialJob {
+				running,
+				nodes: fields[1].parse().ok().filter(|&nodes| nodes > 0).ok_or_else(invalid)?,
+				requested_run_time: number(fields[2])?,
+				run_time: number(fields[3])?,
+				elapsed: number(fields[4])?,
+				user_id: match fields.get(5) {
+					Some(user) => Some(user.parse().map_err(|_| invalid())?),
+					None => None,
+				},
+			});
+		}
+
+		Ok(Self { jobs })
+	}
+}
@@ -1,9 +1,25 @@
-use std::cmp::Ordering;
+use std::{cell::Cell, cmp::Ordering, fmt};
 
-use super::Clock;
+use serde::{Deserialize, Serialize};
 
+use super::{Clock, cluster::{ALL_CLASSES, NodeSet}, malleable::SpeedupModel, workload::SwfStatus};
 
-#[derive(Debug, Eq, PartialOrd)]
+/// A configuration a moldable job can start with, see `Moldability`.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shape {
+	pub nodes: u32,
+	/// Hidden from the schedulers like `Job::run_time`
+	#[serde(default, skip_serializing_if = "hidden")]
+	pub(crate) run_time: Clock,
+	pub requested_run_time: Clock,
+}
+
+/// A job of the workload, as seen by the schedulers. Its actual run time and what the trace
+/// tells of its future (when it ends, is cancelled or started there) are only known to the
+/// engine, schedulers planning with its requested time or a prediction, see `Job::estimate`.
+/// These fields are left out of its `Debug` output, and of its serialization outside of the
+/// snapshots of the engine, see `revealing`.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Job {
 	pub id: u32,
 	pub nodes: u32,
@@ -12,31 +28,296 @@ pub struct Job {
 	pub schedule_time: Clock,
 
 	pub requested_run_time: Clock,
+	/// Predicted run time, see `RuntimeModel`
+	#[serde(default)]
+	pub prediction: Option<Clock>,
+	/// End of the current run at the latest, when the job reaches the time it requested
 	pub expected_end: Clock,
 
 	pub scheduled: bool,
+	/// Nodes the job runs on, empty until it is scheduled
+	pub allocation: NodeSet,
+
+	/// Actual run time, only known to the engine (and to `Oracle`, which plays a best case)
+	#[serde(default, skip_serializing_if = "hidden")]
+	pub(crate) run_time: Clock,
+	/// Run time left, lower than `run_time` once the job was preempted
+	#[serde(default, skip_serializing_if = "hidden")]
+	pub(crate) remaining_time: Clock,
+	/// Time spent restoring the job before it makes progress again, when it was preempted
+	pub restart_overhead: Clock,
+	/// Number of times the job was preempted
+	pub preemptions: u32,
+	/// Number of times the job was killed by a node failure and submitted again
+	pub resubmissions: u32,
+	/// Number of times the job was suspended at the end of a time slice
+	pub suspensions: u32,
+	/// Number of times the job was killed at its walltime and submitted again, see `WalltimeLimit`
+	pub timeouts: u32,
+	/// Number of times the job was shrunk or expanded while running, see `Malleability`
+	#[serde(default)]
+	pub resizes: u32,
+	/// Number of scheduling passes in which narrower jobs queued after this one started while
+	/// it kept waiting, telling how much the policy let it starve
+	#[serde(default)]
+	pub skips: u32,
+	/// Fewest and most nodes the job runs on, if it is malleable, see `Malleability`
+	#[serde(default)]
+	pub malleable: Option<(u32, u32)>,
+	/// Configurations the job can start with if it is moldable, including its traced number
+	/// of nodes, empty once it started, see `Moldability`
+	#[serde(default)]
+	pub shapes: Vec<Shape>,
+	/// Number of nodes of the job in the trace, its average parallelism for the speedup
+	/// models, see `SpeedupModel`
+	#[serde(default)]
+	pub traced_nodes: u32,
+	/// Whether the job was on the machine when the simulation started, see `InitialState`
+	#[serde(default)]
+	pub preloaded: bool,
+	/// First time the job started
+	pub start_time: Option<Clock>,
 
-	pub run_time: Clock,
+	/// Job that has to finish before this one is submitted
+	pub preceding_job: Option<u32>,
+	/// Time between the end of the preceding job and the submission of this one
+	pub think_time: Clock,
+	/// Time the submission was delayed for the preceding job, included in `submit_time`
+	pub dependency_wait: Clock,
+
+	/// Position of the job among the jobs submitted to the engine, starting at 0
+	pub submit_index: usize,
+	/// Index of the partition the job is queued in, set when it is admitted
+	pub partition_idx: usize,
+
+	pub user_id: Option<u32>,
+	pub group_id: Option<u32>,
+	pub queue: Option<u32>,
+	pub partition: Option<u32>,
+	/// In kilobytes per processor
+	pub requested_memory: Option<f64>,
+	/// Kilobytes of memory the job uses on each of its nodes, 0 if the trace doesn't tell
+	pub memory_per_node: u64,
+	#[serde(default, skip_serializing_if = "hidden")]
+	pub(crate) status: Option<SwfStatus>,
+	/// Time the user cancelled the job in the trace, if it never ran there
+	#[serde(default, skip_serializing_if = "hidden")]
+	pub(crate) cancel_time: Option<Clock>,
+	/// Time the job started in the trace, if it records the wait, replayed in validation mode
+	#[serde(default, skip_serializing_if = "hidden")]
+	pub(crate) trace_start: Option<Clock>,
+	/// Node classes the job may run on, as a bit mask over the classes of the cluster
+	pub classes: u64,
+	/// Index of the QoS class of the job, see `QosClass`
+	pub qos: Option<u32>,
+	/// Longest time the job should wait, given by its QoS class
+	pub max_wait: Option<Clock>,
+	/// Time until which the scheduler holds the job in the queue, `Clock::MAX` until it
+	/// releases it, see `Mark::Hold`
+	#[serde(default)]
+	pub held_until: Option<Clock>,
+	/// Priority the scheduler gave the job, 0 unless it changed it, see `Mark`
+	#[serde(default)]
+	pub priority: i64,
 }
 
 impl Job {
+	/// A job that was not scheduled yet, extra trace fields being left unset.
 	pub fn new(id: u32, nodes: u32, submit_time: Clock, run_time: Clock, requested_run_time: Clock) -> Job {
 		Job {
 			id,
 			nodes,
 			requested_run_time,
+			prediction: None,
 			run_time,
+			remaining_time: run_time,
+			restart_overhead: 0,
+			preemptions: 0,
+			resubmissions: 0,
+			suspensions: 0,
+			timeouts: 0,
+			resizes: 0,
+			skips: 0,
+			malleable: None,
+			shapes: Vec::new(),
+			traced_nodes: nodes,
+			preloaded: false,
+			start_time: None,
+			preceding_job: None,
+			think_time: 0,
+			dependency_wait: 0,
 			submit_time,
 			scheduled: false,
+			allocation: NodeSet::default(),
 			schedule_time: 0,
 			expected_end: 0,
+			submit_index: 0,
+			partition_idx: 0,
+			user_id: None,
+			group_id: None,
+			queue: None,
+			partition: None,
+			requested_memory: None,
+			memory_per_node: 0,
+			status: None,
+			cancel_time: None,
+			trace_start: None,
+			classes: ALL_CLASSES,
+			qos: None,
+			max_wait: None,
+			held_until: None,
+			priority: 0,
+		}
+	}
+
+	/// Moves the submission of a job read from the trace, the times the trace records after
+	/// its submission (cancellation, start) moving along.
+	pub fn set_submit_time(&mut self, submit_time: Clock) {
+		let previous = self.submit_time;
+		let shift = |time: Clock| submit_time + (time - previous);
+		self.cancel_time = self.cancel_time.map(shift);
+		self.trace_start = self.trace_start.map(shift);
+		self.submit_time = submit_time;
+	}
+
+	/// Gives the job one of its configurations before it starts, see `Job::shapes`. The
+	/// prediction, made for its traced number of nodes, is dropped.
+	pub fn set_shape(&mut self, shape: usize) {
+		if let Some(&shape) = self.shapes.get(shape) {
+			self.prediction = None;
+			self.nodes = shape.nodes;
+			self.run_time = shape.run_time;
+			self.remaining_time = shape.run_time;
+			self.requested_run_time = shape.requested_run_time;
 		}
 	}
 
+	/// Starts the job, which keeps its number of nodes from then on if it was moldable.
 	pub fn set_scheduled(&mut self, clock: Clock) {
+		self.shapes = Vec::new();
 		self.scheduled = true;
 		self.schedule_time = clock;
-		self.expected_end = clock + self.requested_run_time;
+		self.start_time.get_or_insert(clock);
+		self.expected_end = clock + self.restart_overhead + self.requested_remaining_time();
+	}
+
+	/// Makes the job hold its nodes for `delay` seconds before it runs, the time the scheduler
+	/// took to start it, see `DecisionLatency`. Its start time is the end of the delay.
+	pub fn delay_start(&mut self, clock: Clock, delay: Clock) {
+		self.restart_overhead += delay;
+		self.start_time.get_or_insert(clock + delay);
+	}
+
+	/// Whether the scheduler holds the job in the queue, see `Mark::Hold`.
+	pub fn is_held(&self) -> bool {
+		self.held_until.is_some()
+	}
+
+	/// Requested time minus the time the job already ran.
+	pub fn requested_remaining_time(&self) -> Clock {
+		self.requested_run_time.saturating_sub(self.run_time - self.remaining_time)
+	}
+
+	/// Run time the schedulers plan with: the prediction capped to the requested time if
+	/// there is one, else the requested time.
+	pub fn estimate(&self) -> Clock {
+		self.prediction.map_or(self.requested_run_time, |prediction| prediction.min(self.requested_run_time))
+	}
+
+	/// Estimated run time minus the time the job already ran.
+	pub fn estimated_remaining_time(&self) -> Clock {
+		self.estimate().saturating_sub(self.run_time - self.remaining_time)
+	}
+
+	/// Time at which the current run of the job is expected to end from its estimate, or at
+	/// its `expected_end` once it ran past its prediction.
+	pub fn estimated_end(&self, clock: Clock) -> Clock {
+		match self.prediction {
+			Some(_) => {
+				let end = self.schedule_time + self.restart_overhead + self.estimated_remaining_time();
+				if end > clock { end } else { self.expected_end }
+			}
+			None => self.expected_end,
+		}
+	}
+
+	/// Time at which the current run of the job ends, if it isn't preempted.
+	pub(crate) fn end_time(&self) -> Clock {
+		assert!(self.scheduled);
+		self.schedule_time + self.restart_overhead + self.remaining_time
+	}
+
+	/// Stops the job, which will have to spend `restart_overhead` restoring itself when
+	/// it starts again. Time spent restoring the job doesn't count as progress.
+	pub fn set_preempted(&mut self, clock: Clock, restart_overhead: Clock) {
+		self.stop(clock, restart_overhead);
+		self.preemptions += 1;
+	}
+
+	/// Stops the job at the end of its time slice, like `set_preempted`.
+	pub fn set_suspended(&mut self, clock: Clock, restart_overhead: Clock) {
+		self.stop(clock, restart_overhead);
+		self.suspensions += 1;
+	}
+
+	fn stop(&mut self, clock: Clock, restart_overhead: Clock) {
+		assert!(self.scheduled);
+		let progress = (clock - self.schedule_time).saturating_sub(self.restart_overhead);
+
+		self.remaining_time -= progress.min(self.remaining_time);
+		self.restart_overhead = restart_overhead;
+		self.scheduled = false;
+	}
+
+	/// Moves a running job to `nodes` nodes, the run time it has left (and the time it
+	/// requested) changing with its speed on them. The job goes on from where it was.
+	pub fn set_resized(&mut self, clock: Clock, nodes: u32, speedup: SpeedupModel) {
+		assert!(self.scheduled);
+		let elapsed = clock - self.schedule_time;
+		let progress = elapsed.saturating_sub(self.restart_overhead).min(self.remaining_time);
+		let ran = self.run_time - self.remaining_time + progress;
+		let requested_left = self.requested_run_time.saturating_sub(ran);
+
+		self.remaining_time = speedup.scale(self.remaining_time - progress, self.nodes, nodes, self.traced_nodes);
+		self.run_time = ran + self.remaining_time;
+		self.requested_run_time = ran + speedup.scale(requested_left, self.nodes, nodes, self.traced_nodes);
+		self.restart_overhead = self.restart_overhead.saturating_sub(elapsed);
+		self.prediction = None;
+		self.nodes = nodes;
+		self.resizes += 1;
+		self.set_scheduled(clock);
+	}
+
+	/// Stops the job because one of its nodes failed, the job starting over when it runs again.
+	pub fn set_resubmitted(&mut self) {
+		assert!(self.scheduled);
+		self.remaining_time = self.run_time;
+		self.restart_overhead = 0;
+		self.resubmissions += 1;
+		self.scheduled = false;
+	}
+
+	/// Stops the job at its walltime, the job starting over with `factor` times more time
+	/// requested when it runs again.
+	pub fn set_timed_out(&mut self, factor: f64) {
+		assert!(self.scheduled);
+		self.requested_run_time = (self.requested_run_time as f64 * factor).ceil() as Clock;
+		self.remaining_time = self.run_time;
+		self.restart_overhead = 0;
+		self.timeouts += 1;
+		self.scheduled = false;
+	}
+
+	/// Ends the job at its walltime, the job failing after running for the time it requested.
+	pub fn set_killed(&mut self) {
+		self.run_time = self.requested_run_time;
+		self.remaining_time = 0;
+		self.status = Some(SwfStatus::Failed);
+	}
+
+	/// Number of times the job was stopped or resized, which identifies its current run.
+	pub fn runs(&self) -> u32 {
+		self.preemptions + self.resubmissions + self.suspensions + self.timeouts + self.resizes
 	}
 
 	pub fn wait_time(&self) -> Clock {
@@ -44,8 +325,23 @@ impl Job {
 		self.schedule_time - self.submit_time
 	}
 
-	pub fn wait_time_from(&self, clock: Clock) -> Clock {
-		clock - self.submit_time
+	/// Time between the submission and the end of a job that finished at `end` without
+	/// running, which includes the time it spent preempted and restoring itself.
+	pub fn wait_time_until(&self, end: Clock) -> Clock {
+		(end - self.submit_time).saturating_sub(self.run_time)
+	}
+
+	/// Time by which the job should start to meet the target wait of its QoS class, if it has one.
+	pub fn deadline(&self) -> Option<Clock> {
+		self.max_wait.map(|max_wait| self.submit_time + max_wait)
+	}
+
+	/// Response time over run time, run times shorter than `tau` counting as `tau`
+	/// and the result being at least 1 (the bounded slowdown).
+	/// With a `tau` of 1 this is the plain slowdown.
+	pub fn slowdown(&self, wait_time: Clock, tau: Clock) -> f64 {
+		let response = (wait_time + self.run_time) as f64;
+		(response / self.run_time.max(tau).max(1) as f64).max(1.0)
 	}
 }
 
@@ -55,8 +351,90 @@ impl PartialEq for Job {
     }
 }
 
+impl Eq for Job {}
+
 impl Ord for Job {
     fn cmp(&self, other: &Self) -> Ordering {
         self.id.cmp(&other.id)
     }
 }
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+thread_local! {
+	/// Whether the fields of the jobs hidden from the schedulers are serialized, see `revealing`
+	static REVEALED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Serializes the jobs with the fields hidden from the schedulers (actual run time, trace
+/// status...) while running `f`, which only the snapshots of the engine need to resume a
+/// simulation, see `EngineSnapshot::save`.
+pub(crate) fn revealing<T>(f: impl FnOnce() -> T) -> T {
+	REVEALED.with(|revealed| revealed.set(true));
+	let result = f();
+	REVEALED.with(|revealed| revealed.set(false));
+	result
+}
+
+fn hidden<T>(_: &T) -> bool {
+	!REVEALED.with(Cell::get)
+}
+
+/// Without the run time, hidden from the schedulers.
+impl fmt::Debug for Shape {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Shape")
+			.field("nodes", &self.nodes)
+			.field("requested_run_time", &self.requested_run_time)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Without the fields hidden from the schedulers.
+impl fmt::Debug for Job {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Job")
+			.field("id", &self.id)
+			.field("nodes", &self.nodes)
+			.field("submit_time", &self.submit_time)
+			.field("schedule_time", &self.schedule_time)
+			.field("requested_run_time", &self.requested_run_time)
+			.field("prediction", &self.prediction)
+			.field("expected_end", &self.expected_end)
+			.field("scheduled", &self.scheduled)
+			.field("allocation", &self.allocation)
+			.field("restart_overhead", &self.restart_overhead)
+			.field("preemptions", &self.preemptions)
+			.field("resubmissions", &self.resubmissions)
+			.field("suspensions", &self.suspensions)
+			.field("timeouts", &self.timeouts)
+			.field("resizes", &self.resizes)
+			.field("skips", &self.skips)
+			.field("malleable", &self.malleable)
+			.field("shapes", &self.shapes)
+			.field("traced_nodes", &self.traced_nodes)
+			.field("preloaded", &self.preloaded)
+			.field("start_time", &self.start_time)
+			.field("preceding_job", &self.preceding_job)
+			.field("think_time", &self.think_time)
+			.field("dependency_wait", &self.dependency_wait)
+			.field("submit_index", &self.submit_index)
+			.field("partition_idx", &self.partition_idx)
+			.field("user_id", &self.user_id)
+			.field("group_id", &self.group_id)
+			.field("queue", &self.queue)
+			.field("partition", &self.partition)
+			.field("requested_memory", &self.requested_memory)
+			.field("memory_per_node", &self.memory_per_node)
+			.field("classes", &self.classes)
+			.field("qos", &self.qos)
+			.field("max_wait", &self.max_wait)
+			.field("held_until", &self.held_until)
+			.field("priority", &self.priority)
+			.finish_non_exhaustive()
+	}
+}
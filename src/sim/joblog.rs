@@ -0,0 +1,231 @@
+use std::{fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, cluster::NodeSet, job::Job, report::csv_field};
+#[cfg(feature = "parquet")]
+use super::parquet::{ParquetFile, Values};
+
+/// What happened to a single job during a simulation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRecord {
+	/// Left empty when read back, see `Engine::restore`
+	#[serde(skip_deserializing)]
+	pub scheduler: String,
+	/// Size of the simulated cluster
+	pub cluster_nodes: u32,
+	/// Seed of the run, telling apart the repetitions of a run, see `ExperimentRunner::repetitions`
+	#[serde(default)]
+	pub seed: u64,
+	/// Load factor of the run, telling apart the points of a load sweep
+	#[serde(default = "default_load_factor")]
+	pub load_factor: f64,
+
+	pub id: u32,
+	pub user_id: Option<u32>,
+	pub group_id: Option<u32>,
+	pub queue: Option<u32>,
+	pub partition: Option<u32>,
+	pub nodes: u32,
+
+	pub submit: Clock,
+	pub start: Clock,
+	pub end: Clock,
+	pub wait: Clock,
+	pub run: Clock,
+	pub requested_run: Clock,
+	pub slowdown: f64,
+	pub bounded_slowdown: f64,
+	pub preemptions: u32,
+	/// Scheduling passes in which narrower jobs queued later started first, see `Job::skips`
+	#[serde(default)]
+	pub skips: u32,
+
+	/// Nodes of the last run of the job
+	pub allocation: NodeSet,
+}
+
+impl JobRecord {
+	/// Records a job that finished at `end`.
+	pub fn new(scheduler: &str, cluster_nodes: u32, seed: u64, load_factor: f64, job: &Job, end: Clock, slowdown_tau: Clock) -> Self {
+		let start = job.start_time.unwrap_or(job.schedule_time);
+		let wait = job.wait_time_until(end);
+
+		Self {
+			scheduler: scheduler.to_string(),
+			cluster_nodes,
+			seed,
+			load_factor,
+			id: job.id,
+			user_id: job.user_id,
+			group_id: job.group_id,
+			queue: job.queue,
+			partition: job.partition,
+			nodes: job.nodes,
+			submit: job.submit_time,
+			start,
+			end,
+			wait,
+			run: job.run_time,
+			requested_run: job.requested_run_time,
+			slowdown: job.slowdown(wait, 1),
+			bounded_slowdown: job.slowdown(wait, slowdown_tau),
+			preemptions: job.preemptions,
+			skips: job.skips,
+			allocation: job.allocation.clone(),
+		}
+	}
+}
+
+fn default_load_factor() -> f64 {
+	1.0
+}
+
+const CSV_HEADER: &str = "scheduler,cluster_nodes,seed,load_factor,id,user_id,group_id,queue,partition,nodes,submit,start,end,wait,run,requested_run,slowdown,bounded_slowdown,preemptions,skips,allocation";
+
+fn csv_option(value: Option<u32>) -> String {
+	value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn swf_option(value: Option<u32>) -> String {
+	value.map(|value| value.to_string()).unwrap_or_else(|| "-1".to_string())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JobLogFormat {
+	Csv,
+	/// Standard Workload Format, processors standing for nodes
+	Swf,
+	/// Columns of the CSV format, in a Parquet file with a row group per run
+	#[cfg(feature = "parquet")]
+	Parquet,
+}
+
+impl JobLogFormat {
+	/// Guesses the format from the file extension, defaulting to CSV.
+	pub fn from_path(path: &Path) -> Self {
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some("swf") => JobLogFormat::Swf,
+			#[cfg(feature = "parquet")]
+			Some("parquet") => JobLogFormat::Parquet,
+			_ => JobLogFormat::Csv,
+		}
+	}
+}
+
+/// Writes the job records of one or more runs to a single output.
+pub struct JobLogWriter<W: Write> {
+	out: W,
+	format: JobLogFormat,
+	header_written: bool,
+	#[cfg(feature = "parquet")]
+	parquet: ParquetFile,
+}
+
+impl JobLogWriter<BufWriter<File>> {
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let path = path.as_ref();
+		let file = File::create(path)?;
+		Ok(Self::new(BufWriter::new(file), JobLogFormat::from_path(path)))
+	}
+}
+
+impl<W: Write> JobLogWriter<W> {
+	pub fn new(out: W, format: JobLogFormat) -> Self {
+		Self {
+			out,
+			format,
+			header_written: false,
+			#[cfg(feature = "parquet")]
+			parquet: ParquetFile::default(),
+		}
+	}
+
+	/// Writes the records of a single run. SWF records are sorted by submission time,
+	/// and each run gets its own header comments.
+	pub fn write_run(&mut self, records: &[JobRecord]) -> io::Result<()> {
+		match self.format {
+			JobLogFormat::Csv => {
+				if !self.header_written {
+					writeln!(self.out, "{}", CSV_HEADER)?;
+					self.header_written = true;
+				}
+
+				for record in records {
+					writeln!(
+						self.out, "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+						csv_field(&record.scheduler), record.cluster_nodes, record.seed, record.load_factor, record.id,
+						csv_option(record.user_id), csv_option(record.group_id), csv_option(record.queue), csv_option(record.partition),
+						record.nodes, record.submit, record.start, record.end, record.wait, record.run, record.requested_run,
+						record.slowdown, record.bounded_slowdown, record.preemptions, record.skips, record.allocation,
+					)?;
+				}
+			}
+			JobLogFormat::Swf => {
+				let mut sorted = records.iter().collect::<Vec<_>>();
+				sorted.sort_by_key(|record| (record.submit, record.id));
+
+				if let Some(first) = sorted.first() {
+					writeln!(self.out, "; Scheduler: {}", first.scheduler)?;
+					writeln!(self.out, "; MaxNodes: {}", first.cluster_nodes)?;
+					writeln!(self.out, "; MaxProcs: {}", first.cluster_nodes)?;
+				}
+
+				for record in sorted {
+					writeln!(
+						self.out, "{} {} {} {} {} -1 -1 {} {} -1 1 {} {} -1 {} {} -1 -1",
+						record.id, record.submit, record.wait, record.run, record.nodes,
+						record.nodes, record.requested_run,
+						swf_option(record.user_id), swf_option(record.group_id), swf_option(record.queue), swf_option(record.partition),
+					)?;
+				}
+			}
+			#[cfg(feature = "parquet")]
+			JobLogFormat::Parquet => {
+				let int = |field: fn(&JobRecord) -> u64| Values::Int(records.iter().map(|record| field(record) as i64).collect());
+				let optional = |field: fn(&JobRecord) -> Option<u32>| Values::OptionalInt(records.iter().map(|record| field(record).map(i64::from)).collect());
+				let float = |field: fn(&JobRecord) -> f64| Values::Float(records.iter().map(field).collect());
+
+				self.parquet.write_row_group(&mut self.out, &[
+					("scheduler", Values::Text(records.iter().map(|record| record.scheduler.clone()).collect())),
+					("cluster_nodes", int(|record| record.cluster_nodes as u64)),
+					("seed", int(|record| record.seed)),
+					("load_factor", float(|record| record.load_factor)),
+					("id", int(|record| record.id as u64)),
+					("user_id", optional(|record| record.user_id)),
+					("group_id", optional(|record| record.group_id)),
+					("queue", optional(|record| record.queue)),
+					("partition", optional(|record| record.partition)),
+					("nodes", int(|record| record.nodes as u64)),
+					("submit", int(|record| record.submit)),
+					("start", int(|record| record.start)),
+					("end", int(|record| record.end)),
+					("wait", int(|record| record.wait)),
+					("run", int(|record| record.run)),
+					("requested_run", int(|record| record.requested_run)),
+					("slowdown", float(|record| record.slowdown)),
+					("bounded_slowdown", float(|record| record.bounded_slowdown)),
+					("preemptions", int(|record| record.preemptions as u64)),
+					("skips", int(|record| record.skips as u64)),
+					("allocation", Values::Text(records.iter().map(|record| record.allocation.to_string()).collect())),
+				])?;
+			}
+		}
+
+		Ok(())
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.out.flush()
+	}
+
+	/// Terminates the output, Parquet files being incomplete until then.
+	pub fn finish(mut self) -> io::Result<()> {
+		#[cfg(feature = "parquet")]
+		if self.format == JobLogFormat::Parquet {
+			self.parquet.finish(&mut self.out)?;
+		}
+
+		self.out.flush()
+	}
+}
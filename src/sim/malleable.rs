@@ -0,0 +1,260 @@
+use std::{convert::TryFrom, fmt::Display, str::FromStr};
+
+use rand::Rng;
+use serde::Deserialize;
+
+use super::{Clock, job::{Job, Shape}, rng::{SimRng, Stream}};
+
+/// How much faster a malleable or moldable job runs on more nodes.
+///
+/// Written `linear`, `amdahl:SERIAL`, `power:ALPHA` or `downey:SIGMA`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SpeedupModel {
+	/// Twice the nodes, twice as fast: resizing keeps the node-seconds of the job
+	#[default]
+	Linear,
+	/// Amdahl's law, this fraction of the job not running any faster on more nodes
+	Amdahl(f64),
+	/// The speed grows as the number of nodes to this power, between 0 and 1
+	Power(f64),
+	/// Downey's model, the average parallelism of the jobs being their traced number of nodes
+	/// and this the variance of their parallelism, 0 for jobs with a constant parallelism
+	Downey(f64),
+}
+
+impl SpeedupModel {
+	/// Speed of a job on `nodes` nodes, relative to a single node, for a job of average
+	/// parallelism `parallelism`, see `Job::traced_nodes`.
+	pub fn speedup(&self, nodes: u32, parallelism: u32) -> f64 {
+		let nodes = nodes.max(1) as f64;
+
+		match *self {
+			SpeedupModel::Linear => nodes,
+			SpeedupModel::Amdahl(serial) => 1.0 / (serial + (1.0 - serial) / nodes),
+			SpeedupModel::Power(alpha) => nodes.powf(alpha),
+			SpeedupModel::Downey(sigma) => {
+				let average = parallelism.max(1) as f64;
+
+				if sigma <= 1.0 {
+					if nodes <= average {
+						average * nodes / (average + sigma * (nodes - 1.0) / 2.0)
+					} else if nodes <= 2.0 * average - 1.0 {
+						average * nodes / (sigma * (average - 0.5) + nodes * (1.0 - sigma / 2.0))
+					} else {
+						average
+					}
+				} else if nodes <= average + average * sigma - sigma {
+					nodes * average * (sigma + 1.0) / (sigma * (nodes + average - 1.0) + average)
+				} else {
+					average
+				}
+			}
+		}
+	}
+
+	/// Time a job taking `time` seconds on `from` nodes takes on `to` nodes.
+	pub fn scale(&self, time: Clock, from: u32, to: u32, parallelism: u32) -> Clock {
+		(time as f64 * self.speedup(from, parallelism) / self.speedup(to, parallelism)).ceil() as Clock
+	}
+}
+
+impl FromStr for SpeedupModel {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid speedup model '{}' (expected linear, amdahl:SERIAL, power:ALPHA or downey:SIGMA)", s);
+		let fraction = |value: &str| match value.trim().parse::<f64>() {
+			Ok(value) if (0.0..=1.0).contains(&value) => Ok(value),
+			_ => Err(invalid()),
+		};
+
+		match s.split_once(':') {
+			None if s.trim().eq_ignore_ascii_case("linear") => Ok(SpeedupModel::Linear),
+			Some((model, value)) if model.trim().eq_ignore_ascii_case("amdahl") => Ok(SpeedupModel::Amdahl(fraction(value)?)),
+			Some((model, value)) if model.trim().eq_ignore_ascii_case("power") => Ok(SpeedupModel::Power(fraction(value)?)),
+			Some((model, value)) if model.trim().eq_ignore_ascii_case("downey") => match value.trim().parse::<f64>() {
+				Ok(sigma) if sigma >= 0.0 && sigma.is_finite() => Ok(SpeedupModel::Downey(sigma)),
+				_ => Err(invalid()),
+			},
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl Display for SpeedupModel {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SpeedupModel::Linear => write!(f, "linear"),
+			SpeedupModel::Amdahl(serial) => write!(f, "amdahl:{}", serial),
+			SpeedupModel::Power(alpha) => write!(f, "power:{}", alpha),
+			SpeedupModel::Downey(sigma) => write!(f, "downey:{}", sigma),
+		}
+	}
+}
+
+/// Which jobs are malleable, the scheduler being able to shrink or expand them while they
+/// run (see `Scheduler::resize`), and how their run time follows their number of nodes.
+///
+/// Written `none` or `FRACTION:FACTOR[:SPEEDUP]`: each job is malleable with probability
+/// FRACTION, running on its traced number of nodes divided or multiplied by up to FACTOR,
+/// e.g. `0.5:2:amdahl:0.05`. The speedup model defaults to linear, see `SpeedupModel`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Malleability {
+	/// Jobs keep the nodes they started on
+	#[default]
+	None,
+	Jobs { fraction: f64, factor: f64, speedup: SpeedupModel },
+}
+
+impl Malleability {
+	/// Makes the job malleable or not, the same seed picking the same jobs.
+	pub fn apply(&self, job: &mut Job, rng: &SimRng) {
+		if let Malleability::Jobs { fraction, factor, .. } = *self {
+			if rng.keyed(Stream::Malleability, job.id as u64).random::<f64>() < fraction {
+				let min = ((job.nodes as f64 / factor).ceil() as u32).max(1);
+				job.malleable = Some((min, (job.nodes as f64 * factor).floor() as u32));
+				job.traced_nodes = job.nodes;
+			}
+		}
+	}
+
+	pub fn speedup(&self) -> SpeedupModel {
+		match self {
+			Malleability::None => SpeedupModel::Linear,
+			Malleability::Jobs { speedup, .. } => *speedup,
+		}
+	}
+}
+
+impl FromStr for Malleability {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(match parse_jobs(s, "malleability")? {
+			Some((fraction, factor, speedup)) => Malleability::Jobs { fraction, factor, speedup },
+			None => Malleability::None,
+		})
+	}
+}
+
+impl TryFrom<String> for Malleability {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl Display for Malleability {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Malleability::None => write!(f, "none"),
+			Malleability::Jobs { fraction, factor, speedup } => write!(f, "{}:{}:{}", fraction, factor, speedup),
+		}
+	}
+}
+
+/// Which jobs are moldable, the scheduler picking the number of nodes they start on among
+/// several configurations (see `Scheduler::schedule_shaped`), and how their run time follows it.
+///
+/// Written `none` or `FRACTION:FACTOR[:SPEEDUP]` like `Malleability`: each job is moldable
+/// with probability FRACTION, starting on its traced number of nodes or that number divided
+/// or multiplied by powers of two up to FACTOR, e.g. `1:4:downey:1` for 4 times fewer to 4
+/// times more nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Moldability {
+	/// Jobs start on their traced number of nodes
+	#[default]
+	None,
+	Jobs { fraction: f64, factor: f64, speedup: SpeedupModel },
+}
+
+impl Moldability {
+	/// Gives the job its configurations if it is moldable, the same seed picking the same jobs.
+	pub fn apply(&self, job: &mut Job, rng: &SimRng) {
+		let (fraction, factor, speedup) = match *self {
+			Moldability::Jobs { fraction, factor, speedup } => (fraction, factor, speedup),
+			Moldability::None => return,
+		};
+
+		if rng.keyed(Stream::Moldability, job.id as u64).random::<f64>() >= fraction {
+			return;
+		}
+
+		let traced = job.nodes;
+		let mut widths = vec![traced];
+		let mut scale = 2.0;
+		while scale <= factor {
+			widths.push(((traced as f64 / scale).round() as u32).max(1));
+			widths.push((traced as f64 * scale) as u32);
+			scale *= 2.0;
+		}
+
+		widths.sort_unstable();
+		widths.dedup();
+
+		job.traced_nodes = traced;
+		job.shapes = widths.into_iter()
+			.map(|nodes| Shape {
+				nodes,
+				run_time: speedup.scale(job.run_time, traced, nodes, traced),
+				requested_run_time: speedup.scale(job.requested_run_time, traced, nodes, traced),
+			})
+			.collect();
+	}
+}
+
+impl FromStr for Moldability {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(match parse_jobs(s, "moldability")? {
+			Some((fraction, factor, speedup)) => Moldability::Jobs { fraction, factor, speedup },
+			None => Moldability::None,
+		})
+	}
+}
+
+impl TryFrom<String> for Moldability {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl Display for Moldability {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Moldability::None => write!(f, "none"),
+			Moldability::Jobs { fraction, factor, speedup } => write!(f, "{}:{}:{}", fraction, factor, speedup),
+		}
+	}
+}
+
+/// Parses `none` or `FRACTION:FACTOR[:SPEEDUP]`, None standing for `none`.
+fn parse_jobs(s: &str, what: &str) -> Result<Option<(f64, f64, SpeedupModel)>, String> {
+	let invalid = || format!("invalid {} '{}' (expected none or FRACTION:FACTOR[:SPEEDUP])", what, s);
+
+	if s.trim().eq_ignore_ascii_case("none") {
+		return Ok(None);
+	}
+
+	let mut parts = s.splitn(3, ':');
+	let fraction = match parts.next().map(|value| value.trim().parse::<f64>()) {
+		Some(Ok(fraction)) if (0.0..=1.0).contains(&fraction) => fraction,
+		_ => return Err(invalid()),
+	};
+	let factor = match parts.next().map(|value| value.trim().parse::<f64>()) {
+		Some(Ok(factor)) if factor >= 1.0 && factor.is_finite() => factor,
+		_ => return Err(invalid()),
+	};
+	let speedup = match parts.next() {
+		Some(speedup) => speedup.parse()?,
+		None => SpeedupModel::Linear,
+	};
+
+	Ok(Some((fraction, factor, speedup)))
+}
@@ -0,0 +1,78 @@
+use std::{convert::TryFrom, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, job::Job};
+
+/// Change a scheduler makes to a queued job through `Scheduler::mark`. The engine keeps it
+/// on the job across the scheduling passes, and preemptions, so that policies can delay jobs
+/// on purpose or age them without bookkeeping of their own.
+///
+/// Written `hold[:UNTIL]`, `release`, `boost:N` or `priority:N`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Mark {
+	/// Keeps the job from starting until the given time, or until the scheduler releases it
+	Hold(Option<Clock>),
+	/// Lets a held job start again
+	Release,
+	/// Adds to the priority of the job, see `Job::priority`
+	Boost(i64),
+	/// Sets the priority of the job
+	Priority(i64),
+}
+
+impl Mark {
+	/// Applies the mark to a job at `clock`, holds ending before then releasing it.
+	pub fn apply(&self, job: &mut Job, clock: Clock) {
+		match *self {
+			Mark::Hold(until) => job.held_until = until.map_or(Some(Clock::MAX), |until| (until > clock).then_some(until)),
+			Mark::Release => job.held_until = None,
+			Mark::Boost(boost) => job.priority = job.priority.saturating_add(boost),
+			Mark::Priority(priority) => job.priority = priority,
+		}
+	}
+}
+
+impl FromStr for Mark {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid mark '{}' (expected hold[:UNTIL], release, boost:N or priority:N)", s);
+
+		match s.trim().split_once(':') {
+			None if s.trim() == "hold" => Ok(Mark::Hold(None)),
+			None if s.trim() == "release" => Ok(Mark::Release),
+			Some(("hold", until)) => until.trim().parse().map(|until| Mark::Hold(Some(until))).map_err(|_| invalid()),
+			Some(("boost", boost)) => boost.trim().parse().map(Mark::Boost).map_err(|_| invalid()),
+			Some(("priority", priority)) => priority.trim().parse().map(Mark::Priority).map_err(|_| invalid()),
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for Mark {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<Mark> for String {
+	fn from(mark: Mark) -> Self {
+		mark.to_string()
+	}
+}
+
+impl Display for Mark {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Mark::Hold(None) => write!(f, "hold"),
+			Mark::Hold(Some(until)) => write!(f, "hold:{}", until),
+			Mark::Release => write!(f, "release"),
+			Mark::Boost(boost) => write!(f, "boost:{}", boost),
+			Mark::Priority(priority) => write!(f, "priority:{}", priority),
+		}
+	}
+}
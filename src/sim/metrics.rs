@@ -0,0 +1,169 @@
+use std::{fmt::Write as _, io::{self, BufRead, BufReader, Write}, net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs}, sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}}, thread, time::Duration};
+
+use super::Clock;
+
+/// Time a client has to send its request before its connection is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Name, type, help and value of the metrics published for every run.
+type Metric = (&'static str, &'static str, &'static str, fn(&RunMetrics) -> Option<f64>);
+
+const PER_RUN: &[Metric] = &[
+	("chamallow_simulated_seconds", "gauge", "Simulated time reached by the run", |run| Some(run.clock.load(Ordering::Relaxed) as f64)),
+	("chamallow_steps_total", "counter", "Instants having events simulated by the run", |run| Some(run.steps.load(Ordering::Relaxed) as f64)),
+	("chamallow_jobs", "gauge", "Jobs the run simulates, if known", |run| run.total_jobs.map(|jobs| jobs as f64)),
+	("chamallow_finished_jobs", "gauge", "Jobs finished so far", |run| Some(run.finished_jobs.load(Ordering::Relaxed) as f64)),
+	("chamallow_running_jobs", "gauge", "Jobs running at the simulated time", |run| Some(run.running_jobs.load(Ordering::Relaxed) as f64)),
+	("chamallow_queued_jobs", "gauge", "Jobs waiting in the queues at the simulated time", |run| Some(run.queued_jobs.load(Ordering::Relaxed) as f64)),
+	("chamallow_busy_nodes", "gauge", "Nodes running jobs at the simulated time", |run| Some(run.busy_nodes.load(Ordering::Relaxed) as f64)),
+	("chamallow_utilization", "gauge", "Share of the nodes running jobs at the simulated time", |run| Some(run.busy_nodes.load(Ordering::Relaxed) as f64 / run.nodes.max(1) as f64)),
+	("chamallow_run_done", "gauge", "Whether the simulation of the run ended", |run| Some(run.done.load(Ordering::Relaxed) as u8 as f64)),
+];
+
+/// Gauges of a single run, updated by its engine after every scheduling pass.
+#[derive(Debug)]
+pub struct RunMetrics {
+	workload: String,
+	scheduler: String,
+	nodes: u32,
+	load_factor: f64,
+	seed: u64,
+	/// Jobs to simulate, if known from the limit or the header of the trace
+	total_jobs: Option<usize>,
+	clock: AtomicU64,
+	/// Instants having events simulated so far
+	steps: AtomicU64,
+	finished_jobs: AtomicU64,
+	running_jobs: AtomicU64,
+	queued_jobs: AtomicU64,
+	busy_nodes: AtomicU64,
+	done: AtomicBool,
+}
+
+impl RunMetrics {
+	/// Publishes the state of the run after the events of an instant.
+	pub fn update(&self, clock: Clock, finished_jobs: usize, running_jobs: usize, queued_jobs: usize, busy_nodes: u32) {
+		self.clock.store(clock, Ordering::Relaxed);
+		self.steps.fetch_add(1, Ordering::Relaxed);
+		self.finished_jobs.store(finished_jobs as u64, Ordering::Relaxed);
+		self.running_jobs.store(running_jobs as u64, Ordering::Relaxed);
+		self.queued_jobs.store(queued_jobs as u64, Ordering::Relaxed);
+		self.busy_nodes.store(busy_nodes as u64, Ordering::Relaxed);
+	}
+
+	pub fn finish(&self) {
+		self.done.store(true, Ordering::Relaxed);
+	}
+}
+
+/// Progress and intermediate metrics of the runs of a sweep, published in the Prometheus text
+/// format by `serve` so that sweeps running on a server can be monitored while they run.
+#[derive(Debug, Default)]
+pub struct LiveMetrics {
+	runs: Mutex<Vec<Arc<RunMetrics>>>,
+}
+
+impl LiveMetrics {
+	/// Adds a run, returning the gauges its engine updates.
+	pub fn register(&self, workload: &str, scheduler: &str, nodes: u32, load_factor: f64, seed: u64, total_jobs: Option<usize>) -> Arc<RunMetrics> {
+		let run = Arc::new(RunMetrics {
+			workload: workload.to_string(),
+			scheduler: scheduler.to_string(),
+			nodes,
+			load_factor,
+			seed,
+			total_jobs,
+			clock: AtomicU64::new(0),
+			steps: AtomicU64::new(0),
+			finished_jobs: AtomicU64::new(0),
+			running_jobs: AtomicU64::new(0),
+			queued_jobs: AtomicU64::new(0),
+			busy_nodes: AtomicU64::new(0),
+			done: AtomicBool::new(false),
+		});
+
+		self.runs.lock().expect("the metrics are never poisoned").push(run.clone());
+		run
+	}
+
+	/// Answers `GET /metrics` on the address from a background thread, for as long as the
+	/// process runs, each connection on a thread of its own so that a slow or silent client
+	/// doesn't hold up the others. Returns the address actually bound, e.g. when given port 0.
+	pub fn serve<A: ToSocketAddrs>(self: &Arc<Self>, addr: A) -> io::Result<SocketAddr> {
+		let listener = TcpListener::bind(addr)?;
+		let local = listener.local_addr()?;
+		let metrics = self.clone();
+
+		thread::spawn(move || {
+			for stream in listener.incoming().flatten() {
+				let metrics = metrics.clone();
+				// a client going away is no reason to stop serving the others
+				thread::spawn(move || metrics.answer(stream));
+			}
+		});
+
+		Ok(local)
+	}
+
+	fn answer(&self, mut stream: TcpStream) -> io::Result<()> {
+		stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+		let mut reader = BufReader::new(stream.try_clone()?);
+		let mut request = String::new();
+		reader.read_line(&mut request)?;
+
+		// skip the headers
+		let mut header = String::new();
+		while reader.read_line(&mut header)? > 2 {
+			header.clear();
+		}
+
+		let (status, body) = match request.split_whitespace().collect::<Vec<_>>().as_slice() {
+			["GET", "/metrics", ..] => ("200 OK", self.render()),
+			_ => ("404 Not Found", "try /metrics\n".to_string()),
+		};
+
+		write!(
+			stream,
+			"HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			status, body.len(), body,
+		)?;
+		stream.flush()
+	}
+
+	/// Every metric in the Prometheus text exposition format, runs being told apart by
+	/// their workload, scheduler, node count, load factor and seed.
+	pub fn render(&self) -> String {
+		let runs = self.runs.lock().expect("the metrics are never poisoned").clone();
+		let mut out = String::new();
+
+		let done = runs.iter().filter(|run| run.done.load(Ordering::Relaxed)).count();
+		gauge(&mut out, "chamallow_runs", "Runs started so far");
+		let _ = writeln!(out, "chamallow_runs {}", runs.len());
+		gauge(&mut out, "chamallow_runs_done", "Runs whose simulation ended");
+		let _ = writeln!(out, "chamallow_runs_done {}", done);
+
+		for &(name, kind, help, value) in PER_RUN {
+			let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+
+			for run in &runs {
+				if let Some(value) = value(run) {
+					let _ = writeln!(
+						out, "{}{{workload=\"{}\",scheduler=\"{}\",nodes=\"{}\",load_factor=\"{}\",seed=\"{}\"}} {}",
+						name, escape(&run.workload), escape(&run.scheduler), run.nodes, run.load_factor, run.seed, value,
+					);
+				}
+			}
+		}
+
+		out
+	}
+}
+
+fn gauge(out: &mut String, name: &str, help: &str) {
+	let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", name, help, name);
+}
+
+/// Escapes a label value of the text format.
+fn escape(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -1,269 +1,2238 @@
-use std::{cmp::Ordering, fmt::Display, fs::File, io::{self, BufRead, BufReader}, num::ParseIntError, time::SystemTime};
+use std::{cmp::Ordering, collections::BTreeMap, convert::TryFrom, fmt::Display, io, num::{ParseFloatError, ParseIntError}, str::FromStr, sync::Arc, time::Instant};
 
-use algos::Scheduler;
+use algos::{Replay, Scheduler};
+use hashbrown::{HashMap, HashSet};
 use min_max_heap::MinMaxHeap;
+use serde::{Deserialize, Serialize};
+use tracing::{Level, Span, debug, info, level_filters::LevelFilter, trace, warn};
 
-use self::{cluster::Cluster, job::Job};
+use self::{admission::{AdmissionPolicy, AdmissionRule, PartitionState, Submission, Verdict}, allocation::AllocationPolicy, backlog::Backlog, budget::Budget, cluster::{Cluster, ClusterSpec, DEFAULT_CORES_PER_NODE}, decision::{DecisionRecord, Reason}, dependency::{Admission, Dependencies}, drain::Drain, failure::{FailureModel, Outage}, fragmentation::Fragmentation, grid::{Routing, Site}, initial::InitialState, gang::{GangMatrix, TimeSlicing}, job::Job, joblog::JobRecord, mark::Mark, malleable::{Malleability, Moldability}, observer::{KillReason, Observer}, occupancy::{OccupancySnapshot, PartitionOccupancy, RunningJob}, power::PowerModel, progress::Progress, qos::QosClass, rng::SimRng, runtime::{JobView, PredictionErrors, Predictions, RuntimeModel, RuntimePredictor}, queue::ReadyQueue, rejects::RejectedJob, reservation::{Reservation, ReservedWindow}, report::{BucketReport, EngineReport, JobSums, OwnerReport, PartitionReport, QosReport, RUN_TIME_BUCKETS, SKIP_BUCKETS, WIDTH_BUCKETS}, stats::{Stat, StatsMode}, user::{UserModel, Users}, utilization::{UtilizationSampling, UtilizationTimeline}, walltime::{WalltimeLimit, WalltimeModel, WalltimeNoise}, workload::{JobStream, SwfJob, Workload, WorkloadFilter, WorkloadMeta, open_workload, read_workload_bytes}};
+#[cfg(feature = "metrics")]
+use self::metrics::{LiveMetrics, RunMetrics};
 
 pub mod job;
+pub mod joblog;
+pub mod accounting;
+pub mod admission;
+pub mod allocation;
 pub mod algos;
+pub mod backlog;
+pub mod batsim;
+pub mod budget;
+pub mod characterization;
 pub mod cluster;
+pub mod comparison;
+pub mod confidence;
+pub mod debugger;
+pub mod decision;
+pub mod dependency;
+pub mod drain;
+pub mod experiment;
+pub mod external;
+pub mod failure;
+pub mod fragmentation;
+pub mod gang;
+pub mod golden;
+pub mod grid;
+pub mod initial;
+#[cfg(feature = "viz")]
+pub mod gantt;
+pub mod malleable;
+pub mod mark;
+pub mod observer;
+pub mod occupancy;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod output;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod power;
+pub mod profile;
+pub mod progress;
+pub mod qos;
+pub mod queue;
+pub mod rejects;
+pub mod reservation;
+pub mod rng;
+pub mod runtime;
+#[cfg(feature = "script")]
+pub mod script;
+pub mod snapshot;
+pub mod report;
+pub mod stats;
+pub mod synth;
+pub mod trace;
+pub mod user;
+pub mod utilization;
+pub mod view;
+pub mod walltime;
+pub mod workload;
 
-#[derive(Debug, Eq, PartialEq, PartialOrd, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Copy, Clone, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
 #[repr(u8)]
 pub enum DebugLevel {
 	None,
+	#[default]
 	Info,
-	Verbose
+	Verbose,
+	/// Also log why each queued job did or didn't start, see `Scheduler::explain`
+	Trace,
 }
 
-impl Ord for DebugLevel {
-    fn cmp(&self, other: &Self) -> Ordering {
-        (*self as u8).cmp(&(*other as u8))
-    }
+impl DebugLevel {
+	/// Most detailed events of the `tracing` crate logged at this level.
+	pub fn level_filter(self) -> LevelFilter {
+		match self {
+			DebugLevel::None => LevelFilter::OFF,
+			DebugLevel::Info => LevelFilter::INFO,
+			DebugLevel::Verbose => LevelFilter::DEBUG,
+			DebugLevel::Trace => LevelFilter::TRACE,
+		}
+	}
+}
+
+impl FromStr for DebugLevel {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"none" => Ok(DebugLevel::None),
+			"info" => Ok(DebugLevel::Info),
+			"verbose" => Ok(DebugLevel::Verbose),
+			"trace" => Ok(DebugLevel::Trace),
+			_ => Err(format!("unknown debug level '{}' (expected none, info, verbose or trace)", s)),
+		}
+	}
 }
 
-// the clock type used for all time measurement
-type Clock = u64;
+/// The clock type used for all time measurement, in seconds
+pub type Clock = u64;
 
-#[derive(Debug, Eq, PartialEq, PartialOrd)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Event {
 	NewJob(Job),
-	JobFinished(u32),
+	/// A job finished or reached its walltime, unless it was stopped since: the second field
+	/// is the number of times the job was stopped when it started (see `Job::runs`), telling
+	/// its runs apart
+	JobFinished(u32, u32),
+	/// A job whose submission was delayed until its preceding job finished
+	JobReleased(Job),
+	/// A job submitted by the user model, see `UserModel`
+	UserJob(Job),
+	/// A job cancelled in the trace leaves the queue, unless it started
+	JobCancelled(u32),
+	/// Nodes fail or go into maintenance
+	NodesDown(Outage),
+	/// The nodes of an outage are back
+	NodesUp(Outage),
+	/// The end of a time slice, see `TimeSlicing`
+	SliceEnd,
+	/// The scheduler runs, see `SchedulingCycle::Every`
+	Cycle,
+	/// A job reaches the time it started in the trace, in validation mode
+	TraceStart(u32),
+	/// A reservation starts, given by its partition and its index in the calendar of the partition
+	ReservationStart(usize, usize),
+	/// A reservation ends, freeing its nodes
+	ReservationEnd(usize, usize),
+	/// A job held by the scheduler until now may start, unless it was held again since, see `Mark::Hold`
+	HoldEnd(u32),
+	/// A scheduled downtime starts draining its nodes, given by its index in `EngineConfig::drains`
+	DrainStart(usize),
+	/// A scheduled downtime ends, bringing its nodes back
+	DrainEnd(usize),
 }
 
-impl Ord for Event {
-    fn cmp(&self, other: &Self) -> Ordering {
-		match (self, other) {
-			(Event::NewJob(_), Event::JobFinished(_)) => Ordering::Greater,
-		    (Event::JobFinished(_), Event::NewJob(_)) => Ordering::Less,
-			(Event::NewJob(a), Event::NewJob(b)) => a.cmp(b),
-			(Event::JobFinished(a), Event::JobFinished(b)) => a.cmp(b),
+/// Which events are processed first when several happen at the same time.
+/// When the scheduler runs after every event (see `SchedulingCycle::Event`), this decides
+/// whether it sees the nodes freed by finishing jobs and the newly submitted jobs at once.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventOrder {
+	/// Jobs finish before new ones are submitted
+	#[default]
+	FinishFirst,
+	/// New jobs are submitted before others finish
+	SubmitFirst,
+}
+
+impl EventOrder {
+	fn rank(&self, event: &Event) -> u8 {
+		match (self, event) {
+			(EventOrder::FinishFirst, Event::JobFinished(..)) | (EventOrder::SubmitFirst, Event::NewJob(_) | Event::UserJob(_)) => 0,
+			_ => 1,
 		}
-    }
+	}
 }
 
-pub struct Engine<'a> {
-	debug: DebugLevel,
-	scheduler: &'a Box<dyn Scheduler>,
-	cluster: Cluster,
-	events: MinMaxHeap<(Clock, Event)>,
-	clock: Clock,
+impl FromStr for EventOrder {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"finish-first" => Ok(EventOrder::FinishFirst),
+			"submit-first" => Ok(EventOrder::SubmitFirst),
+			_ => Err(format!("unknown event order '{}' (expected finish-first or submit-first)", s)),
+		}
+	}
 }
 
-#[derive(Debug)]
-pub enum EngineError {
-	ReadError(io::Error),
-	ParseError(ParseIntError)
+/// When the scheduler runs: after every event, once the events happening at the same time
+/// were all processed, or every N seconds like the cycles of a real batch scheduler, the
+/// jobs submitted in between waiting for the next cycle.
+///
+/// Written `event`, `instant` or a number of seconds.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SchedulingCycle {
+	Event,
+	#[default]
+	Instant,
+	Every(Clock),
 }
 
-impl Display for EngineError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-			EngineError::ReadError(why) => write!(f, "Unable to read the input file {}", why),
-			EngineError::ParseError(why) => write!(f, "Unable to read the input file {}", why),
+impl SchedulingCycle {
+	/// Whether the scheduler runs at `clock`, after the events happening then.
+	fn is_due(&self, clock: Clock) -> bool {
+		match self {
+			SchedulingCycle::Every(period) => clock.is_multiple_of(*period),
+			_ => true,
 		}
-    }
+	}
 }
 
-impl From<io::Error> for EngineError {
-    fn from(why: io::Error) -> Self {
-        EngineError::ReadError(why)
-    }
+impl FromStr for SchedulingCycle {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"event" => Ok(SchedulingCycle::Event),
+			"instant" => Ok(SchedulingCycle::Instant),
+			period => match period.parse() {
+				Ok(period) if period > 0 => Ok(SchedulingCycle::Every(period)),
+				_ => Err(format!("invalid scheduling cycle '{}' (expected event, instant or a number of seconds)", s)),
+			},
+		}
+	}
 }
 
-impl From<ParseIntError> for EngineError {
-	fn from(why: ParseIntError) -> Self {
-        EngineError::ParseError(why)
-    }
+impl TryFrom<String> for SchedulingCycle {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
 }
 
-#[derive(Debug)]
-pub struct EngineReport {
-	scheduler_name: &'static str,
+impl From<SchedulingCycle> for String {
+	fn from(cycle: SchedulingCycle) -> Self {
+		cycle.to_string()
+	}
+}
 
-	makespan: Clock,
-	total_completion_time: Clock,
-	min_wait: Clock,
-	max_wait: Clock,
-	avg_wait: f64,
-	median_wait: Clock,
-	total_wait: Clock,
+impl Display for SchedulingCycle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SchedulingCycle::Event => write!(f, "event"),
+			SchedulingCycle::Instant => write!(f, "instant"),
+			SchedulingCycle::Every(period) => write!(f, "{}", period),
+		}
+	}
+}
+
+/// Time the scheduler takes to make its decisions, which heavyweight optimizing schedulers
+/// can't make instantly: the jobs started by a cycle hold their nodes from the time of the
+/// cycle, but only start `per_cycle` seconds later, plus `per_job` seconds for each job
+/// decided in the cycle before and including them, see `Job::delay_start`.
+///
+/// Written `CYCLE[:JOB]`, in seconds.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DecisionLatency {
+	pub per_cycle: Clock,
+	pub per_job: Clock,
+}
+
+impl DecisionLatency {
+	/// Delay of the `nth` job (from 1) started by a cycle.
+	fn delay(&self, nth: Clock) -> Clock {
+		self.per_cycle + self.per_job * nth
+	}
+}
+
+impl FromStr for DecisionLatency {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid decision latency '{}' (expected CYCLE[:JOB] seconds)", s);
+
+		let parts = s.split(':').map(str::trim).collect::<Vec<_>>();
+		let (per_cycle, per_job) = match parts.as_slice() {
+			[per_cycle] => (*per_cycle, "0"),
+			[per_cycle, per_job] => (*per_cycle, *per_job),
+			_ => return Err(invalid()),
+		};
+
+		Ok(DecisionLatency {
+			per_cycle: per_cycle.parse().map_err(|_| invalid())?,
+			per_job: per_job.parse().map_err(|_| invalid())?,
+		})
+	}
+}
+
+impl TryFrom<String> for DecisionLatency {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<DecisionLatency> for String {
+	fn from(latency: DecisionLatency) -> Self {
+		latency.to_string()
+	}
+}
+
+impl Display for DecisionLatency {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}:{}", self.per_cycle, self.per_job)
+	}
+}
+
+/// An event waiting in the engine. Events are ordered by time, then by the rank given by
+/// the EventOrder, then by sequence number (the order in which they were created), so
+/// simulations never depend on the order of equal elements in the heap.
+#[derive(Clone, Serialize, Deserialize)]
+struct PendingEvent {
+	time: Clock,
+	rank: u8,
+	seq: u64,
+	event: Event,
+}
+
+impl PendingEvent {
+	fn key(&self) -> (Clock, u8, u64) {
+		(self.time, self.rank, self.seq)
+	}
+}
+
+impl PartialEq for PendingEvent {
+	fn eq(&self, other: &Self) -> bool {
+		self.key() == other.key()
+	}
+}
 
-	used_ressources: u64,
-	idle: u64,
-	idle_percent: f64,
+impl Eq for PendingEvent {}
 
+impl Ord for PendingEvent {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.key().cmp(&other.key())
+	}
+}
+
+impl PartialOrd for PendingEvent {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+/// Whether the time taken by the runs can be measured, which the web (wasm32 without WASI)
+/// doesn't allow: the runs then take no time and the progress is never printed.
+const HAS_WALL_CLOCK: bool = !cfg!(all(target_arch = "wasm32", target_os = "unknown"));
+
+/// Where the jobs of the simulated workload come from, read again when restoring a snapshot.
+enum Source {
+	File,
+	/// Content of a workload file held in memory
+	Bytes(Arc<[u8]>),
+	/// Workload already parsed, e.g. built by a `WorkloadBuilder`
+	Parsed(Arc<Workload>),
+}
+
+impl Source {
+	fn open(&self, input_file: &str) -> Result<(WorkloadMeta, JobStream), EngineError> {
+		match self {
+			Self::File => open_workload(input_file),
+			Self::Bytes(data) => read_workload_bytes(input_file, data.clone()),
+			Self::Parsed(workload) => {
+				let jobs = workload.clone();
+				let stream = (0..workload.jobs.len()).map(move |idx| Ok(jobs.jobs[idx].clone()));
+				Ok((workload.meta.clone(), Box::new(stream)))
+			}
+		}
+	}
+}
+
+/// Options of a simulation run.
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+	/// Only load the first N jobs of the trace
+	pub task_limit: Option<usize>,
+	/// Number of jobs, in submission order, simulated but left out of the metrics
+	pub warmup: usize,
+	/// Simulate the jobs finishing after this time but leave them out of the metrics (cool-down)
+	pub cutoff: Option<Clock>,
+	/// Stop the simulation early, see `Budget`
+	pub budget: Budget,
+	/// Run time under which jobs are considered interactive when computing the bounded slowdown
+	pub slowdown_tau: Clock,
+	/// Keep a JobRecord of every job in the report
+	pub record_jobs: bool,
+	/// Keep the length of the queue over time in the report
+	pub record_timeline: bool,
+	/// Keep the decisions of the scheduler in the report, see `Scheduler::explain`
+	pub record_decisions: bool,
+	/// Keep a RejectedJob of every job rejected when submitted in the report
+	pub record_rejects: bool,
+	/// Let the scheduler preempt running jobs, which then spend this time restoring
+	/// themselves when they start again. Jobs are never preempted when None
+	pub preemption_overhead: Option<Clock>,
+	/// How the requested run time of each job is derived
+	pub walltime: WalltimeModel,
+	/// Whether jobs running longer than they requested are killed, see `WalltimeLimit`
+	pub walltime_limit: WalltimeLimit,
+	/// Seed of the stochastic components of the run (walltime models, failures, user model and
+	/// schedulers drawing at random), the same seed giving the same run, see `SimRng`
+	pub seed: u64,
+	/// Order of the events happening at the same time
+	pub event_order: EventOrder,
+	/// When the scheduler runs, see `SchedulingCycle`
+	pub cycle: SchedulingCycle,
+	/// Time the scheduler takes to start the jobs it picks, see `DecisionLatency`. Jobs start
+	/// at the time they are picked when None
+	pub decision_latency: Option<DecisionLatency>,
+	/// Node failures and maintenances
+	pub failures: FailureModel,
+	/// Power drawn by the nodes, for the energy metrics
+	pub power: PowerModel,
+	/// Print the progress of the simulation on stderr
+	pub progress: bool,
+	/// Only submit jobs once the job they depend on finished, see `Dependencies`
+	pub dependencies: bool,
+	/// Take the jobs cancelled in the trace before they ran out of the queue at the time they
+	/// were cancelled, see `Job::cancel_time`. They run for no time otherwise
+	pub cancellations: bool,
+	/// Sample the share of the nodes running jobs, summarized in the report. Not sampled when None
+	pub utilization: Option<UtilizationSampling>,
+	/// Keep a snapshot of the jobs running on the nodes every N seconds in the report, see
+	/// `OccupancySnapshot`. No snapshot is taken when None
+	pub occupancy: Option<Clock>,
+	/// Policies deciding which submitted jobs enter the queues, in order, see `AdmissionPolicy`
+	pub admission: Vec<AdmissionRule>,
+	/// Jobs submitted by the users when their jobs end, on top of the trace
+	pub users: UserModel,
+	/// Share the nodes between rows of jobs in turns (gang scheduling). Jobs run to completion when None
+	pub time_slicing: Option<TimeSlicing>,
+	/// Nodes kept out of reach of the jobs over time windows, see `Reservation`
+	pub reservations: Vec<Reservation>,
+	/// Scheduled downtimes draining given nodes, see `Drain`
+	pub drains: Vec<Drain>,
+	/// How the nodes of the jobs are picked among the free ones
+	pub allocation: AllocationPolicy,
+	/// QoS classes of the jobs and their target waits, tried in order, see `QosClass`
+	pub qos: Vec<QosClass>,
+	/// Ignore the scheduler and start the jobs at the time they started in the trace, see `Replay`
+	pub validation: bool,
+	/// Whether the medians and percentiles of the report are exact or estimated in constant memory
+	pub stats: StatsMode,
+	/// Jobs of the trace simulated, the whole trace by default
+	pub filter: WorkloadFilter,
+	/// Submit times are divided by this factor, a factor above 1 bringing the jobs closer
+	/// together to raise the load, below 1 spreading them out
+	pub load_factor: f64,
+	/// Scale the number of nodes of the jobs from the size of the traced machine (read from
+	/// the trace header) to the size of the simulated cluster, instead of rejecting the jobs
+	/// too wide for it
+	pub scale_widths: bool,
+	/// Skip the lines of the trace that can't be read, counting them in the report, instead
+	/// of stopping the run at the first one
+	pub lenient: bool,
+	/// Jobs the scheduler may shrink or expand while they run, see `Scheduler::resize`
+	pub malleability: Malleability,
+	/// Jobs the scheduler may start on other numbers of nodes, see `Scheduler::schedule_shaped`
+	pub moldability: Moldability,
+	/// How the jobs are spread over the partitions, standing for the clusters of a grid
+	pub routing: Routing,
+	/// Jobs running or queued when the simulation starts, see `InitialState`
+	pub initial_state: Arc<InitialState>,
+	/// Run times predicted ahead of the simulation, see `Predictions`
+	pub predictions: Arc<Predictions>,
+	/// What the schedulers know of the run times of the jobs, see `RuntimeModel`
+	pub runtime: RuntimeModel,
+	/// Publishes the progress of the run while it goes, see `LiveMetrics`
+	#[cfg(feature = "metrics")]
+	pub metrics: Option<Arc<LiveMetrics>>,
+}
+
+impl Default for EngineConfig {
+	fn default() -> Self {
+		Self {
+			task_limit: None,
+			warmup: 0,
+			cutoff: None,
+			budget: Budget::default(),
+			slowdown_tau: 10,
+			record_jobs: false,
+			record_timeline: false,
+			record_decisions: false,
+			record_rejects: false,
+			preemption_overhead: None,
+			walltime: WalltimeModel::Trace,
+			walltime_limit: WalltimeLimit::None,
+			seed: 0,
+			event_order: EventOrder::FinishFirst,
+			cycle: SchedulingCycle::Instant,
+			decision_latency: None,
+			failures: FailureModel::None,
+			power: PowerModel::default(),
+			progress: false,
+			dependencies: true,
+			cancellations: true,
+			utilization: None,
+			occupancy: None,
+			admission: Vec::new(),
+			users: UserModel::None,
+			time_slicing: None,
+			reservations: Vec::new(),
+			drains: Vec::new(),
+			allocation: AllocationPolicy::FirstFit,
+			qos: Vec::new(),
+			validation: false,
+			stats: StatsMode::Exact,
+			filter: WorkloadFilter::default(),
+			load_factor: 1.0,
+			scale_widths: false,
+			lenient: false,
+			malleability: Malleability::None,
+			moldability: Moldability::None,
+			routing: Routing::First,
+			initial_state: Arc::default(),
+			predictions: Arc::default(),
+			runtime: RuntimeModel::Requested,
+			#[cfg(feature = "metrics")]
+			metrics: None,
+		}
+	}
+}
+
+/// Simulates a single workload on a single cluster with a single scheduler.
+pub struct Engine {
+	config: EngineConfig,
+	workload: String,
+	source: Source,
+	/// Span of the events logged by the engine
+	span: Span,
+	scheduler: Box<dyn Scheduler>,
+	/// Label of the scheduler in the reports and records, see `Scheduler::label`
+	label: String,
+	partitions: Vec<Partition>,
+	total_nodes: u32,
+	arrivals: Arrivals,
+	/// Whether the next submission is in the events
+	submission_pending: bool,
+	/// Outages that didn't happen yet, read lazily
+	outages: Box<dyn Iterator<Item = Outage> + Send>,
+	/// Outages added to the events so far
+	outages_read: usize,
+	progress: Option<Progress>,
+	#[cfg(feature = "metrics")]
+	metrics: Option<Arc<RunMetrics>>,
+	dependencies: Dependencies,
+	users: Users,
+	admission: Vec<Box<dyn AdmissionPolicy>>,
+	observers: Vec<Box<dyn Observer>>,
+	/// Predicts the run times of the submitted jobs, see `RuntimeModel`
+	predictor: Option<Box<dyn RuntimePredictor>>,
+	/// Number of rejected jobs, by reason
+	rejections: BTreeMap<String, u32>,
+	events: MinMaxHeap<PendingEvent>,
+	/// Sequence number of the next event
+	next_seq: u64,
+	clock: Clock,
+	state: RunState,
+}
+
+/// Counters and records of a run, kept between the calls to `Engine::run_until`.
+#[derive(Default, Clone, Serialize, Deserialize)]
+struct RunState {
+	started: bool,
+	/// Whether the simulation stopped early, its budget being exhausted
+	truncated: bool,
+	/// Jobs started, restarts included
+	started_jobs: usize,
+	finished_jobs: usize,
+	preemptions: u32,
+	/// Malleable jobs shrunk or expanded, see `Malleability`
+	resizes: u32,
+	/// Next partition in round-robin order, see `Routing::RoundRobin`
+	routing_turn: usize,
+	resubmissions: u32,
+	/// Jobs killed at their walltime, see `WalltimeLimit`
+	walltime_kills: u32,
+	/// Killed jobs submitted again with a larger request
+	walltime_resubmissions: u32,
+	/// Jobs taken out of the queue when the trace cancelled them
+	cancelled_jobs: usize,
+	/// Time the cancelled jobs waited until they were cancelled
+	cancellation_wait: Clock,
+	context_switches: u32,
+	/// Node-seconds resumed jobs spent restoring themselves after a context switch
+	switch_overhead: u64,
+	/// Node-seconds the started jobs held their nodes before running, see `DecisionLatency`
+	decision_overhead: u64,
+	/// End of the current time slice, if some partition has jobs to switch to
+	slice_end: Option<Clock>,
+	/// Next scheduling cycle, if some job waits for it, see `SchedulingCycle::Every`
+	next_cycle: Option<Clock>,
+	trimmed_jobs: usize,
+	job_records: Vec<JobRecord>,
+	rejects: Vec<RejectedJob>,
+	/// Node-seconds of the jobs rejected when submitted
+	rejected_work: u64,
+	queue_timeline: Vec<(Clock, usize, u64)>,
+	decisions: Vec<DecisionRecord>,
+	occupancy: Vec<OccupancySnapshot>,
+	/// Time of the next occupancy snapshot
+	next_occupancy: Clock,
+	utilization: Option<UtilizationTimeline>,
+	fragmentation: Fragmentation,
+	backlog: Backlog,
+	/// Queued jobs that would fit on the free nodes but for the reservations
+	blocked_by_reservations: usize,
+	/// Job-seconds the queued jobs waited because of the reservations
+	reservation_delay: u64,
+	/// Queued jobs that would fit on the free nodes if the drained nodes were up
+	blocked_by_drains: usize,
+	/// Job-seconds the queued jobs waited because of the scheduled downtimes
+	drain_delay: u64,
+	/// Jobs started after the time they started in the trace, in validation mode
+	late_starts: u32,
+	/// Metrics of the jobs of every partition
+	metrics: Metrics,
+	/// Time spent simulating so far, in milliseconds
 	time_took: u128,
 }
 
-impl<'a> Engine<'a> {
-	pub fn new(scheduler: &'a Box<dyn Scheduler>, available_nodes: u32, task_limit: Option<usize>, input_file: &str, debug: DebugLevel) -> Result<Self, EngineError> {
-		if debug >= DebugLevel::Verbose {
-			println!("Created a new Engine with scheduler {}", scheduler.name());
-			println!("Created a new Cluster with {} nodes", available_nodes);
+/// Nodes and queue of a partition, scheduled independently from the others.
+#[derive(Clone, Serialize, Deserialize)]
+struct Partition {
+	name: String,
+	cluster: Cluster,
+	queue: ReadyQueue,
+	metrics: Metrics,
+	gang: GangMatrix,
+	/// Jobs routed here away from the data of their user, see `Routing::home`
+	remote_jobs: usize,
+}
+
+/// Per-job metrics of the finished jobs, outside of the warm-up and cool-down, summed or
+/// summarized as they finish, see `StatsMode`.
+#[derive(Clone, Serialize, Deserialize)]
+struct Metrics {
+	wait_times: Stat,
+	total_wait: Clock,
+	total_completion_time: Clock,
+	slowdowns: Stat,
+	bounded_slowdowns: Stat,
+	by_width: Vec<JobSums>,
+	by_run_time: Vec<JobSums>,
+	by_skips: Vec<JobSums>,
+	skips: Stat,
+	/// Sums of the jobs of each user, by user ID
+	users: Vec<(Option<u32>, JobSums)>,
+	groups: Vec<(Option<u32>, JobSums)>,
+	/// Sums of the jobs of each QoS class, by class index
+	qos: Vec<JobSums>,
+	/// Node IDs spanned by the last allocation of each job, per node allocated
+	spans: Stat,
+	/// Blocks of contiguous nodes of the last allocations of the jobs
+	blocks: u64,
+	dependency_wait: Clock,
+	predictions: PredictionErrors,
+}
+
+impl Metrics {
+	fn new(stats: StatsMode) -> Self {
+		Self {
+			wait_times: Stat::new(stats),
+			total_wait: 0,
+			total_completion_time: 0,
+			slowdowns: Stat::new(stats),
+			bounded_slowdowns: Stat::new(stats),
+			by_width: vec![JobSums::default(); WIDTH_BUCKETS.len()],
+			by_run_time: vec![JobSums::default(); RUN_TIME_BUCKETS.len()],
+			by_skips: vec![JobSums::default(); SKIP_BUCKETS.len()],
+			skips: Stat::new(stats),
+			users: Vec::new(),
+			groups: Vec::new(),
+			qos: Vec::new(),
+			spans: Stat::new(stats),
+			blocks: 0,
+			dependency_wait: 0,
+			predictions: PredictionErrors::new(stats),
 		}
+	}
 
-		let mut events = MinMaxHeap::new();
+	fn push(&mut self, job: &Job, end: Clock, slowdown_tau: Clock) {
+		let wait_time = job.wait_time_until(end);
+		let bounded_slowdown = job.slowdown(wait_time, slowdown_tau);
+		self.wait_times.push(wait_time as f64);
+		self.total_wait += wait_time;
+		self.total_completion_time += end;
+		self.slowdowns.push(job.slowdown(wait_time, 1));
+		self.bounded_slowdowns.push(bounded_slowdown);
+		self.skips.push(job.skips as f64);
+		self.dependency_wait += job.dependency_wait;
+		self.predictions.push(job);
 
-		let file = File::open(input_file)?;
-		let reader = BufReader::new(file);
+		let add = |sums: &mut JobSums| sums.add(job, wait_time, bounded_slowdown);
+		if let Some(idx) = BucketReport::bucket(WIDTH_BUCKETS, job.nodes) {
+			add(&mut self.by_width[idx]);
+		}
+		if let Some(idx) = BucketReport::bucket(RUN_TIME_BUCKETS, job.run_time) {
+			add(&mut self.by_run_time[idx]);
+		}
+		if let Some(idx) = BucketReport::bucket(SKIP_BUCKETS, job.skips) {
+			add(&mut self.by_skips[idx]);
+		}
+		add(Self::owner(&mut self.users, job.user_id));
+		add(Self::owner(&mut self.groups, job.group_id));
+		if let Some(idx) = job.qos.map(|qos| qos as usize) {
+			if self.qos.len() <= idx {
+				self.qos.resize(idx + 1, JobSums::default());
+			}
+			add(&mut self.qos[idx]);
+		}
 
-		for line in reader.lines() {
-			let line = line?;
+		if let (Some(first), Some(last)) = (job.allocation.ranges().first(), job.allocation.ranges().last()) {
+			self.spans.push((last.end - first.start) as f64 / job.allocation.len() as f64);
+			self.blocks += job.allocation.ranges().len() as u64;
+		}
+	}
 
-			if line.is_empty() || line.as_bytes()[0] == b';' {
-				continue;
+	/// Sums of the jobs of an owner, the owners being kept sorted by ID.
+	fn owner(owners: &mut Vec<(Option<u32>, JobSums)>, id: Option<u32>) -> &mut JobSums {
+		let idx = match owners.binary_search_by_key(&id, |&(id, _)| id) {
+			Ok(idx) => idx,
+			Err(idx) => {
+				owners.insert(idx, (id, JobSums::default()));
+				idx
 			}
+		};
 
-			let split = line.split_whitespace().collect::<Vec<_>>();
-			assert_eq!(split.len(), 18);
+		&mut owners[idx].1
+	}
+}
 
-			let job_id 			= split[0].parse()?;
-			let submission 		= split[1].parse()?;
-			let run 			= split[3].parse()?;
-			let nproc: u32		= split[7].parse()?;
-			let required_run 	= split[8].parse()?;
-			
-			let nodes = (nproc as f32 / 4.0).ceil() as u32;
-			assert!(nodes > 0);
+impl Default for Metrics {
+	fn default() -> Self {
+		Self::new(StatsMode::default())
+	}
+}
 
+/// Jobs of the trace that were not submitted yet, read lazily.
+struct Arrivals {
+	jobs: JobStream,
+	spec: ClusterSpec,
+	cores_per_node: u32,
+	limit: Option<usize>,
+	/// Records of the trace read so far, including the skipped ones
+	records: usize,
+	read: usize,
+	walltime: WalltimeNoise,
+	power: PowerModel,
+	/// QoS classes the jobs are sorted in
+	qos: Vec<QosClass>,
+	/// Whether jobs cancelled before they ran are cancelled at the same time, see `Job::cancel_time`
+	cancellations: bool,
+	/// Jobs of the trace simulated
+	filter: WorkloadFilter,
+	/// Time of the trace the submit times are re-based from, once a job was kept by the filter
+	origin: Option<Clock>,
+	/// Submit times are divided by this factor, see `EngineConfig::load_factor`
+	load_factor: f64,
+	/// Simulated nodes per node of the traced machine, when the widths of the jobs are scaled
+	width_scale: Option<f64>,
+	/// Whether malformed lines of the trace are skipped, see `EngineConfig::lenient`
+	lenient: bool,
+	/// Malformed lines of the trace skipped so far
+	malformed: usize,
+	/// Records of the trace skipped so far for lacking a run time or a number of processors
+	incomplete: usize,
+	/// Which jobs are malleable, see `Malleability::apply`
+	malleability: Malleability,
+	/// Which jobs are moldable, see `Moldability::apply`
+	moldability: Moldability,
+	rng: SimRng,
+}
 
-			if nodes > available_nodes {
-				if debug >= DebugLevel::Verbose {
-					println!("Skipping job {} as it requires {} > {} nodes", job_id, nodes, available_nodes);
+impl Arrivals {
+	/// Returns the next record of the trace, skipping the malformed ones in lenient runs.
+	fn next_record(&mut self) -> Option<Result<SwfJob, EngineError>> {
+		for swf in &mut self.jobs {
+			self.records += 1;
+			match swf {
+				Err(why @ EngineError::MalformedTrace { .. }) if self.lenient => {
+					warn!("skipping a line of the trace: {}", why);
+					self.malformed += 1;
 				}
+				swf => return Some(swf),
+			}
+		}
 
-				continue;
+		None
+	}
+
+	/// Returns the next job that can be simulated, skipping the incomplete records.
+	fn next_job(&mut self) -> Result<Option<Job>, EngineError> {
+		if self.limit.is_some_and(|limit| self.read >= limit) {
+			return Ok(None);
+		}
+
+		while let Some(swf) = self.next_record() {
+			let swf = swf?;
+
+			let job = match self.take_job(&swf) {
+				Some(job) => job,
+				None => {
+					debug!(job = swf.job_number, "skipping a job whose trace record is incomplete or that is filtered out");
+
+					continue;
+				}
+			};
+
+			let mut job = job;
+			job.submit_index = self.read;
+			job.classes = self.spec.job_classes(&job, self.cores_per_node);
+			job.memory_per_node = job.requested_memory.map_or(0, |memory| (memory.max(0.0) * self.cores_per_node as f64).ceil() as u64);
+			self.walltime.apply(&mut job);
+			self.power.apply(&mut job);
+			self.malleability.apply(&mut job, &self.rng);
+			self.moldability.apply(&mut job, &self.rng);
+			QosClass::assign(&self.qos, &mut job);
+
+			self.read += 1;
+			return Ok(Some(job));
+		}
+
+		Ok(None)
+	}
+
+	/// Reads the trace again from `jobs` up to the given number of records, drawing the
+	/// same walltimes as when they were first read.
+	fn fast_forward(&mut self, jobs: JobStream, walltime: WalltimeNoise, records: usize) -> Result<(), EngineError> {
+		self.jobs = jobs;
+		self.walltime = walltime;
+		self.records = 0;
+		self.malformed = 0;
+		self.incomplete = 0;
+		self.origin = None;
+
+		while self.records < records {
+			let swf = match self.next_record() {
+				Some(swf) => swf?,
+				None => return Err(EngineError::InvalidSnapshot("the workload has fewer jobs than when the snapshot was taken".to_string())),
+			};
+
+			if let Some(mut job) = self.take_job(&swf) {
+				self.walltime.apply(&mut job);
 			}
+		}
 
-			// Reverse because BinaryHeap is a max-heap in Rust
-			events.push((submission, Event::NewJob(Job::new(job_id, nodes, submission, run, required_run))));
+		Ok(())
+	}
 
-			if let Some(limit) = task_limit {
-				if events.len() >= limit {
-					break;
+	/// The job of a trace record, if it can be simulated and is kept by the filter. When
+	/// cancellations are ignored, jobs cancelled before they ran are simulated with no run
+	/// time, unless the trace lacks it.
+	fn take_job(&mut self, swf: &SwfJob) -> Option<Job> {
+		let mut job = match swf.to_job(self.cores_per_node) {
+			Some(job) => job,
+			None => {
+				self.incomplete += 1;
+				return None;
+			}
+		};
+		if !self.cancellations && job.cancel_time.take().is_some() && swf.run_time.is_none() {
+			return None;
+		}
+
+		if !self.filter.is_empty() {
+			if !self.filter.accepts(&job) {
+				return None;
+			}
+
+			let filter = &self.filter;
+			let origin = *self.origin.get_or_insert_with(|| filter.origin(&job));
+			job.set_submit_time(job.submit_time - origin);
+		}
+
+		if self.load_factor != 1.0 {
+			job.set_submit_time((job.submit_time as f64 / self.load_factor).round() as Clock);
+		}
+
+		if let Some(scale) = self.width_scale {
+			job.nodes = ((job.nodes as f64 * scale).round() as u32).clamp(1, self.spec.nodes.max(1));
+		}
+
+		Some(job)
+	}
+
+	/// Gives back the position of the last job read, which was rejected, so that the limit
+	/// and the warm-up only count the admitted jobs.
+	fn unread(&mut self) {
+		self.read -= 1;
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EngineError {
+	#[error("Unable to read the input file: {0}")]
+	ReadError(#[from] io::Error),
+	#[error("Unable to read the input file: {0}")]
+	ParseError(#[from] ParseIntError),
+	#[error("Unable to read the input file: {0}")]
+	ParseFloatError(#[from] ParseFloatError),
+	/// A line of a trace that can't be read, which lenient runs skip, see `EngineConfig::lenient`
+	#[error("Malformed trace {file}, line {line}{}: {reason}", field.map_or_else(String::new, |field| format!(", field {}", field)))]
+	MalformedTrace {
+		file: String,
+		/// Line number in the file, starting at 1
+		line: usize,
+		/// SWF field that can't be read, None if the line as a whole is wrong
+		field: Option<&'static str>,
+		reason: String,
+	},
+	#[error("Invalid workload: {0}")]
+	InvalidWorkload(String),
+	#[error("Invalid cluster: {0}")]
+	InvalidCluster(String),
+	#[error("Invalid failure trace: {0}")]
+	InvalidFailures(String),
+	#[error("Invalid snapshot: {0}")]
+	InvalidSnapshot(String),
+}
+
+impl Engine {
+	/// Prepares the simulation of the workload at `input_file`, an SWF trace, a Batsim
+	/// workload if it ends with .json or an accounting log (see `WorkloadFormat`), which may
+	/// be compressed.
+	/// Only the header of SWF traces is read for now.
+	pub fn new(scheduler: Box<dyn Scheduler>, spec: ClusterSpec, input_file: &str, config: EngineConfig) -> Result<Self, EngineError> {
+		Self::with_workload(scheduler, spec, input_file, Source::File, config)
+	}
+
+	/// Prepares the simulation of a workload held in memory, e.g. for a web page reading
+	/// files on the client. `name` stands for the file name, which gives the format, and
+	/// names the workload in the reports.
+	pub fn from_bytes(scheduler: Box<dyn Scheduler>, spec: ClusterSpec, name: &str, data: Vec<u8>, config: EngineConfig) -> Result<Self, EngineError> {
+		Self::with_workload(scheduler, spec, name, Source::Bytes(data.into()), config)
+	}
+
+	/// Prepares the simulation of a workload built in code, see `WorkloadBuilder`, or loaded
+	/// beforehand. `name` names the workload in the reports.
+	pub fn from_workload(scheduler: Box<dyn Scheduler>, spec: ClusterSpec, name: &str, workload: Workload, config: EngineConfig) -> Result<Self, EngineError> {
+		Self::with_workload(scheduler, spec, name, Source::Parsed(Arc::new(workload)), config)
+	}
+
+	fn with_workload(scheduler: Box<dyn Scheduler>, spec: ClusterSpec, input_file: &str, source: Source, config: EngineConfig) -> Result<Self, EngineError> {
+		let mut scheduler = match config.validation {
+			true => Box::new(Replay),
+			false => scheduler,
+		};
+
+		let rng = SimRng::new(config.seed);
+		scheduler.seed(&rng);
+
+		let available_nodes = spec.nodes;
+		let partition_sizes = spec.partition_sizes().map_err(EngineError::InvalidCluster)?;
+		let class_sizes = spec.class_sizes().map_err(EngineError::InvalidCluster)?;
+
+		let (meta, reader) = source.open(input_file)?;
+		let cores_per_node = spec.cores_per_node
+			.or_else(|| meta.procs_per_node())
+			.unwrap_or(DEFAULT_CORES_PER_NODE);
+
+		if config.load_factor.is_nan() || config.load_factor <= 0.0 {
+			return Err(EngineError::InvalidWorkload(format!("the load factor must be positive ({} given)", config.load_factor)));
+		}
+
+		let width_scale = match config.scale_widths {
+			true => match meta.max_nodes.or_else(|| meta.max_procs.map(|procs| procs.div_ceil(cores_per_node))) {
+				Some(traced) if traced > 0 => Some(available_nodes as f64 / traced as f64),
+				_ => return Err(EngineError::InvalidWorkload("scaling the widths of the jobs needs the size of the traced machine (MaxNodes or MaxProcs in the header)".to_string())),
+			},
+			false => None,
+		};
+
+		let label = scheduler.label();
+		let span = tracing::info_span!("run", scheduler = %label, nodes = available_nodes, workload = input_file);
+		span.in_scope(|| {
+			debug!(cores_per_node, "created the cluster");
+			info!("read the header of the workload, ready for simulation");
+		});
+
+		let outages = config.failures.outages(available_nodes, &rng)?;
+
+		let total_jobs = match (config.task_limit, meta.max_jobs) {
+			(Some(limit), Some(max_jobs)) => Some(limit.min(max_jobs as usize)),
+			(limit, max_jobs) => limit.or(max_jobs.map(|max_jobs| max_jobs as usize)),
+		};
+
+		let progress = (config.progress && HAS_WALL_CLOCK).then(|| Progress::new(format!("{} on {} nodes", label, available_nodes), total_jobs));
+		#[cfg(feature = "metrics")]
+		let metrics = config.metrics.as_ref().map(|live| live.register(input_file, &label, available_nodes, config.load_factor, config.seed, total_jobs));
+
+		for rule in &config.admission {
+			if let AdmissionRule::Reroute(name) = rule {
+				let exists = match spec.partitions.is_empty() {
+					true => name == "default",
+					false => spec.partitions.iter().any(|partition| &partition.name == name),
+				};
+
+				if !exists {
+					return Err(EngineError::InvalidCluster(format!("no partition named '{}' to reroute jobs to", name)));
 				}
 			}
 		}
 
-		if debug >= DebugLevel::Info {
-			println!("Finished reading the input file, {} jobs will be scheduled on {} nodes. Ready for simulation", events.len(), available_nodes);
+		if let Some(name) = spec.class_map.values().find(|&name| !spec.node_classes.iter().any(|class| &class.name == name)) {
+			return Err(EngineError::InvalidCluster(format!("no node class named '{}' for the jobs of the class map", name)));
+		}
+
+		let mut first_node = 0;
+		let classes = class_sizes.iter().map(|&nodes| {
+			first_node += nodes;
+			first_node - nodes..first_node
+		}).collect::<Vec<_>>();
+
+		let mut first_node = 0;
+		let mut partitions = Vec::new();
+
+		for (idx, &nodes) in partition_sizes.iter().enumerate() {
+			let mut cluster = Cluster::with_classes(first_node, nodes, &classes);
+			cluster.set_allocation_policy(config.allocation);
+			cluster.set_class_memory(&spec.class_memory());
+
+			partitions.push(Partition {
+				name: spec.partitions.get(idx).map(|partition| partition.name.clone()).unwrap_or_else(|| "default".to_string()),
+				cluster,
+				queue: ReadyQueue::new(),
+				metrics: Metrics::new(config.stats),
+				gang: config.time_slicing.map_or_else(GangMatrix::default, |slicing| GangMatrix::new(slicing.slots)),
+				remote_jobs: 0,
+			});
+
+			first_node += nodes;
+		}
+
+		for reservation in &config.reservations {
+			let idx = match &reservation.partition {
+				Some(name) => partitions.iter().position(|partition| &partition.name == name)
+					.ok_or_else(|| EngineError::InvalidCluster(format!("no partition named '{}' for the reservation '{}'", name, reservation.name)))?,
+				None => 0,
+			};
+
+			let cluster = &mut partitions[idx].cluster;
+			let nodes = reservation.nodes.unwrap_or(cluster.total_nodes).min(cluster.total_nodes);
+			cluster.add_reservation(ReservedWindow::new(reservation.start, reservation.start + reservation.duration, nodes));
 		}
 
 		Ok(Self {
 			scheduler,
-			debug,
-			cluster: Cluster::new(available_nodes),
-			events,
+			label,
+			partitions,
+			total_nodes: available_nodes,
+			arrivals: Arrivals {
+				jobs: reader,
+				spec,
+				cores_per_node,
+				limit: config.task_limit,
+				records: 0,
+				read: 0,
+				walltime: WalltimeNoise::new(config.walltime, &rng),
+				power: config.power,
+				qos: config.qos.clone(),
+				cancellations: config.cancellations,
+				filter: config.filter.clone(),
+				origin: None,
+				load_factor: config.load_factor,
+				width_scale,
+				lenient: config.lenient,
+				malformed: 0,
+				incomplete: 0,
+				malleability: config.malleability,
+				moldability: config.moldability,
+				rng,
+			},
+			submission_pending: false,
+			outages,
+			outages_read: 0,
+			progress,
+			#[cfg(feature = "metrics")]
+			metrics,
+			dependencies: Dependencies::default(),
+			users: Users::new(config.users, &rng),
+			admission: config.admission.iter().map(AdmissionRule::build).collect(),
+			observers: Vec::new(),
+			predictor: config.runtime.predictor(),
+			rejections: BTreeMap::new(),
+			config,
+			workload: input_file.to_string(),
+			source,
+			span,
+			events: MinMaxHeap::new(),
+			next_seq: 0,
 			clock: 0,
+			state: RunState::default(),
 		})
 	}
 
-	pub fn run(&mut self) -> EngineReport {
-		if self.debug >= DebugLevel::Info {
-			println!("Starting the simulation.");
+	/// Adds an admission policy, called after the ones of the configuration.
+	pub fn add_admission_policy(&mut self, policy: Box<dyn AdmissionPolicy>) {
+		self.admission.push(policy);
+	}
+
+	/// Adds an observer, told of the submissions, starts, ends and kills of the jobs and of
+	/// the time moving forward.
+	pub fn add_observer(&mut self, observer: Box<dyn Observer>) {
+		self.observers.push(observer);
+	}
+
+	/// Predicts the run times of the jobs with the given predictor instead of the one of the
+	/// runtime model of the configuration.
+	pub fn set_runtime_predictor(&mut self, predictor: Box<dyn RuntimePredictor>) {
+		self.predictor = Some(predictor);
+	}
+
+	/// Reads the next job of the trace and adds its submission to the events.
+	/// Only one submission is pending at any time, the trace being sorted by submission time.
+	fn submit_next(&mut self) -> Result<(), EngineError> {
+		self.submission_pending = false;
+
+		if let Some(job) = self.arrivals.next_job()? {
+			// a job submitted out of order is submitted as soon as possible
+			let submit_time = job.submit_time.max(self.clock);
+			self.push_event(submit_time, Event::NewJob(job));
+			self.submission_pending = true;
+		}
+
+		Ok(())
+	}
+
+	/// Adds the next outage to the events, only one being pending at any time.
+	fn next_outage(&mut self) {
+		if let Some(outage) = self.outages.next() {
+			self.outages_read += 1;
+			let time = outage.time.max(self.clock);
+			self.push_event(time, Event::NodesDown(outage));
 		}
+	}
 
-		let start_time = SystemTime::now();
+	/// Whether jobs are still waiting to be submitted (by the trace, after the jobs they depend on
+	/// or by the user model), queued or running.
+	/// Outages alone don't keep the simulation going.
+	fn has_work(&self) -> bool {
+		!self.state.truncated && (self.submission_pending || self.dependencies.waiting() > 0 || self.users.pending() > 0 || self.partitions.iter().any(|partition| !partition.queue.is_empty() || !partition.cluster.running_jobs.is_empty() || partition.gang.suspended() > 0))
+	}
 
-		let mut queue = Vec::new();
-		let mut wait_times = Vec::new();
-		let mut completion_times = Vec::new();
+	fn push_event(&mut self, time: Clock, event: Event) {
+		let rank = self.config.event_order.rank(&event);
+		self.events.push(PendingEvent {
+			time,
+			rank,
+			seq: self.next_seq,
+			event,
+		});
 
-		let mut scheduled_jobs = 0u32;
+		self.next_seq += 1;
+	}
 
-		while !self.events.is_empty() || !queue.is_empty() {
-			if !queue.is_empty() {
-				if self.debug >= DebugLevel::Verbose {
-					println!("DEBUG: Jobs in the queue to schedule {:?}", queue);
+	/// Jobs waiting in the queues of every partition.
+	fn queued_jobs(&self) -> usize {
+		self.partitions.iter().map(|partition| partition.queue.len()).sum()
+	}
+
+	/// Nodes running jobs in every partition.
+	fn busy_nodes(&self) -> u32 {
+		self.partitions.iter()
+			.map(|partition| partition.cluster.total_nodes - partition.cluster.available_nodes - partition.cluster.down_nodes() - partition.cluster.reserved_nodes())
+			.sum()
+	}
+
+	/// Partition the job runs on, if it is running.
+	fn running_partition(&self, job_id: u32) -> Option<usize> {
+		self.partitions.iter().position(|partition| partition.cluster.running_jobs.contains_key(&job_id))
+	}
+
+	/// Whether the decisions of the scheduler are printed or recorded.
+	fn explains(&self) -> bool {
+		self.config.record_decisions || tracing::enabled!(Level::TRACE)
+	}
+
+	/// Prints and records the reason of a decision, depending on the configuration.
+	fn log_decision(&mut self, partition: usize, job_id: u32, reason: Reason) {
+		trace!(clock = self.clock, job = job_id, %reason, "scheduling decision");
+
+		if self.config.record_decisions {
+			self.state.decisions.push(DecisionRecord {
+				scheduler: self.label.clone(),
+				cluster_nodes: self.total_nodes,
+				partition: self.partitions[partition].name.clone(),
+				clock: self.clock,
+				job_id,
+				reason,
+			});
+		}
+	}
+
+	/// Takes the occupancy snapshots due before `until`, the nodes not changing from now until then.
+	fn snapshot_occupancy(&mut self, until: Clock) {
+		let interval = match self.config.occupancy {
+			Some(interval) if interval > 0 => interval,
+			_ => return,
+		};
+
+		while self.state.next_occupancy < until {
+			let clock = self.clock;
+			let partitions = self.partitions.iter().map(|partition| {
+				let cluster = &partition.cluster;
+				let mut running = cluster.running_jobs.values().map(|job| RunningJob {
+					id: job.id,
+					user_id: job.user_id,
+					nodes: job.nodes,
+					start: job.schedule_time,
+					expected_end: job.estimated_end(clock),
+					allocation: job.allocation.clone(),
+				}).collect::<Vec<_>>();
+				running.sort_unstable_by_key(|job| job.id);
+
+				PartitionOccupancy {
+					name: partition.name.clone(),
+					first_node: cluster.first_node(),
+					nodes: cluster.total_nodes,
+					free: cluster.available_nodes,
+					down: cluster.down_nodes(),
+					reserved: cluster.reserved_nodes(),
+					queued_jobs: partition.queue.len(),
+					queued_nodes: partition.queue.queued_nodes(),
+					running,
 				}
+			}).collect();
 
-				while !queue.is_empty() {
-					let index = match self.scheduler.schedule(self.clock, &queue, &self.cluster) {
-						Some(index) => index,
-						None => break
-					};
+			self.state.occupancy.push(OccupancySnapshot {
+				scheduler: self.label.clone(),
+				cluster_nodes: self.total_nodes,
+				clock: self.state.next_occupancy,
+				partitions,
+			});
+			self.state.next_occupancy += interval;
+		}
+	}
+
+	/// Records the queued jobs and the nodes they request in the backlog, and adds a sample
+	/// to the queue timeline if it is recorded and changed, keeping only the last sample of each timestamp.
+	fn record_queue(&mut self) {
+		let (clock, length) = (self.clock, self.queued_jobs());
+		let nodes = self.partitions.iter().map(|partition| partition.queue.queued_nodes()).sum();
+		let free = self.partitions.iter().map(|partition| partition.cluster.available_nodes).sum();
+		self.state.backlog.record(clock, length, nodes, free);
 
-					let job = queue.swap_remove(index);
+		if !self.config.record_timeline {
+			return;
+		}
+
+		match self.state.queue_timeline.last_mut() {
+			Some(last) if (last.1, last.2) == (length, nodes) => {}
+			Some(last) if last.0 == clock => *last = (clock, length, nodes),
+			_ => self.state.queue_timeline.push((clock, length, nodes)),
+		}
+	}
 
-					let end_time = self.clock + job.run_time;
-					wait_times.push(job.wait_time_from(self.clock));
-					completion_times.push(end_time);
+	/// Runs the admission policies on a submitted job, returning it along with the partition
+	/// it goes to, or None if it was rejected.
+	fn admit(&mut self, mut job: Job) -> Option<Job> {
+		let sites = self.sites(&job);
+		let mut route = self.config.routing.route(&job, &sites, &mut self.state.routing_turn);
+		let mut rejection = None;
 
-					// Reverse because BinaryHeap is a max-heap in Rust
-					self.events.push((end_time, Event::JobFinished(job.id)));
-					self.cluster.schedule_job(job, self.clock);
+		if !self.admission.is_empty() {
+			let partitions = self.partitions.iter().map(|partition| PartitionState {
+				name: &partition.name,
+				nodes: partition.cluster.total_nodes,
+				available_nodes: partition.cluster.available_nodes,
+				queued: partition.queue.len(),
+			}).collect::<Vec<_>>();
 
-					scheduled_jobs += 1;
-					if self.debug >= DebugLevel::Info && scheduled_jobs % 1000 == 0 {
-						println!("Scheduled the {}th job.", scheduled_jobs);
+			for policy in &mut self.admission {
+				let submission = Submission { clock: self.clock, route, partitions: &partitions };
+				match policy.admit(&mut job, &submission) {
+					Verdict::Accept => {}
+					Verdict::Reroute(idx) => route = Some(idx),
+					Verdict::Reject(reason) => {
+						rejection = Some(reason);
+						break;
 					}
 				}
 			}
+		}
+
+		let rejection = rejection.or(match route {
+			None => Some("no_partition"),
+			Some(_) if job.classes == 0 => Some("too_much_memory"),
+			Some(idx) if job.nodes > self.partitions[idx].cluster.nodes_for(job.classes) => Some("too_wide"),
+			Some(_) => None,
+		});
+
+		if let Some(reason) = rejection {
+			debug!(clock = self.clock, job = job.id, reason, "job rejected");
+
+			*self.rejections.entry(reason.to_string()).or_insert(0) += 1;
+			self.state.rejected_work += job.nodes as u64 * job.run_time;
+			if self.config.record_rejects {
+				self.state.rejects.push(RejectedJob::new(&self.label, self.total_nodes, &job, reason));
+			}
+			return None;
+		}
+
+		job.partition_idx = route.expect("rejected otherwise");
+		if sites.len() > 1 && job.partition_idx != Routing::home(&job, &sites) {
+			self.partitions[job.partition_idx].remote_jobs += 1;
+		}
+
+		Some(job)
+	}
+
+	/// The partitions as seen by the meta-scheduler routing the job, see `Routing`.
+	fn sites(&self, job: &Job) -> Vec<Site> {
+		self.partitions.iter().enumerate().map(|(idx, partition)| {
+			let cluster = &partition.cluster;
+			let busy = (cluster.total_nodes - cluster.available_nodes) as u64 + partition.queue.queued_nodes();
+
+			Site {
+				accepts: self.arrivals.spec.partitions.get(idx).is_none_or(|spec| spec.accepts(job)),
+				fits: job.nodes <= cluster.nodes_for(job.classes),
+				load: busy as f64 / cluster.total_nodes.max(1) as f64,
+			}
+		}).collect()
+	}
+
+	/// Puts the jobs of the initial state on the machine, the running jobs that don't fit on
+	/// their partition waiting in its queue instead, see `InitialState`.
+	fn preload(&mut self) {
+		let state = self.config.initial_state.clone();
+
+		for (idx, initial) in state.jobs.iter().enumerate() {
+			let mut job = initial.job(u32::MAX - idx as u32, self.clock);
+			job.classes = self.arrivals.spec.job_classes(&job, self.arrivals.cores_per_node);
+
+			let job = match self.admit(job) {
+				Some(job) => job,
+				None => continue,
+			};
+
+			let partition = &mut self.partitions[job.partition_idx];
+			if !initial.running || !partition.cluster.fits(&job) {
+				if initial.running {
+					warn!(job = job.id, nodes = job.nodes, partition = %partition.name, "initially running job queued, its partition being full");
+				}
+
+				self.enqueue(job);
+				continue;
+			}
+
+			let (id, run) = (job.id, job.runs());
+			partition.cluster.schedule_job(job, self.clock);
+			for observer in &mut self.observers {
+				observer.on_start(self.clock, &partition.cluster.running_jobs[&id]);
+			}
+
+			let end_time = self.config.walltime_limit.end_time(&partition.cluster.running_jobs[&id]);
+			self.push_event(end_time, Event::JobFinished(id, run));
+		}
+	}
+
+	/// Admits a submitted job and queues it once the job it depends on finished, returning
+	/// whether it was admitted.
+	fn submit(&mut self, job: Job) -> bool {
+		let job = match self.admit(job) {
+			Some(job) => job,
+			None => return false,
+		};
+
+		let admission = match self.config.dependencies {
+			true => self.dependencies.submit(job, self.clock),
+			false => Admission::Ready(job),
+		};
+
+		match admission {
+			Admission::Ready(job) => self.enqueue(job),
+			Admission::Delayed(time, job) => self.push_event(time, Event::JobReleased(job)),
+			Admission::Held => {}
+		}
+
+		true
+	}
+
+	/// Adds a submitted job to the queue of its partition, with its predicted run time.
+	fn enqueue(&mut self, mut job: Job) {
+		let idx = job.partition_idx;
+
+		job.prediction = match (self.config.predictions.get(job.id), &mut self.predictor) {
+			(Some(prediction), _) => Some(prediction),
+			(None, Some(predictor)) => predictor.predict(JobView::new(&job)),
+			(None, None) if self.config.runtime == RuntimeModel::Exact => Some(job.run_time),
+			(None, None) => None,
+		};
+
+		debug!(clock = self.clock, job = job.id, partition = %self.partitions[idx].name, queued = self.partitions[idx].queue.len() + 1, "job submitted");
+
+		if let Some(time) = job.cancel_time {
+			self.push_event(time.max(self.clock), Event::JobCancelled(job.id));
+		}
+
+		if let Some(start) = job.trace_start.filter(|&start| self.config.validation && start > self.clock) {
+			self.push_event(start, Event::TraceStart(job.id));
+		}
+
+		self.scheduler.on_job_arrival(self.clock, &job);
+		for observer in &mut self.observers {
+			observer.on_submit(self.clock, &job);
+		}
+		self.partitions[idx].queue.push(job);
+
+		self.record_queue();
+	}
+
+	/// Simulates the whole workload and returns the metrics of the run.
+	pub fn run(&mut self) -> Result<EngineReport, EngineError> {
+		self.run_until(Clock::MAX)?;
+		Ok(self.report())
+	}
+
+	/// Simulates the events happening until `time` included, returning whether work is left.
+	/// The simulation goes on from there at the next call, the engine being in a state that
+	/// `snapshot` can save in between.
+	pub fn run_until(&mut self, time: Clock) -> Result<bool, EngineError> {
+		let start_time = HAS_WALL_CLOCK.then(Instant::now);
+		let _span = self.span.clone().entered();
+
+		if !self.state.started {
+			info!("starting the simulation");
+
+			self.state.started = true;
+			self.state.utilization = self.config.utilization.map(UtilizationTimeline::new);
+			self.state.metrics = Metrics::new(self.config.stats);
+			self.preload();
+			self.submit_next()?;
+			self.next_outage();
+
+			for idx in 0..self.partitions.len() {
+				for (window, reservation) in self.partitions[idx].cluster.reservations().to_vec().into_iter().enumerate() {
+					self.push_event(reservation.start, Event::ReservationStart(idx, window));
+					self.push_event(reservation.end, Event::ReservationEnd(idx, window));
+				}
+			}
+
+			for idx in 0..self.config.drains.len() {
+				let drain = &self.config.drains[idx];
+				let (start, end) = (drain.start, drain.end());
+				self.push_event(start, Event::DrainStart(idx));
+				self.push_event(end, Event::DrainEnd(idx));
+			}
+
+			self.schedule();
+		}
+
+		while self.has_work() {
+			// pending work always has an event to wait for
+			let new_clock = self.events.peek_min().unwrap().time;
+			if new_clock > time {
+				break;
+			}
+
+			let millis = self.config.budget.seconds.and(start_time).map(|start| self.state.time_took + start.elapsed().as_millis());
+			if self.config.budget.exhausted(new_clock, self.state.started_jobs, millis) {
+				info!(clock = self.clock, started_jobs = self.state.started_jobs, "budget exhausted, stopping the simulation");
+				self.state.truncated = true;
+				break;
+			}
 
-			let (new_clock, event) = self.events.pop_min().unwrap(); // we already checked that the queue is not empty
 			// assert!(new_clock >= self.clock);
+			if new_clock > self.clock {
+				self.scheduler.on_tick(new_clock);
+				for observer in &mut self.observers {
+					observer.on_advance(self.clock, new_clock);
+				}
+				self.state.reservation_delay += self.state.blocked_by_reservations as u64 * (new_clock - self.clock);
+				self.state.drain_delay += self.state.blocked_by_drains as u64 * (new_clock - self.clock);
+				self.snapshot_occupancy(new_clock);
+			}
+
 			self.clock = new_clock;
 
-			match event {
-			    Event::NewJob(job) => {
-					if self.debug >= DebugLevel::Verbose {
-						println!("\
-							DEBUG: time moved to timestamp {}. \
-							Job {} was submitted now. \
-							The queue now has {} jobs. \
-						", self.clock, job.id, queue.len() + 1);
+			if let Some(progress) = &mut self.progress {
+				progress.event(self.state.finished_jobs, self.clock);
+			}
+
+			// every event happening now is processed before the scheduler runs, including the
+			// events they add for now, unless it runs after each of them
+			while self.events.peek_min().is_some_and(|pending| pending.time == new_clock) {
+				let event = self.events.pop_min().unwrap().event;
+
+				if let Event::JobFinished(id, run) = event {
+					// the job was stopped before the end of this run
+					let current = self.running_partition(id).map(|idx| self.partitions[idx].cluster.running_jobs[&id].runs());
+					if current != Some(run) {
+						continue;
 					}
+				}
+
+				self.handle(event)?;
 
-					queue.push(job); 
+				if self.config.cycle == SchedulingCycle::Event {
+					self.schedule();
 				}
-			    Event::JobFinished(id) => {
-					self.cluster.finish_job(id);
+			}
 
-					if self.debug >= DebugLevel::Verbose {
-						println!("\
-							DEBUG: time moved to timestamp {}. \
-							Job {} finished now. \
-							The cluster now has {} nodes available. \
-						", self.clock, id, self.cluster.available_nodes);
+			if self.config.cycle != SchedulingCycle::Event {
+				self.schedule();
+			}
+
+			#[cfg(feature = "metrics")]
+			if let Some(metrics) = &self.metrics {
+				let running = self.partitions.iter().map(|partition| partition.cluster.running_jobs.len()).sum();
+				metrics.update(self.clock, self.state.finished_jobs, running, self.queued_jobs(), self.busy_nodes());
+			}
+		}
+
+		self.state.time_took += start_time.map_or(0, |start| start.elapsed().as_millis());
+		Ok(self.has_work())
+	}
+
+	/// Simulates the events of the next instant having events, returning whether work is
+	/// left. The first step also starts the simulation, with the events at time 0.
+	pub fn step(&mut self) -> Result<bool, EngineError> {
+		if !self.state.started {
+			return self.run_until(0);
+		}
+
+		match self.events.peek_min() {
+			Some(next) if self.has_work() => self.run_until(next.time),
+			_ => Ok(false),
+		}
+	}
+
+	/// Lets the scheduler start (and preempt) jobs in every partition if a scheduling cycle
+	/// is due, then samples the utilization.
+	fn schedule(&mut self) {
+		// jobs are replayed at the time they started, whatever the scheduling cycle
+		let due = self.config.validation || self.config.cycle.is_due(self.clock);
+		// jobs started by this cycle, delayed by the decisions before them, see `DecisionLatency`
+		let mut decided = 0;
+
+		for idx in 0..self.partitions.len() {
+			self.partitions[idx].cluster.fill_reservations(self.clock);
+
+			if due && self.config.malleability != Malleability::None {
+				self.resize(idx);
+			}
+
+			if !due || self.partitions[idx].queue.is_empty() {
+				continue;
+			}
+
+			debug!(clock = self.clock, partition = %self.partitions[idx].name, queue = ?self.partitions[idx].queue, "scheduling");
+
+			let partition = &mut self.partitions[idx];
+			let mut hold_ends = Vec::new();
+			for (id, mark) in self.scheduler.mark(self.clock, &partition.queue, &partition.cluster) {
+				if let Some(job) = partition.queue.mark(id, mark, self.clock) {
+					debug!(clock = self.clock, job = id, %mark, "job marked");
+					hold_ends.extend(job.held_until.filter(|&until| until != Clock::MAX).map(|until| (until, id)));
+				}
+			}
+
+			for (until, id) in hold_ends {
+				self.push_event(until, Event::HoldEnd(id));
+			}
+
+			if let Some(overhead) = self.config.preemption_overhead {
+				let partition = &mut self.partitions[idx];
+
+				for id in self.scheduler.preempt(self.clock, &partition.queue, &partition.cluster) {
+					let job = match partition.cluster.preempt_job(id, self.clock, overhead) {
+						Some(job) => job,
+						None => continue,
+					};
+
+					debug!(clock = self.clock, job = job.id, remaining = job.remaining_time, "job preempted");
+
+					self.scheduler.on_job_arrival(self.clock, &job);
+					partition.queue.push(job);
+					self.state.preemptions += 1;
+				}
+			}
+
+			// queued jobs that later jobs started before in this pass, see `Job::skips`
+			let mut skipped = HashSet::new();
+
+			while !self.partitions[idx].queue.is_empty() {
+				let partition = &mut self.partitions[idx];
+				let shaped = self.scheduler.schedule_shaped(self.clock, &partition.queue, &partition.cluster);
+
+				// schedulers unaware of the node classes or of the reservations may pick jobs that don't
+				// fit on their classes or would keep nodes from a reservation, which wait for the next event
+				let mut free = partition.cluster.free_by_class();
+				let (clock, cluster) = (self.clock, &partition.cluster);
+				let mut claimed = 0;
+				let mut shapes = HashMap::new();
+				let batch = shaped.into_iter()
+					.filter(|&(index, shape)| partition.queue.get(index).is_none_or(|job| {
+						if job.is_held() {
+							return false;
+						}
+
+						let (nodes, requested) = match shape.and_then(|shape| job.shapes.get(shape)) {
+							Some(shape) => (shape.nodes, shape.requested_run_time),
+							None => (job.nodes, job.requested_run_time),
+						};
+
+						let around_reservations = cluster.reservations().is_empty()
+							|| nodes + claimed + cluster.reserved_between(clock, clock + requested.max(1)) <= cluster.available_nodes;
+
+						let fits = around_reservations && cluster::claim_nodes(&mut free, nodes, job.classes);
+						if fits {
+							claimed += nodes;
+						}
+
+						fits
+					}))
+					.map(|(index, shape)| {
+						if let Some(shape) = shape {
+							shapes.insert(index, shape);
+						}
+						index
+					})
+					.collect::<Vec<_>>();
+
+				if batch.is_empty() {
+					break;
+				}
+
+				// take the jobs out of the queue from the last one, so the indices stay valid
+				// and the queue stays in submission order
+				let mut indices = batch.clone();
+				indices.sort_unstable_by(|a, b| b.cmp(a));
+				indices.dedup();
+				assert_eq!(indices.len(), batch.len(), "the scheduler picked a job twice");
+
+				// jobs queued before a narrower job starting now were skipped
+				let mut narrowest = u32::MAX;
+				for (index, job) in partition.queue.iter().enumerate().take(indices[0] + 1).rev() {
+					if indices.contains(&index) {
+						narrowest = narrowest.min(job.nodes);
+					} else if narrowest < job.nodes {
+						skipped.insert(job.id);
+					}
+				}
+
+				let mut taken = indices.into_iter()
+					.map(|index| (index, partition.queue.remove(index)))
+					.collect::<HashMap<_, _>>();
+				let jobs = batch.iter()
+					.map(|index| {
+						let mut job = taken.remove(index).unwrap();
+						if let Some(&shape) = shapes.get(index) {
+							job.set_shape(shape);
+						}
+						job
+					})
+					.collect::<Vec<_>>();
+
+				for job in jobs {
+					if self.config.validation && job.runs() == 0 && job.trace_start.is_some_and(|start| start < self.clock) {
+						self.state.late_starts += 1;
 					}
+
+					let mut job = job;
+					if let Some(latency) = self.config.decision_latency {
+						decided += 1;
+						let delay = latency.delay(decided);
+						job.delay_start(self.clock, delay);
+						self.state.decision_overhead += delay * job.nodes as u64;
+					}
+
+					let partition = &mut self.partitions[idx];
+					let (id, run) = (job.id, job.runs());
+					partition.cluster.schedule_job(job, self.clock);
+					self.state.started_jobs += 1;
+					for observer in &mut self.observers {
+						observer.on_start(self.clock, &partition.cluster.running_jobs[&id]);
+					}
+
+					let end_time = self.config.walltime_limit.end_time(&partition.cluster.running_jobs[&id]);
+					self.push_event(end_time, Event::JobFinished(id, run));
+
+					if self.explains() {
+						self.log_decision(idx, id, Reason::Started);
+					}
+
+				}
+
+				self.record_queue();
+
+				if self.scheduler.complete_batches() {
+					break;
 				}
 			}
+
+			self.partitions[idx].queue.skip(&skipped);
+
+			if self.explains() {
+				let partition = &self.partitions[idx];
+				let held = partition.queue.iter().filter_map(|job| Some((job.id, job.held_until?))).collect::<HashMap<_, _>>();
+				for decision in self.scheduler.explain(self.clock, &partition.queue, &partition.cluster) {
+					let reason = match held.get(&decision.job_id) {
+						Some(&until) => Reason::Held { until: (until != Clock::MAX).then_some(until) },
+						None => decision.reason,
+					};
+					self.log_decision(idx, decision.job_id, reason);
+				}
+			}
+		}
+
+		let clock = self.clock;
+		self.state.blocked_by_reservations = self.partitions.iter()
+			.filter(|partition| !partition.cluster.reservations().is_empty())
+			.flat_map(|partition| partition.queue.iter().filter(move |job| partition.cluster.fits(job) && !partition.cluster.fits_around_reservations(job, clock)))
+			.count();
+		self.state.blocked_by_drains = self.partitions.iter()
+			.filter(|partition| partition.cluster.drained_nodes() > 0)
+			.flat_map(|partition| partition.queue.iter().filter(move |job| !partition.cluster.fits(job) && job.nodes <= partition.cluster.available_nodes + partition.cluster.drained_nodes()))
+			.count();
+
+		// the node classes, the reservations or the scheduler itself may leave the first job waiting
+		for partition in self.partitions.iter().filter(|_| due) {
+			if partition.queue.first().is_some_and(|job| job.nodes <= partition.cluster.available_nodes) {
+				self.state.fragmentation.stall();
+			}
+		}
+
+		let holes = self.partitions.iter()
+			.filter(|partition| !partition.queue.is_empty())
+			.map(|partition| partition.cluster.available_nodes)
+			.sum();
+		let stretches = self.partitions.iter().flat_map(|partition| partition.cluster.free_blocks().map(|block| block.end - block.start));
+		self.state.fragmentation.record(self.clock, stretches, holes);
+		self.record_queue();
+
+		if let Some(utilization) = &mut self.state.utilization {
+			let busy = self.partitions.iter()
+				.map(|partition| partition.cluster.total_nodes - partition.cluster.available_nodes - partition.cluster.down_nodes() - partition.cluster.reserved_nodes())
+				.sum();
+			utilization.record(self.clock, busy, self.total_nodes);
 		}
 
+		self.next_slice();
+		self.next_cycle();
+	}
+
+	/// Shrinks or expands the running malleable jobs of a partition as the scheduler asks,
+	/// within their bounds and around the reservations, see `Scheduler::resize`.
+	fn resize(&mut self, idx: usize) {
+		let speedup = self.config.malleability.speedup();
+		let partition = &mut self.partitions[idx];
+		let mut ends = Vec::new();
+
+		for (id, nodes) in self.scheduler.resize(self.clock, &partition.queue, &partition.cluster) {
+			let cluster = &mut partition.cluster;
+			let (current, nodes, requested) = match cluster.running_jobs.get(&id) {
+				Some(job) => match job.malleable {
+					Some((min, max)) => (job.nodes, nodes.clamp(min, max), job.requested_remaining_time()),
+					None => continue,
+				},
+				None => continue,
+			};
+
+			let around_reservations = nodes <= current || cluster.reservations().is_empty()
+				|| nodes - current + cluster.reserved_between(self.clock, self.clock + requested.max(1)) <= cluster.available_nodes;
+
+			if nodes == current || !around_reservations || !cluster.resize_job(id, nodes, self.clock, speedup) {
+				continue;
+			}
+
+			let job = &cluster.running_jobs[&id];
+			debug!(clock = self.clock, job = id, from = current, to = nodes, remaining = job.remaining_time, "job resized");
+
+			ends.push((self.config.walltime_limit.end_time(job), id, job.runs()));
+			self.state.resizes += 1;
+		}
+
+		for (time, id, run) in ends {
+			self.push_event(time, Event::JobFinished(id, run));
+		}
+	}
+
+	/// Applies an event to the cluster, queues and pending events.
+	fn handle(&mut self, event: Event) -> Result<(), EngineError> {
+		match event {
+		    Event::NewJob(job) => {
+				if !self.submit(job) {
+					self.arrivals.unread();
+				}
+
+				self.submit_next()?;
+			}
+			Event::JobReleased(job) => {
+				self.dependencies.release();
+				self.enqueue(job);
+			}
+			Event::UserJob(job) => {
+				self.users.release();
+				self.submit(job);
+			}
+			Event::NodesDown(outage) => {
+				for node in outage.first_node..outage.first_node + outage.nodes {
+					let partition = match self.partitions.iter_mut().find(|partition| partition.cluster.contains_node(node)) {
+						Some(partition) => partition,
+						None => continue,
+					};
+
+					if let Some(job) = partition.cluster.fail_node(node, self.clock) {
+						debug!(clock = self.clock, job = job.id, node, "job killed by a node failure and submitted again");
+
+						for observer in &mut self.observers {
+							observer.on_kill(self.clock, &job, KillReason::NodeFailure);
+						}
+						self.scheduler.on_job_arrival(self.clock, &job);
+						partition.queue.push(job);
+						self.state.resubmissions += 1;
+					}
+				}
+
+				debug!(clock = self.clock, first_node = outage.first_node, nodes = outage.nodes, duration = outage.duration, "nodes down");
+
+				self.push_event(self.clock + outage.duration, Event::NodesUp(outage));
+				self.next_outage();
+			}
+			Event::JobCancelled(id) => {
+				// jobs the simulation started earlier than the trace run for no time instead
+				let found = self.partitions.iter().enumerate().find_map(|(idx, partition)| {
+					partition.queue.iter().position(|job| job.id == id && job.runs() == 0).map(|index| (idx, index))
+				});
+
+				let job = match found {
+					Some((idx, index)) => self.partitions[idx].queue.remove(index),
+					None => return Ok(()),
+				};
+
+				debug!(clock = self.clock, job = id, waited = self.clock - job.submit_time, "job cancelled while queued");
+
+				self.scheduler.on_job_cancel(self.clock, &job);
+				self.state.cancelled_jobs += 1;
+				self.state.cancellation_wait += self.clock - job.submit_time;
+
+				self.record_queue();
+
+				// jobs depending on the cancelled one don't wait for it any longer
+				for (time, job) in self.dependencies.finish(id, self.clock) {
+					self.push_event(time, Event::JobReleased(job));
+				}
+
+				if let Some((time, job)) = self.users.finished(&job, self.clock) {
+					self.push_event(time, Event::UserJob(job));
+				}
+			}
+			Event::Cycle => {
+				self.state.next_cycle = None;
+			}
+			// only wakes the scheduler up
+			Event::TraceStart(_) => {}
+			Event::HoldEnd(id) => {
+				let clock = self.clock;
+				for partition in &mut self.partitions {
+					if partition.queue.iter().any(|job| job.id == id && job.held_until.is_some_and(|until| until <= clock)) {
+						partition.queue.mark(id, Mark::Release, clock);
+						debug!(clock, job = id, "job hold ended");
+					}
+				}
+			}
+			Event::SliceEnd => {
+				self.state.slice_end = None;
+
+				if let Some(slicing) = self.config.time_slicing {
+					self.switch_rows(slicing.overhead);
+				}
+			}
+			Event::NodesUp(outage) => {
+				for node in outage.first_node..outage.first_node + outage.nodes {
+					if let Some(partition) = self.partitions.iter_mut().find(|partition| partition.cluster.contains_node(node)) {
+						partition.cluster.repair_node(node, self.clock);
+					}
+				}
+
+				debug!(clock = self.clock, first_node = outage.first_node, nodes = outage.nodes, "nodes back up");
+			}
+			Event::ReservationStart(idx, window) => {
+				let partition = &mut self.partitions[idx];
+				partition.cluster.start_reservation(window, self.clock);
+
+				let reservation = &partition.cluster.reservations()[window];
+				debug!(clock = self.clock, partition = %partition.name, nodes = reservation.nodes, held = reservation.held().len(), "reservation started");
+			}
+			Event::ReservationEnd(idx, window) => {
+				self.partitions[idx].cluster.end_reservation(window, self.clock);
+
+				debug!(clock = self.clock, partition = %self.partitions[idx].name, "reservation ended");
+			}
+			Event::DrainStart(idx) => {
+				let nodes = self.config.drains[idx].node_ids(self.total_nodes);
+				for node in nodes.clone() {
+					if let Some(partition) = self.partitions.iter_mut().find(|partition| partition.cluster.contains_node(node)) {
+						partition.cluster.drain_node(node, self.clock);
+					}
+				}
+
+				let drained = self.partitions.iter().map(|partition| partition.cluster.drained_nodes()).sum::<u32>();
+				debug!(clock = self.clock, downtime = %self.config.drains[idx].name, first_node = nodes.start, nodes = nodes.len(), drained, "nodes draining");
+			}
+			Event::DrainEnd(idx) => {
+				for node in self.config.drains[idx].node_ids(self.total_nodes) {
+					if let Some(partition) = self.partitions.iter_mut().find(|partition| partition.cluster.contains_node(node)) {
+						partition.cluster.undrain_node(node, self.clock);
+					}
+				}
+
+				debug!(clock = self.clock, downtime = %self.config.drains[idx].name, "drained nodes back up");
+			}
+		    Event::JobFinished(id, _) => {
+				let idx = self.running_partition(id).expect("the job is running");
+				let partition = &mut self.partitions[idx];
+				let mut job = partition.cluster.finish_job(id, self.clock).expect("the job is running");
+
+				// the job was killed at its walltime
+				if self.clock < job.end_time() {
+					self.state.walltime_kills += 1;
+					for observer in &mut self.observers {
+						observer.on_kill(self.clock, &job, KillReason::Walltime);
+					}
+
+					match self.config.walltime_limit {
+						WalltimeLimit::Resubmit { factor, retries } if job.timeouts < retries => {
+							job.set_timed_out(factor);
+							debug!(clock = self.clock, job = id, requested = job.requested_run_time, "job killed at its walltime and submitted again");
+
+							self.scheduler.on_job_arrival(self.clock, &job);
+							partition.queue.push(job);
+							self.state.walltime_resubmissions += 1;
+							return Ok(());
+						}
+						_ => {
+							debug!(clock = self.clock, job = id, "job killed at its walltime");
+							job.set_killed();
+						}
+					}
+				}
+
+				self.state.finished_jobs += 1;
+				self.scheduler.on_job_finish(self.clock, &job);
+				for observer in &mut self.observers {
+					observer.on_finish(self.clock, &job);
+				}
+				if let Some(predictor) = &mut self.predictor {
+					predictor.on_job_finish(JobView::new(&job), job.run_time);
+				}
+
+				let clock = self.clock;
+				let cooling_down = self.config.cutoff.is_some_and(|cutoff| clock > cutoff);
+				if job.submit_index >= self.config.warmup && !cooling_down && !job.preloaded {
+					self.state.metrics.push(&job, self.clock, self.config.slowdown_tau);
+					if !self.arrivals.spec.partitions.is_empty() {
+						partition.metrics.push(&job, self.clock, self.config.slowdown_tau);
+					}
+				} else {
+					self.state.trimmed_jobs += 1;
+				}
+
+				if self.config.record_jobs {
+					self.state.job_records.push(JobRecord::new(&self.label, self.total_nodes, self.config.seed, self.config.load_factor, &job, self.clock, self.config.slowdown_tau));
+				}
+
+				debug!(clock = self.clock, job = id, partition = %partition.name, available_nodes = partition.cluster.available_nodes, "job finished");
+
+				for (time, job) in self.dependencies.finish(id, self.clock) {
+					self.push_event(time, Event::JobReleased(job));
+				}
+
+				if let Some((time, job)) = self.users.finished(&job, self.clock) {
+					self.push_event(time, Event::UserJob(job));
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Suspends the running jobs of every partition having another row of jobs to run, and
+	/// resumes the jobs of that row, see `TimeSlicing`.
+	fn switch_rows(&mut self, overhead: Clock) {
+		let clock = self.clock;
+		let mut ends = Vec::new();
+
+		for partition in &mut self.partitions {
+			let row = match partition.gang.next_row(!partition.queue.is_empty()) {
+				Some(row) => row,
+				None => continue,
+			};
+
+			let mut running = partition.cluster.running_jobs.keys().copied().collect::<Vec<_>>();
+			running.sort_unstable();
+
+			let suspended = running.into_iter()
+				.filter_map(|id| partition.cluster.suspend_job(id, clock, overhead))
+				.collect();
+
+			self.state.context_switches += 1;
+
+			for job in partition.gang.switch(suspended, row) {
+				// nodes may have failed while the job was suspended
+				if !partition.cluster.fits(&job) {
+					self.scheduler.on_job_arrival(clock, &job);
+					partition.queue.push(job);
+					continue;
+				}
+
+				self.state.switch_overhead += job.restart_overhead * job.nodes as u64;
+
+				let (id, run) = (job.id, job.runs());
+				partition.cluster.schedule_job(job, clock);
+				for observer in &mut self.observers {
+					observer.on_start(clock, &partition.cluster.running_jobs[&id]);
+				}
+				ends.push((self.config.walltime_limit.end_time(&partition.cluster.running_jobs[&id]), id, run));
+			}
+		}
+
+		for (time, id, run) in ends {
+			self.push_event(time, Event::JobFinished(id, run));
+		}
+	}
+
+	/// Adds the end of the current time slice to the events, when time slicing and some
+	/// partition has another row of jobs to run.
+	fn next_slice(&mut self) {
+		let slicing = match self.config.time_slicing {
+			Some(slicing) if self.state.slice_end.is_none() => slicing,
+			_ => return,
+		};
+
+		if self.partitions.iter().any(|partition| partition.gang.next_row(!partition.queue.is_empty()).is_some()) {
+			let end = self.clock + slicing.quantum;
+			self.state.slice_end = Some(end);
+			self.push_event(end, Event::SliceEnd);
+		}
+	}
+
+	/// Adds the next scheduling cycle to the events, when the scheduler runs every N seconds
+	/// and some job waits for it.
+	fn next_cycle(&mut self) {
+		let period = match self.config.cycle {
+			SchedulingCycle::Every(period) if self.state.next_cycle.is_none() => period,
+			_ => return,
+		};
+
+		if self.queued_jobs() > 0 {
+			let next = (self.clock / period + 1) * period;
+			self.state.next_cycle = Some(next);
+			self.push_event(next, Event::Cycle);
+		}
+	}
+
+	/// Metrics of the run, once `run_until` simulated every job or exhausted the budget.
+	fn report(&mut self) -> EngineReport {
 		// making sure we emptied the queue too when we finished all events
-		assert!(queue.is_empty());
+		assert!(self.state.truncated || self.queued_jobs() == 0);
 
-		wait_times.sort_unstable();
+		if let Some(progress) = &self.progress {
+			progress.finish(self.state.finished_jobs, self.clock);
+		}
 
-		let total_wait 	= wait_times.iter().sum();
-		let avg_wait 	= total_wait as f64 / wait_times.len() as f64;
-		let median_wait = wait_times[wait_times.len() / 2];
-		let min_wait 	= *wait_times.first().unwrap();
-		let max_wait 	= *wait_times.last().unwrap();
+		#[cfg(feature = "metrics")]
+		if let Some(metrics) = &self.metrics {
+			metrics.finish();
+		}
+
+		let (utilization_timeline, utilization) = match self.state.utilization.take() {
+			Some(mut timeline) => {
+				timeline.record(self.clock, self.busy_nodes(), self.total_nodes);
+				let (samples, summary) = timeline.finish(self.clock);
+				(samples, Some(summary))
+			}
+			None => (Vec::new(), None),
+		};
+
+		let (free_stretch, stalls, backfill_holes) = self.state.fragmentation.finish(self.clock);
+		let backlog = self.state.backlog.finish(self.clock);
+
+		let mut metrics = std::mem::take(&mut self.state.metrics);
+		let jobs = metrics.wait_times.len();
+
+		let by_width = BucketReport::breakdown(WIDTH_BUCKETS, &metrics.by_width);
+		let by_run_time = BucketReport::breakdown(RUN_TIME_BUCKETS, &metrics.by_run_time);
+		let by_skips = BucketReport::breakdown(SKIP_BUCKETS, &metrics.by_skips);
+		let by_user = OwnerReport::breakdown(&metrics.users);
+		let by_group = OwnerReport::breakdown(&metrics.groups);
+		let by_qos = QosReport::breakdown(&self.config.qos, &metrics.qos);
+		let predictions = metrics.predictions.finish();
+
+		let total_wait 	= metrics.total_wait;
+		let avg_wait 	= total_wait as f64 / jobs.max(1) as f64;
+		let median_wait = metrics.wait_times.summary().median.round() as Clock;
+		let min_wait 	= metrics.wait_times.min() as Clock;
+		let max_wait 	= metrics.wait_times.max() as Clock;
 
-		let total_res = self.clock * self.cluster.total_nodes as u64;
-		println!("{} {} {} {}", self.clock, self.cluster.total_nodes, total_res, self.cluster.used_resources);
-		let idle = total_res - self.cluster.used_resources as u64;
+		let used_resources = self.partitions.iter().map(|partition| partition.cluster.used_resources).sum::<u64>();
+		let downtime = self.partitions.iter().map(|partition| partition.cluster.downtime(self.clock)).sum::<u64>();
+		let drain_downtime = self.partitions.iter().map(|partition| partition.cluster.drain_downtime(self.clock)).sum::<u64>();
+		let total_res = self.clock * self.total_nodes as u64;
+		let idle = total_res - used_resources;
+		let energy = self.config.power.energy(used_resources, idle - downtime - drain_downtime);
 
+		let (min_node_usage, max_node_usage) = self.partitions.iter()
+			.flat_map(|partition| partition.cluster.node_usage(self.clock))
+			.fold((f64::INFINITY, 0f64), |(min, max), usage| (min.min(usage), max.max(usage)));
+
+		let used_memory = self.partitions.iter().map(|partition| partition.cluster.used_memory).sum::<u64>();
+		let memory_capacity = self.partitions.iter().map(|partition| partition.cluster.memory_capacity()).sum::<Option<u64>>();
+
+		let (asked, obtained) = self.partitions.iter()
+			.map(|partition| partition.cluster.reservation_usage(self.clock))
+			.fold((0, 0), |(asked, obtained), (a, o)| (asked + a, obtained + o));
+
+		let makespan = self.clock;
+		let partitions = if self.arrivals.spec.partitions.is_empty() {
+			Vec::new()
+		} else {
+			self.partitions.iter_mut().map(|partition| {
+				let cluster = &partition.cluster;
+				let metrics = &mut partition.metrics;
+				let total = makespan * cluster.total_nodes as u64;
+
+				PartitionReport {
+					name: partition.name.clone(),
+					nodes: cluster.total_nodes,
+					jobs: metrics.wait_times.len(),
+					avg_wait: metrics.total_wait as f64 / metrics.wait_times.len().max(1) as f64,
+					max_wait: metrics.wait_times.max() as Clock,
+					used_ressources: cluster.used_resources,
+					idle_percent: (total - cluster.used_resources) as f64 * 100f64 / total.max(1) as f64,
+					bounded_slowdown: metrics.bounded_slowdowns.summary(),
+					remote_jobs: partition.remote_jobs,
+				}
+			}).collect()
+		};
 
 		EngineReport {
-			scheduler_name: self.scheduler.name(),
+			workload: self.workload.clone(),
+			scheduler_name: self.label.clone(),
+			nodes: self.total_nodes,
+			cores_per_node: self.arrivals.cores_per_node,
+			walltime: self.config.walltime.to_string(),
+			power: self.config.power.to_string(),
+			allocation: self.config.allocation.to_string(),
+			seed: self.config.seed,
+			load_factor: self.config.load_factor,
+			jobs,
+			warmup: self.config.warmup,
+			cutoff: self.config.cutoff,
+			truncated: self.state.truncated,
+			trimmed_jobs: self.state.trimmed_jobs,
+			rejected_jobs: self.rejections.values().sum(),
+			rejected_work: self.state.rejected_work,
+			rejections: self.rejections.clone(),
+			cancelled_jobs: self.state.cancelled_jobs,
+			cancellation_wait: self.state.cancellation_wait,
+			malformed_lines: self.arrivals.malformed,
+			incomplete_records: self.arrivals.incomplete,
+			remote_jobs: self.partitions.iter().map(|partition| partition.remote_jobs).sum(),
 
 			makespan: self.clock,
-			total_completion_time: completion_times.iter().sum(),
+			total_completion_time: metrics.total_completion_time,
 			min_wait,
 			max_wait,
 			avg_wait,
 			median_wait,
 			total_wait,
 
-			used_ressources: self.cluster.used_resources,
+			used_ressources: used_resources,
 			idle,
 			idle_percent: idle as f64 * 100f64 / total_res as f64,
+			min_node_usage,
+			max_node_usage,
+			memory_utilization: memory_capacity.map(|capacity| used_memory as f64 / (capacity as f64 * self.clock.max(1) as f64)),
+
+			slowdown_tau: self.config.slowdown_tau,
+			slowdown: metrics.slowdowns.summary(),
+			bounded_slowdown: metrics.bounded_slowdowns.summary(),
+			user_fairness: OwnerReport::fairness(&by_user),
+			group_fairness: OwnerReport::fairness(&by_group),
+			slo_violations: by_qos.iter().map(|class| class.violations).sum(),
+			skips: metrics.skips.summary(),
+			preemptions: self.state.preemptions,
+			resizes: self.state.resizes,
+			context_switches: self.state.context_switches,
+			switch_overhead: self.state.switch_overhead,
+			decision_overhead: self.state.decision_overhead,
+			resubmissions: self.state.resubmissions,
+			walltime_kills: self.state.walltime_kills,
+			walltime_resubmissions: self.state.walltime_resubmissions,
+			downtime,
+			reservation_utilization: (asked > 0).then(|| obtained as f64 / asked as f64),
+			reservation_delay: self.state.reservation_delay,
+			drain_downtime,
+			drain_delay: self.state.drain_delay,
+			late_starts: self.config.validation.then_some(self.state.late_starts),
+			dependency_wait: metrics.dependency_wait,
+			ignored_dependencies: self.dependencies.ignored,
+			generated_jobs: self.users.generated,
+			energy,
+			energy_per_job: energy / jobs.max(1) as f64,
+			energy_delay_product: energy * self.clock as f64,
+			utilization,
+			free_stretch,
+			stalls,
+			backfill_holes,
+			avg_queue_length: backlog.avg_queue_length,
+			max_queue_length: backlog.max_queue_length,
+			avg_backlog: backlog.avg_backlog,
+			max_backlog: backlog.max_backlog,
+			saturated_time: backlog.saturated_time,
+			predicted_jobs: predictions.jobs,
+			prediction_error: predictions.error,
+			prediction_accuracy: predictions.accuracy,
+			request_accuracy: predictions.request_accuracy,
+			underpredicted: predictions.underpredicted,
+			allocation_span: metrics.spans.summary(),
+			allocation_blocks: metrics.blocks as f64 / metrics.spans.len().max(1) as f64,
+			partitions,
+			by_width,
+			by_run_time,
+			by_skips,
+			by_user,
+			by_group,
+			by_qos,
+
+			time_took: self.state.time_took,
 
-			time_took: start_time.elapsed().unwrap().as_millis()
+			job_records: std::mem::take(&mut self.state.job_records),
+			rejects: std::mem::take(&mut self.state.rejects),
+			queue_timeline: std::mem::take(&mut self.state.queue_timeline),
+			utilization_timeline,
+			decisions: std::mem::take(&mut self.state.decisions),
+			occupancy: std::mem::take(&mut self.state.occupancy),
 		}
 	}
 }
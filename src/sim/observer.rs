@@ -0,0 +1,60 @@
+use std::sync::{Arc, Mutex};
+
+use super::{Clock, job::Job};
+
+/// Why a running job was killed, see `Observer::on_kill`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillReason {
+	/// It ran up to its requested time, see `WalltimeLimit`
+	Walltime,
+	/// One of its nodes failed, see `FailureModel`
+	NodeFailure,
+}
+
+/// Follows a run as it goes, to collect metrics, draw or log it without changing the engine,
+/// see `Engine::add_observer`. Every callback does nothing by default.
+///
+/// Jobs preempted, suspended or killed and submitted again go back to their queue without a
+/// call to `on_submit`, and `on_start` is called again when they restart. Observers aren't
+/// part of engine snapshots.
+pub trait Observer: Send {
+	/// A job entered the queue of its partition (`job.partition_idx`), once admitted and once
+	/// the job it depends on finished.
+	fn on_submit(&mut self, _clock: Clock, _job: &Job) {}
+
+	/// A job started, on the nodes of `job.allocation`.
+	fn on_start(&mut self, _clock: Clock, _job: &Job) {}
+
+	/// A job finished, including the jobs killed at their walltime and not submitted again.
+	fn on_finish(&mut self, _clock: Clock, _job: &Job) {}
+
+	/// A running job was killed, before it finishes or goes back to its queue.
+	fn on_kill(&mut self, _clock: Clock, _job: &Job, _reason: KillReason) {}
+
+	/// The simulated time moved from `from` to `to`, before the events of the new time.
+	fn on_advance(&mut self, _from: Clock, _to: Clock) {}
+}
+
+/// Lets the caller keep a handle on the observer and read what it collected once the run is
+/// done, e.g. `engine.add_observer(Box::new(counter.clone()))`.
+impl<O: Observer> Observer for Arc<Mutex<O>> {
+	fn on_submit(&mut self, clock: Clock, job: &Job) {
+		self.lock().unwrap().on_submit(clock, job);
+	}
+
+	fn on_start(&mut self, clock: Clock, job: &Job) {
+		self.lock().unwrap().on_start(clock, job);
+	}
+
+	fn on_finish(&mut self, clock: Clock, job: &Job) {
+		self.lock().unwrap().on_finish(clock, job);
+	}
+
+	fn on_kill(&mut self, clock: Clock, job: &Job, reason: KillReason) {
+		self.lock().unwrap().on_kill(clock, job, reason);
+	}
+
+	fn on_advance(&mut self, from: Clock, to: Clock) {
+		self.lock().unwrap().on_advance(from, to);
+	}
+}
@@ -0,0 +1,82 @@
+use std::{fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, cluster::NodeSet};
+
+/// Interval of the snapshots when only their file is given, in seconds.
+pub const DEFAULT_OCCUPANCY_INTERVAL: Clock = 3600;
+
+/// Which jobs occupy which nodes at a point of the simulated time, taken every
+/// `EngineConfig::occupancy` seconds so that dashboards and notebooks can animate the machine.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OccupancySnapshot {
+	/// Left empty when read back, see `Engine::restore`
+	#[serde(skip_deserializing)]
+	pub scheduler: String,
+	/// Size of the simulated cluster
+	pub cluster_nodes: u32,
+	pub clock: Clock,
+	pub partitions: Vec<PartitionOccupancy>,
+}
+
+/// State of the nodes and of the queue of a partition in an `OccupancySnapshot`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartitionOccupancy {
+	pub name: String,
+	pub first_node: u32,
+	pub nodes: u32,
+	pub free: u32,
+	/// Nodes failed or drained, see `Cluster::down_nodes`
+	pub down: u32,
+	/// Nodes held by the reservations
+	pub reserved: u32,
+	pub queued_jobs: usize,
+	/// Nodes requested by the queued jobs altogether
+	pub queued_nodes: u64,
+	/// Running jobs, by ID
+	pub running: Vec<RunningJob>,
+}
+
+/// A job running when an `OccupancySnapshot` was taken.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunningJob {
+	pub id: u32,
+	pub user_id: Option<u32>,
+	pub nodes: u32,
+	pub start: Clock,
+	/// End the scheduler expects, from the estimated run time of the job
+	pub expected_end: Clock,
+	pub allocation: NodeSet,
+}
+
+/// Writes the occupancy snapshots of one or more runs as JSON lines.
+pub struct OccupancyWriter<W: Write> {
+	out: W,
+}
+
+impl OccupancyWriter<BufWriter<File>> {
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let file = File::create(path)?;
+		Ok(Self::new(BufWriter::new(file)))
+	}
+}
+
+impl<W: Write> OccupancyWriter<W> {
+	pub fn new(out: W) -> Self {
+		Self { out }
+	}
+
+	pub fn write_run(&mut self, snapshots: &[OccupancySnapshot]) -> io::Result<()> {
+		for snapshot in snapshots {
+			serde_json::to_writer(&mut self.out, snapshot)?;
+			writeln!(self.out)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.out.flush()
+	}
+}
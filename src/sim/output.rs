@@ -0,0 +1,229 @@
+use std::{fs, io, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, comparison::Comparison, confidence::Confidence, decision::DecisionLogWriter, joblog::JobLogWriter, occupancy::OccupancyWriter, rejects::RejectsWriter, report::{EngineReport, ReportWriter}, trace::TraceWriter, utilization::UtilizationWriter};
+
+/// What an experiment directory holds, written to its `manifest.json`, see `OutputManager`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+	pub name: String,
+	/// Version of chamallow the experiment ran with
+	pub version: String,
+	/// Seconds since the Unix epoch
+	pub created: u64,
+	/// Copy of the configuration file, if the experiment was read from one
+	pub config: Option<String>,
+	/// Files of the whole experiment, relative to its directory
+	pub files: Vec<String>,
+	pub runs: Vec<RunEntry>,
+}
+
+/// A run of an experiment and the files of its directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunEntry {
+	/// Directory of the run, relative to the one of the experiment
+	pub directory: String,
+	pub workload: String,
+	pub nodes: u32,
+	pub scheduler: String,
+	pub seed: u64,
+	#[serde(default = "default_load_factor")]
+	pub load_factor: f64,
+	pub jobs: usize,
+	pub makespan: Clock,
+	pub avg_wait: f64,
+	/// Files of the run, relative to its directory
+	pub files: Vec<String>,
+}
+
+fn default_load_factor() -> f64 {
+	1.0
+}
+
+/// Writes the results of an experiment to a directory of their own, described by a manifest,
+/// so that sweeps leave organized artifacts that can be told apart and read back:
+///
+/// ```text
+/// results/sweep/
+///   manifest.json       what was run and where its results are, see `Manifest`
+///   config.toml         copy of the configuration file
+///   reports.csv         one report per run
+///   comparison.csv      the schedulers compared to the baseline
+///   confidence.csv      confidence intervals over the seeds, when the runs were repeated
+///   runs/003-intrepid-1024-EasyBackfill-x1.5/
+///     report.json  jobs.csv  timeline.json  decisions.jsonl  utilization.csv  occupancy.jsonl  rejects.csv
+/// ```
+///
+/// Runs only get the files they have records for, and their load factor at the end of the
+/// name of their directory unless it is 1. An experiment run again gets a new
+/// directory, numbered after its name, rather than overwriting the previous results.
+pub struct OutputManager {
+	root: PathBuf,
+	manifest: Manifest,
+}
+
+impl OutputManager {
+	/// Creates the directory of an experiment in `parent`.
+	pub fn create<P: AsRef<Path>>(parent: P, name: &str) -> io::Result<Self> {
+		let parent = parent.as_ref();
+		fs::create_dir_all(parent)?;
+
+		let name = sanitize(name);
+		let mut root = parent.join(&name);
+		let mut attempt = 1;
+		while let Err(why) = fs::create_dir(&root) {
+			if why.kind() != io::ErrorKind::AlreadyExists {
+				return Err(why);
+			}
+
+			attempt += 1;
+			root = parent.join(format!("{}-{}", name, attempt));
+		}
+
+		let created = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+
+		Ok(Self {
+			manifest: Manifest {
+				name: root.file_name().map_or(name, |name| name.to_string_lossy().into_owned()),
+				version: env!("CARGO_PKG_VERSION").to_string(),
+				created,
+				config: None,
+				files: Vec::new(),
+				runs: Vec::new(),
+			},
+			root,
+		})
+	}
+
+	/// Directory of the experiment.
+	pub fn path(&self) -> &Path {
+		&self.root
+	}
+
+	/// Copies the configuration file of the experiment into its directory.
+	pub fn copy_config<P: AsRef<Path>>(&mut self, config: P) -> io::Result<()> {
+		let config = config.as_ref();
+		let name = match config.extension() {
+			Some(extension) => format!("config.{}", extension.to_string_lossy()),
+			None => "config".to_string(),
+		};
+
+		fs::copy(config, self.root.join(&name))?;
+		self.manifest.config = Some(name.clone());
+		self.manifest.files.push(name);
+		Ok(())
+	}
+
+	/// Writes the report of a run and its records to a directory of the run.
+	pub fn write_run(&mut self, report: &EngineReport) -> io::Result<()> {
+		let workload = Path::new(&report.workload).file_name().map_or_else(|| report.workload.clone(), |name| name.to_string_lossy().into_owned());
+		let workload = workload.split('.').next().unwrap_or_default();
+		let mut name = format!("{}-{}-{}", workload, report.nodes, report.scheduler_name);
+		if report.load_factor != 1.0 {
+			name += &format!("-x{}", report.load_factor);
+		}
+		let directory = format!("runs/{:03}-{}", self.manifest.runs.len(), sanitize(&name));
+		let path = self.root.join(&directory);
+		fs::create_dir_all(&path)?;
+
+		let mut files = vec!["report.json".to_string()];
+		fs::write(path.join("report.json"), report.to_json() + "\n")?;
+
+		if !report.job_records.is_empty() {
+			let mut writer = JobLogWriter::create(path.join("jobs.csv"))?;
+			writer.write_run(&report.job_records)?;
+			writer.finish()?;
+			files.push("jobs.csv".to_string());
+		}
+
+		if !report.job_records.is_empty() || !report.queue_timeline.is_empty() {
+			let mut writer = TraceWriter::create(path.join("timeline.json"))?;
+			writer.write_run(report)?;
+			writer.finish()?;
+			files.push("timeline.json".to_string());
+		}
+
+		if !report.rejects.is_empty() {
+			let mut writer = RejectsWriter::create(path.join("rejects.csv"))?;
+			writer.write_run(&report.rejects)?;
+			writer.flush()?;
+			files.push("rejects.csv".to_string());
+		}
+
+		if !report.decisions.is_empty() {
+			let mut writer = DecisionLogWriter::create(path.join("decisions.jsonl"))?;
+			writer.write_run(&report.decisions)?;
+			writer.flush()?;
+			files.push("decisions.jsonl".to_string());
+		}
+
+		if !report.utilization_timeline.is_empty() {
+			let mut writer = UtilizationWriter::create(path.join("utilization.csv"))?;
+			writer.write_run(report)?;
+			writer.finish()?;
+			files.push("utilization.csv".to_string());
+		}
+
+		if !report.occupancy.is_empty() {
+			let mut writer = OccupancyWriter::create(path.join("occupancy.jsonl"))?;
+			writer.write_run(&report.occupancy)?;
+			writer.flush()?;
+			files.push("occupancy.jsonl".to_string());
+		}
+
+		self.manifest.runs.push(RunEntry {
+			directory,
+			workload: report.workload.clone(),
+			nodes: report.nodes,
+			scheduler: report.scheduler_name.clone(),
+			seed: report.seed,
+			load_factor: report.load_factor,
+			jobs: report.jobs,
+			makespan: report.makespan,
+			avg_wait: report.avg_wait,
+			files,
+		});
+
+		Ok(())
+	}
+
+	/// Writes the reports of every run, their comparison, their confidence intervals if they
+	/// were repeated and the manifest, returning the directory of the experiment.
+	pub fn finish(mut self, reports: &[EngineReport], baseline: Option<&str>) -> io::Result<PathBuf> {
+		let mut writer = ReportWriter::create(self.root.join("reports.csv"))?;
+		for report in reports {
+			writer.write(report)?;
+		}
+		writer.flush()?;
+		self.manifest.files.push("reports.csv".to_string());
+
+		if !reports.is_empty() {
+			Comparison::new(reports, baseline).save_csv(self.root.join("comparison.csv"))?;
+			self.manifest.files.push("comparison.csv".to_string());
+		}
+
+		let confidence = Confidence::new(reports);
+		if confidence.is_repeated() {
+			confidence.save_csv(self.root.join("confidence.csv"))?;
+			self.manifest.files.push("confidence.csv".to_string());
+		}
+
+		let manifest = serde_json::to_string_pretty(&self.manifest).expect("a Manifest is always serializable");
+		fs::write(self.root.join("manifest.json"), manifest + "\n")?;
+		Ok(self.root)
+	}
+}
+
+/// Name usable as a file name, other characters than letters, digits, `-`, `_`, `=` and
+/// `.` being replaced by `_`.
+fn sanitize(name: &str) -> String {
+	let name = name.chars()
+		.map(|c| if c.is_ascii_alphanumeric() || "-_=.".contains(c) { c } else { '_' })
+		.collect::<String>();
+
+	match name.trim_matches(|c| c == '_' || c == '.') {
+		"" => "experiment".to_string(),
+		name => name.to_string(),
+	}
+}
@@ -0,0 +1,120 @@
+use std::{io::{self, Write}, sync::Arc};
+
+use parquet::{
+	basic::{ConvertedType, Repetition, Type as PhysicalType},
+	data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type},
+	errors::ParquetError,
+	file::{properties::WriterProperties, writer::SerializedFileWriter},
+	schema::types::Type,
+};
+
+/// Values of a column in a row group, a single physical type each.
+pub enum Values {
+	Int(Vec<i64>),
+	OptionalInt(Vec<Option<i64>>),
+	Float(Vec<f64>),
+	/// UTF-8 strings
+	Text(Vec<String>),
+}
+
+impl Values {
+	fn len(&self) -> usize {
+		match self {
+			Values::Int(values) => values.len(),
+			Values::OptionalInt(values) => values.len(),
+			Values::Float(values) => values.len(),
+			Values::Text(values) => values.len(),
+		}
+	}
+
+	/// Schema of a column holding these values.
+	fn column(&self, name: &str) -> Result<Type, ParquetError> {
+		let (kind, repetition) = match self {
+			Values::Int(_) => (PhysicalType::INT64, Repetition::REQUIRED),
+			Values::OptionalInt(_) => (PhysicalType::INT64, Repetition::OPTIONAL),
+			Values::Float(_) => (PhysicalType::DOUBLE, Repetition::REQUIRED),
+			Values::Text(_) => (PhysicalType::BYTE_ARRAY, Repetition::REQUIRED),
+		};
+
+		let converted = match self {
+			Values::Text(_) => ConvertedType::UTF8,
+			_ => ConvertedType::NONE,
+		};
+
+		Type::primitive_type_builder(name, kind).with_repetition(repetition).with_converted_type(converted).build()
+	}
+}
+
+/// A Parquet file being written with the `parquet` crate, holding flat columns of integers,
+/// floats and strings, with a row group per call of `write_row_group`. The file is only
+/// readable once `finish` wrote its footer.
+#[derive(Default)]
+pub struct ParquetFile {
+	/// Created with the schema of the first row group, the columns being the same in every
+	/// row group. It writes to a buffer moved to the output after every row group, which
+	/// doesn't change the offsets it counts.
+	writer: Option<SerializedFileWriter<Vec<u8>>>,
+}
+
+impl ParquetFile {
+	/// Writes a row group, the columns being the same in every row group.
+	pub fn write_row_group<W: Write>(&mut self, out: &mut W, columns: &[(&str, Values)]) -> io::Result<()> {
+		let rows = columns.first().map_or(0, |(_, values)| values.len());
+		if rows == 0 {
+			return Ok(());
+		}
+
+		let writer = match &mut self.writer {
+			Some(writer) => writer,
+			None => self.writer.insert(create(columns)?),
+		};
+
+		let mut group = writer.next_row_group().map_err(io_error)?;
+		for (_, values) in columns {
+			let mut column = group.next_column().map_err(io_error)?
+				.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "a row group has more columns than the first one"))?;
+
+			match values {
+				Values::Int(values) => column.typed::<Int64Type>().write_batch(values, None, None),
+				Values::OptionalInt(values) => {
+					let levels = values.iter().map(|value| value.is_some() as i16).collect::<Vec<_>>();
+					let set = values.iter().flatten().copied().collect::<Vec<_>>();
+					column.typed::<Int64Type>().write_batch(&set, Some(&levels), None)
+				}
+				Values::Float(values) => column.typed::<DoubleType>().write_batch(values, None, None),
+				Values::Text(values) => {
+					let values = values.iter().map(|value| ByteArray::from(value.as_str())).collect::<Vec<_>>();
+					column.typed::<ByteArrayType>().write_batch(&values, None, None)
+				}
+			}.map_err(io_error)?;
+
+			column.close().map_err(io_error)?;
+		}
+
+		group.close().map_err(io_error)?;
+		out.write_all(&std::mem::take(writer.inner_mut()))
+	}
+
+	/// Writes the footer, describing the schema and the row groups.
+	pub fn finish<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+		let writer = match &mut self.writer {
+			Some(writer) => writer,
+			None => self.writer.insert(create(&[])?),
+		};
+
+		writer.finish().map_err(io_error)?;
+		out.write_all(&std::mem::take(writer.inner_mut()))
+	}
+}
+
+fn create(columns: &[(&str, Values)]) -> io::Result<SerializedFileWriter<Vec<u8>>> {
+	let fields = columns.iter().map(|(name, values)| values.column(name).map(Arc::new)).collect::<Result<Vec<_>, _>>().map_err(io_error)?;
+	let schema = Type::group_type_builder("schema").with_fields(fields).build().map_err(io_error)?;
+	let properties = WriterProperties::builder().set_created_by(concat!("chamallow version ", env!("CARGO_PKG_VERSION")).to_string()).build();
+
+	SerializedFileWriter::new(Vec::new(), Arc::new(schema), Arc::new(properties)).map_err(io_error)
+}
+
+fn io_error(why: ParquetError) -> io::Error {
+	io::Error::other(why)
+}
@@ -0,0 +1,116 @@
+use std::{convert::TryFrom, fmt::Display, str::FromStr};
+
+use serde::Deserialize;
+
+use super::{Clock, job::Job};
+
+/// Frequency and power of the nodes when running jobs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DvfsState {
+	/// Speed relative to the nominal frequency, jobs running `1 / speed` times longer
+	pub speed: f64,
+	/// Power drawn by a node running a job, in watts
+	pub watts: f64,
+}
+
+/// Power drawn by the nodes, to compare the energy used by schedulers.
+///
+/// Written `IDLE:ACTIVE` or `IDLE:ACTIVE:SPEED:WATTS` to run the jobs at a DVFS state,
+/// powers being in watts per node.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct PowerModel {
+	/// Power drawn by a node waiting for jobs
+	pub idle_watts: f64,
+	/// Power drawn by a node running a job at the nominal frequency
+	pub active_watts: f64,
+	/// State the nodes run jobs at, the nominal frequency if None
+	pub dvfs: Option<DvfsState>,
+}
+
+impl Default for PowerModel {
+	/// Rough figures for a dual socket compute node.
+	fn default() -> Self {
+		Self {
+			idle_watts: 100.0,
+			active_watts: 250.0,
+			dvfs: None,
+		}
+	}
+}
+
+impl PowerModel {
+	/// Power drawn by a node running a job.
+	pub fn running_watts(&self) -> f64 {
+		self.dvfs.map_or(self.active_watts, |state| state.watts)
+	}
+
+	/// Stretches the run time of a job to the speed of the DVFS state. The requested
+	/// run time is stretched as well, users knowing the frequency of the nodes.
+	pub fn apply(&self, job: &mut Job) {
+		if let Some(state) = self.dvfs {
+			let stretch = |time: Clock| (time as f64 / state.speed).ceil() as Clock;
+
+			job.run_time = stretch(job.run_time);
+			job.remaining_time = job.run_time;
+			job.requested_run_time = stretch(job.requested_run_time);
+		}
+	}
+
+	/// Energy in joules of nodes running jobs for `busy` node-seconds and waiting for `idle` node-seconds.
+	pub fn energy(&self, busy: u64, idle: u64) -> f64 {
+		busy as f64 * self.running_watts() + idle as f64 * self.idle_watts
+	}
+}
+
+impl FromStr for PowerModel {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid power model '{}' (expected IDLE:ACTIVE or IDLE:ACTIVE:SPEED:WATTS)", s);
+		let value = |value: &str| match value.trim().parse::<f64>() {
+			Ok(value) if value >= 0.0 && value.is_finite() => Ok(value),
+			_ => Err(invalid()),
+		};
+
+		let parts = s.split(':').collect::<Vec<_>>();
+		let (idle, active, dvfs) = match parts.as_slice() {
+			[idle, active] => (idle, active, None),
+			[idle, active, speed, watts] => {
+				let speed = value(speed)?;
+				if speed == 0.0 {
+					return Err(invalid());
+				}
+
+				(idle, active, Some(DvfsState { speed, watts: value(watts)? }))
+			}
+			_ => return Err(invalid()),
+		};
+
+		Ok(Self {
+			idle_watts: value(idle)?,
+			active_watts: value(active)?,
+			dvfs,
+		})
+	}
+}
+
+impl TryFrom<String> for PowerModel {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl Display for PowerModel {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}:{}", self.idle_watts, self.active_watts)?;
+
+		if let Some(state) = self.dvfs {
+			write!(f, ":{}:{}", state.speed, state.watts)?;
+		}
+
+		Ok(())
+	}
+}
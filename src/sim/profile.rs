@@ -0,0 +1,167 @@
+use super::{Clock, cluster::{self, Cluster}};
+
+/// Number of free nodes over time, as a step function, and of each class on clusters with
+/// several node classes. Each step holds the number of free nodes from its timestamp until the
+/// next step, the last one extending forever.
+#[derive(Debug, Clone)]
+pub struct Profile {
+	steps: Vec<(Clock, u32)>,
+	/// Indices of the classes in the order the cluster allocates them, see `Cluster::free_by_class`
+	classes: Vec<usize>,
+	/// Free nodes of each class at each step, a row of `classes.len()` values per step, empty
+	/// with a single class
+	by_class: Vec<u32>,
+}
+
+impl Profile {
+	/// Builds the profile of the cluster at the given time, assuming running jobs end when
+	/// expected from their estimate, see `Job::estimated_end`. Jobs running past it are
+	/// expected to end at the next time step, as they still hold their nodes right now.
+	pub fn new(clock: Clock, cluster: &Cluster) -> Self {
+		let mut releases = cluster.running_jobs.values()
+			.map(|job| (job.estimated_end(clock).max(clock + 1), job))
+			.collect::<Vec<_>>();
+		releases.sort_unstable_by_key(|&(time, job)| (time, job.id));
+
+		let (classes, by_class): (Vec<_>, Vec<_>) = cluster.free_by_class().into_iter().unzip();
+		let width = classes.len();
+		let mut profile = Self {
+			steps: vec![(clock, cluster.available_nodes)],
+			by_class: if width > 1 { by_class } else { Vec::new() },
+			classes,
+		};
+
+		for (time, job) in releases {
+			let (last_time, last_free) = *profile.steps.last().unwrap();
+
+			if last_time == time {
+				profile.steps.last_mut().unwrap().1 += job.nodes;
+			} else {
+				profile.steps.push((time, last_free + job.nodes));
+				if width > 1 {
+					profile.by_class.extend_from_within(profile.by_class.len() - width..);
+				}
+			}
+
+			if width > 1 {
+				let last = profile.by_class.len() - width;
+				for (free, &class) in profile.by_class[last..].iter_mut().zip(&profile.classes) {
+					*free += cluster.nodes_in_class(&job.allocation, class);
+				}
+			}
+		}
+
+		profile
+	}
+
+	/// Returns the earliest time at which `nodes` nodes of the `classes` mask are free for
+	/// `duration` seconds, or None if there aren't that many nodes up.
+	pub fn earliest_start(&self, nodes: u32, classes: u64, duration: Clock) -> Option<Clock> {
+		let duration = duration.max(1);
+		let mut idx = 0;
+
+		'candidates: while idx < self.steps.len() {
+			let start = self.steps[idx].0;
+
+			for (offset, &(time, free)) in self.steps[idx..].iter().enumerate() {
+				if time >= start + duration {
+					break;
+				}
+
+				let free = if self.by_class.is_empty() { free } else { self.available(idx + offset, classes) };
+				if free < nodes {
+					idx += offset + 1;
+					continue 'candidates;
+				}
+			}
+
+			// enough nodes at every step, but maybe not of the same classes
+			if self.by_class.is_empty() || self.claim(idx, duration, nodes, classes).is_some() {
+				return Some(start);
+			}
+
+			idx += 1;
+		}
+
+		// the last step holds every node of the cluster that is up
+		None
+	}
+
+	/// Removes `nodes` free nodes of the `classes` mask from the profile between `start` and
+	/// `start + duration`, the same nodes of each class for the whole time.
+	pub fn reserve(&mut self, start: Clock, duration: Clock, nodes: u32, classes: u64) {
+		let first = self.split(start);
+		let last = self.split(start + duration.max(1));
+
+		if !self.by_class.is_empty() {
+			let width = self.classes.len();
+			let taken = self.claim(first, duration, nodes, classes).expect("the reserved nodes are free");
+
+			for row in self.by_class[first * width..last * width].chunks_mut(width) {
+				for (free, taken) in row.iter_mut().zip(&taken) {
+					*free -= taken;
+				}
+			}
+		}
+
+		for step in &mut self.steps[first..last] {
+			assert!(step.1 >= nodes);
+			step.1 -= nodes;
+		}
+	}
+
+	/// Free nodes among the classes of the `classes` mask at the step `idx`.
+	fn available(&self, idx: usize, classes: u64) -> u32 {
+		self.row(idx).iter().zip(&self.classes)
+			.filter(|&(_, &class)| classes & (1 << class) != 0)
+			.map(|(&free, _)| free)
+			.sum()
+	}
+
+	fn row(&self, idx: usize) -> &[u32] {
+		let width = self.classes.len();
+		&self.by_class[idx * width..(idx + 1) * width]
+	}
+
+	/// Nodes of each class, in allocation order, a job on `nodes` nodes of the `classes` mask
+	/// takes if it starts at the step `idx` for `duration` seconds, or None if they aren't
+	/// free for the whole time.
+	fn claim(&self, idx: usize, duration: Clock, nodes: u32, classes: u64) -> Option<Vec<u32>> {
+		let end = self.steps[idx].0 + duration.max(1);
+
+		// the nodes of each class free for the whole time
+		let mut free = self.classes.iter().copied().zip(self.row(idx).iter().copied()).collect::<Vec<_>>();
+		for step in (idx + 1..self.steps.len()).take_while(|&step| self.steps[step].0 < end) {
+			for ((_, free), &step) in free.iter_mut().zip(self.row(step)) {
+				*free = (*free).min(step);
+			}
+		}
+
+		let before = free.clone();
+		match cluster::claim_nodes(&mut free, nodes, classes) {
+			true => Some(before.iter().zip(&free).map(|((_, before), (_, after))| before - after).collect()),
+			false => None,
+		}
+	}
+
+	/// Makes sure a step starts at the given time and returns its index.
+	fn split(&mut self, time: Clock) -> usize {
+		match self.steps.binary_search_by_key(&time, |&(time, _)| time) {
+			Ok(idx) => idx,
+			Err(idx) => {
+				// a profile always starts at the current time, so idx > 0
+				let free = self.steps[idx - 1].1;
+				self.steps.insert(idx, (time, free));
+
+				let width = self.classes.len();
+				if !self.by_class.is_empty() {
+					for class in 0..width {
+						self.by_class.insert(idx * width + class, self.by_class[(idx - 1) * width + class]);
+					}
+				}
+
+				idx
+			}
+		}
+	}
+}
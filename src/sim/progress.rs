@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+use super::Clock;
+
+const BAR_WIDTH: usize = 30;
+
+/// Reports how far a simulation went on stderr, at most once per interval.
+pub struct Progress {
+	label: String,
+	/// Jobs to simulate, if known from the limit or the header of the trace
+	total_jobs: Option<usize>,
+	started: Instant,
+	last_update: Instant,
+	interval: Duration,
+	events: u64,
+}
+
+impl Progress {
+	pub fn new(label: String, total_jobs: Option<usize>) -> Self {
+		let now = Instant::now();
+
+		Self {
+			label,
+			total_jobs,
+			started: now,
+			last_update: now,
+			interval: Duration::from_secs(1),
+			events: 0,
+		}
+	}
+
+	/// Counts an event, printing the progress if the interval elapsed.
+	pub fn event(&mut self, finished_jobs: usize, clock: Clock) {
+		self.events += 1;
+
+		// checking the time on every event would slow the simulation down
+		if self.events.is_multiple_of(1024) && self.last_update.elapsed() >= self.interval {
+			self.last_update = Instant::now();
+			eprintln!("{}", self.line(finished_jobs, clock));
+		}
+	}
+
+	/// Prints the progress once the simulation ended.
+	pub fn finish(&self, finished_jobs: usize, clock: Clock) {
+		eprintln!("{}", self.line(finished_jobs, clock));
+	}
+
+	fn line(&self, finished_jobs: usize, clock: Clock) -> String {
+		let elapsed = self.started.elapsed();
+		let events_per_sec = self.events as f64 / elapsed.as_secs_f64().max(1e-3);
+		let simulated = humantime::format_duration(Duration::from_secs(clock));
+
+		let total = match self.total_jobs {
+			Some(total) => total.max(finished_jobs),
+			None => return format!("[{}] {} jobs, simulated {}, {:.0} events/s", self.label, finished_jobs, simulated, events_per_sec),
+		};
+
+		let done = finished_jobs as f64 / total.max(1) as f64;
+		let filled = (done * BAR_WIDTH as f64) as usize;
+		let eta = match finished_jobs {
+			0 => "?".to_string(),
+			_ => {
+				let left = elapsed.as_secs_f64() * (1.0 - done) / done;
+				humantime::format_duration(Duration::from_secs(left.round() as u64)).to_string()
+			}
+		};
+
+		format!(
+			"[{}] [{}{}] {:3.0}% {}/{} jobs, simulated {}, {:.0} events/s, ETA {}",
+			self.label, "=".repeat(filled), " ".repeat(BAR_WIDTH - filled), done * 100.0,
+			finished_jobs, total, simulated, events_per_sec, eta,
+		)
+	}
+}
@@ -0,0 +1,79 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use super::{Clock, algos::SchedulerSpec, job::Job};
+
+/// A quality of service class (e.g. premium, standard, scavenger) and the longest time its
+/// jobs should wait, the service level objective (SLO) reported by the runs.
+/// Jobs get the first class matching their SWF queue and partition.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QosClass {
+	pub name: String,
+	/// Longest time the jobs of the class should wait, in seconds
+	pub max_wait: Clock,
+	/// SWF queues of the jobs of the class, any queue if empty
+	#[serde(default)]
+	pub queues: Vec<u32>,
+	/// SWF partitions of the jobs of the class, any partition if empty
+	#[serde(default)]
+	pub partitions: Vec<u32>,
+}
+
+impl QosClass {
+	/// Whether the job belongs to the class.
+	pub fn accepts(&self, job: &Job) -> bool {
+		(self.queues.is_empty() || job.queue.is_some_and(|queue| self.queues.contains(&queue)))
+			&& (self.partitions.is_empty() || job.partition.is_some_and(|partition| self.partitions.contains(&partition)))
+	}
+
+	/// Sets the class of the job and its target wait, jobs matching no class keeping none.
+	pub fn assign(classes: &[Self], job: &mut Job) {
+		if let Some(idx) = classes.iter().position(|class| class.accepts(job)) {
+			job.qos = Some(idx as u32);
+			job.max_wait = Some(classes[idx].max_wait);
+		}
+	}
+
+	/// Parses a comma separated list of classes.
+	pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+		SchedulerSpec::parse_list(list)
+			.map_err(|why| why.to_string())?
+			.iter()
+			.map(Self::from_spec)
+			.collect()
+	}
+
+	fn from_spec(spec: &SchedulerSpec) -> Result<Self, String> {
+		let params = &spec.params;
+		params.allow_only(&["max_wait", "queues", "partitions"]).map_err(|why| why.to_string())?;
+
+		let ids = |key: &str| -> Result<Vec<u32>, String> {
+			match params.get::<String>(key).map_err(|why| why.to_string())? {
+				Some(list) => list.split(';')
+					.map(|id| id.trim().parse().map_err(|_| format!("Invalid {} '{}' for QoS class {}", key, id, spec.name)))
+					.collect(),
+				None => Ok(Vec::new()),
+			}
+		};
+
+		Ok(Self {
+			name: spec.name.clone(),
+			max_wait: params.get("max_wait")
+				.map_err(|why| why.to_string())?
+				.ok_or_else(|| format!("QoS class '{}' lacks its max_wait", spec.name))?,
+			queues: ids("queues")?,
+			partitions: ids("partitions")?,
+		})
+	}
+}
+
+/// Written like schedulers, e.g. `premium(queues=1;2, max_wait=600)` or `standard(max_wait=86400)`.
+impl FromStr for QosClass {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::from_spec(&s.parse().map_err(|why: super::algos::SchedulerError| why.to_string())?)
+	}
+}
@@ -0,0 +1,148 @@
+use std::{collections::BTreeSet, fmt::Debug, ops::Deref};
+
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, job::Job, mark::Mark};
+
+/// Queue of the jobs waiting to start, in the order they were queued, along with indices by
+/// number of nodes and by estimated run time (see `Job::estimate`) so that schedulers can pick a job in O(log n) instead
+/// of scanning the whole queue.
+///
+/// Dereferences to the slice of the jobs in queue order, the positions in this slice being
+/// what schedulers return.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct ReadyQueue {
+	jobs: Vec<Job>,
+	/// Sequence number of each job, increasing along the queue
+	seqs: Vec<u64>,
+	next_seq: u64,
+	by_nodes: BTreeSet<(u32, u32, u64)>,
+	by_estimate: BTreeSet<(Clock, u32, u64)>,
+	/// Nodes of the queued jobs
+	nodes: u64,
+}
+
+impl ReadyQueue {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds a job at the end of the queue.
+	pub fn push(&mut self, job: Job) {
+		let seq = self.next_seq;
+		self.next_seq += 1;
+
+		self.by_nodes.insert((job.nodes, job.id, seq));
+		self.by_estimate.insert((job.estimate(), job.id, seq));
+		self.nodes += job.nodes as u64;
+		self.seqs.push(seq);
+		self.jobs.push(job);
+	}
+
+	/// Takes the job at the given position out of the queue, keeping the others in order.
+	pub fn remove(&mut self, index: usize) -> Job {
+		let seq = self.seqs.remove(index);
+		let job = self.jobs.remove(index);
+
+		self.by_nodes.remove(&(job.nodes, job.id, seq));
+		self.by_estimate.remove(&(job.estimate(), job.id, seq));
+		self.nodes -= job.nodes as u64;
+		job
+	}
+
+	/// Applies a mark of the scheduler to the queued job of the given ID, returning the job
+	/// if it is queued. Marks don't change what the queue is indexed by.
+	pub fn mark(&mut self, id: u32, mark: Mark, clock: Clock) -> Option<&Job> {
+		let job = self.jobs.iter_mut().find(|job| job.id == id)?;
+		mark.apply(job, clock);
+		Some(job)
+	}
+
+	/// Counts a skip for each of the given queued jobs, see `Job::skips`.
+	pub(crate) fn skip(&mut self, ids: &HashSet<u32>) {
+		for job in self.jobs.iter_mut().filter(|job| ids.contains(&job.id)) {
+			job.skips += 1;
+		}
+	}
+
+	/// Nodes requested by the queued jobs altogether.
+	pub fn queued_nodes(&self) -> u64 {
+		self.nodes
+	}
+
+	/// Smallest number of nodes requested by a queued job.
+	pub fn min_nodes(&self) -> Option<u32> {
+		self.by_nodes.first().map(|&(nodes, _, _)| nodes)
+	}
+
+	/// Position of the first job in queue order needing at most `available` nodes.
+	pub fn first_fitting(&self, available: u32) -> Option<usize> {
+		if self.min_nodes()? > available {
+			return None;
+		}
+
+		self.jobs.iter().position(|job| job.nodes <= available)
+	}
+
+	/// Position of the job with the most nodes among the ones needing at most `available`
+	/// nodes, ties going to the lowest ID.
+	pub fn largest_fitting(&self, available: u32) -> Option<usize> {
+		let &(nodes, _, _) = self.by_nodes.range(..=(available, u32::MAX, u64::MAX)).next_back()?;
+		let &(_, _, seq) = self.by_nodes.range((nodes, 0, 0)..).next()?;
+		Some(self.position(seq))
+	}
+
+	/// Position of the job with the fewest nodes if it needs at most `available` nodes, ties
+	/// going to the lowest ID.
+	pub fn smallest_fitting(&self, available: u32) -> Option<usize> {
+		let &(nodes, _, seq) = self.by_nodes.first()?;
+		(nodes <= available).then(|| self.position(seq))
+	}
+
+	/// Position of the job with the shortest estimated run time among the ones needing at most
+	/// `available` nodes, ties going to the lowest ID.
+	pub fn shortest_fitting(&self, available: u32) -> Option<usize> {
+		if self.min_nodes()? > available {
+			return None;
+		}
+
+		self.by_estimate.iter()
+			.find(|&&(_, _, seq)| self.jobs[self.position(seq)].nodes <= available)
+			.map(|&(_, _, seq)| self.position(seq))
+	}
+
+	/// Position of the job with the longest estimated run time among the ones needing at most
+	/// `available` nodes, ties going to the lowest ID.
+	pub fn longest_fitting(&self, available: u32) -> Option<usize> {
+		if self.min_nodes()? > available {
+			return None;
+		}
+
+		let fits = |&&(_, _, seq): &&(Clock, u32, u64)| self.jobs[self.position(seq)].nodes <= available;
+		let &(estimate, _, _) = self.by_estimate.iter().rev().find(fits)?;
+
+		self.by_estimate.range((estimate, 0, 0)..)
+			.find(fits)
+			.map(|&(_, _, seq)| self.position(seq))
+	}
+
+	/// Position in the queue of the job with the given sequence number.
+	fn position(&self, seq: u64) -> usize {
+		self.seqs.binary_search(&seq).expect("indexed jobs are queued")
+	}
+}
+
+impl Deref for ReadyQueue {
+	type Target = [Job];
+
+	fn deref(&self) -> &Self::Target {
+		&self.jobs
+	}
+}
+
+impl Debug for ReadyQueue {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		self.jobs.fmt(f)
+	}
+}
@@ -0,0 +1,83 @@
+use std::{fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, job::Job, report::csv_field};
+
+/// A submitted job the engine didn't simulate, see `EngineReport::rejected_jobs`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RejectedJob {
+	/// Left empty when read back, see `Engine::restore`
+	#[serde(skip_deserializing)]
+	pub scheduler: String,
+	/// Size of the simulated cluster
+	pub cluster_nodes: u32,
+
+	pub id: u32,
+	pub user_id: Option<u32>,
+	pub nodes: u32,
+	pub submit: Clock,
+	pub run: Clock,
+	pub requested_run: Clock,
+	/// Why it was rejected: `too_wide`, `too_much_memory`, `no_partition` or the reason of
+	/// an admission policy, see `AdmissionPolicy`
+	pub reason: String,
+}
+
+impl RejectedJob {
+	pub fn new(scheduler: &str, cluster_nodes: u32, job: &Job, reason: &str) -> Self {
+		Self {
+			scheduler: scheduler.to_string(),
+			cluster_nodes,
+			id: job.id,
+			user_id: job.user_id,
+			nodes: job.nodes,
+			submit: job.submit_time,
+			run: job.run_time,
+			requested_run: job.requested_run_time,
+			reason: reason.to_string(),
+		}
+	}
+}
+
+const CSV_HEADER: &str = "scheduler,cluster_nodes,id,user_id,nodes,submit,run,requested_run,reason";
+
+/// Writes the rejected jobs of one or more runs as CSV.
+pub struct RejectsWriter<W: Write> {
+	out: W,
+	header_written: bool,
+}
+
+impl RejectsWriter<BufWriter<File>> {
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let file = File::create(path)?;
+		Ok(Self::new(BufWriter::new(file)))
+	}
+}
+
+impl<W: Write> RejectsWriter<W> {
+	pub fn new(out: W) -> Self {
+		Self { out, header_written: false }
+	}
+
+	pub fn write_run(&mut self, rejects: &[RejectedJob]) -> io::Result<()> {
+		if !self.header_written {
+			writeln!(self.out, "{}", CSV_HEADER)?;
+			self.header_written = true;
+		}
+
+		for reject in rejects {
+			writeln!(
+				self.out, "{},{},{},{},{},{},{},{},{}",
+				csv_field(&reject.scheduler), reject.cluster_nodes, reject.id, reject.user_id.map(|user| user.to_string()).unwrap_or_default(),
+				reject.nodes, reject.submit, reject.run, reject.requested_run, csv_field(&reject.reason),
+			)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.out.flush()
+	}
+}
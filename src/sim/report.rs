@@ -0,0 +1,545 @@
+use std::{collections::BTreeMap, fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, decision::DecisionRecord, job::Job, joblog::JobRecord, occupancy::OccupancySnapshot, qos::QosClass, rejects::RejectedJob};
+
+/// Summary of a per-job metric.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Distribution {
+	pub avg: f64,
+	pub median: f64,
+	pub p95: f64,
+	pub max: f64,
+}
+
+impl Distribution {
+	/// Summarizes the given values, sorting them in place.
+	pub fn from_values(values: &mut [f64]) -> Self {
+		if values.is_empty() {
+			return Self::default();
+		}
+
+		values.sort_unstable_by(|a, b| a.total_cmp(b));
+
+		let len = values.len();
+		Self {
+			avg: values.iter().sum::<f64>() / len as f64,
+			median: values[len / 2],
+			p95: values[((len as f64 * 0.95).ceil() as usize).clamp(1, len) - 1],
+			max: values[len - 1],
+		}
+	}
+
+	/// Summarizes values given along with their weight, such as the time they lasted,
+	/// sorting them in place.
+	pub fn from_weighted(values: &mut [(f64, f64)]) -> Self {
+		let total = values.iter().map(|&(_, weight)| weight).sum::<f64>();
+		if values.is_empty() || total <= 0.0 {
+			return Self::default();
+		}
+
+		values.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+
+		let quantile = |q: f64| {
+			let mut cumulated = 0.0;
+			values.iter()
+				.find(|&&(_, weight)| {
+					cumulated += weight;
+					cumulated >= q * total
+				})
+				.map_or(values[values.len() - 1].0, |&(value, _)| value)
+		};
+
+		Self {
+			avg: values.iter().map(|&(value, weight)| value * weight).sum::<f64>() / total,
+			median: quantile(0.5),
+			p95: quantile(0.95),
+			max: values[values.len() - 1].0,
+		}
+	}
+}
+
+/// Sums over a set of finished jobs, updated as they finish, the breakdowns taking their
+/// averages at the end of the run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobSums {
+	pub jobs: usize,
+	pub wait: Clock,
+	pub max_wait: Clock,
+	pub bounded_slowdown: f64,
+	/// Node-seconds the jobs ran for
+	pub used_ressources: u64,
+	/// Jobs that waited longer than their target wait, see `Job::max_wait`
+	pub late: usize,
+}
+
+impl JobSums {
+	pub fn add(&mut self, job: &Job, wait: Clock, bounded_slowdown: f64) {
+		self.jobs += 1;
+		self.wait += wait;
+		self.max_wait = self.max_wait.max(wait);
+		self.bounded_slowdown += bounded_slowdown;
+		self.used_ressources += job.nodes as u64 * job.run_time;
+		self.late += job.max_wait.is_some_and(|max_wait| wait > max_wait) as usize;
+	}
+
+	fn avg_wait(&self) -> f64 {
+		self.wait as f64 / self.jobs.max(1) as f64
+	}
+
+	fn avg_bounded_slowdown(&self) -> f64 {
+		self.bounded_slowdown / self.jobs.max(1) as f64
+	}
+}
+
+/// Metrics of the jobs of a single partition.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartitionReport {
+	pub name: String,
+	pub nodes: u32,
+	pub jobs: usize,
+	pub avg_wait: f64,
+	pub max_wait: Clock,
+	pub used_ressources: u64,
+	pub idle_percent: f64,
+	pub bounded_slowdown: Distribution,
+	/// Jobs routed to the partition away from the data of their user, see `Routing::home`
+	pub remote_jobs: usize,
+}
+
+/// Upper bounds of the buckets of jobs by number of nodes.
+pub const WIDTH_BUCKETS: &[(&str, u32)] = &[("1", 1), ("2-8", 8), ("9-64", 64), (">64", u32::MAX)];
+
+/// Upper bounds of the buckets of jobs by run time: up to an hour, up to 10 hours and longer.
+pub const RUN_TIME_BUCKETS: &[(&str, Clock)] = &[("short", 3600), ("medium", 36000), ("long", Clock::MAX)];
+
+/// Upper bounds of the buckets of jobs by the number of scheduling passes in which narrower
+/// jobs queued later started before them, see `Job::skips`.
+pub const SKIP_BUCKETS: &[(&str, u32)] = &[("0", 0), ("1", 1), ("2-9", 9), ("10-99", 99), (">99", u32::MAX)];
+
+/// Metrics of the jobs of a bucket, by width, by run time or by skips.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BucketReport {
+	pub bucket: &'static str,
+	pub jobs: usize,
+	pub avg_wait: f64,
+	pub avg_bounded_slowdown: f64,
+}
+
+impl BucketReport {
+	/// Index of the first bucket whose upper bound is at least the value, the bucket of a job.
+	pub fn bucket<T: PartialOrd>(buckets: &[(&'static str, T)], value: T) -> Option<usize> {
+		buckets.iter().position(|(_, bound)| value <= *bound)
+	}
+
+	/// Breaks down jobs given as the sums of each bucket.
+	pub fn breakdown<T>(buckets: &[(&'static str, T)], sums: &[JobSums]) -> Vec<Self> {
+		buckets.iter().enumerate().map(|(idx, &(bucket, _))| {
+			let sums = sums.get(idx).copied().unwrap_or_default();
+			BucketReport {
+				bucket,
+				jobs: sums.jobs,
+				avg_wait: sums.avg_wait(),
+				avg_bounded_slowdown: sums.avg_bounded_slowdown(),
+			}
+		}).collect()
+	}
+}
+
+/// Metrics of the jobs of a user or of a group.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OwnerReport {
+	/// User or group ID, None for the jobs the trace gives none
+	pub id: Option<u32>,
+	pub jobs: usize,
+	pub avg_wait: f64,
+	pub max_wait: Clock,
+	pub avg_bounded_slowdown: f64,
+	/// Node-seconds the jobs ran for
+	pub used_ressources: u64,
+}
+
+impl OwnerReport {
+	/// Breaks down jobs given as the sums of each owner, by owner ID.
+	pub fn breakdown(owners: &[(Option<u32>, JobSums)]) -> Vec<Self> {
+		owners.iter().map(|&(id, sums)| OwnerReport {
+			id,
+			jobs: sums.jobs,
+			avg_wait: sums.avg_wait(),
+			max_wait: sums.max_wait,
+			avg_bounded_slowdown: sums.avg_bounded_slowdown(),
+			used_ressources: sums.used_ressources,
+		}).collect()
+	}
+
+	/// Jain's fairness index of the average bounded slowdowns of the owners: 1 when they are
+	/// all equal, down to 1/n when a single one of the n owners suffers. 1 without any owner.
+	pub fn fairness(owners: &[Self]) -> f64 {
+		let sum = owners.iter().map(|owner| owner.avg_bounded_slowdown).sum::<f64>();
+		let squares = owners.iter().map(|owner| owner.avg_bounded_slowdown.powi(2)).sum::<f64>();
+
+		match squares > 0.0 {
+			true => sum * sum / (owners.len() as f64 * squares),
+			false => 1.0,
+		}
+	}
+}
+
+/// Metrics of the jobs of a QoS class, against the target wait of the class.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QosReport {
+	pub name: String,
+	/// Longest time the jobs of the class should wait
+	pub target_wait: Clock,
+	pub jobs: usize,
+	pub avg_wait: f64,
+	pub max_wait: Clock,
+	/// Jobs that waited longer than the target
+	pub violations: usize,
+	pub violation_percent: f64,
+}
+
+impl QosReport {
+	/// Breaks down jobs given as the sums of each class, by class index.
+	pub fn breakdown(classes: &[QosClass], sums: &[JobSums]) -> Vec<Self> {
+		classes.iter().enumerate().map(|(idx, class)| {
+			let sums = sums.get(idx).copied().unwrap_or_default();
+			QosReport {
+				name: class.name.clone(),
+				target_wait: class.max_wait,
+				jobs: sums.jobs,
+				avg_wait: sums.avg_wait(),
+				max_wait: sums.max_wait,
+				violations: sums.late,
+				violation_percent: sums.late as f64 * 100f64 / sums.jobs.max(1) as f64,
+			}
+		}).collect()
+	}
+}
+
+/// Metrics of a single simulation run, returned by `Engine::run`.
+#[derive(Debug, Serialize)]
+pub struct EngineReport {
+	pub workload: String,
+	/// Label of the scheduler, see `Scheduler::label`
+	pub scheduler_name: String,
+	pub nodes: u32,
+	pub cores_per_node: u32,
+	/// Walltime model the requested run times come from
+	pub walltime: String,
+	/// Power model the energy comes from
+	pub power: String,
+	/// Allocation policy the nodes of the jobs were picked by
+	pub allocation: String,
+	/// Seed of the run, see `SimRng`
+	pub seed: u64,
+	/// Factor the submit times were divided by, see `EngineConfig::load_factor`
+	pub load_factor: f64,
+	/// Jobs the metrics are computed over
+	pub jobs: usize,
+	/// Number of jobs simulated first but left out of the metrics
+	pub warmup: usize,
+	/// Jobs finishing after this time are left out of the metrics
+	pub cutoff: Option<Clock>,
+	/// Whether the simulation stopped before the end of the workload, see `Budget`
+	pub truncated: bool,
+	/// Jobs simulated but left out of the metrics, because of the warm-up, the cutoff or
+	/// because they were on the machine when the simulation started
+	pub trimmed_jobs: usize,
+	/// Jobs rejected by the admission policies or that can't run on the cluster
+	pub rejected_jobs: u32,
+	/// Node-seconds of the rejected jobs, the work left out of the metrics
+	pub rejected_work: u64,
+	/// Number of rejected jobs by reason (only written as JSON)
+	pub rejections: BTreeMap<String, u32>,
+	/// Jobs taken out of the queue when the trace cancelled them, left out of the metrics
+	pub cancelled_jobs: usize,
+	/// Time the cancelled jobs waited until they were cancelled
+	pub cancellation_wait: Clock,
+	/// Lines of the trace skipped because they couldn't be read, see `EngineConfig::lenient`
+	pub malformed_lines: usize,
+	/// Records of the trace skipped because they lack a run time or a number of processors
+	pub incomplete_records: usize,
+	/// Jobs routed to another partition than the one holding the data of their user, see `Routing`
+	pub remote_jobs: usize,
+
+	pub makespan: Clock,
+	pub total_completion_time: Clock,
+	pub min_wait: Clock,
+	pub max_wait: Clock,
+	pub avg_wait: f64,
+	pub median_wait: Clock,
+	pub total_wait: Clock,
+
+	pub used_ressources: u64,
+	pub idle: u64,
+	pub idle_percent: f64,
+	/// Lowest and highest share of the makespan a single node spent running jobs, in percent
+	pub min_node_usage: f64,
+	pub max_node_usage: f64,
+	/// Share of the memory of the nodes the jobs used over the makespan, if every node has a
+	/// known memory, see `ClusterSpec::node_memory`
+	pub memory_utilization: Option<f64>,
+
+	/// Run time under which jobs count as `slowdown_tau` for the bounded slowdown
+	pub slowdown_tau: Clock,
+	pub slowdown: Distribution,
+	pub bounded_slowdown: Distribution,
+	/// Jain's fairness index of the average bounded slowdowns of the users, see `OwnerReport::fairness`
+	pub user_fairness: f64,
+	/// Jain's fairness index of the average bounded slowdowns of the groups
+	pub group_fairness: f64,
+	/// Jobs that waited longer than the target wait of their QoS class, see `QosClass`
+	pub slo_violations: usize,
+	/// Scheduling passes in which narrower jobs queued later started before each job, see `Job::skips`
+	pub skips: Distribution,
+	/// Number of times a running job was stopped to make room for another
+	pub preemptions: u32,
+	/// Number of times a running malleable job was shrunk or expanded, see `Malleability`
+	pub resizes: u32,
+	/// Number of times a job was killed by a node failure and submitted again
+	pub resubmissions: u32,
+	/// Number of times a job was killed at its walltime, see `WalltimeLimit`
+	pub walltime_kills: u32,
+	/// Number of times a job killed at its walltime was submitted again with a larger request
+	pub walltime_resubmissions: u32,
+	/// Number of times a partition switched to another row of jobs, when time slicing
+	pub context_switches: u32,
+	/// Node-seconds resumed jobs spent restoring themselves after a context switch
+	pub switch_overhead: u64,
+	/// Node-seconds the started jobs held their nodes waiting for the decisions of the
+	/// scheduler, see `DecisionLatency`
+	pub decision_overhead: u64,
+	/// Node-seconds lost to failed nodes and maintenances
+	pub downtime: u64,
+	/// Share of the node-seconds asked by the reservations they held, the rest being kept by
+	/// jobs still running when they started, if there were reservations
+	pub reservation_utilization: Option<f64>,
+	/// Job-seconds queued jobs waited though free nodes could run them, kept for the reservations
+	pub reservation_delay: u64,
+	/// Node-seconds the nodes of the scheduled downtimes were down, once drained, see `Drain`
+	pub drain_downtime: u64,
+	/// Job-seconds queued jobs waited though they would have fit on the drained nodes
+	pub drain_delay: u64,
+	/// Jobs started after the time they started in the trace, the simulated cluster lacking free
+	/// nodes then, if the run replayed the trace (validation mode)
+	pub late_starts: Option<u32>,
+	/// Total time the submission of jobs was delayed until the jobs they depend on finished
+	pub dependency_wait: Clock,
+	/// Dependencies on jobs that were not submitted before, which were ignored
+	pub ignored_dependencies: u32,
+	/// Jobs submitted by the user model on top of the trace
+	pub generated_jobs: u32,
+	/// Energy used by the nodes over the makespan, in joules
+	pub energy: f64,
+	pub energy_per_job: f64,
+	/// Energy times makespan, in joule-seconds
+	pub energy_delay_product: f64,
+	/// Share of the nodes running jobs over time, weighted by time, if it was sampled
+	pub utilization: Option<Distribution>,
+	/// Number of contiguous free nodes in each stretch of free nodes, weighted by how long
+	/// the stretches lasted, see `Fragmentation`
+	pub free_stretch: Distribution,
+	/// Scheduling passes after which the first queued job of a partition waited though
+	/// enough nodes were free for it
+	pub stalls: u32,
+	/// Node-seconds nodes stayed free while jobs were queued in their partition
+	pub backfill_holes: u64,
+	/// Queued jobs over time, weighted by time, see `Backlog`
+	pub avg_queue_length: f64,
+	pub max_queue_length: usize,
+	/// Nodes requested by the queued jobs altogether over time, weighted by time
+	pub avg_backlog: f64,
+	pub max_backlog: u64,
+	/// Time the queued jobs requested more nodes than were free
+	pub saturated_time: Clock,
+	/// Jobs planned with a predicted run time, see `PredictionErrors`
+	pub predicted_jobs: usize,
+	/// Absolute error of the predicted run times in seconds
+	pub prediction_error: Distribution,
+	/// Average accuracy of the predicted and requested run times of these jobs, the shorter
+	/// of the estimate and the run time over the longer
+	pub prediction_accuracy: f64,
+	pub request_accuracy: f64,
+	/// Share of the predictions shorter than the actual run time
+	pub underpredicted: f64,
+	/// Node IDs between the first and last node of each job, per node of the job: 1 when
+	/// its nodes are contiguous, higher the more they are spread over the machine
+	pub allocation_span: Distribution,
+	/// Average number of blocks of contiguous nodes the jobs ran on
+	pub allocation_blocks: f64,
+	/// Breakdown by partition, empty if the cluster isn't partitioned (only written as JSON)
+	pub partitions: Vec<PartitionReport>,
+	/// Breakdown by number of nodes, see `WIDTH_BUCKETS` (only written as JSON)
+	pub by_width: Vec<BucketReport>,
+	/// Breakdown by run time, see `RUN_TIME_BUCKETS` (only written as JSON)
+	pub by_run_time: Vec<BucketReport>,
+	/// Breakdown by skips, a histogram of the starvation of the jobs, see `SKIP_BUCKETS`
+	/// (only written as JSON)
+	pub by_skips: Vec<BucketReport>,
+	/// Breakdown by user (only written as JSON)
+	pub by_user: Vec<OwnerReport>,
+	/// Breakdown by group (only written as JSON)
+	pub by_group: Vec<OwnerReport>,
+	/// Breakdown by QoS class, empty without classes (only written as JSON)
+	pub by_qos: Vec<QosReport>,
+
+	pub time_took: u128,
+
+	/// Every job of the run, if the engine was configured to record them
+	#[serde(skip)]
+	pub job_records: Vec<JobRecord>,
+	/// Jobs rejected when submitted, if the engine was configured to record them
+	#[serde(skip)]
+	pub rejects: Vec<RejectedJob>,
+	/// Length of the queue and nodes requested by the queued jobs after each change, if the
+	/// engine was configured to record them
+	#[serde(skip)]
+	pub queue_timeline: Vec<(Clock, usize, u64)>,
+	/// Utilization of the cluster at each sample, if the engine was configured to sample it
+	#[serde(skip)]
+	pub utilization_timeline: Vec<(Clock, f64)>,
+	/// Decisions of the scheduler, if the engine was configured to record them
+	#[serde(skip)]
+	pub decisions: Vec<DecisionRecord>,
+	/// Snapshots of the jobs running on the nodes, if the engine was configured to take them
+	#[serde(skip)]
+	pub occupancy: Vec<OccupancySnapshot>,
+}
+
+const CSV_COLUMNS: &[&str] = &[
+	"workload", "scheduler", "nodes", "cores_per_node", "walltime", "power", "allocation", "seed", "load_factor", "jobs", "warmup", "cutoff", "truncated", "trimmed_jobs", "rejected_jobs", "rejected_work", "cancelled_jobs", "cancellation_wait", "malformed_lines", "incomplete_records", "remote_jobs",
+	"makespan", "total_completion_time", "min_wait", "max_wait", "avg_wait", "median_wait", "total_wait",
+	"used_ressources", "idle", "idle_percent", "min_node_usage", "max_node_usage", "memory_utilization",
+	"slowdown_tau", "slowdown_avg", "slowdown_median", "slowdown_p95", "slowdown_max",
+	"bounded_slowdown_avg", "bounded_slowdown_median", "bounded_slowdown_p95", "bounded_slowdown_max",
+	"user_fairness", "group_fairness", "slo_violations", "skips_avg", "skips_median", "skips_p95", "skips_max",
+	"preemptions", "resizes", "resubmissions", "walltime_kills", "walltime_resubmissions", "context_switches", "switch_overhead", "decision_overhead", "downtime",
+	"reservation_utilization", "reservation_delay", "drain_downtime", "drain_delay", "late_starts",
+	"dependency_wait", "ignored_dependencies", "generated_jobs", "energy", "energy_per_job", "energy_delay_product",
+	"utilization_avg", "utilization_median", "utilization_p95", "utilization_max",
+	"free_stretch_avg", "free_stretch_median", "free_stretch_p95", "free_stretch_max", "stalls", "backfill_holes",
+	"avg_queue_length", "max_queue_length", "avg_backlog", "max_backlog", "saturated_time",
+	"predicted_jobs", "prediction_error_avg", "prediction_error_median", "prediction_error_p95", "prediction_error_max",
+	"prediction_accuracy", "request_accuracy", "underpredicted",
+	"allocation_span_avg", "allocation_span_median", "allocation_span_p95", "allocation_span_max", "allocation_blocks", "time_took",
+];
+
+/// A CSV field, quoted as RFC 4180 wants when it holds a comma, a quote or a line break,
+/// e.g. the label of a scheduler given several parameters.
+pub(crate) fn csv_field(text: &str) -> String {
+	match text.contains([',', '"', '\n', '\r']) {
+		true => format!("\"{}\"", text.replace('"', "\"\"")),
+		false => text.to_string(),
+	}
+}
+
+impl EngineReport {
+	pub fn csv_header() -> String {
+		CSV_COLUMNS.join(",")
+	}
+
+	/// One CSV line matching `csv_header`, without the trailing newline.
+	pub fn to_csv_row(&self) -> String {
+		let utilization = |field: fn(&Distribution) -> f64| self.utilization.as_ref().map_or_else(String::new, |summary| field(summary).to_string());
+
+		let fields = [
+			csv_field(&self.workload), csv_field(&self.scheduler_name), self.nodes.to_string(), self.cores_per_node.to_string(), csv_field(&self.walltime), csv_field(&self.power), csv_field(&self.allocation), self.seed.to_string(), self.load_factor.to_string(), self.jobs.to_string(),
+			self.warmup.to_string(), self.cutoff.map_or_else(String::new, |cutoff| cutoff.to_string()), self.truncated.to_string(), self.trimmed_jobs.to_string(), self.rejected_jobs.to_string(), self.rejected_work.to_string(),
+			self.cancelled_jobs.to_string(), self.cancellation_wait.to_string(), self.malformed_lines.to_string(), self.incomplete_records.to_string(), self.remote_jobs.to_string(),
+			self.makespan.to_string(), self.total_completion_time.to_string(), self.min_wait.to_string(),
+			self.max_wait.to_string(), self.avg_wait.to_string(), self.median_wait.to_string(), self.total_wait.to_string(),
+			self.used_ressources.to_string(), self.idle.to_string(), self.idle_percent.to_string(),
+			self.min_node_usage.to_string(), self.max_node_usage.to_string(),
+			self.memory_utilization.map_or_else(String::new, |utilization| utilization.to_string()),
+			self.slowdown_tau.to_string(),
+			self.slowdown.avg.to_string(), self.slowdown.median.to_string(), self.slowdown.p95.to_string(), self.slowdown.max.to_string(),
+			self.bounded_slowdown.avg.to_string(), self.bounded_slowdown.median.to_string(),
+			self.bounded_slowdown.p95.to_string(), self.bounded_slowdown.max.to_string(),
+			self.user_fairness.to_string(), self.group_fairness.to_string(), self.slo_violations.to_string(),
+			self.skips.avg.to_string(), self.skips.median.to_string(), self.skips.p95.to_string(), self.skips.max.to_string(),
+			self.preemptions.to_string(), self.resizes.to_string(), self.resubmissions.to_string(), self.walltime_kills.to_string(), self.walltime_resubmissions.to_string(), self.context_switches.to_string(), self.switch_overhead.to_string(), self.decision_overhead.to_string(), self.downtime.to_string(),
+			self.reservation_utilization.map_or_else(String::new, |utilization| utilization.to_string()), self.reservation_delay.to_string(), self.drain_downtime.to_string(), self.drain_delay.to_string(),
+			self.late_starts.map_or_else(String::new, |late| late.to_string()),
+			self.dependency_wait.to_string(), self.ignored_dependencies.to_string(), self.generated_jobs.to_string(), self.energy.to_string(), self.energy_per_job.to_string(), self.energy_delay_product.to_string(),
+			utilization(|summary| summary.avg), utilization(|summary| summary.median), utilization(|summary| summary.p95), utilization(|summary| summary.max),
+			self.free_stretch.avg.to_string(), self.free_stretch.median.to_string(), self.free_stretch.p95.to_string(), self.free_stretch.max.to_string(),
+			self.stalls.to_string(), self.backfill_holes.to_string(),
+			self.avg_queue_length.to_string(), self.max_queue_length.to_string(), self.avg_backlog.to_string(), self.max_backlog.to_string(), self.saturated_time.to_string(),
+			self.predicted_jobs.to_string(),
+			self.prediction_error.avg.to_string(), self.prediction_error.median.to_string(), self.prediction_error.p95.to_string(), self.prediction_error.max.to_string(),
+			self.prediction_accuracy.to_string(), self.request_accuracy.to_string(), self.underpredicted.to_string(),
+			self.allocation_span.avg.to_string(), self.allocation_span.median.to_string(), self.allocation_span.p95.to_string(), self.allocation_span.max.to_string(),
+			self.allocation_blocks.to_string(),
+			self.time_took.to_string(),
+		];
+
+		debug_assert_eq!(fields.len(), CSV_COLUMNS.len());
+		fields.join(",")
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string(self).expect("an EngineReport is always serializable")
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportFormat {
+	Csv,
+	/// One JSON object per line
+	Json,
+}
+
+impl ReportFormat {
+	/// Guesses the format from the file extension, defaulting to CSV.
+	pub fn from_path(path: &Path) -> Self {
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some("json") | Some("jsonl") => ReportFormat::Json,
+			_ => ReportFormat::Csv,
+		}
+	}
+}
+
+/// Writes a sequence of reports to a single output, one line per report.
+pub struct ReportWriter<W: Write> {
+	out: W,
+	format: ReportFormat,
+	header_written: bool,
+}
+
+impl ReportWriter<BufWriter<File>> {
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let path = path.as_ref();
+		let file = File::create(path)?;
+		Ok(Self::new(BufWriter::new(file), ReportFormat::from_path(path)))
+	}
+}
+
+impl<W: Write> ReportWriter<W> {
+	pub fn new(out: W, format: ReportFormat) -> Self {
+		Self {
+			out,
+			format,
+			header_written: false,
+		}
+	}
+
+	pub fn write(&mut self, report: &EngineReport) -> io::Result<()> {
+		match self.format {
+			ReportFormat::Csv => {
+				if !self.header_written {
+					writeln!(self.out, "{}", EngineReport::csv_header())?;
+					self.header_written = true;
+				}
+
+				writeln!(self.out, "{}", report.to_csv_row())
+			}
+			ReportFormat::Json => writeln!(self.out, "{}", report.to_json()),
+		}
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.out.flush()
+	}
+}
@@ -0,0 +1,160 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, algos::SchedulerSpec};
+
+/// An advance reservation: nodes kept out of reach of the jobs over a time window, e.g. for a
+/// maintenance or a demo. The reservation takes free nodes when it starts, and the nodes
+/// freed later on while it still lacks some.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Reservation {
+	pub name: String,
+	/// Seconds since the start of the trace
+	pub start: Clock,
+	pub duration: Clock,
+	/// Nodes to reserve, every node of the partition if unset
+	#[serde(default)]
+	pub nodes: Option<u32>,
+	/// Partition the nodes are reserved in, the first one if unset
+	#[serde(default)]
+	pub partition: Option<String>,
+}
+
+impl Reservation {
+	/// Parses a comma separated list of reservations.
+	pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+		SchedulerSpec::parse_list(list)
+			.map_err(|why| why.to_string())?
+			.iter()
+			.map(Self::from_spec)
+			.collect()
+	}
+
+	fn from_spec(spec: &SchedulerSpec) -> Result<Self, String> {
+		let params = &spec.params;
+		params.allow_only(&["start", "duration", "nodes", "partition"]).map_err(|why| why.to_string())?;
+
+		let required = |key: &str| params.get(key)
+			.map_err(|why| why.to_string())?
+			.ok_or_else(|| format!("reservation '{}' lacks its {}", spec.name, key));
+
+		Ok(Self {
+			name: spec.name.clone(),
+			start: required("start")?,
+			duration: required("duration")?,
+			nodes: params.get("nodes").map_err(|why| why.to_string())?,
+			partition: params.get("partition").map_err(|why| why.to_string())?,
+		})
+	}
+}
+
+/// Written like schedulers, e.g. `maintenance(start=86400, duration=7200)` or
+/// `demo(start=3600, duration=600, nodes=128, partition=batch)`.
+impl FromStr for Reservation {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::from_spec(&s.parse().map_err(|why: super::algos::SchedulerError| why.to_string())?)
+	}
+}
+
+/// A reservation in the calendar of a cluster, see `Cluster::reservations`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReservedWindow {
+	pub start: Clock,
+	pub end: Clock,
+	pub nodes: u32,
+	/// Nodes held while the window is active
+	held: Vec<u32>,
+	active: bool,
+	/// Node-seconds held so far, counted until `since`
+	obtained: u64,
+	since: Clock,
+}
+
+impl ReservedWindow {
+	pub fn new(start: Clock, end: Clock, nodes: u32) -> Self {
+		Self {
+			start,
+			end,
+			nodes,
+			held: Vec::new(),
+			active: false,
+			obtained: 0,
+			since: start,
+		}
+	}
+
+	pub fn is_active(&self) -> bool {
+		self.active
+	}
+
+	/// Nodes the window holds now.
+	pub fn held(&self) -> &[u32] {
+		&self.held
+	}
+
+	/// Nodes the window still lacks, taken as soon as they are free.
+	pub fn missing(&self) -> u32 {
+		match self.active {
+			true => self.nodes - self.held.len() as u32,
+			false => 0,
+		}
+	}
+
+	/// Whether the window still keeps nodes from the jobs between `from` and `to`: nodes it
+	/// will take, or nodes it lacks.
+	pub fn blocks(&self, from: Clock, to: Clock) -> bool {
+		self.start < to && self.end > from && (!self.active || self.missing() > 0)
+	}
+
+	/// Node-seconds held until `clock`.
+	pub fn obtained(&self, clock: Clock) -> u64 {
+		match self.active {
+			true => self.obtained + self.held.len() as u64 * clock.saturating_sub(self.since),
+			false => self.obtained,
+		}
+	}
+
+	/// Node-seconds asked over the whole window.
+	pub fn asked(&self) -> u64 {
+		self.nodes as u64 * (self.end - self.start)
+	}
+
+	pub(crate) fn start(&mut self, clock: Clock) {
+		self.active = true;
+		self.since = clock;
+	}
+
+	/// Records the node-seconds held until now, before the held nodes change.
+	fn count(&mut self, clock: Clock) {
+		self.obtained = self.obtained(clock);
+		self.since = clock;
+	}
+
+	pub(crate) fn hold(&mut self, nodes: impl Iterator<Item = u32>, clock: Clock) {
+		self.count(clock);
+		self.held.extend(nodes);
+	}
+
+	/// Gives back a held node, e.g. one that failed. Returns whether the window held it.
+	pub(crate) fn drop_node(&mut self, node: u32, clock: Clock) -> bool {
+		match self.held.iter().position(|&held| held == node) {
+			Some(idx) => {
+				self.count(clock);
+				self.held.swap_remove(idx);
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Ends the window, returning the nodes it held.
+	pub(crate) fn finish(&mut self, clock: Clock) -> Vec<u32> {
+		self.count(clock);
+		self.active = false;
+		std::mem::take(&mut self.held)
+	}
+}
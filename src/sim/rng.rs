@@ -0,0 +1,52 @@
+use rand::{SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+/// Stochastic components of a run, each drawing from its own stream so that drawing more
+/// numbers in one of them doesn't change what the others draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+	Walltime,
+	Failures,
+	Users,
+	Scheduler,
+	Malleability,
+	Moldability,
+}
+
+/// Random number generator of a run, the same seed giving the same run. Components don't
+/// share a generator but take their own from it, either a single stream drawn from in order
+/// or generators keyed by what the draws are about (a job, a time), which don't depend on
+/// the order of the draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SimRng {
+	seed: u64,
+}
+
+impl SimRng {
+	pub fn new(seed: u64) -> Self {
+		Self { seed }
+	}
+
+	pub fn seed(&self) -> u64 {
+		self.seed
+	}
+
+	/// Generator of a component, drawn from in order.
+	pub fn stream(&self, stream: Stream) -> StdRng {
+		self.keyed(stream, 0)
+	}
+
+	/// Generator of a component for draws about `key`.
+	pub fn keyed(&self, stream: Stream, key: u64) -> StdRng {
+		let stream = splitmix(self.seed ^ splitmix(stream as u64 + 1));
+		StdRng::seed_from_u64(splitmix(stream ^ key))
+	}
+}
+
+/// Mixes the bits of a value (SplitMix64), so that close seeds and keys give unrelated generators.
+fn splitmix(value: u64) -> u64 {
+	let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
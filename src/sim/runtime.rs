@@ -0,0 +1,288 @@
+use std::{collections::{HashMap, VecDeque}, convert::TryFrom, fmt::Display, fs::File, io::{self, BufRead, BufReader}, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, job::Job, report::Distribution, stats::{Stat, StatsMode}};
+
+/// What is known of a job before it runs, without its actual run time: what a batch system
+/// knows when a job is submitted. Given to the `RuntimePredictor`s so they can't cheat.
+#[derive(Debug, Clone, Copy)]
+pub struct JobView<'a> {
+	job: &'a Job,
+}
+
+impl<'a> JobView<'a> {
+	pub fn new(job: &'a Job) -> Self {
+		Self { job }
+	}
+
+	pub fn id(&self) -> u32 {
+		self.job.id
+	}
+
+	pub fn nodes(&self) -> u32 {
+		self.job.nodes
+	}
+
+	pub fn submit_time(&self) -> Clock {
+		self.job.submit_time
+	}
+
+	pub fn requested_run_time(&self) -> Clock {
+		self.job.requested_run_time
+	}
+
+	pub fn user_id(&self) -> Option<u32> {
+		self.job.user_id
+	}
+
+	pub fn group_id(&self) -> Option<u32> {
+		self.job.group_id
+	}
+
+	pub fn queue(&self) -> Option<u32> {
+		self.job.queue
+	}
+
+	pub fn partition(&self) -> Option<u32> {
+		self.job.partition
+	}
+
+	/// In kilobytes per processor
+	pub fn requested_memory(&self) -> Option<f64> {
+		self.job.requested_memory
+	}
+
+	pub fn qos(&self) -> Option<u32> {
+		self.job.qos
+	}
+}
+
+/// Predicts the run time of the jobs as they are submitted, from what is known of them then
+/// and the run times of the jobs that finished before, see `Job::prediction`.
+///
+/// Custom predictors are given to `Engine::set_runtime_predictor`. Like the scheduler, they
+/// start over when a snapshot is restored.
+pub trait RuntimePredictor: Send {
+	fn name(&self) -> &'static str;
+
+	/// Predicted run time of a submitted job, None to plan with its requested time.
+	fn predict(&mut self, job: JobView) -> Option<Clock>;
+
+	/// Called as the jobs finish, with the time they actually ran.
+	fn on_job_finish(&mut self, _job: JobView, _run_time: Clock) {}
+}
+
+/// Average run time of the last jobs of the user of the job, the predictor of Tsafrir,
+/// Etsion and Feitelson. Jobs of users without finished jobs get no prediction.
+#[derive(Debug, Clone, Default)]
+pub struct UserAverage {
+	jobs: usize,
+	/// Run times of the last finished jobs of each user, latest last
+	history: HashMap<u32, VecDeque<Clock>>,
+}
+
+impl UserAverage {
+	pub fn new(jobs: usize) -> Self {
+		Self { jobs: jobs.max(1), history: HashMap::new() }
+	}
+}
+
+impl RuntimePredictor for UserAverage {
+	fn name(&self) -> &'static str {
+		"user-average"
+	}
+
+	fn predict(&mut self, job: JobView) -> Option<Clock> {
+		let runs = self.history.get(&job.user_id()?)?;
+		Some(runs.iter().sum::<Clock>() / runs.len() as Clock)
+	}
+
+	fn on_job_finish(&mut self, job: JobView, run_time: Clock) {
+		if let Some(user) = job.user_id() {
+			let runs = self.history.entry(user).or_default();
+			if runs.len() == self.jobs {
+				runs.pop_front();
+			}
+			runs.push_back(run_time);
+		}
+	}
+}
+
+/// Run times predicted for the jobs ahead of the simulation, e.g. by a model trained on
+/// the trace, taking precedence over the `RuntimeModel` for the jobs they give a time for.
+///
+/// Read from `job_id,run_time` lines, lines starting with `#` being comments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Predictions {
+	run_times: HashMap<u32, Clock>,
+}
+
+impl Predictions {
+	pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let mut run_times = HashMap::new();
+
+		for (idx, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+			let line = line?;
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("line {} of the predictions isn't job_id,run_time: {}", idx + 1, line));
+			let (id, run_time) = line.split_once(',').ok_or_else(invalid)?;
+			run_times.insert(id.trim().parse().map_err(|_| invalid())?, run_time.trim().parse().map_err(|_| invalid())?);
+		}
+
+		Ok(Self { run_times })
+	}
+
+	/// Predicted run time of the job of the given ID, if any.
+	pub fn get(&self, id: u32) -> Option<Clock> {
+		self.run_times.get(&id).copied()
+	}
+
+	pub fn len(&self) -> usize {
+		self.run_times.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.run_times.is_empty()
+	}
+}
+
+/// How far the predicted run times were from the actual ones, over the finished jobs that
+/// were given a prediction, the requested times being measured alike for comparison.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PredictionErrors {
+	/// Absolute error of each prediction, in seconds
+	errors: Stat,
+	/// Sums of the accuracies of the predictions and of the requested times
+	accuracy: f64,
+	request_accuracy: f64,
+	underpredicted: usize,
+}
+
+/// Summary of the `PredictionErrors` of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PredictionSummary {
+	pub jobs: usize,
+	pub error: Distribution,
+	pub accuracy: f64,
+	pub request_accuracy: f64,
+	/// Share of the predictions shorter than the actual run time
+	pub underpredicted: f64,
+}
+
+impl PredictionErrors {
+	pub fn new(mode: StatsMode) -> Self {
+		Self { errors: Stat::new(mode), accuracy: 0., request_accuracy: 0., underpredicted: 0 }
+	}
+
+	/// Accuracy of an estimate of a run time, as Tsafrir et al. define it: the shorter of
+	/// both over the longer, 1 for exact estimates.
+	pub fn accuracy(estimate: Clock, run_time: Clock) -> f64 {
+		match estimate.max(run_time) {
+			0 => 1.,
+			longest => estimate.min(run_time) as f64 / longest as f64,
+		}
+	}
+
+	/// Measures the prediction of a finished job, if it was given one.
+	pub fn push(&mut self, job: &Job) {
+		if let Some(prediction) = job.prediction {
+			self.errors.push(prediction.abs_diff(job.run_time) as f64);
+			self.accuracy += Self::accuracy(prediction, job.run_time);
+			self.request_accuracy += Self::accuracy(job.requested_run_time, job.run_time);
+			self.underpredicted += (prediction < job.run_time) as usize;
+		}
+	}
+
+	pub fn finish(&mut self) -> PredictionSummary {
+		let jobs = self.errors.len();
+		let share = |sum: f64| sum / jobs.max(1) as f64;
+
+		PredictionSummary {
+			jobs,
+			error: self.errors.summary(),
+			accuracy: share(self.accuracy),
+			request_accuracy: share(self.request_accuracy),
+			underpredicted: share(self.underpredicted as f64),
+		}
+	}
+}
+
+impl Default for PredictionErrors {
+	fn default() -> Self {
+		Self::new(StatsMode::default())
+	}
+}
+
+/// What the schedulers know of the run times of the jobs, which they plan with (see
+/// `Job::estimate`), the actual run times being hidden from them until the jobs finish.
+///
+/// Written `requested`, `user-average[:JOBS]` or `exact`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RuntimeModel {
+	/// The time the users requested
+	#[default]
+	Requested,
+	/// Predictions averaging the run times of the last jobs of the user, 2 by default, see `UserAverage`
+	UserAverage(usize),
+	/// Perfect predictions, the actual run times, as a best case
+	Exact,
+}
+
+impl RuntimeModel {
+	/// Predictor of the model, None for the models that don't learn from the finished jobs.
+	pub fn predictor(&self) -> Option<Box<dyn RuntimePredictor>> {
+		match *self {
+			RuntimeModel::UserAverage(jobs) => Some(Box::new(UserAverage::new(jobs))),
+			RuntimeModel::Requested | RuntimeModel::Exact => None,
+		}
+	}
+}
+
+impl FromStr for RuntimeModel {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid runtime model '{}' (expected requested, user-average[:JOBS] or exact)", s);
+
+		match s.trim().split_once(':') {
+			None if s.trim() == "requested" => Ok(RuntimeModel::Requested),
+			None if s.trim() == "user-average" => Ok(RuntimeModel::UserAverage(2)),
+			None if s.trim() == "exact" => Ok(RuntimeModel::Exact),
+			Some(("user-average", jobs)) => match jobs.trim().parse() {
+				Ok(jobs) if jobs > 0 => Ok(RuntimeModel::UserAverage(jobs)),
+				_ => Err(invalid()),
+			},
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for RuntimeModel {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<RuntimeModel> for String {
+	fn from(model: RuntimeModel) -> Self {
+		model.to_string()
+	}
+}
+
+impl Display for RuntimeModel {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			RuntimeModel::Requested => write!(f, "requested"),
+			RuntimeModel::UserAverage(jobs) => write!(f, "user-average:{}", jobs),
+			RuntimeModel::Exact => write!(f, "exact"),
+		}
+	}
+}
@@ -0,0 +1,247 @@
+use std::{fmt::Display, fs, path::Path};
+
+use hashbrown::HashMap;
+use rhai::{AST, CallFnOptions, Dynamic, Engine, FLOAT, INT, Map, Scope};
+
+use super::{Clock, algos::{self, FromParams, Scheduler, SchedulerError, SchedulerParams}, cluster::Cluster, job::Job, queue::ReadyQueue};
+
+/// Operations a single call of a policy may take, so that a policy stuck in a loop fails
+/// instead of hanging the run.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A policy that fails to load, with the line at fault (from 1, 0 for the whole file).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+	pub line: usize,
+	pub message: String,
+}
+
+impl Display for ScriptError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.line {
+			0 => write!(f, "{}", self.message),
+			line => write!(f, "line {}: {}", line, self.message),
+		}
+	}
+}
+
+/// Functions a policy may define, with their parameters.
+const FUNCTIONS: &[(&str, &[&str])] = &[
+	("priority", &["job", "cluster"]),
+	("eligible", &["job", "cluster"]),
+	("on_submit", &["job"]),
+	("on_finish", &["job"]),
+];
+
+/// A scheduling policy written in Rhai (https://rhai.rs), so that policies can be prototyped
+/// without recompiling the crate, with the functions, loops and variables of a scripting
+/// language:
+///
+/// ```text
+/// // narrow jobs first, unless a job waited for a day
+/// fn priority(job, cluster) {
+///     if job.wait > 86400 { 1e9 + job.wait } else { -job.nodes }
+/// }
+///
+/// fn eligible(job, cluster) {
+///     job.user_nodes < cluster.total_nodes / 2
+/// }
+/// ```
+///
+/// `priority(job, cluster)` (required) orders the queue, the highest first, and
+/// `eligible(job, cluster)` (true if undefined) tells which jobs may start now. `job` has
+/// `id`, `nodes`, `submit`, `wait`, `requested_time`, `estimate`, `user`, `group`, `queue`,
+/// `qos` (-1 when unset), its `position` in the queue, and the nodes and jobs its user holds
+/// (`user_nodes`, `user_jobs`). `cluster` has `clock`, `total_nodes`, `free_nodes`,
+/// `down_nodes`, `reserved_nodes`, `running_jobs`, `queued_jobs` and `queued_nodes`.
+///
+/// Every function sees `this`, a map kept for the whole run, which `on_submit(job)` and
+/// `on_finish(job)` may update as jobs enter the queue and finish, for stateful policies.
+#[derive(Debug, Clone)]
+pub struct Policy {
+	ast: AST,
+	eligible: bool,
+	on_submit: bool,
+	on_finish: bool,
+}
+
+impl Policy {
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ScriptError> {
+		let text = fs::read_to_string(path).map_err(|why| ScriptError { line: 0, message: why.to_string() })?;
+		Self::parse(&text)
+	}
+
+	pub fn parse(text: &str) -> Result<Self, ScriptError> {
+		let ast = engine().compile(text).map_err(|why| ScriptError { line: why.1.line().unwrap_or(0), message: why.0.to_string() })?;
+
+		let mut defined = Vec::new();
+		for function in ast.iter_functions() {
+			let error = |message: String| ScriptError { line: 0, message };
+			match FUNCTIONS.iter().find(|(name, _)| *name == function.name) {
+				Some((name, params)) if function.params.len() != params.len() => {
+					return Err(error(format!("{} takes {} arguments ({}), {} given", name, params.len(), params.join(", "), function.params.len())));
+				}
+				Some((name, _)) => defined.push(*name),
+				None => {}
+			}
+		}
+
+		if !defined.contains(&"priority") {
+			return Err(ScriptError { line: 0, message: "the policy doesn't define priority(job, cluster)".to_string() });
+		}
+
+		Ok(Self {
+			ast,
+			eligible: defined.contains(&"eligible"),
+			on_submit: defined.contains(&"on_submit"),
+			on_finish: defined.contains(&"on_finish"),
+		})
+	}
+}
+
+fn engine() -> Engine {
+	let mut engine = Engine::new();
+	engine.set_max_operations(MAX_OPERATIONS);
+	engine
+}
+
+/// Policy of a `Script` without file: the first job that fits, in queue order.
+const DEFAULT_POLICY: &str = "fn priority(job, cluster) { -job.position }";
+
+/// Runs a `Policy` loaded from a file, e.g. `script(file=policy.rhai)`: jobs start by
+/// decreasing priority among the eligible ones, the first that fits starting, or, with
+/// `blocking=true`, the first one in priority order blocking the others until it fits.
+/// The run panics if a function of the policy fails, like a division by zero.
+#[derive(Debug)]
+pub struct Script {
+	/// File the policy was read from, `DEFAULT_POLICY` being run if None
+	pub file: Option<String>,
+	pub blocking: bool,
+	pub policy: Policy,
+	engine: Engine,
+	/// `this` of the functions of the policy
+	state: Dynamic,
+}
+
+impl FromParams for Script {
+	fn from_params(params: &SchedulerParams) -> Result<Self, SchedulerError> {
+		params.allow_only(&["file", "blocking"])?;
+
+		let file = params.get::<String>("file")?;
+		let policy = match &file {
+			Some(file) => Policy::load(file).map_err(|why| SchedulerError::InvalidScript(file.clone(), why.to_string()))?,
+			None => Policy::parse(DEFAULT_POLICY).expect("the default policy is valid"),
+		};
+
+		Ok(Self {
+			file,
+			blocking: params.get_or("blocking", false)?,
+			policy,
+			engine: engine(),
+			state: Map::new().into(),
+		})
+	}
+}
+
+/// The fields of a job every function of a policy sees.
+fn job_map(clock: Clock, job: &Job) -> Map {
+	let id = |id: Option<u32>| id.map_or(-1, INT::from);
+
+	map(&[
+		("id", job.id as INT), ("nodes", job.nodes as INT), ("submit", job.submit_time as INT),
+		("wait", clock.saturating_sub(job.submit_time) as INT), ("requested_time", job.requested_run_time as INT),
+		("estimate", job.estimate() as INT), ("user", id(job.user_id)), ("group", id(job.group_id)),
+		("queue", id(job.queue)), ("qos", id(job.qos)),
+	])
+}
+
+fn map(fields: &[(&str, INT)]) -> Map {
+	fields.iter().map(|&(key, value)| (key.into(), value.into())).collect()
+}
+
+impl Script {
+	/// Calls a function of the policy, with `this` bound to the state of the run.
+	fn call(&mut self, name: &str, args: impl rhai::FuncArgs) -> Dynamic {
+		let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.state);
+		self.engine.call_fn_with_options(options, &mut Scope::new(), &self.policy.ast, name, args)
+			.unwrap_or_else(|why| panic!("the policy {} failed in {}: {}", self.file.as_deref().unwrap_or("script"), name, why))
+	}
+
+	/// Positions of the eligible jobs of the queue, by decreasing priority.
+	fn order(&mut self, clock: Clock, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		let mut by_user: HashMap<Option<u32>, (u32, u32)> = HashMap::new();
+		for job in cluster.running_jobs.values() {
+			let (nodes, count) = by_user.entry(job.user_id).or_default();
+			*nodes += job.nodes;
+			*count += 1;
+		}
+
+		let partition = map(&[
+			("clock", clock as INT), ("total_nodes", cluster.total_nodes as INT), ("free_nodes", cluster.available_nodes as INT),
+			("down_nodes", cluster.down_nodes() as INT), ("reserved_nodes", cluster.reserved_nodes() as INT),
+			("running_jobs", cluster.running_jobs.len() as INT), ("queued_jobs", jobs.len() as INT), ("queued_nodes", jobs.queued_nodes() as INT),
+		]);
+
+		let mut ranked = Vec::new();
+		for (position, job) in jobs.iter().enumerate() {
+			let (user_nodes, user_jobs) = by_user.get(&job.user_id).copied().unwrap_or_default();
+			let mut map = job_map(clock, job);
+			map.insert("position".into(), (position as INT).into());
+			map.insert("user_nodes".into(), (user_nodes as INT).into());
+			map.insert("user_jobs".into(), (user_jobs as INT).into());
+
+			if self.policy.eligible {
+				let eligible = self.call("eligible", (map.clone(), partition.clone()));
+				if !eligible.as_bool().unwrap_or_else(|_| number(&eligible) != 0.0) {
+					continue;
+				}
+			}
+
+			let priority = number(&self.call("priority", (map, partition.clone())));
+			ranked.push((if priority.is_nan() { f64::NEG_INFINITY } else { priority }, position));
+		}
+
+		// stable, so that jobs of equal priority keep the order of the queue
+		ranked.sort_by(|(lhs, _), (rhs, _)| rhs.total_cmp(lhs));
+		ranked.into_iter().map(|(_, position)| position).collect()
+	}
+}
+
+/// Value of a number returned by a policy, NaN for other values.
+fn number(value: &Dynamic) -> f64 {
+	value.as_float().or_else(|_| value.as_int().map(|value| value as FLOAT)).unwrap_or(f64::NAN)
+}
+
+impl Scheduler for Script {
+	fn name(&self) -> &'static str {
+		"Script"
+	}
+
+	fn label(&self) -> String {
+		let file = self.file.as_ref().map(|file| Path::new(file).file_name().map_or_else(|| file.clone(), |name| name.to_string_lossy().into_owned()));
+		algos::label(self.name(), &[
+			("file", file),
+			("blocking", self.blocking.then(|| "true".to_string())),
+		])
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		let order = self.order(clock, jobs, cluster);
+		match self.blocking {
+			true => order.first().copied().filter(|&idx| cluster.fits(&jobs[idx])),
+			false => order.into_iter().find(|&idx| cluster.fits(&jobs[idx])),
+		}
+	}
+
+	fn on_job_arrival(&mut self, clock: u64, job: &Job) {
+		if self.policy.on_submit {
+			let _ = self.call("on_submit", (job_map(clock, job),));
+		}
+	}
+
+	fn on_job_finish(&mut self, clock: u64, job: &Job) {
+		if self.policy.on_finish {
+			let _ = self.call("on_finish", (job_map(clock, job),));
+		}
+	}
+}
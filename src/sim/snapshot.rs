@@ -0,0 +1,127 @@
+use std::{collections::BTreeMap, fs::File, io::{self, BufReader, BufWriter, Write}, path::Path};
+
+use min_max_heap::MinMaxHeap;
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, Engine, EngineError, Partition, PendingEvent, RunState, dependency::Dependencies, job, rng::SimRng, user::Users, walltime::WalltimeNoise};
+
+/// State of a simulation between two calls to `Engine::run_until`: clock, queues, clusters,
+/// pending events and the counters of the run.
+///
+/// Snapshots can be saved to pause a long simulation and resume it later, or cloned to
+/// restore an engine several times and compare what would happen from there. The trace and
+/// the outages are not part of the snapshot, they are read again when restoring it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EngineSnapshot {
+	workload: String,
+	nodes: u32,
+	clock: Clock,
+	events: Vec<PendingEvent>,
+	next_seq: u64,
+	partitions: Vec<Partition>,
+	/// Records of the trace read so far, and jobs among them counting towards the limit
+	records: usize,
+	read: usize,
+	submission_pending: bool,
+	outages_read: usize,
+	dependencies: Dependencies,
+	users: Users,
+	rejections: BTreeMap<String, u32>,
+	state: RunState,
+}
+
+impl EngineSnapshot {
+	/// Simulated time when the snapshot was taken.
+	pub fn clock(&self) -> Clock {
+		self.clock
+	}
+
+	/// Writes the snapshot as JSON, with the actual run times of the jobs, which are left out
+	/// when it is serialized otherwise, see `job::revealing`.
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		let mut out = BufWriter::new(File::create(path)?);
+		job::revealing(|| serde_json::to_writer(&mut out, self))?;
+		out.flush()
+	}
+
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, EngineError> {
+		let reader = BufReader::new(File::open(path)?);
+		serde_json::from_reader(reader).map_err(|why| EngineError::InvalidSnapshot(why.to_string()))
+	}
+}
+
+impl Engine {
+	/// Saves the state of the simulation, see `EngineSnapshot`.
+	pub fn snapshot(&self) -> EngineSnapshot {
+		EngineSnapshot {
+			workload: self.workload.clone(),
+			nodes: self.total_nodes,
+			clock: self.clock,
+			events: self.events.iter().cloned().collect(),
+			next_seq: self.next_seq,
+			partitions: self.partitions.clone(),
+			records: self.arrivals.records,
+			read: self.arrivals.read,
+			submission_pending: self.submission_pending,
+			outages_read: self.outages_read,
+			dependencies: self.dependencies.clone(),
+			users: self.users.clone(),
+			rejections: self.rejections.clone(),
+			state: self.state.clone(),
+		}
+	}
+
+	/// Puts the simulation back in the state of the snapshot, to go on with `run_until` or `run`.
+	///
+	/// The snapshot has to come from an engine simulating the same workload on the same
+	/// cluster with the same configuration, but the scheduler may differ. Records of the jobs
+	/// and decisions taken before the snapshot are attributed to the scheduler of this engine.
+	pub fn restore(&mut self, snapshot: EngineSnapshot) -> Result<(), EngineError> {
+		let same_partitions = snapshot.partitions.len() == self.partitions.len()
+			&& snapshot.partitions.iter().zip(&self.partitions).all(|(saved, partition)| saved.name == partition.name && saved.cluster.total_nodes == partition.cluster.total_nodes);
+
+		if snapshot.workload != self.workload || snapshot.nodes != self.total_nodes || !same_partitions {
+			return Err(EngineError::InvalidSnapshot(format!(
+				"taken while simulating {} on {} nodes, not {} on {} nodes", snapshot.workload, snapshot.nodes, self.workload, self.total_nodes,
+			)));
+		}
+
+		let (_, jobs) = self.source.open(&self.workload)?;
+		self.arrivals.fast_forward(jobs, WalltimeNoise::new(self.config.walltime, &SimRng::new(self.config.seed)), snapshot.records)?;
+		self.arrivals.read = snapshot.read;
+
+		self.outages = self.config.failures.outages(self.total_nodes, &SimRng::new(self.config.seed))?;
+		for _ in 0..snapshot.outages_read {
+			self.outages.next();
+		}
+
+		let mut state = snapshot.state;
+		for record in &mut state.job_records {
+			record.scheduler = self.label.clone();
+		}
+
+		for reject in &mut state.rejects {
+			reject.scheduler = self.label.clone();
+		}
+
+		for decision in &mut state.decisions {
+			decision.scheduler = self.label.clone();
+		}
+
+		for snapshot in &mut state.occupancy {
+			snapshot.scheduler = self.label.clone();
+		}
+
+		self.clock = snapshot.clock;
+		self.events = MinMaxHeap::from(snapshot.events);
+		self.next_seq = snapshot.next_seq;
+		self.partitions = snapshot.partitions;
+		self.submission_pending = snapshot.submission_pending;
+		self.outages_read = snapshot.outages_read;
+		self.dependencies = snapshot.dependencies;
+		self.users = snapshot.users;
+		self.rejections = snapshot.rejections;
+		self.state = state;
+		Ok(())
+	}
+}
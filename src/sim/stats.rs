@@ -0,0 +1,198 @@
+use std::{collections::BTreeMap, convert::TryFrom, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::report::Distribution;
+
+/// How the per-job metrics are summarized.
+///
+/// Written `exact` or `streaming`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum StatsMode {
+	/// Every value is kept until the end of the run, for exact medians and percentiles
+	#[default]
+	Exact,
+	/// Medians and percentiles are estimated as the jobs finish, within 0.5%, from the number
+	/// of values by range of values instead of the values, see `Histogram`
+	Streaming,
+}
+
+impl FromStr for StatsMode {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim() {
+			"exact" => Ok(StatsMode::Exact),
+			"streaming" => Ok(StatsMode::Streaming),
+			_ => Err(format!("invalid statistics mode '{}' (expected exact or streaming)", s)),
+		}
+	}
+}
+
+impl TryFrom<String> for StatsMode {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<StatsMode> for String {
+	fn from(mode: StatsMode) -> Self {
+		mode.to_string()
+	}
+}
+
+impl Display for StatsMode {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			StatsMode::Exact => write!(f, "exact"),
+			StatsMode::Streaming => write!(f, "streaming"),
+		}
+	}
+}
+
+/// Summary of a per-job metric, updated as the jobs finish, see `StatsMode`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stat {
+	count: usize,
+	sum: f64,
+	/// Infinite until a value is pushed, which JSON writes as null
+	#[serde(deserialize_with = "infinite_if_null")]
+	min: f64,
+	#[serde(deserialize_with = "neg_infinite_if_null")]
+	max: f64,
+	quantiles: Quantiles,
+}
+
+fn infinite_if_null<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+	Ok(Option::deserialize(deserializer)?.unwrap_or(f64::INFINITY))
+}
+
+fn neg_infinite_if_null<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+	Ok(Option::deserialize(deserializer)?.unwrap_or(f64::NEG_INFINITY))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Quantiles {
+	/// Every value so far
+	Exact(Vec<f64>),
+	/// Number of values by range of values
+	Streaming(Histogram),
+}
+
+impl Stat {
+	pub fn new(mode: StatsMode) -> Self {
+		Self {
+			count: 0,
+			sum: 0.0,
+			min: f64::INFINITY,
+			max: f64::NEG_INFINITY,
+			quantiles: match mode {
+				StatsMode::Exact => Quantiles::Exact(Vec::new()),
+				StatsMode::Streaming => Quantiles::Streaming(Histogram::default()),
+			},
+		}
+	}
+
+	pub fn push(&mut self, value: f64) {
+		self.count += 1;
+		self.sum += value;
+		self.min = self.min.min(value);
+		self.max = self.max.max(value);
+
+		match &mut self.quantiles {
+			Quantiles::Exact(values) => values.push(value),
+			Quantiles::Streaming(histogram) => histogram.push(value),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.count
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.count == 0
+	}
+
+	/// Lowest value, 0 without any.
+	pub fn min(&self) -> f64 {
+		if self.is_empty() { 0.0 } else { self.min }
+	}
+
+	/// Highest value, 0 without any.
+	pub fn max(&self) -> f64 {
+		if self.is_empty() { 0.0 } else { self.max }
+	}
+
+	/// Summarizes the values, exactly like `Distribution::from_values` in exact mode.
+	pub fn summary(&mut self) -> Distribution {
+		let count = self.count;
+		match &mut self.quantiles {
+			Quantiles::Exact(values) => Distribution::from_values(values),
+			Quantiles::Streaming(_) if count == 0 => Distribution::default(),
+			// at the same positions as `Distribution::from_values`
+			Quantiles::Streaming(histogram) => Distribution {
+				avg: self.sum / count as f64,
+				median: histogram.value_at(count / 2),
+				p95: histogram.value_at(((count as f64 * 0.95).ceil() as usize).clamp(1, count) - 1),
+				max: self.max,
+			},
+		}
+	}
+}
+
+/// Counts of the values by bucket of exponentially growing width, giving every quantile
+/// within `RELATIVE_ERROR` of the exact one whatever the order of the values, in memory
+/// growing with the logarithm of the range of the values and not with their number.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Histogram {
+	/// Values of 0 and below
+	zeros: usize,
+	/// Number of values of each bucket with the lowest and highest of them, by bucket index,
+	/// see `Histogram::bucket`
+	buckets: BTreeMap<i32, (usize, f64, f64)>,
+}
+
+/// Largest relative error of the quantiles estimated by a `Histogram`.
+const RELATIVE_ERROR: f64 = 0.005;
+
+impl Histogram {
+	/// Growth of the bucket bounds, bucket `idx` holding the values in `[GROWTH^idx, GROWTH^(idx + 1))`
+	const GROWTH: f64 = (1.0 + RELATIVE_ERROR) / (1.0 - RELATIVE_ERROR);
+
+	fn push(&mut self, value: f64) {
+		match value > 0.0 {
+			true => {
+				let (count, min, max) = self.buckets.entry(Self::bucket(value)).or_insert((0, value, value));
+				*count += 1;
+				*min = min.min(value);
+				*max = max.max(value);
+			}
+			false => self.zeros += 1,
+		}
+	}
+
+	fn bucket(value: f64) -> i32 {
+		(value.ln() / Self::GROWTH.ln()).floor() as i32
+	}
+
+	/// Estimated value at the given position among the sorted values, the middle of the
+	/// bucket holding it in relative terms, exact when the values of the bucket are equal.
+	fn value_at(&self, rank: usize) -> f64 {
+		if rank < self.zeros {
+			return 0.0;
+		}
+
+		let mut seen = self.zeros;
+		for (&idx, &(count, min, max)) in &self.buckets {
+			seen += count;
+			if rank < seen {
+				return (2.0 * Self::GROWTH.powi(idx + 1) / (1.0 + Self::GROWTH)).clamp(min, max);
+			}
+		}
+
+		0.0
+	}
+}
@@ -0,0 +1,233 @@
+use std::{convert::TryFrom, fmt::Display, str::FromStr};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::Deserialize;
+
+use super::{Clock, workload::{SwfJob, SwfStatus, Workload, WorkloadMeta}};
+
+/// How the jobs of a synthetic workload are drawn.
+///
+/// Written `poisson:MEAN_INTERARRIVAL:MIN_RUN:MAX_RUN` or `lublin[:ARRIVAL_FACTOR]`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum SynthModel {
+	/// Poisson arrivals, with sizes and run times log-uniform between 1 node and the whole
+	/// machine and between the given run times
+	Poisson { mean_interarrival: f64, min_run_time: Clock, max_run_time: Clock },
+	/// Model of batch jobs from Lublin and Feitelson (2003), without the daily cycle of the
+	/// arrivals, which are spread by the given factor to change the load
+	Lublin { arrival_factor: f64 },
+}
+
+// parameters of the batch jobs of the Lublin model
+const SERIAL_PROB: f64 = 0.244;
+const POW2_PROB: f64 = 0.576;
+const ULOW: f64 = 0.8;
+const UPROB: f64 = 0.86;
+const A1: f64 = 6.57;
+const B1: f64 = 0.823;
+const A2: f64 = 639.1;
+const B2: f64 = 0.0156;
+const PA: f64 = -0.0054;
+const PB: f64 = 0.78;
+const AARR: f64 = 10.2303;
+const BARR: f64 = 0.4871;
+
+impl SynthModel {
+	fn interarrival(&self, rng: &mut StdRng) -> f64 {
+		match *self {
+			SynthModel::Poisson { mean_interarrival, .. } => -(1.0 - rng.random::<f64>()).ln() * mean_interarrival,
+			SynthModel::Lublin { arrival_factor } => gamma(AARR, BARR, rng).exp() * arrival_factor,
+		}
+	}
+
+	fn size(&self, nodes: u32, rng: &mut StdRng) -> u32 {
+		let uhi = (nodes as f64).log2();
+
+		let size = match *self {
+			SynthModel::Poisson { .. } => rng.random_range(0.0..=uhi).exp2().round(),
+			SynthModel::Lublin { .. } => {
+				if rng.random::<f64>() < SERIAL_PROB {
+					1.0
+				} else {
+					// two-stage uniform distribution of log2(size)
+					let umed = (uhi - 2.5).max(ULOW);
+					let log_size = if rng.random::<f64>() < UPROB {
+						rng.random_range(ULOW..=umed)
+					} else {
+						rng.random_range(umed..=uhi.max(umed))
+					};
+
+					if rng.random::<f64>() < POW2_PROB {
+						log_size.round().exp2()
+					} else {
+						log_size.exp2().round()
+					}
+				}
+			}
+		};
+
+		(size as u32).clamp(1, nodes)
+	}
+
+	fn run_time(&self, size: u32, rng: &mut StdRng) -> Clock {
+		let run_time = match *self {
+			SynthModel::Poisson { min_run_time, max_run_time, .. } => {
+				let (low, high) = ((min_run_time.max(1) as f64).ln(), (max_run_time.max(1) as f64).ln());
+				rng.random_range(low..=high.max(low)).exp()
+			}
+			SynthModel::Lublin { .. } => {
+				// hyper-gamma distribution of log(run time), larger jobs running longer
+				let p = (PA * size as f64 + PB).clamp(0.0, 1.0);
+				let log_run_time = if rng.random::<f64>() < p { gamma(A1, B1, rng) } else { gamma(A2, B2, rng) };
+				log_run_time.exp()
+			}
+		};
+
+		(run_time.round() as Clock).max(1)
+	}
+}
+
+impl FromStr for SynthModel {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid synthetic model '{}' (expected poisson:MEAN_INTERARRIVAL:MIN_RUN:MAX_RUN or lublin[:ARRIVAL_FACTOR])", s);
+		let positive = |value: &str| match value.trim().parse::<f64>() {
+			Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+			_ => Err(invalid()),
+		};
+
+		let parts = s.split(':').collect::<Vec<_>>();
+		match parts.as_slice() {
+			["poisson", mean, min, max] => {
+				let (min_run_time, max_run_time) = (min.trim().parse().map_err(|_| invalid())?, max.trim().parse().map_err(|_| invalid())?);
+				if min_run_time > max_run_time {
+					return Err(invalid());
+				}
+
+				Ok(SynthModel::Poisson { mean_interarrival: positive(mean)?, min_run_time, max_run_time })
+			}
+			["lublin"] => Ok(SynthModel::Lublin { arrival_factor: 1.0 }),
+			["lublin", factor] => Ok(SynthModel::Lublin { arrival_factor: positive(factor)? }),
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for SynthModel {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl Display for SynthModel {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SynthModel::Poisson { mean_interarrival, min_run_time, max_run_time } => write!(f, "poisson:{}:{}:{}", mean_interarrival, min_run_time, max_run_time),
+			SynthModel::Lublin { arrival_factor } => write!(f, "lublin:{}", arrival_factor),
+		}
+	}
+}
+
+/// A synthetic workload of `jobs` jobs for a machine of `nodes` nodes, the same seed giving
+/// the same workload. Jobs request one processor per node and exactly their run time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthSpec {
+	pub model: SynthModel,
+	pub jobs: usize,
+	pub nodes: u32,
+	pub seed: u64,
+}
+
+impl SynthSpec {
+	pub fn generate(&self) -> Workload {
+		let mut rng = StdRng::seed_from_u64(self.seed);
+		let mut time = 0.0;
+
+		let jobs = (0..self.jobs).map(|idx| {
+			time += self.model.interarrival(&mut rng);
+			let size = self.model.size(self.nodes, &mut rng);
+			let run_time = self.model.run_time(size, &mut rng);
+
+			SwfJob {
+				job_number: idx as u32 + 1,
+				submit_time: time as Clock,
+				wait_time: None,
+				run_time: Some(run_time),
+				allocated_procs: Some(size),
+				avg_cpu_time: None,
+				used_memory: None,
+				requested_procs: Some(size),
+				requested_time: Some(run_time),
+				requested_memory: None,
+				status: Some(SwfStatus::Completed),
+				user_id: None,
+				group_id: None,
+				executable: None,
+				queue: None,
+				partition: None,
+				preceding_job: None,
+				think_time: None,
+			}
+		}).collect::<Vec<_>>();
+
+		let count = jobs.len() as u32;
+		let entries = [
+			("Version", "2.2".to_string()),
+			("Computer", "Synthetic".to_string()),
+			("Note", format!("Generated with the {} model and the seed {}", self.model, self.seed)),
+			("MaxJobs", count.to_string()),
+			("MaxRecords", count.to_string()),
+			("MaxNodes", self.nodes.to_string()),
+			("MaxProcs", self.nodes.to_string()),
+		];
+
+		Workload {
+			meta: WorkloadMeta {
+				version: Some("2.2".to_string()),
+				computer: Some("Synthetic".to_string()),
+				max_jobs: Some(count),
+				max_records: Some(count),
+				max_nodes: Some(self.nodes),
+				max_procs: Some(self.nodes),
+				entries: entries.iter().map(|(key, value)| (key.to_string(), value.clone())).collect(),
+				..WorkloadMeta::default()
+			},
+			jobs,
+		}
+	}
+}
+
+/// Draws from a gamma distribution of the given shape and scale (Marsaglia and Tsang).
+fn gamma(shape: f64, scale: f64, rng: &mut StdRng) -> f64 {
+	if shape < 1.0 {
+		let boost = rng.random::<f64>().powf(1.0 / shape);
+		return gamma(shape + 1.0, scale, rng) * boost;
+	}
+
+	let d = shape - 1.0 / 3.0;
+	let c = 1.0 / (9.0 * d).sqrt();
+
+	loop {
+		let x = normal(rng);
+		let v = (1.0 + c * x).powi(3);
+		if v <= 0.0 {
+			continue;
+		}
+
+		let u = 1.0 - rng.random::<f64>();
+		if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+			return d * v * scale;
+		}
+	}
+}
+
+/// Draws from the standard normal distribution (Box-Muller).
+fn normal(rng: &mut StdRng) -> f64 {
+	let u1 = 1.0 - rng.random::<f64>();
+	let u2 = rng.random::<f64>();
+	(-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
@@ -0,0 +1,155 @@
+use std::{fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use serde_json::{Value, json};
+
+use super::report::EngineReport;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TraceFormat {
+	/// Chrome trace-event format, readable by chrome://tracing and Perfetto.
+	/// Each run is a process, and jobs are drawn on the lane of their first node.
+	Chrome,
+	/// One JSON object per line, either a `job` or a `queue` sample, `run_index` telling runs apart
+	JsonLines,
+}
+
+impl TraceFormat {
+	/// Guesses the format from the file extension, defaulting to the Chrome format.
+	pub fn from_path(path: &Path) -> Self {
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some("jsonl") => TraceFormat::JsonLines,
+			_ => TraceFormat::Chrome,
+		}
+	}
+}
+
+/// Writes the timeline of one or more runs: when each job ran and on how many nodes,
+/// and how long the queue was over time. Reports must hold job records and the queue
+/// timeline, see `EngineConfig::record_jobs` and `EngineConfig::record_timeline`.
+pub struct TraceWriter<W: Write> {
+	out: W,
+	format: TraceFormat,
+	runs: usize,
+	events: usize,
+}
+
+impl TraceWriter<BufWriter<File>> {
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let path = path.as_ref();
+		let file = File::create(path)?;
+		Self::new(BufWriter::new(file), TraceFormat::from_path(path))
+	}
+}
+
+impl<W: Write> TraceWriter<W> {
+	pub fn new(mut out: W, format: TraceFormat) -> io::Result<Self> {
+		if format == TraceFormat::Chrome {
+			write!(out, "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[")?;
+		}
+
+		Ok(Self {
+			out,
+			format,
+			runs: 0,
+			events: 0,
+		})
+	}
+
+	pub fn write_run(&mut self, report: &EngineReport) -> io::Result<()> {
+		let run = self.runs;
+		self.runs += 1;
+
+		match self.format {
+			TraceFormat::Chrome => self.write_chrome(run, report),
+			TraceFormat::JsonLines => self.write_lines(run, report),
+		}
+	}
+
+	fn write_chrome(&mut self, run: usize, report: &EngineReport) -> io::Result<()> {
+		// simulated seconds are shown as microseconds, the unit of the format
+		let name = format!("{} on {} nodes ({})", report.scheduler_name, report.nodes, report.workload);
+		self.write_event(json!({ "name": "process_name", "ph": "M", "pid": run, "args": { "name": name } }))?;
+
+		for record in &report.job_records {
+			let lane = record.allocation.ranges().first().map(|range| range.start).unwrap_or(0);
+
+			self.write_event(json!({
+				"name": format!("job {}", record.id),
+				"cat": "job",
+				"ph": "X",
+				"ts": record.start,
+				"dur": record.end - record.start,
+				"pid": run,
+				"tid": lane,
+				"args": {
+					"nodes": record.nodes,
+					"allocation": record.allocation.to_string(),
+					"submit": record.submit,
+					"wait": record.wait,
+					"user": record.user_id,
+					"preemptions": record.preemptions,
+				},
+			}))?;
+		}
+
+		for &(time, length, nodes) in &report.queue_timeline {
+			self.write_event(json!({ "name": "queue", "ph": "C", "ts": time, "pid": run, "args": { "jobs": length, "nodes": nodes } }))?;
+		}
+
+		Ok(())
+	}
+
+	fn write_event(&mut self, event: Value) -> io::Result<()> {
+		if self.events > 0 {
+			write!(self.out, ",")?;
+		}
+
+		self.events += 1;
+		writeln!(self.out)?;
+		serde_json::to_writer(&mut self.out, &event)?;
+		Ok(())
+	}
+
+	fn write_lines(&mut self, run: usize, report: &EngineReport) -> io::Result<()> {
+		for record in &report.job_records {
+			let line = json!({
+				"type": "job",
+				"run_index": run,
+				"scheduler": report.scheduler_name,
+				"cluster_nodes": report.nodes,
+				"id": record.id,
+				"start": record.start,
+				"end": record.end,
+				"nodes": record.nodes,
+				"allocation": record.allocation.to_string(),
+			});
+
+			writeln!(self.out, "{}", line)?;
+		}
+
+		for &(time, length, nodes) in &report.queue_timeline {
+			let line = json!({
+				"type": "queue",
+				"run_index": run,
+				"scheduler": report.scheduler_name,
+				"cluster_nodes": report.nodes,
+				"time": time,
+				"length": length,
+				"nodes": nodes,
+			});
+
+			writeln!(self.out, "{}", line)?;
+		}
+
+		Ok(())
+	}
+
+	/// Terminates the output, which is incomplete until then.
+	pub fn finish(mut self) -> io::Result<()> {
+		if self.format == TraceFormat::Chrome {
+			writeln!(self.out, "]}}")?;
+		}
+
+		self.out.flush()
+	}
+}
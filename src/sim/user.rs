@@ -0,0 +1,178 @@
+use std::{convert::TryFrom, fmt::Display, str::FromStr};
+
+use hashbrown::HashMap;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, job::Job, rng::{SimRng, Stream}, workload::SwfStatus};
+
+/// How users react to the end of their jobs, submitting new jobs during the simulation
+/// (closed loop) on top of the replayed trace (open loop).
+///
+/// Written `none`, `resubmit:DELAY[:RETRIES]` or `follow-up:PROBABILITY:MEAN_THINK_TIME`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum UserModel {
+	/// Only the jobs of the trace are submitted
+	#[default]
+	None,
+	/// Jobs the trace marks as failed or cancelled are submitted again, up to `retries`
+	/// times, `delay` seconds after they end or after their think time if the trace gives one
+	Resubmit { delay: Clock, retries: u32 },
+	/// Once a job ends, its user submits the same job again with the given probability,
+	/// after its think time if the trace gives one or else a time drawn from an exponential
+	/// distribution of the given mean
+	FollowUp { probability: f64, mean_think_time: f64 },
+}
+
+impl FromStr for UserModel {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid user model '{}' (expected none, resubmit:DELAY[:RETRIES] or follow-up:PROBABILITY:MEAN_THINK_TIME)", s);
+
+		let parts = s.split(':').map(str::trim).collect::<Vec<_>>();
+		match parts.as_slice() {
+			["none"] => Ok(UserModel::None),
+			["resubmit", delay] => Ok(UserModel::Resubmit { delay: delay.parse().map_err(|_| invalid())?, retries: 1 }),
+			["resubmit", delay, retries] => Ok(UserModel::Resubmit {
+				delay: delay.parse().map_err(|_| invalid())?,
+				retries: retries.parse().map_err(|_| invalid())?,
+			}),
+			["follow-up", probability, mean_think_time] => {
+				let probability = probability.parse::<f64>().map_err(|_| invalid())?;
+				let mean_think_time = mean_think_time.parse::<f64>().map_err(|_| invalid())?;
+
+				// a probability of 1 would never stop submitting jobs
+				if (0.0..1.0).contains(&probability) && mean_think_time >= 0.0 && mean_think_time.is_finite() {
+					Ok(UserModel::FollowUp { probability, mean_think_time })
+				} else {
+					Err(invalid())
+				}
+			}
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for UserModel {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<UserModel> for String {
+	fn from(model: UserModel) -> Self {
+		model.to_string()
+	}
+}
+
+impl Display for UserModel {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			UserModel::None => write!(f, "none"),
+			UserModel::Resubmit { delay, retries } => write!(f, "resubmit:{}:{}", delay, retries),
+			UserModel::FollowUp { probability, mean_think_time } => write!(f, "follow-up:{}:{}", probability, mean_think_time),
+		}
+	}
+}
+
+/// Applies a user model to the jobs of a run, the same seed giving the same submissions.
+/// Random draws only depend on the seed and on the job that ended, so that they don't
+/// change with the order jobs end in.
+///
+/// Generated jobs get IDs counting down from `u32::MAX` so that they don't collide with
+/// the jobs of the trace, and share the position of the job they come from for the warm-up.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Users {
+	model: UserModel,
+	rng: SimRng,
+	next_id: u32,
+	/// Number of times each generated job was resubmitted
+	attempts: HashMap<u32, u32>,
+	/// Generated jobs not submitted yet
+	pending: usize,
+	/// Number of jobs generated so far
+	pub generated: u32,
+}
+
+impl Users {
+	pub fn new(model: UserModel, rng: &SimRng) -> Self {
+		Self {
+			model,
+			rng: *rng,
+			next_id: u32::MAX,
+			attempts: HashMap::new(),
+			pending: 0,
+			generated: 0,
+		}
+	}
+
+	/// The job the user of a job that just ended submits next, along with its submit time.
+	pub fn finished(&mut self, job: &Job, clock: Clock) -> Option<(Clock, Job)> {
+		let (think_time, attempts) = match self.model {
+			UserModel::None => return None,
+			UserModel::Resubmit { delay, retries } => {
+				let failed = matches!(job.status, Some(SwfStatus::Failed | SwfStatus::PartialFailed | SwfStatus::Cancelled));
+				let attempts = self.attempts.get(&job.id).copied().unwrap_or(0) + 1;
+				if !failed || attempts > retries {
+					return None;
+				}
+
+				(if job.think_time > 0 { job.think_time } else { delay }, attempts)
+			}
+			UserModel::FollowUp { probability, mean_think_time } => {
+				let mut rng = self.rng.keyed(Stream::Users, job.id as u64);
+				if rng.random::<f64>() >= probability {
+					return None;
+				}
+
+				let think_time = match job.think_time {
+					0 => (-(1.0 - rng.random::<f64>()).ln() * mean_think_time).round() as Clock,
+					think_time => think_time,
+				};
+
+				(think_time, 0)
+			}
+		};
+
+		let id = self.next_id;
+		self.next_id -= 1;
+		if attempts > 0 {
+			self.attempts.insert(id, attempts);
+		}
+
+		self.pending += 1;
+		self.generated += 1;
+
+		let submit_time = clock + think_time;
+		let mut copy = Job::new(id, job.nodes, submit_time, job.run_time, job.requested_run_time);
+		copy.submit_index = job.submit_index;
+		copy.user_id = job.user_id;
+		copy.group_id = job.group_id;
+		copy.queue = job.queue;
+		copy.partition = job.partition;
+		copy.requested_memory = job.requested_memory;
+		copy.memory_per_node = job.memory_per_node;
+		copy.malleable = job.malleable;
+		copy.traced_nodes = job.traced_nodes;
+		copy.status = job.status;
+		copy.classes = job.classes;
+		copy.qos = job.qos;
+		copy.max_wait = job.max_wait;
+
+		Some((submit_time, copy))
+	}
+
+	/// Records that a generated job was submitted.
+	pub fn release(&mut self) {
+		self.pending -= 1;
+	}
+
+	/// Number of generated jobs not submitted yet.
+	pub fn pending(&self) -> usize {
+		self.pending
+	}
+}
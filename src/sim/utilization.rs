@@ -0,0 +1,205 @@
+use std::{convert::TryFrom, fmt::Display, fs::File, io::{self, BufWriter, Write}, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, report::{Distribution, EngineReport, csv_field}};
+#[cfg(feature = "parquet")]
+use super::parquet::{ParquetFile, Values};
+
+/// When the utilization of the cluster is sampled.
+///
+/// Written `events` or `interval:SECONDS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum UtilizationSampling {
+	/// After every scheduling pass, at most one sample per timestamp
+	Events,
+	/// Every given number of seconds from the start of the simulation
+	Interval(Clock),
+}
+
+impl FromStr for UtilizationSampling {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid utilization sampling '{}' (expected events or interval:SECONDS)", s);
+
+		let parts = s.split(':').collect::<Vec<_>>();
+		match parts.as_slice() {
+			["events"] => Ok(UtilizationSampling::Events),
+			["interval", seconds] => match seconds.trim().parse() {
+				Ok(seconds) if seconds > 0 => Ok(UtilizationSampling::Interval(seconds)),
+				_ => Err(invalid()),
+			},
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for UtilizationSampling {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<UtilizationSampling> for String {
+	fn from(sampling: UtilizationSampling) -> Self {
+		sampling.to_string()
+	}
+}
+
+impl Display for UtilizationSampling {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			UtilizationSampling::Events => write!(f, "events"),
+			UtilizationSampling::Interval(seconds) => write!(f, "interval:{}", seconds),
+		}
+	}
+}
+
+/// Share of the nodes running jobs over the simulated period, between 0 and 1.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UtilizationTimeline {
+	sampling: UtilizationSampling,
+	samples: Vec<(Clock, f64)>,
+	/// Utilization since the last change, for the samples at a fixed interval
+	current: f64,
+	/// Time of the next sample at a fixed interval
+	next_sample: Clock,
+}
+
+impl UtilizationTimeline {
+	pub fn new(sampling: UtilizationSampling) -> Self {
+		Self {
+			sampling,
+			samples: Vec::new(),
+			current: 0.0,
+			next_sample: 0,
+		}
+	}
+
+	/// Records that `busy` of the `total` nodes run jobs from `clock` until the next call.
+	pub fn record(&mut self, clock: Clock, busy: u32, total: u32) {
+		let utilization = if total == 0 { 0.0 } else { busy as f64 / total as f64 };
+
+		match self.sampling {
+			UtilizationSampling::Events => match self.samples.last_mut() {
+				Some(last) if last.0 == clock => last.1 = utilization,
+				_ => self.samples.push((clock, utilization)),
+			},
+			UtilizationSampling::Interval(interval) => {
+				self.sample_until(clock, interval);
+				self.current = utilization;
+			}
+		}
+	}
+
+	/// Samples and their summary over the simulation ending at `end`, each sample
+	/// weighing the time until the next one.
+	pub fn finish(mut self, end: Clock) -> (Vec<(Clock, f64)>, Distribution) {
+		if let UtilizationSampling::Interval(interval) = self.sampling {
+			self.sample_until(end, interval);
+		}
+
+		let mut weighted = self.samples.iter().enumerate().map(|(idx, &(time, utilization))| {
+			let until = self.samples.get(idx + 1).map_or(end, |next| next.0);
+			(utilization, until.saturating_sub(time) as f64)
+		}).collect::<Vec<_>>();
+
+		let summary = Distribution::from_weighted(&mut weighted);
+		(self.samples, summary)
+	}
+
+	/// Adds the samples due before `clock`, at the current utilization.
+	fn sample_until(&mut self, clock: Clock, interval: Clock) {
+		while self.next_sample < clock {
+			self.samples.push((self.next_sample, self.current));
+			self.next_sample += interval;
+		}
+	}
+}
+
+/// Writes the utilization timelines of one or more runs as CSV, or as Parquet with the
+/// `parquet` feature.
+pub struct UtilizationWriter<W: Write> {
+	out: W,
+	header_written: bool,
+	#[cfg(feature = "parquet")]
+	parquet: Option<ParquetFile>,
+}
+
+impl UtilizationWriter<BufWriter<File>> {
+	/// Writes Parquet to the paths ending in `.parquet` if the feature is enabled, else CSV.
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let file = BufWriter::new(File::create(path.as_ref())?);
+
+		#[cfg(feature = "parquet")]
+		if path.as_ref().extension().is_some_and(|ext| ext == "parquet") {
+			return Ok(Self::parquet(file));
+		}
+
+		Ok(Self::new(file))
+	}
+}
+
+impl<W: Write> UtilizationWriter<W> {
+	pub fn new(out: W) -> Self {
+		Self {
+			out,
+			header_written: false,
+			#[cfg(feature = "parquet")]
+			parquet: None,
+		}
+	}
+
+	/// Writes a Parquet file with a row group per run, complete once `finish` is called.
+	#[cfg(feature = "parquet")]
+	pub fn parquet(out: W) -> Self {
+		Self {
+			out,
+			header_written: false,
+			parquet: Some(ParquetFile::default()),
+		}
+	}
+
+	pub fn write_run(&mut self, report: &EngineReport) -> io::Result<()> {
+		#[cfg(feature = "parquet")]
+		if let Some(parquet) = &mut self.parquet {
+			let timeline = &report.utilization_timeline;
+			return parquet.write_row_group(&mut self.out, &[
+				("workload", Values::Text(vec![report.workload.clone(); timeline.len()])),
+				("scheduler", Values::Text(vec![report.scheduler_name.clone(); timeline.len()])),
+				("nodes", Values::Int(vec![report.nodes as i64; timeline.len()])),
+				("time", Values::Int(timeline.iter().map(|&(time, _)| time as i64).collect())),
+				("utilization", Values::Float(timeline.iter().map(|&(_, utilization)| utilization).collect())),
+			]);
+		}
+
+		if !self.header_written {
+			writeln!(self.out, "workload,scheduler,nodes,time,utilization")?;
+			self.header_written = true;
+		}
+
+		for &(time, utilization) in &report.utilization_timeline {
+			writeln!(self.out, "{},{},{},{},{}", csv_field(&report.workload), csv_field(&report.scheduler_name), report.nodes, time, utilization)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.out.flush()
+	}
+
+	/// Terminates the output, Parquet files being incomplete until then.
+	pub fn finish(mut self) -> io::Result<()> {
+		#[cfg(feature = "parquet")]
+		if let Some(parquet) = &mut self.parquet {
+			parquet.finish(&mut self.out)?;
+		}
+
+		self.out.flush()
+	}
+}
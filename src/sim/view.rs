@@ -0,0 +1,124 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use super::{Clock, cluster::Cluster, job::Job};
+
+/// A copy of a partition that schedulers can play forward to try placements, without
+/// touching the state of the engine. Clone it to compare several plans.
+///
+/// Jobs are expected to run for their estimated run time (see `Job::estimate`), and jobs
+/// running past it to end at the next time step. The view knows nothing of the submissions,
+/// failures and events to come, and doesn't go further than its horizon.
+#[derive(Debug, Clone)]
+pub struct SimView<'a> {
+	jobs: &'a [Job],
+	clock: Clock,
+	end: Clock,
+	free_nodes: u32,
+	/// Expected ends of the running jobs, real and hypothetical, with their nodes
+	releases: BinaryHeap<Reverse<(Clock, u32)>>,
+	/// Positions in `jobs` of the jobs started in the view, with their start time
+	started: Vec<(usize, Clock)>,
+	is_started: Vec<bool>,
+}
+
+impl<'a> SimView<'a> {
+	/// A view of the cluster at the given time, with the given queued jobs (or a part of the
+	/// queue), which can be played up to `horizon` seconds ahead.
+	pub fn new(clock: Clock, jobs: &'a [Job], cluster: &Cluster, horizon: Clock) -> Self {
+		let releases = cluster.running_jobs.values()
+			.map(|job| Reverse((job.estimated_end(clock).max(clock + 1), job.nodes)))
+			.collect();
+
+		Self {
+			jobs,
+			clock,
+			end: clock.saturating_add(horizon),
+			free_nodes: cluster.available_nodes,
+			releases,
+			started: Vec::new(),
+			is_started: vec![false; jobs.len()],
+		}
+	}
+
+	pub fn clock(&self) -> Clock {
+		self.clock
+	}
+
+	/// Time past which the view can't be played.
+	pub fn horizon(&self) -> Clock {
+		self.end
+	}
+
+	pub fn free_nodes(&self) -> u32 {
+		self.free_nodes
+	}
+
+	pub fn jobs(&self) -> &'a [Job] {
+		self.jobs
+	}
+
+	/// Jobs started in the view, by position in `jobs`, with their start time.
+	pub fn started(&self) -> &[(usize, Clock)] {
+		&self.started
+	}
+
+	/// Positions in `jobs` of the jobs not started in the view, in queue order.
+	pub fn waiting(&self) -> impl Iterator<Item = usize> + '_ {
+		(0..self.jobs.len()).filter(move |&idx| !self.is_started[idx])
+	}
+
+	/// Whether the job at the given position can start now in the view.
+	pub fn fits(&self, index: usize) -> bool {
+		!self.is_started[index] && self.jobs[index].nodes <= self.free_nodes
+	}
+
+	/// Starts the job at the given position now, if it fits.
+	pub fn start(&mut self, index: usize) -> bool {
+		if !self.fits(index) {
+			return false;
+		}
+
+		let job = &self.jobs[index];
+		self.free_nodes -= job.nodes;
+		self.releases.push(Reverse((self.clock + job.estimate().max(1), job.nodes)));
+		self.started.push((index, self.clock));
+		self.is_started[index] = true;
+		true
+	}
+
+	/// Moves to the next time jobs are expected to end, releasing their nodes.
+	/// Returns false if no job ends before the horizon.
+	pub fn advance(&mut self) -> bool {
+		match self.releases.peek() {
+			Some(&Reverse((time, _))) if time <= self.end => {
+				self.advance_to(time);
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// Moves to the given time, bounded by the horizon, releasing the nodes of the jobs
+	/// expected to end by then.
+	pub fn advance_to(&mut self, time: Clock) {
+		let time = time.min(self.end);
+
+		while let Some(&Reverse((end, nodes))) = self.releases.peek() {
+			if end > time {
+				break;
+			}
+
+			self.free_nodes += nodes;
+			self.releases.pop();
+		}
+
+		self.clock = self.clock.max(time);
+	}
+
+	/// Total wait of the jobs, the jobs not started in the view waiting until the horizon.
+	pub fn total_wait(&self) -> Clock {
+		let started = self.started.iter().map(|&(idx, start)| start.saturating_sub(self.jobs[idx].submit_time));
+		let waiting = self.waiting().map(|idx| self.end.saturating_sub(self.jobs[idx].submit_time));
+		started.chain(waiting).sum()
+	}
+}
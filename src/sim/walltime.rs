@@ -0,0 +1,179 @@
+use std::{convert::TryFrom, fmt::Display, str::FromStr};
+
+use rand::{Rng, rngs::StdRng};
+use serde::Deserialize;
+
+use super::{Clock, job::Job, rng::{SimRng, Stream}};
+
+/// How the requested run time of the jobs (their walltime) is derived, to study how
+/// schedulers relying on it behave with more or less accurate estimates.
+///
+/// Written `trace`, `exact`, `factor:F` or `uniform:MIN:MAX`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum WalltimeModel {
+	/// Keep the time requested in the trace
+	#[default]
+	Trace,
+	/// Perfect estimates, the requested time is the run time
+	Exact,
+	/// The run time multiplied by a factor
+	Factor(f64),
+	/// The run time multiplied by a factor drawn uniformly between two bounds for each job
+	Uniform(f64, f64),
+}
+
+impl WalltimeModel {
+	/// Requested run time of the job under this model.
+	pub fn requested_run_time(&self, job: &Job, rng: &mut StdRng) -> Clock {
+		let factor = match *self {
+			WalltimeModel::Trace => return job.requested_run_time,
+			WalltimeModel::Exact => return job.run_time,
+			WalltimeModel::Factor(factor) => factor,
+			WalltimeModel::Uniform(min, max) => rng.random_range(min..=max),
+		};
+
+		(job.run_time as f64 * factor).ceil() as Clock
+	}
+}
+
+impl FromStr for WalltimeModel {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid walltime model '{}' (expected trace, exact, factor:F or uniform:MIN:MAX)", s);
+		let factor = |value: &str| match value.trim().parse::<f64>() {
+			Ok(factor) if factor > 0.0 && factor.is_finite() => Ok(factor),
+			_ => Err(invalid()),
+		};
+
+		let mut parts = s.split(':');
+		let model = match (parts.next().unwrap_or("").trim().to_ascii_lowercase().as_str(), parts.next(), parts.next()) {
+			("trace", None, None) => WalltimeModel::Trace,
+			("exact", None, None) => WalltimeModel::Exact,
+			("factor", Some(value), None) => WalltimeModel::Factor(factor(value)?),
+			("uniform", Some(min), Some(max)) => {
+				let (min, max) = (factor(min)?, factor(max)?);
+				if min > max {
+					return Err(invalid());
+				}
+
+				WalltimeModel::Uniform(min, max)
+			}
+			_ => return Err(invalid()),
+		};
+
+		match parts.next() {
+			Some(_) => Err(invalid()),
+			None => Ok(model),
+		}
+	}
+}
+
+impl TryFrom<String> for WalltimeModel {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl Display for WalltimeModel {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			WalltimeModel::Trace => write!(f, "trace"),
+			WalltimeModel::Exact => write!(f, "exact"),
+			WalltimeModel::Factor(factor) => write!(f, "factor:{}", factor),
+			WalltimeModel::Uniform(min, max) => write!(f, "uniform:{}:{}", min, max),
+		}
+	}
+}
+
+/// Applies a walltime model to the jobs of a run, the same seed giving the same estimates.
+pub struct WalltimeNoise {
+	model: WalltimeModel,
+	rng: StdRng,
+}
+
+impl WalltimeNoise {
+	pub fn new(model: WalltimeModel, rng: &SimRng) -> Self {
+		Self {
+			model,
+			rng: rng.stream(Stream::Walltime),
+		}
+	}
+
+	pub fn apply(&mut self, job: &mut Job) {
+		job.requested_run_time = self.model.requested_run_time(job, &mut self.rng);
+	}
+}
+
+/// What happens to the jobs running longer than they requested: they run to completion
+/// like most traces record them, or the resource manager kills them at their walltime and
+/// they count as failed. Killed jobs may be submitted again requesting `factor` times more
+/// time, up to `retries` times, starting over.
+///
+/// Written `none`, `kill` or `resubmit:FACTOR[:RETRIES]`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum WalltimeLimit {
+	/// Jobs run their whole run time
+	#[default]
+	None,
+	/// Jobs are killed at their walltime
+	Kill,
+	/// Jobs are killed at their walltime, and submitted again with a larger request
+	Resubmit { factor: f64, retries: u32 },
+}
+
+impl WalltimeLimit {
+	/// Time the current run of a running job ends, at its walltime if it is killed there.
+	pub fn end_time(&self, job: &Job) -> Clock {
+		match self {
+			WalltimeLimit::None => job.end_time(),
+			_ => job.end_time().min(job.expected_end),
+		}
+	}
+}
+
+impl FromStr for WalltimeLimit {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid walltime limit '{}' (expected none, kill or resubmit:FACTOR[:RETRIES])", s);
+		let factor = |value: &str| match value.parse::<f64>() {
+			Ok(factor) if factor > 1.0 && factor.is_finite() => Ok(factor),
+			_ => Err(invalid()),
+		};
+
+		let parts = s.split(':').map(str::trim).collect::<Vec<_>>();
+		match parts.as_slice() {
+			["none"] => Ok(WalltimeLimit::None),
+			["kill"] => Ok(WalltimeLimit::Kill),
+			["resubmit", value] => Ok(WalltimeLimit::Resubmit { factor: factor(value)?, retries: 1 }),
+			["resubmit", value, retries] => Ok(WalltimeLimit::Resubmit {
+				factor: factor(value)?,
+				retries: retries.parse().map_err(|_| invalid())?,
+			}),
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl TryFrom<String> for WalltimeLimit {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl Display for WalltimeLimit {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			WalltimeLimit::None => write!(f, "none"),
+			WalltimeLimit::Kill => write!(f, "kill"),
+			WalltimeLimit::Resubmit { factor, retries } => write!(f, "resubmit:{}:{}", factor, retries),
+		}
+	}
+}
@@ -0,0 +1,775 @@
+use std::{collections::HashMap, fmt::Display, fs::File, io::{self, BufRead, BufReader, BufWriter, Cursor, Lines, Write}, path::Path, str::FromStr, sync::Arc};
+
+use flate2::bufread::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use xz2::bufread::XzDecoder;
+
+use super::{Clock, EngineError, accounting, batsim, cluster::DEFAULT_CORES_PER_NODE, job::Job};
+
+/// Completion status of a job, as recorded in the trace.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwfStatus {
+	Failed,
+	Completed,
+	/// Partial execution, to be continued by another record
+	PartialContinued,
+	/// Last partial execution, the job completed
+	PartialCompleted,
+	/// Last partial execution, the job failed
+	PartialFailed,
+	Cancelled,
+}
+
+impl SwfStatus {
+	fn from_code(code: i64) -> Option<Self> {
+		match code {
+			0 => Some(SwfStatus::Failed),
+			1 => Some(SwfStatus::Completed),
+			2 => Some(SwfStatus::PartialContinued),
+			3 => Some(SwfStatus::PartialCompleted),
+			4 => Some(SwfStatus::PartialFailed),
+			5 => Some(SwfStatus::Cancelled),
+			_ => None,
+		}
+	}
+
+	fn code(&self) -> i64 {
+		match self {
+			SwfStatus::Failed => 0,
+			SwfStatus::Completed => 1,
+			SwfStatus::PartialContinued => 2,
+			SwfStatus::PartialCompleted => 3,
+			SwfStatus::PartialFailed => 4,
+			SwfStatus::Cancelled => 5,
+		}
+	}
+}
+
+/// One line of a Standard Workload Format trace, with all of its 18 fields.
+/// Fields recorded as -1 (unknown) in the trace are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwfJob {
+	pub job_number: u32,
+	pub submit_time: Clock,
+	pub wait_time: Option<Clock>,
+	pub run_time: Option<Clock>,
+	pub allocated_procs: Option<u32>,
+	pub avg_cpu_time: Option<f64>,
+	/// In kilobytes per processor
+	pub used_memory: Option<f64>,
+	pub requested_procs: Option<u32>,
+	pub requested_time: Option<Clock>,
+	/// In kilobytes per processor
+	pub requested_memory: Option<f64>,
+	pub status: Option<SwfStatus>,
+	pub user_id: Option<u32>,
+	pub group_id: Option<u32>,
+	pub executable: Option<u32>,
+	pub queue: Option<u32>,
+	pub partition: Option<u32>,
+	pub preceding_job: Option<u32>,
+	pub think_time: Option<Clock>,
+}
+
+/// Error about a field of an SWF line, its position being filled in by `SwfReader`.
+fn malformed(field: Option<&'static str>, reason: String) -> EngineError {
+	EngineError::MalformedTrace { file: String::new(), line: 0, field, reason }
+}
+
+/// Parses a field that must be known.
+fn field<T: FromStr>(value: &str, name: &'static str) -> Result<T, EngineError>
+where T::Err: Display {
+	value.parse().map_err(|why: T::Err| malformed(Some(name), format!("'{}' ({})", value, why)))
+}
+
+/// Parses an integer field, negative values meaning the field is unknown.
+fn int_field<T: FromStr>(value: &str, name: &'static str) -> Result<Option<T>, EngineError>
+where T::Err: Display {
+	if value.starts_with('-') {
+		// make sure this is a number anyway
+		field::<i64>(value, name)?;
+		Ok(None)
+	} else {
+		Ok(Some(field(value, name)?))
+	}
+}
+
+fn float_field(value: &str, name: &'static str) -> Result<Option<f64>, EngineError> {
+	let value: f64 = field(value, name)?;
+	Ok(if value < 0.0 { None } else { Some(value) })
+}
+
+impl SwfJob {
+	/// Parses a line of an SWF trace. Errors are `EngineError::MalformedTrace`, without the
+	/// position of the line unless read by a `SwfReader`.
+	pub fn parse(line: &str) -> Result<Self, EngineError> {
+		let split = line.split_whitespace().collect::<Vec<_>>();
+		if split.len() != 18 {
+			return Err(malformed(None, format!("expected 18 fields, found {}", split.len())));
+		}
+
+		Ok(Self {
+			job_number: 		field(split[0], "job_number")?,
+			submit_time: 		field(split[1], "submit_time")?,
+			wait_time: 			int_field(split[2], "wait_time")?,
+			run_time: 			int_field(split[3], "run_time")?,
+			allocated_procs: 	int_field(split[4], "allocated_procs")?,
+			avg_cpu_time: 		float_field(split[5], "avg_cpu_time")?,
+			used_memory: 		float_field(split[6], "used_memory")?,
+			requested_procs: 	int_field(split[7], "requested_procs")?,
+			requested_time: 	int_field(split[8], "requested_time")?,
+			requested_memory: 	float_field(split[9], "requested_memory")?,
+			status: 			int_field(split[10], "status")?.and_then(SwfStatus::from_code),
+			user_id: 			int_field(split[11], "user_id")?,
+			group_id: 			int_field(split[12], "group_id")?,
+			executable: 		int_field(split[13], "executable")?,
+			queue: 				int_field(split[14], "queue")?,
+			partition: 			int_field(split[15], "partition")?,
+			preceding_job: 		int_field(split[16], "preceding_job")?,
+			think_time: 		int_field(split[17], "think_time")?,
+		})
+	}
+
+	/// Converts this record into a simulated job running on nodes of `procs_per_node` processors.
+	/// The requested processors and time fall back to the allocated ones and the actual run time.
+	/// Jobs cancelled before they ran get their cancellation time, their wait time being the
+	/// time until they were cancelled. Returns None if the record lacks the information needed
+	/// to simulate it.
+	pub fn to_job(&self, procs_per_node: u32) -> Option<Job> {
+		let cancelled = self.status == Some(SwfStatus::Cancelled) && self.run_time.is_none_or(|run_time| run_time == 0);
+		let run_time = match cancelled {
+			true => 0,
+			false => self.run_time?,
+		};
+		let procs = self.requested_procs.or(self.allocated_procs)?;
+		let requested_run_time = self.requested_time.unwrap_or(run_time);
+
+		let nodes = procs.div_ceil(procs_per_node);
+		if nodes == 0 {
+			return None;
+		}
+
+		let mut job = Job::new(self.job_number, nodes, self.submit_time, run_time, requested_run_time);
+		job.user_id = self.user_id;
+		job.group_id = self.group_id;
+		job.queue = self.queue;
+		job.partition = self.partition;
+		job.requested_memory = self.requested_memory;
+		job.status = self.status;
+		job.cancel_time = cancelled.then(|| self.submit_time + self.wait_time.unwrap_or(0));
+		job.trace_start = self.wait_time.filter(|_| !cancelled).map(|wait_time| self.submit_time + wait_time);
+		job.preceding_job = self.preceding_job;
+		job.think_time = self.think_time.unwrap_or(0);
+		Some(job)
+	}
+}
+
+/// The 18 fields of the record as a trace line, unknown fields being written -1.
+impl Display for SwfJob {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		fn int<T: Display>(value: Option<T>) -> String {
+			value.map_or_else(|| "-1".to_string(), |value| value.to_string())
+		}
+
+		write!(
+			f, "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+			self.job_number, self.submit_time, int(self.wait_time), int(self.run_time), int(self.allocated_procs),
+			int(self.avg_cpu_time), int(self.used_memory), int(self.requested_procs), int(self.requested_time),
+			int(self.requested_memory), int(self.status.map(|status| status.code())), int(self.user_id), int(self.group_id),
+			int(self.executable), int(self.queue), int(self.partition), int(self.preceding_job), int(self.think_time),
+		)
+	}
+}
+
+/// Subset of a trace to simulate, chosen as the trace is read: the jobs submitted within a
+/// window of days, by some users, on enough nodes. The submit times of the jobs kept are
+/// re-based so that the first of them arrives at 0, see `WorkloadFilter::origin`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkloadFilter {
+	/// Day of the trace (from its start, counting from 0) from which jobs are kept
+	#[serde(default)]
+	pub from_day: Option<u32>,
+	/// Day of the trace from which jobs are left out
+	#[serde(default)]
+	pub to_day: Option<u32>,
+	/// Users whose jobs are kept, every user if empty
+	#[serde(default)]
+	pub users: Vec<u32>,
+	/// Jobs on fewer nodes are left out
+	#[serde(default)]
+	pub min_nodes: Option<u32>,
+}
+
+impl WorkloadFilter {
+	/// Whether the whole trace is kept, submit times being left as is.
+	pub fn is_empty(&self) -> bool {
+		self == &Self::default()
+	}
+
+	/// Whether a job of the trace is kept, given with its submit time in the trace.
+	pub fn accepts(&self, job: &Job) -> bool {
+		let day = job.submit_time / 86400;
+		self.from_day.is_none_or(|from| day >= from as Clock)
+			&& self.to_day.is_none_or(|to| day < to as Clock)
+			&& (self.users.is_empty() || job.user_id.is_some_and(|user| self.users.contains(&user)))
+			&& self.min_nodes.is_none_or(|min| job.nodes >= min)
+	}
+
+	/// Time the submit times are re-based from, given the first job kept: the start of the
+	/// window of days if any, so that jobs keep their time of day, else the first submission.
+	pub fn origin(&self, first: &Job) -> Clock {
+		match self.from_day {
+			Some(day) => day as Clock * 86400,
+			None => first.submit_time,
+		}
+	}
+}
+
+/// Information found in the header comments of a trace.
+#[derive(Debug, Clone, Default)]
+pub struct WorkloadMeta {
+	pub version: Option<String>,
+	pub computer: Option<String>,
+	pub installation: Option<String>,
+	pub max_jobs: Option<u32>,
+	pub max_records: Option<u32>,
+	pub max_nodes: Option<u32>,
+	pub max_procs: Option<u32>,
+	pub unix_start_time: Option<i64>,
+	pub time_zone_string: Option<String>,
+	pub preemption: Option<String>,
+
+	/// Every `; Key: value` entry of the header, in order, including the ones above
+	pub entries: Vec<(String, String)>,
+}
+
+impl WorkloadMeta {
+	/// Processors per node of the traced machine, if the header tells both of them.
+	pub fn procs_per_node(&self) -> Option<u32> {
+		match (self.max_procs, self.max_nodes) {
+			(Some(procs), Some(nodes)) if nodes > 0 && procs >= nodes => Some(procs / nodes),
+			_ => None,
+		}
+	}
+
+	/// Reads a header comment line (without the leading `;`), ignoring free-form comments.
+	pub(crate) fn parse_comment(&mut self, comment: &str) {
+		let (key, value) = match comment.split_once(':') {
+			Some((key, value)) => (key.trim(), value.trim()),
+			None => return,
+		};
+
+		if key.is_empty() || key.contains(char::is_whitespace) {
+			return;
+		}
+
+		match key {
+			"Version" => self.version = Some(value.to_string()),
+			"Computer" => self.computer = Some(value.to_string()),
+			"Installation" => self.installation = Some(value.to_string()),
+			"MaxJobs" => self.max_jobs = value.parse().ok(),
+			"MaxRecords" => self.max_records = value.parse().ok(),
+			"MaxNodes" => self.max_nodes = value.parse().ok(),
+			"MaxProcs" => self.max_procs = value.parse().ok(),
+			"UnixStartTime" => self.unix_start_time = value.parse().ok(),
+			"TimeZoneString" => self.time_zone_string = Some(value.to_string()),
+			"Preemption" => self.preemption = Some(value.to_string()),
+			_ => {}
+		}
+
+		self.entries.push((key.to_string(), value.to_string()));
+	}
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Opens a trace file, transparently decompressing it if it is gzip or xz compressed.
+/// The compression is detected from the first bytes of the file, not from its extension.
+pub fn open_trace<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead + Send>, EngineError> {
+	decompress(BufReader::new(File::open(path)?))
+}
+
+fn decompress<R: BufRead + Send + 'static>(mut reader: R) -> Result<Box<dyn BufRead + Send>, EngineError> {
+	let magic = reader.fill_buf()?;
+
+	Ok(if magic.starts_with(GZIP_MAGIC) {
+		Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+	} else if magic.starts_with(XZ_MAGIC) {
+		#[cfg(not(target_arch = "wasm32"))]
+		return Ok(Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader))));
+		#[cfg(target_arch = "wasm32")]
+		return Err(io::Error::new(io::ErrorKind::Unsupported, "xz compressed workloads can't be read on this target").into());
+	} else {
+		Box::new(reader)
+	})
+}
+
+/// Format of a workload file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WorkloadFormat {
+	/// Standard Workload Format
+	Swf,
+	/// JSON workload of Batsim, see `batsim::read`
+	Batsim,
+	/// Output of Slurm's `sacct --parsable2`, see `accounting::read_sacct`
+	Sacct,
+	/// PBS accounting log, see `accounting::read_pbs`
+	Pbs,
+}
+
+impl WorkloadFormat {
+	/// Guesses the format from the file extension, ignoring a compression extension and
+	/// defaulting to SWF.
+	pub fn from_path(path: &Path) -> Self {
+		let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+		let name = name.strip_suffix(".gz").or_else(|| name.strip_suffix(".xz")).unwrap_or(name);
+
+		if name.ends_with(".json") {
+			WorkloadFormat::Batsim
+		} else {
+			WorkloadFormat::Swf
+		}
+	}
+
+	/// Guesses the format from the file extension, then from the first line of the files
+	/// taken for SWF traces, which tells accounting logs apart whatever their name.
+	pub fn detect(path: &Path, reader: &mut dyn BufRead) -> io::Result<Self> {
+		let format = Self::from_path(path);
+		if format != WorkloadFormat::Swf {
+			return Ok(format);
+		}
+
+		let start = reader.fill_buf()?;
+		let first_line = String::from_utf8_lossy(start.split(|&byte| byte == b'\n').next().unwrap_or_default());
+
+		Ok(if accounting::is_sacct_header(&first_line) {
+			WorkloadFormat::Sacct
+		} else if accounting::is_pbs_record(&first_line) {
+			WorkloadFormat::Pbs
+		} else {
+			WorkloadFormat::Swf
+		})
+	}
+}
+
+/// Jobs of a workload file, in submission order, read lazily when possible.
+pub type JobStream = Box<dyn Iterator<Item = Result<SwfJob, EngineError>> + Send>;
+
+/// Opens a workload file of any format, which may be compressed, reading its header.
+pub fn open_workload<P: AsRef<Path>>(path: P) -> Result<(WorkloadMeta, JobStream), EngineError> {
+	let path = path.as_ref();
+	read_workload(path, &path.display().to_string(), open_trace(path)?)
+}
+
+/// Reads a workload held in memory, e.g. a file uploaded to a web page, `name` standing for
+/// its file name to guess the format. The data may be compressed.
+pub fn read_workload_bytes(name: &str, data: Arc<[u8]>) -> Result<(WorkloadMeta, JobStream), EngineError> {
+	read_workload(Path::new(name), name, decompress(Cursor::new(data))?)
+}
+
+fn read_workload(path: &Path, name: &str, mut reader: Box<dyn BufRead + Send>) -> Result<(WorkloadMeta, JobStream), EngineError> {
+	match WorkloadFormat::detect(path, &mut reader)? {
+		WorkloadFormat::Swf => {
+			let reader = SwfReader::new(reader)?.named(name);
+			Ok((reader.meta().clone(), Box::new(reader)))
+		}
+		WorkloadFormat::Batsim => {
+			let (meta, jobs) = batsim::read(reader)?;
+			Ok((meta, Box::new(jobs.into_iter().map(Ok))))
+		}
+		WorkloadFormat::Sacct => {
+			let (meta, jobs) = accounting::read_sacct(reader)?;
+			Ok((meta, Box::new(jobs.into_iter().map(Ok))))
+		}
+		WorkloadFormat::Pbs => {
+			let (meta, jobs) = accounting::read_pbs(reader)?;
+			Ok((meta, Box::new(jobs.into_iter().map(Ok))))
+		}
+	}
+}
+
+/// Reads the jobs of a trace one at a time, so a trace never needs to fit in memory.
+/// The header is read as soon as the reader is created.
+pub struct SwfReader<R: BufRead> {
+	lines: Lines<R>,
+	meta: WorkloadMeta,
+	/// Name of the trace in the errors, its path when opened from a file
+	name: String,
+	/// Number of lines read so far
+	line: usize,
+	/// First job line and its number, read along with the header
+	pending: Option<(usize, String)>,
+}
+
+impl<R: BufRead> SwfReader<R> {
+	pub fn new(reader: R) -> Result<Self, EngineError> {
+		let mut swf = Self {
+			lines: reader.lines(),
+			meta: WorkloadMeta::default(),
+			name: "trace".to_string(),
+			line: 0,
+			pending: None,
+		};
+
+		swf.pending = swf.next_line()?;
+		Ok(swf)
+	}
+
+	/// Names the trace in the errors about its lines.
+	pub fn named(mut self, name: &str) -> Self {
+		self.name = name.to_string();
+		self
+	}
+
+	pub fn meta(&self) -> &WorkloadMeta {
+		&self.meta
+	}
+
+	/// Returns the next job line and its number, reading the comments found on the way.
+	fn next_line(&mut self) -> Result<Option<(usize, String)>, EngineError> {
+		if let Some(line) = self.pending.take() {
+			return Ok(Some(line));
+		}
+
+		for line in &mut self.lines {
+			let line = line?;
+			self.line += 1;
+			let trimmed = line.trim();
+
+			if trimmed.is_empty() {
+				continue;
+			}
+
+			if let Some(comment) = trimmed.strip_prefix(';') {
+				self.meta.parse_comment(comment);
+				continue;
+			}
+
+			return Ok(Some((self.line, line)));
+		}
+
+		Ok(None)
+	}
+}
+
+impl SwfReader<Box<dyn BufRead + Send>> {
+	/// Opens a trace file, which may be gzip or xz compressed.
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, EngineError> {
+		let path = path.as_ref();
+		Ok(Self::new(open_trace(path)?)?.named(&path.display().to_string()))
+	}
+}
+
+impl<R: BufRead> Iterator for SwfReader<R> {
+	type Item = Result<SwfJob, EngineError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		match self.next_line() {
+			Ok(Some((number, line))) => Some(SwfJob::parse(&line).map_err(|why| match why {
+				EngineError::MalformedTrace { field, reason, .. } => EngineError::MalformedTrace { file: self.name.clone(), line: number, field, reason },
+				why => why,
+			})),
+			Ok(None) => None,
+			Err(why) => Some(Err(why)),
+		}
+	}
+}
+
+/// A whole trace loaded in memory.
+#[derive(Debug, Clone, Default)]
+pub struct Workload {
+	pub meta: WorkloadMeta,
+	pub jobs: Vec<SwfJob>,
+}
+
+impl Workload {
+	/// Loads a workload file of any format, which may be gzip or xz compressed.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, EngineError> {
+		let path = path.as_ref();
+		let mut reader = open_trace(path)?;
+
+		let (meta, jobs) = match WorkloadFormat::detect(path, &mut reader)? {
+			WorkloadFormat::Swf => return Self::collect(SwfReader::new(reader)?.named(&path.display().to_string())),
+			WorkloadFormat::Batsim => batsim::read(reader)?,
+			WorkloadFormat::Sacct => accounting::read_sacct(reader)?,
+			WorkloadFormat::Pbs => accounting::read_pbs(reader)?,
+		};
+		Ok(Self { meta, jobs })
+	}
+
+	/// Writes the workload as an SWF trace, with the header entries of `meta`.
+	pub fn write_swf<W: Write>(&self, mut out: W) -> io::Result<()> {
+		for (key, value) in &self.meta.entries {
+			writeln!(out, "; {}: {}", key, value)?;
+		}
+
+		for job in &self.jobs {
+			writeln!(out, "{}", job)?;
+		}
+
+		out.flush()
+	}
+
+	pub fn save_swf<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		self.write_swf(BufWriter::new(File::create(path)?))
+	}
+
+	/// Reads an SWF trace.
+	pub fn read<R: BufRead>(reader: R) -> Result<Self, EngineError> {
+		Self::collect(SwfReader::new(reader)?)
+	}
+
+	fn collect<R: BufRead>(mut reader: SwfReader<R>) -> Result<Self, EngineError> {
+		let jobs = (&mut reader).collect::<Result<Vec<_>, _>>()?;
+
+		Ok(Self {
+			meta: reader.meta,
+			jobs,
+		})
+	}
+}
+
+/// Builds small workloads in code, e.g. for tests of the schedulers that shouldn't need
+/// traces on disk, to be simulated with `Engine::from_workload`, as in
+/// `WorkloadBuilder::new().job(1, 4, 0, 100).job(2, 2, 10, 50).requesting(80).build()`.
+/// Jobs use one processor per node, the header telling as much.
+#[derive(Debug, Clone, Default)]
+pub struct WorkloadBuilder {
+	jobs: Vec<SwfJob>,
+}
+
+impl WorkloadBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds a job of `nodes` nodes submitted at `submit` and completed after running for
+	/// `run` seconds, which it requested.
+	pub fn job(mut self, id: u32, nodes: u32, submit: Clock, run: Clock) -> Self {
+		self.jobs.push(SwfJob {
+			job_number: id,
+			submit_time: submit,
+			wait_time: None,
+			run_time: Some(run),
+			allocated_procs: Some(nodes),
+			avg_cpu_time: None,
+			used_memory: None,
+			requested_procs: Some(nodes),
+			requested_time: Some(run),
+			requested_memory: None,
+			status: Some(SwfStatus::Completed),
+			user_id: None,
+			group_id: None,
+			executable: None,
+			queue: None,
+			partition: None,
+			preceding_job: None,
+			think_time: None,
+		});
+		self
+	}
+
+	/// Sets the run time requested by the last job added.
+	pub fn requesting(mut self, time: Clock) -> Self {
+		if let Some(job) = self.jobs.last_mut() {
+			job.requested_time = Some(time);
+		}
+		self
+	}
+
+	/// Sets the user of the last job added.
+	pub fn user(mut self, user: u32) -> Self {
+		if let Some(job) = self.jobs.last_mut() {
+			job.user_id = Some(user);
+		}
+		self
+	}
+
+	/// The jobs in order of submission, with a header telling their number and the largest
+	/// of them as the size of the machine.
+	pub fn build(mut self) -> Workload {
+		self.jobs.sort_by_key(|job| job.submit_time);
+
+		let count = self.jobs.len() as u32;
+		let nodes = self.jobs.iter().filter_map(|job| job.requested_procs).max().unwrap_or(1);
+		let entries = [
+			("Version", "2.2".to_string()),
+			("MaxJobs", count.to_string()),
+			("MaxRecords", count.to_string()),
+			("MaxNodes", nodes.to_string()),
+			("MaxProcs", nodes.to_string()),
+		];
+
+		Workload {
+			meta: WorkloadMeta {
+				version: Some("2.2".to_string()),
+				max_jobs: Some(count),
+				max_records: Some(count),
+				max_nodes: Some(nodes),
+				max_procs: Some(nodes),
+				entries: entries.iter().map(|(key, value)| (key.to_string(), value.clone())).collect(),
+				..WorkloadMeta::default()
+			},
+			jobs: self.jobs,
+		}
+	}
+}
+
+/// How `Workload::export` processes a trace before it is written back out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportOptions {
+	/// Jobs kept, their submit times being re-based like in a simulation
+	pub filter: WorkloadFilter,
+	/// Submit times are divided by this factor, see `EngineConfig::load_factor`
+	pub load_factor: f64,
+	/// Only the first N jobs kept are written
+	pub limit: Option<usize>,
+	/// Processors per node, to filter the jobs on their number of nodes. Defaults to the one
+	/// of the header, see `WorkloadMeta::procs_per_node`
+	pub procs_per_node: Option<u32>,
+	/// Renumbers the users, groups and executables in order of first appearance
+	pub anonymize: bool,
+}
+
+impl Default for ExportOptions {
+	fn default() -> Self {
+		Self {
+			filter: WorkloadFilter::default(),
+			load_factor: 1.0,
+			limit: None,
+			procs_per_node: None,
+			anonymize: false,
+		}
+	}
+}
+
+/// Number of each distinct value in order of first appearance, from 1.
+#[derive(Debug, Default)]
+struct Renumbering(HashMap<u32, u32>);
+
+impl Renumbering {
+	fn apply(&mut self, value: Option<u32>) -> Option<u32> {
+		let next = self.0.len() as u32 + 1;
+		value.map(|value| *self.0.entry(value).or_insert(next))
+	}
+}
+
+impl Workload {
+	/// The workload as a standalone trace once filtered and transformed, to be shared or fed
+	/// to other simulators. Jobs are numbered again from 1, preceding jobs left out being
+	/// forgotten, and the header tells the new number of jobs, the new start time and what
+	/// was done to the trace. Records that can't be simulated are kept unless filtered out.
+	pub fn export(&self, options: &ExportOptions) -> Workload {
+		let procs_per_node = options.procs_per_node.or(self.meta.procs_per_node()).unwrap_or(DEFAULT_CORES_PER_NODE).max(1);
+		let mut origin = None;
+		let mut numbers = HashMap::new();
+		let (mut users, mut groups, mut executables) = (Renumbering::default(), Renumbering::default(), Renumbering::default());
+		let mut jobs = Vec::new();
+
+		for swf in &self.jobs {
+			if options.limit.is_some_and(|limit| jobs.len() >= limit) {
+				break;
+			}
+
+			let mut job = swf.clone();
+
+			if !options.filter.is_empty() {
+				// filtered on what the simulation would see of the record, even if incomplete
+				let nodes = swf.requested_procs.or(swf.allocated_procs).map_or(0, |procs| procs.div_ceil(procs_per_node));
+				let mut proxy = Job::new(swf.job_number, nodes, swf.submit_time, 0, 0);
+				proxy.user_id = swf.user_id;
+
+				if !options.filter.accepts(&proxy) {
+					continue;
+				}
+
+				let origin = *origin.get_or_insert_with(|| options.filter.origin(&proxy));
+				job.submit_time -= origin;
+			}
+
+			if options.load_factor != 1.0 {
+				job.submit_time = (job.submit_time as f64 / options.load_factor).round() as Clock;
+			}
+
+			if options.anonymize {
+				job.user_id = users.apply(job.user_id);
+				job.group_id = groups.apply(job.group_id);
+				job.executable = executables.apply(job.executable);
+			}
+
+			job.job_number = jobs.len() as u32 + 1;
+			numbers.insert(swf.job_number, job.job_number);
+			jobs.push(job);
+		}
+
+		for job in &mut jobs {
+			job.preceding_job = job.preceding_job.and_then(|preceding| numbers.get(&preceding).copied());
+			if job.preceding_job.is_none() {
+				job.think_time = None;
+			}
+		}
+
+		Workload { meta: self.export_meta(options, jobs.len(), origin.unwrap_or(0)), jobs }
+	}
+
+	/// Header of an exported trace, see `Workload::export`.
+	fn export_meta(&self, options: &ExportOptions, jobs: usize, origin: Clock) -> WorkloadMeta {
+		let mut note = vec![format!("Rewritten by chamallow from {} records", self.jobs.len())];
+		let filter = &options.filter;
+		if let Some(day) = filter.from_day {
+			note.push(format!("jobs from day {}", day));
+		}
+		if let Some(day) = filter.to_day {
+			note.push(format!("jobs before day {}", day));
+		}
+		if !filter.users.is_empty() {
+			note.push(format!("jobs of {} users", filter.users.len()));
+		}
+		if let Some(nodes) = filter.min_nodes {
+			note.push(format!("jobs on at least {} nodes", nodes));
+		}
+		if !filter.is_empty() {
+			note.push(format!("submit times re-based by {} s", origin));
+		}
+		if options.load_factor != 1.0 {
+			note.push(format!("submit times divided by {}", options.load_factor));
+		}
+		if let Some(limit) = options.limit {
+			note.push(format!("limited to {} jobs", limit));
+		}
+		if options.anonymize {
+			note.push("users, groups and executables renumbered".to_string());
+		}
+		note.push("jobs renumbered from 1".to_string());
+
+		let mut meta = WorkloadMeta::default();
+		let mut entries = self.meta.entries.clone();
+		let mut set = |key: &str, value: String| match entries.iter_mut().find(|(entry, _)| entry == key) {
+			Some((_, old)) => *old = value,
+			None => entries.push((key.to_string(), value)),
+		};
+
+		set("MaxJobs", jobs.to_string());
+		set("MaxRecords", jobs.to_string());
+		if let Some(start) = self.meta.unix_start_time {
+			set("UnixStartTime", (start + origin as i64).to_string());
+		}
+
+		if self.meta.version.is_none() {
+			entries.insert(0, ("Version".to_string(), "2.2".to_string()));
+		}
+		if jobs < self.jobs.len() || options.load_factor != 1.0 {
+			// the dates of the first and last jobs no longer hold
+			entries.retain(|(key, _)| key != "StartTime" && key != "EndTime");
+		}
+		entries.push(("Note".to_string(), note.join(", ")));
+		for (key, value) in entries {
+			meta.parse_comment(&format!("{}: {}", key, value));
+		}
+
+		meta
+	}
+}
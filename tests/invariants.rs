@@ -0,0 +1,126 @@
+//! Properties every run must have, whatever the workload and the scheduler: checked on
+//! random synthetic workloads (see `SynthSpec`) with every scheduler of the registry.
+
+use chamallow::{AllocationPolicy, Cluster, ClusterSpec, Engine, EngineConfig, Job, Malleability, Moldability, ReadyQueue, RuntimeModel, Scheduler, SchedulingCycle, SimRng, SpeedupModel, SynthModel, SynthSpec, algos};
+use proptest::prelude::*;
+
+/// Wraps a scheduler to check the cluster it is given at every call.
+struct Checked {
+	inner: Box<dyn Scheduler>,
+	clock: u64,
+}
+
+impl Checked {
+	fn check(&mut self, clock: u64, cluster: &Cluster) {
+		assert!(clock >= self.clock, "the clock went back to {}", clock);
+		self.clock = clock;
+
+		let running = cluster.running_jobs.values().map(|job| job.nodes).sum::<u32>();
+		assert!(cluster.available_nodes <= cluster.total_nodes, "{} nodes available out of {}", cluster.available_nodes, cluster.total_nodes);
+		assert_eq!(cluster.available_nodes + running + cluster.down_nodes() + cluster.reserved_nodes(), cluster.total_nodes, "nodes were lost at {}", clock);
+	}
+}
+
+impl Scheduler for Checked {
+	fn name(&self) -> &'static str {
+		self.inner.name()
+	}
+
+	fn label(&self) -> String {
+		self.inner.label()
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		self.check(clock, cluster);
+		self.inner.schedule(clock, jobs, cluster)
+	}
+
+	fn schedule_batch(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<usize> {
+		self.check(clock, cluster);
+		self.inner.schedule_batch(clock, jobs, cluster)
+	}
+
+	fn schedule_shaped(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<(usize, Option<usize>)> {
+		self.check(clock, cluster);
+		self.inner.schedule_shaped(clock, jobs, cluster)
+	}
+
+	fn complete_batches(&self) -> bool {
+		self.inner.complete_batches()
+	}
+
+	fn preempt(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<u32> {
+		self.check(clock, cluster);
+		self.inner.preempt(clock, jobs, cluster)
+	}
+
+	fn resize(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Vec<(u32, u32)> {
+		self.check(clock, cluster);
+		self.inner.resize(clock, jobs, cluster)
+	}
+
+	fn on_job_arrival(&mut self, clock: u64, job: &Job) {
+		self.inner.on_job_arrival(clock, job);
+	}
+
+	fn on_job_finish(&mut self, clock: u64, job: &Job) {
+		self.inner.on_job_finish(clock, job);
+	}
+
+	fn on_job_cancel(&mut self, clock: u64, job: &Job) {
+		self.inner.on_job_cancel(clock, job);
+	}
+
+	fn on_tick(&mut self, clock: u64) {
+		assert!(clock >= self.clock, "the clock went back to {}", clock);
+		self.inner.on_tick(clock);
+	}
+
+	fn seed(&mut self, rng: &SimRng) {
+		self.inner.seed(rng);
+	}
+}
+
+fn workloads() -> impl Strategy<Value = SynthSpec> {
+	let poisson = (1.0..600.0, 1u64..100, 1u64..20_000).prop_map(|(mean_interarrival, min_run_time, extra)| {
+		SynthModel::Poisson { mean_interarrival, min_run_time, max_run_time: min_run_time + extra }
+	});
+	let lublin = (0.1..4.0).prop_map(|arrival_factor| SynthModel::Lublin { arrival_factor });
+
+	(prop_oneof![poisson, lublin], 1usize..150, 1u32..128, any::<u64>())
+		.prop_map(|(model, jobs, nodes, seed)| SynthSpec { model, jobs, nodes, seed })
+}
+
+proptest! {
+	#[test]
+	fn runs_keep_invariants(
+		spec in workloads(),
+		scheduler in proptest::sample::select(algos::names().collect::<Vec<_>>()),
+		preemption_overhead in proptest::option::of(0u64..60),
+		allocation in proptest::sample::select(vec![AllocationPolicy::FirstFit, AllocationPolicy::BestFit, AllocationPolicy::Contiguous, AllocationPolicy::Torus(4, 4, 8)]),
+		cycle in prop_oneof![Just(SchedulingCycle::Event), Just(SchedulingCycle::Instant), (1u64..600).prop_map(SchedulingCycle::Every)],
+		malleability in prop_oneof![
+			Just(Malleability::None),
+			(0.0..=1.0, 1.0..4.0, prop_oneof![Just(SpeedupModel::Linear), (0.0..=1.0).prop_map(SpeedupModel::Amdahl)])
+				.prop_map(|(fraction, factor, speedup)| Malleability::Jobs { fraction, factor, speedup }),
+		],
+		moldability in prop_oneof![
+			Just(Moldability::None),
+			(0.0..=1.0, 1.0..8.0, prop_oneof![Just(SpeedupModel::Linear), (0.0..4.0).prop_map(SpeedupModel::Downey)])
+				.prop_map(|(fraction, factor, speedup)| Moldability::Jobs { fraction, factor, speedup }),
+		],
+		runtime in prop_oneof![Just(RuntimeModel::Requested), (1usize..4).prop_map(RuntimeModel::UserAverage), Just(RuntimeModel::Exact)],
+	) {
+		let config = EngineConfig { record_jobs: true, preemption_overhead, cycle, allocation, malleability, moldability, runtime, ..EngineConfig::default() };
+		let checked = Checked { inner: algos::by_name(scheduler).unwrap(), clock: 0 };
+		let cluster = ClusterSpec::with_cores_per_node(spec.nodes, 1);
+		let report = Engine::from_workload(Box::new(checked), cluster, "synthetic", spec.generate(), config).unwrap().run().unwrap();
+
+		// every job ends, and only once
+		prop_assert_eq!(report.jobs + report.trimmed_jobs + report.rejected_jobs as usize, spec.jobs);
+		prop_assert_eq!(report.job_records.len(), report.jobs + report.trimmed_jobs);
+
+		prop_assert!(report.used_ressources <= report.makespan * spec.nodes as u64);
+		prop_assert!(report.job_records.iter().all(|record| record.submit <= record.start && record.start + record.run <= record.end && record.end <= report.makespan));
+	}
+}
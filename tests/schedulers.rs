@@ -0,0 +1,743 @@
+//! Decisions of the schedulers on small workloads built in code, see `WorkloadBuilder`.
+
+use std::sync::{Arc, Mutex};
+
+use chamallow::{Cluster, ClusterSpec, Comparison, Confidence, Drain, Engine, EngineConfig, EngineReport, EngineSnapshot, NodeClassSpec, ReadyQueue, Scheduler, Workload, WorkloadBuilder, algos, sim::joblog::{JobLogFormat, JobLogWriter, JobRecord}};
+
+/// Simulates the workload on `nodes` nodes, returning the records of its jobs by id.
+fn simulate(scheduler: &str, nodes: u32, workload: Workload) -> Vec<JobRecord> {
+	simulate_on(scheduler, ClusterSpec::with_cores_per_node(nodes, 1), workload)
+}
+
+fn simulate_on(scheduler: &str, cluster: ClusterSpec, workload: Workload) -> Vec<JobRecord> {
+	let config = EngineConfig { record_jobs: true, ..EngineConfig::default() };
+	let mut report = Engine::from_workload(algos::by_name(scheduler).unwrap(), cluster, "fixture", workload, config).unwrap().run().unwrap();
+
+	report.job_records.sort_by_key(|record| record.id);
+	report.job_records
+}
+
+/// A directory of its own for the files of a test, removed with them when dropped, even if the
+/// test fails, so that tests running at the same time don't share files.
+struct TempDir(std::path::PathBuf);
+
+impl TempDir {
+	fn new(test: &str) -> Self {
+		let dir = std::env::temp_dir().join(format!("chamallow-{}-{}", std::process::id(), test));
+		std::fs::create_dir_all(&dir).unwrap();
+		TempDir(dir)
+	}
+
+	fn join(&self, file: &str) -> std::path::PathBuf {
+		self.0.join(file)
+	}
+}
+
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_dir_all(&self.0);
+	}
+}
+
+/// Two cpu nodes and two gpu nodes, jobs 1 and 2 running on the gpu ones.
+fn gpu_cluster() -> ClusterSpec {
+	let mut cluster = ClusterSpec::with_cores_per_node(4, 1);
+	cluster.node_classes = vec![NodeClassSpec { nodes: Some(2), ..NodeClassSpec::new("cpu") }, NodeClassSpec::new("gpu")];
+	cluster.class_map = Arc::new([(1, "gpu".to_string()), (2, "gpu".to_string())].iter().cloned().collect());
+	cluster
+}
+
+/// A job holding the gpu nodes, a gpu job waiting for them and a job fitting on the cpu nodes.
+fn gpu_blocked() -> Workload {
+	WorkloadBuilder::new()
+		.job(1, 2, 0, 100)
+		.job(2, 1, 1, 50)
+		.job(3, 2, 2, 500)
+		.build()
+}
+
+/// A wide job blocked behind a running one, followed by a short job fitting beside it.
+fn blocked() -> Workload {
+	WorkloadBuilder::new()
+		.job(1, 3, 0, 100)
+		.job(2, 4, 1, 10)
+		.job(3, 1, 2, 50)
+		.build()
+}
+
+fn starts(records: &[JobRecord]) -> Vec<u64> {
+	records.iter().map(|record| record.start).collect()
+}
+
+#[test]
+fn fcfs_keeps_the_order_of_submission() {
+	assert_eq!(starts(&simulate("fcfs", 4, blocked())), [0, 100, 110]);
+}
+
+#[test]
+fn easy_backfills_jobs_ending_before_the_reservation() {
+	assert_eq!(starts(&simulate("easy", 4, blocked())), [0, 100, 2]);
+}
+
+#[test]
+fn easy_doesnt_delay_the_first_job() {
+	// the short job would end after the start reserved for the wide one
+	let workload = WorkloadBuilder::new()
+		.job(1, 3, 0, 100)
+		.job(2, 4, 1, 10)
+		.job(3, 1, 2, 500)
+		.build();
+
+	assert_eq!(starts(&simulate("easy", 4, workload)), [0, 100, 110]);
+}
+
+#[test]
+fn easy_backfills_beside_the_reservation_without_delaying_it() {
+	// job 2 reserves 4 of the 8 nodes freed at 100: job 3 runs past the reservation on the
+	// nodes it leaves, job 4 ends before it
+	let workload = || WorkloadBuilder::new()
+		.job(1, 6, 0, 100)
+		.job(2, 4, 1, 50)
+		.job(3, 1, 2, 500)
+		.job(4, 1, 3, 90)
+		.build();
+
+	assert_eq!(starts(&simulate("fcfs", 8, workload())), [0, 100, 100, 100]);
+	assert_eq!(starts(&simulate("easy", 8, workload())), [0, 100, 2, 3]);
+}
+
+#[test]
+fn conservative_doesnt_delay_any_reservation() {
+	// job 4 fits beside the reservation of job 2, but would delay job 3 until 203
+	let workload = || WorkloadBuilder::new()
+		.job(1, 2, 0, 100)
+		.job(2, 3, 1, 50)
+		.job(3, 4, 2, 50)
+		.job(4, 1, 3, 200)
+		.build();
+
+	assert_eq!(starts(&simulate("easy", 4, workload())), [0, 100, 203, 3]);
+	assert_eq!(starts(&simulate("conservative", 4, workload())), [0, 100, 150, 200]);
+}
+
+#[test]
+fn conservative_reserves_the_nodes_of_the_class_of_the_job() {
+	// job 2 waits for the gpu nodes, leaving the cpu ones to job 3
+	assert_eq!(starts(&simulate_on("conservative", gpu_cluster(), gpu_blocked())), [0, 100, 2]);
+}
+
+#[test]
+fn only_narrower_jobs_starting_first_skip_a_job() {
+	let skips = |records: Vec<JobRecord>| records.iter().map(|record| record.skips).collect::<Vec<_>>();
+
+	// the short narrow job starts first under SJF, keeping the wide one waiting
+	let narrow_last = || WorkloadBuilder::new()
+		.job(1, 4, 0, 100)
+		.job(2, 4, 1, 100)
+		.job(3, 1, 2, 10)
+		.build();
+
+	assert_eq!(skips(simulate("sjf", 4, narrow_last())), [0, 1, 0]);
+	assert_eq!(skips(simulate("ljf", 4, narrow_last())), [0, 0, 0]);
+
+	// a wider job starting first doesn't skip the narrower one
+	let wide_last = WorkloadBuilder::new()
+		.job(1, 4, 0, 100)
+		.job(2, 2, 1, 50)
+		.job(3, 3, 2, 100)
+		.build();
+
+	assert_eq!(starts(&simulate("ljf", 4, wide_last.clone())), [0, 200, 100]);
+	assert_eq!(skips(simulate("ljf", 4, wide_last)), [0, 0, 0]);
+}
+
+/// A job holding the whole machine, then a long wide job queued before narrow short ones.
+fn wide_first(narrow: u32) -> Workload {
+	(0..narrow).fold(WorkloadBuilder::new().job(1, 4, 0, 100).job(2, 4, 1, 1000), |workload, idx| workload.job(3 + idx, 1, 2 + idx as u64, 10)).build()
+}
+
+#[test]
+fn lookahead_starts_the_narrow_jobs_before_the_wide_one() {
+	assert_eq!(starts(&simulate("fcfs", 4, wide_first(2))), [0, 100, 1100, 1100]);
+	assert_eq!(starts(&simulate("lookahead", 4, wide_first(2))), [0, 110, 100, 100]);
+
+	// 8 jobs in the window, ordered by simulated annealing
+	let annealed = starts(&simulate("lookahead(depth=8)", 4, wide_first(7)));
+	assert_eq!(annealed[1], 120);
+	assert!(annealed[2..].iter().all(|&start| start <= 110));
+}
+
+#[test]
+fn lookahead_plans_with_the_nodes_of_the_class_of_each_job() {
+	assert_eq!(starts(&simulate_on("lookahead", gpu_cluster(), gpu_blocked())), [0, 100, 2]);
+}
+
+/// Three jobs queued behind a job holding the whole machine, no two of them fitting together:
+/// the widest (2), the longest (1) and the one of smallest area (3).
+fn queued_behind() -> Workload {
+	WorkloadBuilder::new()
+		.job(1, 4, 0, 100)
+		.job(2, 3, 1, 300)
+		.job(3, 4, 2, 50)
+		.job(4, 2, 3, 20)
+		.build()
+}
+
+#[test]
+fn saf_starts_the_job_of_smallest_area_first() {
+	// areas of 900, 200 and 40 node-seconds
+	assert_eq!(starts(&simulate("saf", 4, queued_behind())), [0, 170, 120, 100]);
+}
+
+#[test]
+fn ljf_starts_the_widest_job_first() {
+	assert_eq!(starts(&simulate("ljf", 4, queued_behind())), [0, 150, 100, 450]);
+}
+
+#[test]
+fn lpt_starts_the_longest_requested_job_first() {
+	assert_eq!(starts(&simulate("lpt", 4, queued_behind())), [0, 100, 400, 450]);
+}
+
+#[test]
+fn wfp3_favours_the_job_that_waited_the_longest_for_its_length() {
+	// at 100, (99 / 100)^3 * 2 = 1.94 for job 2 and (10 / 20)^3 * 4 = 0.5 for job 3,
+	// although job 3 has the smaller area
+	let workload = || WorkloadBuilder::new()
+		.job(1, 4, 0, 100)
+		.job(2, 2, 1, 100)
+		.job(3, 4, 90, 20)
+		.build();
+
+	assert_eq!(starts(&simulate("wfp3", 4, workload())), [0, 100, 200]);
+	assert_eq!(starts(&simulate("saf", 4, workload())), [0, 120, 100]);
+}
+
+#[test]
+fn bin_packing_schedulers_fill_the_free_nodes() {
+	// three nodes free when jobs on 2, 3 and 1 nodes arrive together
+	let workload = || WorkloadBuilder::new()
+		.job(1, 1, 0, 100)
+		.job(2, 2, 1, 10)
+		.job(3, 3, 1, 10)
+		.job(4, 1, 1, 10)
+		.build();
+
+	assert_eq!(starts(&simulate("bf", 4, workload())), [0, 11, 1, 11]);
+	assert_eq!(starts(&simulate("ffd", 4, workload())), [0, 11, 1, 11]);
+	assert_eq!(starts(&simulate("wf", 4, workload())), [0, 1, 11, 1]);
+}
+
+#[test]
+fn twolevel_keeps_the_short_pool_for_short_jobs() {
+	// two long jobs and a short one on a machine split in half
+	let workload = WorkloadBuilder::new()
+		.job(1, 2, 0, 100).requesting(7200)
+		.job(2, 2, 1, 100).requesting(7200)
+		.job(3, 1, 2, 50).requesting(60)
+		.build();
+
+	assert_eq!(starts(&simulate("easy", 4, workload.clone())), [0, 1, 100]);
+	assert_eq!(starts(&simulate("twolevel(short_share=0.5)", 4, workload)), [0, 100, 2]);
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics_are_served_past_a_silent_client() {
+	use std::{io::{Read, Write}, net::TcpStream, sync::Arc};
+	use chamallow::sim::metrics::LiveMetrics;
+
+	let live = Arc::new(LiveMetrics::default());
+	live.register("fixture", "FCFS", 4, 1.5, 7, None);
+	let addr = live.serve("127.0.0.1:0").unwrap();
+
+	// connects and never sends its request
+	let _silent = TcpStream::connect(addr).unwrap();
+
+	let mut client = TcpStream::connect(addr).unwrap();
+	client.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+	let mut answer = String::new();
+	client.read_to_string(&mut answer).unwrap();
+
+	assert!(answer.starts_with("HTTP/1.1 200 OK"));
+	assert!(answer.contains(r#"chamallow_run_done{workload="fixture",scheduler="FCFS",nodes="4",load_factor="1.5",seed="7"} 0"#));
+}
+
+#[cfg(feature = "script")]
+#[test]
+fn script_starts_jobs_by_priority() {
+	let dir = TempDir::new("script_starts_jobs_by_priority");
+	let policy = dir.join("widest-first.rhai");
+	std::fs::write(&policy, "// widest first\nfn priority(job, cluster) { job.nodes }\n").unwrap();
+	let widest = format!("script(file={})", policy.display());
+	let blocking = format!("script(file={}, blocking=true)", policy.display());
+
+	assert_eq!(starts(&simulate(&widest, 4, blocked())), [0, 100, 2]);
+	// the wide job comes first and keeps the narrow one waiting until it fits
+	assert_eq!(starts(&simulate(&blocking, 4, blocked())), [0, 100, 110]);
+	// without a file, the first job that fits
+	assert_eq!(starts(&simulate("script", 4, blocked())), starts(&simulate("ff", 4, blocked())));
+}
+
+#[cfg(feature = "script")]
+#[test]
+fn script_policies_keep_state_across_calls() {
+	// a job may only start once two jobs finished, counted in `this`
+	let dir = TempDir::new("script_policies_keep_state_across_calls");
+	let policy = dir.join("after-two.rhai");
+	std::fs::write(&policy, "\
+fn on_finish(job) {
+	this.finished = (this.finished ?? 0) + 1;
+}
+
+fn priority(job, cluster) {
+	-job.position
+}
+
+fn eligible(job, cluster) {
+	job.id != 3 || (this.finished ?? 0) >= 2
+}
+").unwrap();
+
+	// job 3 fits next to job 1 but waits for jobs 1 and 2 to finish
+	assert_eq!(starts(&simulate(&format!("script(file={})", policy.display()), 4, blocked())), [0, 100, 110]);
+}
+
+#[cfg(feature = "script")]
+#[test]
+fn script_errors_tell_the_line() {
+	use chamallow::sim::script::Policy;
+
+	let error = Policy::parse("fn priority(job, cluster) {\n\tjob.nodes +\n}").unwrap_err();
+	assert_eq!(error.line, 3);
+	assert_eq!(Policy::parse("fn priority(job) { 1 }").unwrap_err().to_string(), "priority takes 2 arguments (job, cluster), 1 given");
+	assert!(Policy::parse("fn eligible(job, cluster) { true }").is_err());
+}
+
+#[cfg(not(feature = "script"))]
+#[test]
+fn script_tells_the_feature_it_needs() {
+	let error = algos::by_name("script").err().unwrap();
+	assert_eq!(error.to_string(), "The 'script' scheduler needs chamallow built with --features script");
+}
+
+#[test]
+fn external_process_makes_the_decisions() {
+	use chamallow::external::{Decision, Message};
+	use std::{collections::HashMap, io::{BufRead, BufReader, Write}, net::TcpListener, thread};
+
+	// a decision process starting the queue in order until a job doesn't fit, like fcfs
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap().to_string();
+	let process = thread::spawn(move || {
+		let (stream, _) = listener.accept().unwrap();
+		let mut writer = stream.try_clone().unwrap();
+		let mut nodes = HashMap::new();
+		let mut events = Vec::new();
+
+		for line in BufReader::new(stream).lines() {
+			let message: Message = serde_json::from_str(&line.unwrap()).unwrap();
+			match &message {
+				Message::Submitted { job, .. } => {
+					nodes.insert(job.id, job.nodes);
+				}
+				Message::Schedule { free_nodes, queue, .. } => {
+					let (mut free, mut start) = (*free_nodes, Vec::new());
+					for id in queue {
+						if nodes[id] > free {
+							break;
+						}
+						free -= nodes[id];
+						start.push(*id);
+					}
+					writeln!(writer, "{}", serde_json::to_string(&Decision { start }).unwrap()).unwrap();
+				}
+				_ => {}
+			}
+
+			let end = message == Message::End;
+			events.push(message);
+			if end {
+				break;
+			}
+		}
+
+		events
+	});
+
+	let config = EngineConfig { record_jobs: true, ..EngineConfig::default() };
+	let external = Box::new(chamallow::External::new(&addr));
+	let mut report = Engine::from_workload(external, ClusterSpec::with_cores_per_node(4, 1), "fixture", blocked(), config).unwrap().run().unwrap();
+	report.job_records.sort_by_key(|record| record.id);
+
+	assert_eq!(report.scheduler_name, format!("External({})", addr));
+	assert_eq!(starts(&report.job_records), starts(&simulate("fcfs", 4, blocked())));
+
+	let events = process.join().unwrap();
+	assert_eq!(events.first(), Some(&Message::Hello { version: 1 }));
+	assert_eq!(events.iter().filter(|event| matches!(event, Message::Finished { .. })).count(), 3);
+}
+
+#[test]
+fn observers_follow_the_jobs() {
+	use chamallow::{Clock, Job, KillReason, Observer, walltime::WalltimeLimit};
+	use std::sync::{Arc, Mutex};
+
+	#[derive(Default)]
+	struct Events {
+		log: Vec<(Clock, &'static str, u32)>,
+		advanced: Clock,
+	}
+
+	impl Observer for Events {
+		fn on_submit(&mut self, clock: Clock, job: &Job) {
+			self.log.push((clock, "submit", job.id));
+		}
+
+		fn on_start(&mut self, clock: Clock, job: &Job) {
+			self.log.push((clock, "start", job.id));
+		}
+
+		fn on_finish(&mut self, clock: Clock, job: &Job) {
+			self.log.push((clock, "finish", job.id));
+		}
+
+		fn on_kill(&mut self, clock: Clock, job: &Job, reason: KillReason) {
+			assert_eq!(reason, KillReason::Walltime);
+			self.log.push((clock, "kill", job.id));
+		}
+
+		fn on_advance(&mut self, from: Clock, to: Clock) {
+			self.advanced += to - from;
+		}
+	}
+
+	// the second job runs past its requested time
+	let workload = WorkloadBuilder::new()
+		.job(1, 2, 0, 100)
+		.job(2, 2, 10, 50).requesting(20)
+		.build();
+
+	let events = Arc::new(Mutex::new(Events::default()));
+	let config = EngineConfig { walltime_limit: WalltimeLimit::Kill, ..EngineConfig::default() };
+	let mut engine = Engine::from_workload(algos::by_name("fcfs").unwrap(), ClusterSpec::with_cores_per_node(4, 1), "fixture", workload, config).unwrap();
+	engine.add_observer(Box::new(events.clone()));
+	let report = engine.run().unwrap();
+
+	let events = events.lock().unwrap();
+	assert_eq!(events.log, [
+		(0, "submit", 1), (0, "start", 1),
+		(10, "submit", 2), (10, "start", 2),
+		(30, "kill", 2), (30, "finish", 2),
+		(100, "finish", 1),
+	]);
+	assert_eq!(events.advanced, report.makespan);
+}
+
+/// FCFS, keeping what it can tell of the jobs it is given.
+struct Peeking {
+	inner: Box<dyn Scheduler>,
+	seen: Arc<Mutex<Vec<String>>>,
+}
+
+impl Scheduler for Peeking {
+	fn name(&self) -> &'static str {
+		"Peeking"
+	}
+
+	fn schedule(&mut self, clock: u64, jobs: &ReadyQueue, cluster: &Cluster) -> Option<usize> {
+		let mut seen = self.seen.lock().unwrap();
+		for job in jobs.iter() {
+			seen.push(format!("{:?}", job));
+			seen.push(serde_json::to_string(job).unwrap());
+		}
+
+		self.inner.schedule(clock, jobs, cluster)
+	}
+}
+
+#[test]
+fn schedulers_cant_read_the_actual_run_times() {
+	// jobs requesting far more time than they run
+	let workload = WorkloadBuilder::new()
+		.job(1, 4, 0, 100).requesting(7777)
+		.job(2, 4, 1, 200).requesting(8888)
+		.job(3, 4, 2, 300).requesting(9999)
+		.build();
+
+	let config = EngineConfig { record_jobs: true, ..EngineConfig::default() };
+	let seen = Arc::new(Mutex::new(Vec::new()));
+	let peeking = Peeking { inner: algos::by_name("fcfs").unwrap(), seen: seen.clone() };
+	Engine::from_workload(Box::new(peeking), ClusterSpec::with_cores_per_node(4, 1), "fixture", workload, config).unwrap().run().unwrap();
+
+	let seen = seen.lock().unwrap();
+	assert!(!seen.is_empty());
+	for seen in seen.iter() {
+		assert!(!seen.contains(" run_time") && !seen.contains("\"run_time\"") && !seen.contains("remaining_time"), "{}", seen);
+	}
+}
+
+#[test]
+fn snapshots_resume_runs_saved_before_any_job_finished() {
+	let engine = || Engine::from_workload(algos::by_name("fcfs").unwrap(), ClusterSpec::with_cores_per_node(4, 1), "fixture", blocked(), EngineConfig::default()).unwrap();
+	let dir = TempDir::new("snapshots_resume_runs_saved_before_any_job_finished");
+	let path = dir.join("snapshot.json");
+
+	let mut paused = engine();
+	paused.run_until(50).unwrap();
+	paused.snapshot().save(&path).unwrap();
+
+	let mut resumed = engine();
+	resumed.restore(EngineSnapshot::load(&path).unwrap()).unwrap();
+	assert_eq!(resumed.run().unwrap().makespan, 160);
+}
+
+#[test]
+fn decision_latency_delays_the_starts() {
+	let latency = "10:2".parse().unwrap();
+	let config = EngineConfig { record_jobs: true, decision_latency: Some(latency), ..EngineConfig::default() };
+	let report = Engine::from_workload(algos::by_name("fcfs").unwrap(), ClusterSpec::with_cores_per_node(4, 1), "fixture", blocked(), config).unwrap().run().unwrap();
+
+	// every cycle starts a single job, which holds its nodes for 12 seconds before it runs
+	assert_eq!(starts(&report.job_records), [12, 124, 146]);
+	assert_eq!(report.decision_overhead, 12 * (3 + 4 + 1));
+
+	// the jobs of a cycle are decided one after the other
+	let workload = WorkloadBuilder::new()
+		.job(1, 1, 0, 100)
+		.job(2, 1, 0, 100)
+		.build();
+	let config = EngineConfig { record_jobs: true, decision_latency: Some(latency), ..EngineConfig::default() };
+	let mut report = Engine::from_workload(algos::by_name("fcfs").unwrap(), ClusterSpec::with_cores_per_node(4, 1), "fixture", workload, config).unwrap().run().unwrap();
+	report.job_records.sort_by_key(|record| record.id);
+	assert_eq!(starts(&report.job_records), [12, 14]);
+}
+
+#[test]
+fn job_records_tell_the_load_factor_of_their_run() {
+	let config = EngineConfig { load_factor: 2.0, record_jobs: true, ..EngineConfig::default() };
+	let report = Engine::from_workload(algos::by_name("fcfs").unwrap(), ClusterSpec::with_cores_per_node(4, 1), "fixture", blocked(), config).unwrap().run().unwrap();
+
+	assert_eq!(report.load_factor, 2.0);
+	assert!(report.job_records.iter().all(|record| record.load_factor == 2.0));
+}
+
+#[test]
+fn repetitions_are_summarized_over_their_seeds() {
+	// the short job runs almost as long as the first one
+	let workload = WorkloadBuilder::new()
+		.job(1, 3, 0, 100)
+		.job(2, 4, 1, 10)
+		.job(3, 1, 2, 90)
+		.build();
+
+	let reports = ["fcfs", "easy"].iter()
+		.flat_map(|scheduler| (0..5).map(move |seed| (scheduler, seed)))
+		.map(|(scheduler, seed)| {
+			let config = EngineConfig { seed, walltime: "uniform:1:5".parse().unwrap(), record_jobs: true, ..EngineConfig::default() };
+			Engine::from_workload(algos::by_name(scheduler).unwrap(), ClusterSpec::with_cores_per_node(4, 1), "fixture", workload.clone(), config).unwrap().run().unwrap()
+		})
+		.collect::<Vec<_>>();
+
+	let confidence = Confidence::new(&reports);
+	assert!(confidence.is_repeated());
+	assert_eq!(confidence.runs.len(), 2);
+	assert_eq!(confidence.runs[1].seeds, [0, 1, 2, 3, 4]);
+
+	// the seeds decide whether the requested time of the short job lets it start before the wide one
+	let wait = &confidence.runs[1].estimates[1];
+	assert!(wait.std_dev > 0.0 && wait.half_width > wait.std_dev);
+
+	// the comparison has a row per scheduler, with the mean of its runs
+	let comparison = Comparison::new(&reports, None);
+	assert_eq!(comparison.groups[0].rows.len(), 2);
+	assert_eq!(comparison.groups[0].rows[1].1[1].value, wait.mean);
+
+	// and the job records of the repetitions are told apart by their seed
+	assert!(reports[7].job_records.iter().all(|record| record.seed == 2));
+}
+
+#[test]
+fn drained_nodes_take_no_job_until_the_downtime_ends() {
+	let workload = WorkloadBuilder::new()
+		.job(1, 2, 0, 100)
+		.job(2, 4, 60, 10)
+		.build();
+
+	let drain = Drain { name: "maintenance".to_string(), start: 50, duration: 100, first_node: 0, nodes: None };
+	let config = EngineConfig { record_jobs: true, drains: vec![drain], ..EngineConfig::default() };
+	let report = Engine::from_workload(algos::by_name("fcfs").unwrap(), ClusterSpec::with_cores_per_node(4, 1), "fixture", workload, config).unwrap().run().unwrap();
+
+	// the free nodes go down at 50, the nodes of the running job once it ends at 100
+	assert_eq!(report.drain_downtime, 2 * 100 + 2 * 50);
+	assert_eq!(report.job_records.iter().find(|record| record.id == 2).unwrap().start, 150);
+}
+
+#[test]
+fn rejected_jobs_are_accounted_for() {
+	let workload = WorkloadBuilder::new()
+		.job(1, 2, 0, 100)
+		.job(2, 8, 10, 50)
+		.build();
+
+	let config = EngineConfig { record_rejects: true, ..EngineConfig::default() };
+	let report = Engine::from_workload(algos::by_name("fcfs").unwrap(), ClusterSpec::with_cores_per_node(4, 1), "fixture", workload, config).unwrap().run().unwrap();
+
+	assert_eq!((report.jobs, report.rejected_jobs, report.rejected_work), (1, 1, 8 * 50));
+	assert_eq!(report.rejects.len(), 1);
+	assert_eq!((report.rejects[0].id, report.rejects[0].reason.as_str()), (2, "too_wide"));
+}
+
+#[test]
+fn workloads_are_characterized_without_simulating_them() {
+	let stats = WorkloadBuilder::new()
+		.job(1, 4, 0, 100).requesting(200)
+		.job(2, 1, 50, 400).requesting(400)
+		.job(3, 2, 100, 100).requesting(100)
+		.build()
+		.characterize(None);
+
+	assert_eq!(stats.jobs, 3);
+	assert_eq!(stats.work, 4 * 100 + 400 + 2 * 100);
+	assert_eq!(stats.demand, 1000.0 / 100.0);
+	assert_eq!(stats.estimate_accuracy, (0.5 + 1.0 + 1.0) / 3.0);
+	assert_eq!(stats.interarrival.max, 50.0);
+	assert_eq!(stats.offered_load(20), 0.5);
+	assert_eq!(stats.too_wide(1), 2);
+	assert_eq!(stats.days.len(), 1);
+}
+
+#[test]
+fn builder_sorts_the_jobs_by_submission() {
+	let workload = WorkloadBuilder::new()
+		.job(2, 1, 20, 10)
+		.job(1, 2, 10, 10).requesting(30)
+		.build();
+
+	assert_eq!(workload.jobs.iter().map(|job| job.job_number).collect::<Vec<_>>(), [1, 2]);
+	assert_eq!(workload.jobs[0].requested_time, Some(30));
+	assert_eq!(workload.meta.procs_per_node(), Some(1));
+}
+
+#[test]
+fn accounting_logs_are_read_as_workloads() {
+	let dir = TempDir::new("accounting_logs_are_read_as_workloads");
+	let sacct = dir.join("jan.sacct");
+	std::fs::write(&sacct, "\
+JobIDRaw|JobID|User|Group|Partition|Submit|Start|End|Elapsed|Timelimit|NNodes|NCPUS|State
+101|101|alice|phys|batch|2024-01-15T10:00:00|2024-01-15T10:00:00|2024-01-15T10:01:40|00:01:40|00:05:00|2|16|COMPLETED
+101.batch|101.batch||||2024-01-15T10:00:00|2024-01-15T10:00:00|2024-01-15T10:01:40|00:01:40||1|8|COMPLETED
+102|102|bob|chem|batch|2024-01-15T10:00:10|2024-01-15T10:01:40|2024-01-15T10:01:50|00:00:10|1-00:00:00|1|4|FAILED
+103|103|alice|phys|debug|2024-01-15T10:00:20|None|2024-01-15T10:00:30|00:00:00|UNLIMITED|1|8|CANCELLED by 1000
+104|104|bob|chem|batch|2024-01-15T10:00:30|Unknown|Unknown|00:00:00|00:10:00|1|8|PENDING
+").unwrap();
+
+	let workload = Workload::load(&sacct).unwrap();
+	assert_eq!(workload.jobs.iter().map(|job| job.job_number).collect::<Vec<_>>(), [101, 102, 103, 104]);
+	assert_eq!(workload.jobs.iter().map(|job| job.submit_time).collect::<Vec<_>>(), [0, 10, 20, 30]);
+	assert_eq!(workload.jobs.iter().map(|job| job.run_time).collect::<Vec<_>>(), [Some(100), Some(10), None, None]);
+	assert_eq!(workload.jobs.iter().map(|job| job.requested_time).collect::<Vec<_>>(), [Some(300), Some(86400), None, Some(600)]);
+	assert_eq!(workload.jobs.iter().map(|job| job.user_id).collect::<Vec<_>>(), [Some(1), Some(2), Some(1), Some(2)]);
+	assert_eq!(workload.jobs[2].partition, Some(2));
+	assert_eq!(workload.meta.procs_per_node(), Some(8));
+	assert_eq!(workload.meta.max_nodes, Some(2));
+
+	// 8 cores per node, from the header
+	let config = EngineConfig { record_jobs: true, ..EngineConfig::default() };
+	let report = Engine::from_workload(algos::by_name("fcfs").unwrap(), ClusterSpec::new(2), "jan", workload, config).unwrap().run().unwrap();
+	assert_eq!(report.job_records.iter().map(|record| (record.id, record.nodes)).collect::<Vec<_>>(), [(101, 2), (102, 1)]);
+	assert_eq!(report.incomplete_records, 1);
+
+	let pbs = dir.join("20240115");
+	std::fs::write(&pbs, "\
+01/15/2024 10:00:00;Q;7.server;queue=batch
+01/15/2024 10:05:00;E;7.server;user=alice group=phys queue=batch ctime=1705312800 qtime=1705312800 start=1705312800 end=1705313100 Resource_List.nodes=2:ppn=8 Resource_List.walltime=01:00:00 resources_used.walltime=00:05:00 Exit_status=0
+01/15/2024 10:06:00;E;8[1].server;user=bob group=chem queue=long qtime=1705312860 start=1705313100 end=1705313160 exec_host=n1/0+n1/1+n2/0 Exit_status=1
+01/15/2024 10:06:00;E;8[2].server;user=bob group=chem queue=long qtime=1705312860 start=1705313100 end=1705313160 Resource_List.select=1:ncpus=4 Exit_status=0
+").unwrap();
+
+	let workload = Workload::load(&pbs).unwrap();
+	assert_eq!(workload.jobs.iter().map(|job| job.job_number).collect::<Vec<_>>(), [1, 2, 3]);
+	assert_eq!(workload.jobs.iter().map(|job| (job.submit_time, job.wait_time, job.run_time)).collect::<Vec<_>>(),
+		[(0, Some(0), Some(300)), (60, Some(240), Some(60)), (60, Some(240), Some(60))]);
+	assert_eq!(workload.jobs.iter().map(|job| job.queue).collect::<Vec<_>>(), [Some(1), Some(2), Some(2)]);
+	assert_eq!(workload.jobs.iter().map(|job| job.requested_procs).collect::<Vec<_>>(), [Some(16), Some(16), Some(8)]);
+	assert_eq!(workload.meta.max_nodes, Some(3));
+}
+
+/// Fields of a CSV line, unquoting the quoted ones as RFC 4180 does.
+fn csv_fields(line: &str) -> Vec<String> {
+	let mut fields = vec![String::new()];
+	let mut quoted = false;
+	let mut chars = line.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		match c {
+			'"' if quoted && chars.peek() == Some(&'"') => {
+				chars.next();
+				fields.last_mut().unwrap().push('"');
+			}
+			'"' => quoted = !quoted,
+			',' if !quoted => fields.push(String::new()),
+			c => fields.last_mut().unwrap().push(c),
+		}
+	}
+
+	fields
+}
+
+#[test]
+fn csv_rows_quote_the_labels_of_schedulers_with_several_parameters() {
+	let spec = "easy(backfill_depth=4, backfill_order=sjf)";
+	let config = EngineConfig { record_jobs: true, ..EngineConfig::default() };
+	let report = Engine::from_workload(algos::by_name(spec).unwrap(), ClusterSpec::with_cores_per_node(4, 1), "fixture", blocked(), config).unwrap().run().unwrap();
+	assert!(report.scheduler_name.contains(", "));
+
+	let header = csv_fields(&EngineReport::csv_header());
+	let row = csv_fields(&report.to_csv_row());
+	assert_eq!(row.len(), header.len());
+	assert_eq!(row[1], report.scheduler_name);
+	assert_eq!(row[header.iter().position(|column| column == "jobs").unwrap()], "3");
+
+	let mut log = Vec::new();
+	JobLogWriter::new(&mut log, JobLogFormat::Csv).write_run(&report.job_records).unwrap();
+	let log = String::from_utf8(log).unwrap();
+	let mut lines = log.lines().map(csv_fields);
+	let header = lines.next().unwrap();
+	for row in lines {
+		assert_eq!(row.len(), header.len());
+		assert_eq!(row[0], report.scheduler_name);
+	}
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn parquet_job_logs_are_read_back() {
+	use parquet::{file::reader::{FileReader, SerializedFileReader}, record::RowAccessor};
+	use std::convert::TryInto;
+
+	let dir = TempDir::new("parquet_job_logs_are_read_back");
+	let path = dir.join("jobs.parquet");
+	let mut log = JobLogWriter::create(&path).unwrap();
+	log.write_run(&simulate("fcfs", 4, blocked())).unwrap();
+	log.write_run(&simulate("easy(backfill_depth=4, backfill_order=sjf)", 4, blocked())).unwrap();
+	log.finish().unwrap();
+
+	// a magic number at both ends, the footer length before the last one
+	let bytes = std::fs::read(&path).unwrap();
+	assert_eq!(&bytes[..4], b"PAR1");
+	assert_eq!(&bytes[bytes.len() - 4..], b"PAR1");
+	let footer = u32::from_le_bytes(bytes[bytes.len() - 8..bytes.len() - 4].try_into().unwrap()) as usize;
+	assert!(footer > 0 && footer + 12 < bytes.len());
+
+	let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+	let meta = reader.metadata().file_metadata();
+	assert_eq!(meta.num_rows(), 6);
+	assert_eq!(reader.num_row_groups(), 2);
+
+	let columns = meta.schema_descr().columns().iter().map(|column| column.name().to_string()).collect::<Vec<_>>();
+	let (scheduler, start) = (columns.iter().position(|name| name == "scheduler").unwrap(), columns.iter().position(|name| name == "start").unwrap());
+	let rows = reader.get_row_iter(None).unwrap().map(Result::unwrap).collect::<Vec<_>>();
+	assert_eq!(rows.iter().map(|row| row.get_long(start).unwrap()).collect::<Vec<_>>(), [0, 100, 110, 0, 100, 2]);
+	assert_eq!(rows[5].get_string(scheduler).unwrap(), "EasyBackfill(backfill_depth=4, backfill_order=sjf)");
+}